@@ -3,6 +3,7 @@ use wasm_bindgen::prelude::*;
 mod rest;
 mod player;
 mod logger;
+mod storage;
 
 #[wasm_bindgen]
 extern "C" {
@@ -0,0 +1,360 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+#[cfg(all(test, target_arch = "wasm32"))]
+use web_sys::console;
+use crate::storage::local_storage;
+
+mod sinks;
+pub use sinks::{ConsoleSink, RemoteSink, RingBufferSink};
+
+/// Query parameter that sets the minimum level for one page load, as in `?log=debug`.
+pub const LOG_LEVEL_PARAM: &str = "log";
+/// localStorage key that sets the minimum level until it is removed.
+pub const LOG_LEVEL_STORAGE_KEY: &str = "wasm-video-player.log-level";
+
+const EVENT_PAGEHIDE: &str = "pagehide";
+
+// Development builds show what the player does; release builds only what went wrong
+const DEFAULT_LEVEL: LogLevel = if cfg!(debug_assertions) { LogLevel::Debug } else { LogLevel::Warn };
+
+thread_local! {
+    static LOGGER: RefCell<LoggerState> = RefCell::new(LoggerState::new());
+}
+
+#[derive(Debug)]
+pub enum LoggerError {
+    InvalidMessage(String),
+    InvalidStyle(String),
+    SinkConfig(String),
+}
+
+impl std::fmt::Display for LoggerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoggerError::InvalidMessage(msg) => write!(f, "Invalid message: {}", msg),
+            LoggerError::InvalidStyle(msg) => write!(f, "Invalid style: {}", msg),
+            LoggerError::SinkConfig(msg) => write!(f, "Invalid sink configuration: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LoggerError {}
+
+impl From<LoggerError> for JsValue {
+    fn from(error: LoggerError) -> Self {
+        JsValue::from_str(&error.to_string())
+    }
+}
+
+/// How much a message matters, from step-by-step tracing to failures. Messages
+/// below the minimum level are dropped before they reach any sink; `Off` drops all.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Off,
+}
+
+impl LogLevel {
+    /// Reads a level name such as `"debug"` or `"WARN"`; `"warning"` is taken for `Warn`.
+    pub fn parse(name: &str) -> Option<LogLevel> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "trace" => Some(LogLevel::Trace),
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            "off" => Some(LogLevel::Off),
+            _ => None,
+        }
+    }
+}
+
+/// One message that passed the level filter. `timestamp` is in milliseconds since the epoch.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub message: String,
+    pub timestamp: f64,
+}
+
+/// Somewhere log records go. Sinks are shared, so they keep their own state
+/// behind interior mutability, and must not log themselves.
+pub trait LogSink {
+    fn write(&self, record: &LogRecord);
+
+    /// Sends on anything held back, for when the page is going away.
+    fn flush(&self) {}
+}
+
+struct LoggerState {
+    level: LogLevel,
+    sinks: Vec<Rc<dyn LogSink>>,
+    // The ring buffer the exported functions read, when one is installed
+    history: Option<Rc<RingBufferSink>>,
+    // Flushes the sinks as the page goes away, once a sink holds records back
+    pagehide: Option<Closure<dyn FnMut()>>,
+}
+
+impl LoggerState {
+    fn new() -> LoggerState {
+        LoggerState { level: DEFAULT_LEVEL, sinks: vec![Rc::new(ConsoleSink)], history: None, pagehide: None }
+    }
+}
+
+pub struct Logger;
+
+impl Logger {
+    pub fn trace(message: &str) -> Result<(), LoggerError> {
+        Logger::log(LogLevel::Trace, message)
+    }
+
+    pub fn debug(message: &str) -> Result<(), LoggerError> {
+        Logger::log(LogLevel::Debug, message)
+    }
+
+    pub fn info(message: &str) -> Result<(), LoggerError> {
+        Logger::log(LogLevel::Info, message)
+    }
+
+    pub fn warn(message: &str) -> Result<(), LoggerError> {
+        Logger::log(LogLevel::Warn, message)
+    }
+
+    pub fn error(message: &str) -> Result<(), LoggerError> {
+        Logger::log(LogLevel::Error, message)
+    }
+
+    /// Hands `message` to every sink, unless `level` is below the minimum.
+    pub fn log(level: LogLevel, message: &str) -> Result<(), LoggerError> {
+        if message.is_empty() {
+            return Err(LoggerError::InvalidMessage("Message cannot be empty".to_string()));
+        }
+        if Logger::enabled(level) {
+            dispatch(&LogRecord { level, message: message.to_string(), timestamp: now() });
+        }
+        Ok(())
+    }
+
+    /// Whether messages at `level` are kept.
+    pub fn enabled(level: LogLevel) -> bool {
+        level != LogLevel::Off && level >= Logger::level()
+    }
+
+    pub fn level() -> LogLevel {
+        LOGGER.with(|logger| logger.borrow().level)
+    }
+
+    pub fn set_level(level: LogLevel) {
+        LOGGER.with(|logger| logger.borrow_mut().level = level);
+    }
+
+    pub fn add_sink(sink: Rc<dyn LogSink>) {
+        LOGGER.with(|logger| logger.borrow_mut().sinks.push(sink));
+    }
+
+    /// Replaces every sink, the console included.
+    #[cfg(test)]
+    pub fn set_sinks(sinks: Vec<Rc<dyn LogSink>>) {
+        LOGGER.with(|logger| {
+            let mut logger = logger.borrow_mut();
+            logger.sinks = sinks;
+            logger.history = None;
+        });
+    }
+
+    pub fn flush() {
+        for sink in sinks() {
+            sink.flush();
+        }
+    }
+
+    #[cfg(test)]
+    pub fn log_with_style(message: &str, style: &str) -> Result<(), LoggerError> {
+        if message.is_empty() {
+            return Err(LoggerError::InvalidMessage("Message cannot be empty".to_string()));
+        }
+        if style.is_empty() {
+            return Err(LoggerError::InvalidStyle("Style cannot be empty".to_string()));
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let formatted = format!("%c{}", message);
+            let style = JsValue::from_str(style);
+            console::log_2(&JsValue::from_str(&formatted), &style);
+        }
+        // Native builds have no console to style
+        #[cfg(not(target_arch = "wasm32"))]
+        eprintln!("{}", message);
+        Ok(())
+    }
+}
+
+// Milliseconds since the epoch; native builds such as `cargo test` have no JS clock
+fn now() -> f64 {
+    #[cfg(target_arch = "wasm32")]
+    return js_sys::Date::now();
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64() * 1000.0);
+}
+
+// Copied out so a sink can write while others are added
+fn sinks() -> Vec<Rc<dyn LogSink>> {
+    LOGGER.with(|logger| logger.borrow().sinks.clone())
+}
+
+fn dispatch(record: &LogRecord) {
+    if !Logger::enabled(record.level) {
+        return;
+    }
+    for sink in sinks() {
+        sink.write(record);
+    }
+}
+
+// One listener for the page, however many players or sinks there are
+fn flush_on_pagehide() -> Result<(), LoggerError> {
+    if LOGGER.with(|logger| logger.borrow().pagehide.is_some()) {
+        return Ok(());
+    }
+    let window = web_sys::window().ok_or_else(|| LoggerError::SinkConfig("no window to flush logs from".to_string()))?;
+    let flush = Closure::<dyn FnMut()>::new(Logger::flush);
+    window
+        .add_event_listener_with_callback(EVENT_PAGEHIDE, flush.as_ref().unchecked_ref())
+        .map_err(|e| LoggerError::SinkConfig(format!("Failed to listen for {}: {:?}", EVENT_PAGEHIDE, e)))?;
+    LOGGER.with(|logger| logger.borrow_mut().pagehide = Some(flush));
+    Ok(())
+}
+
+/// The level the page asks for: the query parameter, then localStorage.
+pub fn page_log_level() -> Option<LogLevel> {
+    let window = web_sys::window()?;
+    let search = window.location().search().unwrap_or_default();
+    query_param(&search, LOG_LEVEL_PARAM)
+        .and_then(|name| LogLevel::parse(&name))
+        .or_else(|| {
+            let name = local_storage()?.get_item(LOG_LEVEL_STORAGE_KEY).ok().flatten()?;
+            LogLevel::parse(&name)
+        })
+}
+
+/// The value of `name` in a query string such as `"?log=debug&t=10"`.
+fn query_param(search: &str, name: &str) -> Option<String> {
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+#[wasm_bindgen]
+pub fn set_log_level(level: LogLevel) {
+    Logger::set_level(level);
+}
+
+#[wasm_bindgen]
+pub fn get_log_level() -> LogLevel {
+    Logger::level()
+}
+
+/// Keeps the last `capacity` records in memory, for `get_log_history` to read back
+/// when the viewer reports a problem.
+#[wasm_bindgen]
+pub fn enable_log_history(capacity: usize) -> Result<(), JsValue> {
+    if capacity == 0 {
+        return Err(LoggerError::SinkConfig("history capacity must be at least 1".to_string()).into());
+    }
+    let history = Rc::new(RingBufferSink::new(capacity));
+    LOGGER.with(|logger| {
+        let mut logger = logger.borrow_mut();
+        if let Some(previous) = logger.history.take() {
+            let previous: Rc<dyn LogSink> = previous;
+            logger.sinks.retain(|sink| !Rc::ptr_eq(sink, &previous));
+        }
+        logger.sinks.push(history.clone());
+        logger.history = Some(history);
+    });
+    Ok(())
+}
+
+/// The records kept by `enable_log_history`, oldest first, as
+/// `{ level, message, timestamp }` objects.
+#[wasm_bindgen]
+pub fn get_log_history() -> Result<JsValue, JsValue> {
+    let records = LOGGER.with(|logger| logger.borrow().history.as_ref().map(|history| history.records()).unwrap_or_default());
+    serde_wasm_bindgen::to_value(&records).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// POSTs records to `endpoint` as JSON arrays of `batch_size`; whatever is left
+/// is sent when the page is hidden or `flush_logs` is called.
+#[wasm_bindgen]
+pub fn enable_remote_logging(endpoint: String, batch_size: usize) -> Result<(), JsValue> {
+    if endpoint.is_empty() {
+        return Err(LoggerError::SinkConfig("remote logging endpoint cannot be empty".to_string()).into());
+    }
+    if batch_size == 0 {
+        return Err(LoggerError::SinkConfig("batch size must be at least 1".to_string()).into());
+    }
+    flush_on_pagehide()?;
+    Logger::add_sink(Rc::new(RemoteSink::new(endpoint, batch_size)));
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn flush_logs() {
+    Logger::flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(level: LogLevel, message: &str) -> LogRecord {
+        LogRecord { level, message: message.to_string(), timestamp: 0.0 }
+    }
+
+    #[test]
+    fn test_logger_functions() {
+        assert!(Logger::log(LogLevel::Info, "Test log message").is_ok());
+        assert!(Logger::warn("Test warning message").is_ok());
+        assert!(Logger::debug("Test debug message").is_ok());
+        assert!(Logger::log_with_style("Test styled message", "color: red").is_ok());
+    }
+
+    #[test]
+    fn test_levels_below_the_minimum_are_dropped() {
+        let history = Rc::new(RingBufferSink::new(10));
+        Logger::set_sinks(vec![history.clone()]);
+        Logger::set_level(LogLevel::Info);
+        for level in [LogLevel::Trace, LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error] {
+            dispatch(&record(level, "message"));
+        }
+        let levels: Vec<_> = history.records().iter().map(|record| record.level).collect();
+        assert_eq!(levels, [LogLevel::Info, LogLevel::Warn, LogLevel::Error]);
+
+        Logger::set_level(LogLevel::Off);
+        dispatch(&record(LogLevel::Error, "message"));
+        assert_eq!(history.records().len(), 3);
+        assert!(matches!(Logger::log(LogLevel::Error, ""), Err(LoggerError::InvalidMessage(_))));
+    }
+
+    #[test]
+    fn test_level_from_name_and_query() {
+        assert_eq!(LogLevel::parse(" WARNING "), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::parse("verbose"), None);
+        assert_eq!(query_param("?t=10&log=trace", LOG_LEVEL_PARAM).as_deref(), Some("trace"));
+        assert_eq!(query_param("?login=1", LOG_LEVEL_PARAM), None);
+        assert_eq!(query_param("", LOG_LEVEL_PARAM), None);
+    }
+}
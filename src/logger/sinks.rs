@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, RequestMode};
+#[cfg(target_arch = "wasm32")]
+use web_sys::console;
+#[cfg(target_arch = "wasm32")]
+use crate::logger::LogLevel;
+use crate::logger::{LogRecord, LogSink};
+
+/// Writes records to the browser console with the method matching their level,
+/// or to stderr in native builds.
+pub struct ConsoleSink;
+
+impl LogSink for ConsoleSink {
+    #[cfg(target_arch = "wasm32")]
+    fn write(&self, record: &LogRecord) {
+        let message = JsValue::from_str(&record.message);
+        match record.level {
+            // console.trace would print a stack for every message
+            LogLevel::Trace | LogLevel::Debug => console::debug_1(&message),
+            LogLevel::Info => console::info_1(&message),
+            LogLevel::Warn => console::warn_1(&message),
+            LogLevel::Error | LogLevel::Off => console::error_1(&message),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write(&self, record: &LogRecord) {
+        eprintln!("[{:?}] {}", record.level, record.message);
+    }
+}
+
+/// Keeps the last `capacity` records in memory, dropping the oldest.
+pub struct RingBufferSink {
+    capacity: usize,
+    records: RefCell<VecDeque<LogRecord>>,
+}
+
+impl RingBufferSink {
+    pub fn new(capacity: usize) -> RingBufferSink {
+        RingBufferSink { capacity, records: RefCell::new(VecDeque::with_capacity(capacity)) }
+    }
+
+    /// The records kept, oldest first.
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.borrow().iter().cloned().collect()
+    }
+}
+
+impl LogSink for RingBufferSink {
+    fn write(&self, record: &LogRecord) {
+        let mut records = self.records.borrow_mut();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+    }
+}
+
+/// Collects records and POSTs them to an endpoint as a JSON array once
+/// `batch_size` have gathered. A flush sends the rest as a beacon, which the
+/// browser delivers even after the page is gone.
+pub struct RemoteSink {
+    endpoint: String,
+    batch_size: usize,
+    pending: RefCell<Vec<LogRecord>>,
+}
+
+impl RemoteSink {
+    pub fn new(endpoint: String, batch_size: usize) -> RemoteSink {
+        RemoteSink { endpoint, batch_size, pending: RefCell::new(Vec::new()) }
+    }
+
+    /// Adds `record`, and returns the batch to send if it is now full.
+    fn push(&self, record: &LogRecord) -> Option<Vec<LogRecord>> {
+        let mut pending = self.pending.borrow_mut();
+        pending.push(record.clone());
+        (pending.len() >= self.batch_size).then(|| std::mem::take(&mut *pending))
+    }
+
+    fn send(&self, body: String) {
+        let endpoint = self.endpoint.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            // A failure is not logged, as that would only add to the next batch
+            post_json(&endpoint, &body).await.unwrap_or_default();
+        });
+    }
+}
+
+async fn post_json(url: &str, body: &str) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or_else(|| JsValue::from_str("no window"))?;
+    let init = RequestInit::new();
+    init.set_method("POST");
+    init.set_mode(RequestMode::Cors);
+    init.set_body(&JsValue::from_str(body));
+    let request = Request::new_with_str_and_init(url, &init)?;
+    request.headers().set("Content-Type", "application/json")?;
+    JsFuture::from(window.fetch_with_request(&request)).await?;
+    Ok(())
+}
+
+impl LogSink for RemoteSink {
+    fn write(&self, record: &LogRecord) {
+        if let Some(body) = self.push(record).and_then(|batch| serde_json::to_string(&batch).ok()) {
+            self.send(body);
+        }
+    }
+
+    fn flush(&self) {
+        let batch = std::mem::take(&mut *self.pending.borrow_mut());
+        if batch.is_empty() {
+            return;
+        }
+        let Ok(body) = serde_json::to_string(&batch) else {
+            return;
+        };
+        // A fetch is cancelled with the page. Beacons of a string go out as text/plain,
+        // and the browser refuses them past its quota
+        let queued = web_sys::window()
+            .and_then(|window| window.navigator().send_beacon_with_opt_str(&self.endpoint, Some(&body)).ok())
+            .unwrap_or(false);
+        if !queued {
+            self.send(body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::LogLevel;
+
+    fn record(message: &str) -> LogRecord {
+        LogRecord { level: LogLevel::Info, message: message.to_string(), timestamp: 0.0 }
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_the_latest() {
+        let history = RingBufferSink::new(2);
+        for message in ["first", "second", "third"] {
+            history.write(&record(message));
+        }
+        let messages: Vec<_> = history.records().into_iter().map(|record| record.message).collect();
+        assert_eq!(messages, ["second", "third"]);
+    }
+
+    #[test]
+    fn test_remote_sink_sends_full_batches() {
+        let remote = RemoteSink::new("https://logs.example.com".to_string(), 2);
+        assert_eq!(remote.push(&record("first")), None);
+        assert_eq!(remote.push(&record("second")).map(|batch| batch.len()), Some(2));
+        assert_eq!(remote.push(&record("third")), None);
+        assert_eq!(remote.pending.borrow().len(), 1);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::player::abr::throughput::ThroughputStrategy;
+use crate::player::abr::{AbrInput, AbrStrategy};
+
+// Below this buffer level (seconds) BOLA always picks the lowest rendition
+const MINIMUM_BUFFER: f64 = 10.0;
+// Extra buffer target per rendition so larger ladders can still reach the top
+const BUFFER_PER_LEVEL: f64 = 2.0;
+
+/// Buffer-based selection after BOLA (Spiteri et al.): each rendition's utility is the
+/// log of its bitrate, and the choice maximises utility minus the buffer it costs.
+#[derive(Clone, Debug)]
+pub struct BolaStrategy {
+    /// Buffer level (seconds) at which the top rendition is chosen.
+    pub stable_buffer: f64,
+    // Used until there is enough buffer for BOLA's decisions to be meaningful
+    startup: ThroughputStrategy,
+}
+
+impl Default for BolaStrategy {
+    fn default() -> Self {
+        BolaStrategy {
+            stable_buffer: 24.0,
+            startup: ThroughputStrategy::default(),
+        }
+    }
+}
+
+impl AbrStrategy for BolaStrategy {
+    fn name(&self) -> &'static str {
+        "bola"
+    }
+
+    fn choose(&mut self, input: &AbrInput) -> usize {
+        let order = input.by_bandwidth();
+        if order.len() < 2 {
+            return input.lowest();
+        }
+        if input.buffer_level < input.segment_duration && input.throughput.is_some() {
+            return self.startup.choose(input);
+        }
+
+        let bitrates: Vec<f64> = order.iter().map(|index| input.renditions[*index].bandwidth.max(1) as f64).collect();
+        // Utilities are shifted so the lowest rendition has utility 1
+        let utilities: Vec<f64> = bitrates.iter().map(|bitrate| (bitrate / bitrates[0]).ln() + 1.0).collect();
+
+        let buffer_time = self.stable_buffer.max(MINIMUM_BUFFER + BUFFER_PER_LEVEL * bitrates.len() as f64);
+        let gp = (utilities[utilities.len() - 1] - 1.0) / (buffer_time / MINIMUM_BUFFER - 1.0);
+        let vp = MINIMUM_BUFFER / gp;
+
+        let score = |level: usize| (vp * (utilities[level] + gp) - input.buffer_level) / bitrates[level];
+        let best = (0..bitrates.len())
+            .max_by(|a, b| score(*a).total_cmp(&score(*b)))
+            .unwrap_or(0);
+        order[best]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::abr::RenditionInfo;
+
+    #[test]
+    fn test_bola_climbs_with_buffer_level() {
+        let renditions = [RenditionInfo::new(400_000), RenditionInfo::new(1_000_000), RenditionInfo::new(2_500_000), RenditionInfo::new(5_000_000)];
+        let mut strategy = BolaStrategy::default();
+        let mut choose = |buffer_level| {
+            strategy.choose(&AbrInput {
+                renditions: &renditions,
+                current: None,
+                throughput: None,
+                buffer_level,
+                segment_duration: 4.0,
+            })
+        };
+
+        let choices: Vec<usize> = [0.0, 8.0, 12.0, 16.0, 20.0, 30.0].into_iter().map(&mut choose).collect();
+        assert_eq!(choices[0], 0);
+        assert!(choices.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", choices);
+        assert_eq!(*choices.last().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_bola_uses_throughput_while_buffer_is_empty() {
+        let renditions = [RenditionInfo::new(400_000), RenditionInfo::new(2_500_000)];
+        let choice = BolaStrategy::default().choose(&AbrInput {
+            renditions: &renditions,
+            current: None,
+            throughput: Some(10_000_000.0),
+            buffer_level: 0.0,
+            segment_duration: 4.0,
+        });
+        assert_eq!(choice, 1);
+    }
+}
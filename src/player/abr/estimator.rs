@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+// Downloads smaller than this mostly measure latency and caching, not bandwidth
+const MIN_SAMPLE_BYTES: u64 = 16_000;
+// Downloads faster than this cannot be timed reliably
+const MIN_SAMPLE_MS: f64 = 5.0;
+
+/// Turns segment download timings into a throughput estimate in bits per second.
+pub trait BandwidthEstimator {
+    fn sample(&mut self, bytes: u64, duration_ms: f64);
+
+    fn estimate(&self) -> Option<f64>;
+}
+
+fn is_usable(bytes: u64, duration_ms: f64) -> bool {
+    bytes >= MIN_SAMPLE_BYTES && duration_ms >= MIN_SAMPLE_MS
+}
+
+fn bits_per_second(bytes: u64, duration_ms: f64) -> f64 {
+    bytes as f64 * 8.0 * 1000.0 / duration_ms
+}
+
+/// One exponentially weighted moving average, weighted by download duration so long
+/// downloads count for more than short ones.
+#[derive(Clone, Debug)]
+struct Ewma {
+    alpha: f64,
+    estimate: f64,
+    total_weight: f64,
+}
+
+impl Ewma {
+    fn new(half_life_seconds: f64) -> Ewma {
+        Ewma {
+            alpha: (0.5f64.ln() / half_life_seconds).exp(),
+            estimate: 0.0,
+            total_weight: 0.0,
+        }
+    }
+
+    fn sample(&mut self, weight: f64, value: f64) {
+        let adjusted_alpha = self.alpha.powf(weight);
+        self.estimate = value * (1.0 - adjusted_alpha) + adjusted_alpha * self.estimate;
+        self.total_weight += weight;
+    }
+
+    fn estimate(&self) -> f64 {
+        // Corrects the bias towards zero of an average that started empty
+        let zero_factor = 1.0 - self.alpha.powf(self.total_weight);
+        self.estimate / zero_factor
+    }
+}
+
+/// A fast and a slow EWMA; the lower of the two is used so drops are picked up
+/// quickly while increases have to be sustained.
+#[derive(Clone, Debug)]
+pub struct EwmaEstimator {
+    fast: Ewma,
+    slow: Ewma,
+    bytes_sampled: u64,
+    min_total_bytes: u64,
+}
+
+impl EwmaEstimator {
+    pub fn new(fast_half_life: f64, slow_half_life: f64) -> EwmaEstimator {
+        EwmaEstimator {
+            fast: Ewma::new(fast_half_life),
+            slow: Ewma::new(slow_half_life),
+            bytes_sampled: 0,
+            min_total_bytes: 128_000,
+        }
+    }
+}
+
+impl Default for EwmaEstimator {
+    fn default() -> Self {
+        EwmaEstimator::new(2.0, 5.0)
+    }
+}
+
+impl BandwidthEstimator for EwmaEstimator {
+    fn sample(&mut self, bytes: u64, duration_ms: f64) {
+        if !is_usable(bytes, duration_ms) {
+            return;
+        }
+        let bandwidth = bits_per_second(bytes, duration_ms);
+        let weight = duration_ms / 1000.0;
+        self.bytes_sampled += bytes;
+        self.fast.sample(weight, bandwidth);
+        self.slow.sample(weight, bandwidth);
+    }
+
+    fn estimate(&self) -> Option<f64> {
+        (self.bytes_sampled >= self.min_total_bytes).then(|| self.fast.estimate().min(self.slow.estimate()))
+    }
+}
+
+/// Harmonic mean of the last few downloads, which is dominated by the slow ones.
+#[derive(Clone, Debug)]
+pub struct SlidingWindowEstimator {
+    window: usize,
+    samples: VecDeque<f64>,
+}
+
+impl SlidingWindowEstimator {
+    pub fn new(window: usize) -> SlidingWindowEstimator {
+        SlidingWindowEstimator {
+            window: window.max(1),
+            samples: VecDeque::new(),
+        }
+    }
+}
+
+impl Default for SlidingWindowEstimator {
+    fn default() -> Self {
+        SlidingWindowEstimator::new(5)
+    }
+}
+
+impl BandwidthEstimator for SlidingWindowEstimator {
+    fn sample(&mut self, bytes: u64, duration_ms: f64) {
+        if !is_usable(bytes, duration_ms) {
+            return;
+        }
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(bits_per_second(bytes, duration_ms));
+    }
+
+    fn estimate(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let inverse_sum: f64 = self.samples.iter().map(|sample| 1.0 / sample).sum();
+        Some(self.samples.len() as f64 / inverse_sum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 MB in one second is 8 Mbit/s
+    const MEGABYTE: u64 = 1_000_000;
+
+    #[test]
+    fn test_ewma_estimator_converges_and_reacts_to_drops() {
+        let mut estimator = EwmaEstimator::default();
+        assert_eq!(estimator.estimate(), None);
+
+        for _ in 0..10 {
+            estimator.sample(MEGABYTE, 1000.0);
+        }
+        let steady = estimator.estimate().unwrap();
+        assert!((steady - 8_000_000.0).abs() < 1.0);
+
+        // A single slow download pulls the fast average, and so the estimate, down
+        estimator.sample(MEGABYTE, 4000.0);
+        let after_drop = estimator.estimate().unwrap();
+        assert!(after_drop < 6_500_000.0);
+    }
+
+    #[test]
+    fn test_estimators_ignore_tiny_samples() {
+        let mut ewma = EwmaEstimator::default();
+        let mut window = SlidingWindowEstimator::default();
+        for _ in 0..100 {
+            ewma.sample(1_000, 1.0);
+            window.sample(1_000, 1.0);
+        }
+        assert_eq!(ewma.estimate(), None);
+        assert_eq!(window.estimate(), None);
+    }
+
+    #[test]
+    fn test_sliding_window_uses_harmonic_mean_of_recent_samples() {
+        let mut estimator = SlidingWindowEstimator::new(2);
+        estimator.sample(MEGABYTE, 100.0);
+        estimator.sample(MEGABYTE, 1000.0);
+        estimator.sample(MEGABYTE, 4000.0);
+        // Only the last two remain: 8 and 2 Mbit/s
+        assert!((estimator.estimate().unwrap() - 3_200_000.0).abs() < 1.0);
+    }
+}
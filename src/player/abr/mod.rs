@@ -0,0 +1,346 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+pub mod bola;
+pub mod estimator;
+pub mod throughput;
+pub use bola::BolaStrategy;
+pub use estimator::{BandwidthEstimator, EwmaEstimator, SlidingWindowEstimator};
+pub use throughput::ThroughputStrategy;
+
+/// What ABR needs to know about one rendition of a stream.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RenditionInfo {
+    /// Peak bandwidth in bits per second, as advertised by the manifest.
+    pub bandwidth: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl RenditionInfo {
+    pub fn new(bandwidth: u64) -> RenditionInfo {
+        RenditionInfo {
+            bandwidth,
+            ..RenditionInfo::default()
+        }
+    }
+}
+
+/// Everything a strategy bases its decision on.
+#[derive(Clone, Debug)]
+pub struct AbrInput<'a> {
+    pub renditions: &'a [RenditionInfo],
+    pub current: Option<usize>,
+    /// Estimated throughput in bits per second.
+    pub throughput: Option<f64>,
+    /// Seconds buffered ahead of the playhead.
+    pub buffer_level: f64,
+    pub segment_duration: f64,
+}
+
+impl AbrInput<'_> {
+    /// Rendition indices ordered from lowest to highest bandwidth.
+    pub fn by_bandwidth(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.renditions.len()).collect();
+        order.sort_by_key(|index| self.renditions[*index].bandwidth);
+        order
+    }
+
+    pub fn lowest(&self) -> usize {
+        self.by_bandwidth().first().copied().unwrap_or(0)
+    }
+}
+
+/// Chooses a rendition index for the next segment. Implementations are pure so they
+/// can be driven by simulated bandwidth traces.
+pub trait AbrStrategy {
+    fn name(&self) -> &'static str;
+
+    fn choose(&mut self, input: &AbrInput) -> usize;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SwitchReason {
+    Abr,
+    Manual,
+}
+
+/// A rendition change, reported to the page as a `qualitychange` event.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AbrSwitch {
+    pub index: usize,
+    pub previous: Option<usize>,
+    pub bandwidth: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub reason: SwitchReason,
+}
+
+/// Which `AbrStrategy` a player uses for adaptive streams.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AbrStrategyKind {
+    #[default]
+    Throughput,
+    Bola,
+}
+
+impl AbrStrategyKind {
+    pub fn controller(&self) -> AbrController {
+        match self {
+            AbrStrategyKind::Throughput => AbrController::default(),
+            // BOLA only consults throughput during startup, where the cautious harmonic mean fits better
+            AbrStrategyKind::Bola => AbrController::new(
+                Box::<BolaStrategy>::default(),
+                Box::<SlidingWindowEstimator>::default(),
+                AbrConfig::default(),
+            ),
+        }
+    }
+}
+
+/// Hysteresis applied on top of a strategy's choices.
+#[derive(Clone, Debug)]
+pub struct AbrConfig {
+    /// Seconds that must pass between two automatic switches.
+    pub min_switch_interval: f64,
+    /// Buffer (seconds) required before switching up.
+    pub up_switch_buffer: f64,
+    /// Below this buffer (seconds) down-switches ignore `min_switch_interval`.
+    pub panic_buffer: f64,
+}
+
+impl Default for AbrConfig {
+    fn default() -> Self {
+        AbrConfig {
+            min_switch_interval: 8.0,
+            up_switch_buffer: 6.0,
+            panic_buffer: 4.0,
+        }
+    }
+}
+
+/// Feeds download timings into an estimator and turns a strategy's choices into
+/// switches, holding back the ones that would make quality oscillate.
+pub struct AbrController {
+    strategy: Box<dyn AbrStrategy>,
+    estimator: Box<dyn BandwidthEstimator>,
+    config: AbrConfig,
+    current: Option<usize>,
+    manual: Option<usize>,
+    last_switch: Option<f64>,
+}
+
+impl AbrController {
+    pub fn new(strategy: Box<dyn AbrStrategy>, estimator: Box<dyn BandwidthEstimator>, config: AbrConfig) -> AbrController {
+        AbrController {
+            strategy,
+            estimator,
+            config,
+            current: None,
+            manual: None,
+            last_switch: None,
+        }
+    }
+
+    pub fn strategy_name(&self) -> &'static str {
+        self.strategy.name()
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// Records the rendition the stream started with.
+    pub fn set_current(&mut self, index: usize) {
+        self.current = Some(index);
+    }
+
+    /// Pins a rendition, or returns to automatic selection with `None`.
+    pub fn set_manual(&mut self, index: Option<usize>) {
+        self.manual = index;
+    }
+
+    pub fn manual(&self) -> Option<usize> {
+        self.manual
+    }
+
+    pub fn record_download(&mut self, bytes: u64, duration_ms: f64) {
+        self.estimator.sample(bytes, duration_ms);
+    }
+
+    pub fn estimate(&self) -> Option<f64> {
+        self.estimator.estimate()
+    }
+
+    /// Decides which rendition the next segment should come from. `now` is a
+    /// monotonic time in seconds used for the switch interval.
+    pub fn choose(&mut self, renditions: &[RenditionInfo], buffer_level: f64, segment_duration: f64, now: f64) -> Option<AbrSwitch> {
+        if renditions.is_empty() {
+            return None;
+        }
+        let (target, reason) = match self.manual {
+            Some(index) => (index.min(renditions.len() - 1), SwitchReason::Manual),
+            None => {
+                let input = AbrInput {
+                    renditions,
+                    current: self.current,
+                    throughput: self.estimator.estimate(),
+                    buffer_level,
+                    segment_duration,
+                };
+                (self.strategy.choose(&input).min(renditions.len() - 1), SwitchReason::Abr)
+            }
+        };
+        if self.current == Some(target) {
+            return None;
+        }
+
+        if let (SwitchReason::Abr, Some(current)) = (reason, self.current.filter(|current| *current < renditions.len())) {
+            let since_last = self.last_switch.map(|last| now - last).unwrap_or(f64::INFINITY);
+            let too_soon = since_last < self.config.min_switch_interval;
+            if renditions[target].bandwidth > renditions[current].bandwidth {
+                if too_soon || buffer_level < self.config.up_switch_buffer {
+                    return None;
+                }
+            } else if too_soon && buffer_level >= self.config.panic_buffer {
+                return None;
+            }
+        }
+
+        let previous = self.current.replace(target);
+        self.last_switch = Some(now);
+        let rendition = &renditions[target];
+        Some(AbrSwitch {
+            index: target,
+            previous,
+            bandwidth: rendition.bandwidth,
+            width: rendition.width,
+            height: rendition.height,
+            reason,
+        })
+    }
+}
+
+impl Default for AbrController {
+    fn default() -> Self {
+        AbrController::new(Box::<ThroughputStrategy>::default(), Box::<EwmaEstimator>::default(), AbrConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEGMENT_DURATION: f64 = 4.0;
+    const MAX_BUFFER: f64 = 30.0;
+
+    fn ladder() -> Vec<RenditionInfo> {
+        [400_000, 1_000_000, 2_500_000, 5_000_000].into_iter().map(RenditionInfo::new).collect()
+    }
+
+    /// Plays segments through `controller` with the network delivering `trace[i]` bits
+    /// per second while segment `i` downloads. Returns the rendition of every segment
+    /// and how often the buffer ran dry.
+    fn simulate(controller: &mut AbrController, trace: &[f64]) -> (Vec<usize>, usize) {
+        let renditions = ladder();
+        let mut buffer = 0.0;
+        let mut clock = 0.0;
+        let mut stalls = 0;
+        let mut chosen = Vec::new();
+        controller.set_current(0);
+
+        for bandwidth in trace {
+            let index = controller.current().unwrap_or(0);
+            let bytes = (renditions[index].bandwidth as f64 * SEGMENT_DURATION / 8.0) as u64;
+            let download = bytes as f64 * 8.0 / bandwidth;
+            clock += download;
+            if download > buffer && !chosen.is_empty() {
+                stalls += 1;
+            }
+            buffer = (buffer - download).max(0.0) + SEGMENT_DURATION;
+            if buffer > MAX_BUFFER {
+                // The pipeline idles while the playhead catches up
+                clock += buffer - MAX_BUFFER;
+                buffer = MAX_BUFFER;
+            }
+            controller.record_download(bytes, download * 1000.0);
+            controller.choose(&renditions, buffer, SEGMENT_DURATION, clock);
+            chosen.push(index);
+        }
+        (chosen, stalls)
+    }
+
+    fn switches(chosen: &[usize]) -> usize {
+        chosen.windows(2).filter(|pair| pair[0] != pair[1]).count()
+    }
+
+    #[test]
+    fn test_throughput_controller_climbs_on_fast_network() {
+        let mut controller = AbrController::default();
+        let (chosen, stalls) = simulate(&mut controller, &[10_000_000.0; 20]);
+        assert_eq!(*chosen.last().unwrap(), 3);
+        assert_eq!(stalls, 0);
+    }
+
+    #[test]
+    fn test_throughput_controller_steps_down_after_bandwidth_drop() {
+        let mut controller = AbrController::default();
+        let mut trace = vec![10_000_000.0; 15];
+        trace.extend([1_500_000.0; 10]);
+        let (chosen, stalls) = simulate(&mut controller, &trace);
+
+        assert_eq!(chosen[14], 3);
+        assert!(chosen[18] <= 1, "{:?}", chosen);
+        assert_eq!(*chosen.last().unwrap(), 1);
+        assert_eq!(stalls, 0);
+    }
+
+    #[test]
+    fn test_hysteresis_limits_oscillation() {
+        // Raw per-segment throughput flapping around the 2.5 Mbit/s rendition
+        let trace: Vec<f64> = (0..40).map(|i| if i % 2 == 0 { 2_800_000.0 } else { 3_300_000.0 }).collect();
+        let controller = |config| AbrController::new(Box::<ThroughputStrategy>::default(), Box::new(SlidingWindowEstimator::new(1)), config);
+
+        let eager = AbrConfig {
+            min_switch_interval: 0.0,
+            up_switch_buffer: 0.0,
+            panic_buffer: 0.0,
+        };
+        let (eager_choices, _) = simulate(&mut controller(eager), &trace);
+        let (damped_choices, _) = simulate(&mut controller(AbrConfig::default()), &trace);
+
+        assert!(switches(&eager_choices) > 10, "{:?}", eager_choices);
+        assert!(switches(&damped_choices) * 2 < switches(&eager_choices), "{:?}", damped_choices);
+    }
+
+    #[test]
+    fn test_bola_controller_follows_buffer() {
+        let mut controller = AbrController::new(Box::<BolaStrategy>::default(), Box::<EwmaEstimator>::default(), AbrConfig::default());
+        let mut trace = vec![10_000_000.0; 30];
+        trace.extend([1_200_000.0; 30]);
+        let (chosen, _) = simulate(&mut controller, &trace);
+
+        assert_eq!(chosen[29], 3);
+        assert!(*chosen.last().unwrap() <= 1, "{:?}", chosen);
+    }
+
+    #[test]
+    fn test_manual_selection_overrides_strategy() {
+        let renditions = ladder();
+        let mut controller = AbrController::default();
+        controller.set_current(0);
+        controller.set_manual(Some(2));
+
+        let switch = controller.choose(&renditions, 0.0, SEGMENT_DURATION, 0.0).unwrap();
+        assert_eq!(switch.index, 2);
+        assert_eq!(switch.previous, Some(0));
+        assert_eq!(switch.reason, SwitchReason::Manual);
+        assert!(controller.choose(&renditions, 0.0, SEGMENT_DURATION, 0.1).is_none());
+
+        // Back to automatic with no estimate yet keeps the current rendition
+        controller.set_manual(None);
+        assert!(controller.choose(&renditions, 0.0, SEGMENT_DURATION, 0.2).is_none());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::player::abr::{AbrInput, AbrStrategy};
+
+/// Picks the highest rendition whose bandwidth fits within a safety margin of the
+/// measured throughput.
+#[derive(Clone, Debug)]
+pub struct ThroughputStrategy {
+    /// Fraction of the estimated throughput a rendition may use.
+    pub safety_factor: f64,
+}
+
+impl Default for ThroughputStrategy {
+    fn default() -> Self {
+        ThroughputStrategy { safety_factor: 0.85 }
+    }
+}
+
+impl AbrStrategy for ThroughputStrategy {
+    fn name(&self) -> &'static str {
+        "throughput"
+    }
+
+    fn choose(&mut self, input: &AbrInput) -> usize {
+        let Some(throughput) = input.throughput else {
+            return input.current.unwrap_or_else(|| input.lowest());
+        };
+        let budget = throughput * self.safety_factor;
+        input
+            .by_bandwidth()
+            .into_iter()
+            .rev()
+            .find(|index| input.renditions[*index].bandwidth as f64 <= budget)
+            .unwrap_or_else(|| input.lowest())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::abr::RenditionInfo;
+
+    #[test]
+    fn test_throughput_strategy_picks_highest_affordable_rendition() {
+        // Deliberately unsorted to check indices refer to the original order
+        let renditions = [RenditionInfo::new(2_500_000), RenditionInfo::new(400_000), RenditionInfo::new(1_000_000)];
+        let mut strategy = ThroughputStrategy::default();
+        let input = |throughput| AbrInput {
+            renditions: &renditions,
+            current: None,
+            throughput,
+            buffer_level: 0.0,
+            segment_duration: 4.0,
+        };
+
+        assert_eq!(strategy.choose(&input(None)), 1);
+        assert_eq!(strategy.choose(&input(Some(300_000.0))), 1);
+        assert_eq!(strategy.choose(&input(Some(1_200_000.0))), 2);
+        // 2.5 Mbit/s needs about 2.95 Mbit/s of measured throughput
+        assert_eq!(strategy.choose(&input(Some(2_900_000.0))), 2);
+        assert_eq!(strategy.choose(&input(Some(3_000_000.0))), 0);
+    }
+}
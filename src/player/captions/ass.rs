@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use crate::player::captions::cue::{nodes_from_runs, Color, Cue, CueList, CueNode, CueSettings, Line, LineAlign, PositionAlign, RunFormat, TextAlign, TextStyle};
+use crate::player::error::VideoError;
+
+// Field order used when a section has no Format line
+const DEFAULT_STYLE_FORMAT: &str = "Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding";
+const DEFAULT_EVENT_FORMAT: &str = "Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
+// Script resolution assumed by renderers when a script does not give one
+const DEFAULT_PLAY_RES: (f64, f64) = (384.0, 288.0);
+// Numpad alignment: bottom centre
+const DEFAULT_ALIGNMENT: u8 = 2;
+
+/// An ASS style, with sizes already converted to percentages of the video.
+#[derive(Clone, Debug)]
+struct Style {
+    format: RunFormat,
+    alignment: u8,
+    /// Percentage of the video height.
+    margin_vertical: f64,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            format: RunFormat {
+                style: TextStyle {
+                    color: Some(Color::rgb(255, 255, 255)),
+                    outline: Some(Color::rgb(0, 0, 0)),
+                    ..TextStyle::default()
+                },
+                ..RunFormat::default()
+            },
+            alignment: DEFAULT_ALIGNMENT,
+            margin_vertical: 0.0,
+        }
+    }
+}
+
+/// Parses an Advanced SubStation Alpha (v4.00+) or SubStation Alpha (v4.00) script.
+/// Style colours, fonts and sizes become inline styling, and `\an`/`\pos` overrides
+/// become cue positions. Animation, drawing and karaoke tags are ignored.
+pub fn parse_ass(text: &str) -> Result<CueList, VideoError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut section = String::new();
+    let mut info: HashMap<String, String> = HashMap::new();
+    let mut style_lines: Vec<(bool, &str)> = Vec::new();
+    let mut event_lines: Vec<&str> = Vec::new();
+    let mut style_format = None;
+    let mut event_format = None;
+    let mut has_events = false;
+
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_ascii_lowercase();
+            has_events |= section == "events";
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match (section.as_str(), key.trim()) {
+            ("script info", key) => {
+                info.insert(key.to_ascii_lowercase(), value.to_string());
+            }
+            ("v4+ styles" | "v4 styles", "Format") => style_format = Some(value),
+            ("v4+ styles", "Style") => style_lines.push((false, value)),
+            ("v4 styles", "Style") => style_lines.push((true, value)),
+            ("events", "Format") => event_format = Some(value),
+            ("events", "Dialogue") => event_lines.push(value),
+            _ => {}
+        }
+    }
+    if !has_events {
+        return Err(VideoError::CaptionParseError("no [Events] section".to_string()));
+    }
+
+    let resolution = |key: &str| info.get(key).and_then(|value| value.parse::<f64>().ok()).filter(|value| *value > 0.0);
+    let play_res = match (resolution("playresx"), resolution("playresy")) {
+        (Some(x), Some(y)) => (x, y),
+        (Some(x), None) => (x, x * 3.0 / 4.0),
+        (None, Some(y)) => (y * 4.0 / 3.0, y),
+        (None, None) => DEFAULT_PLAY_RES,
+    };
+
+    let style_fields = field_names(style_format.unwrap_or(DEFAULT_STYLE_FORMAT));
+    let styles: HashMap<String, Style> = style_lines
+        .into_iter()
+        .map(|(legacy, line)| {
+            let fields = split_fields(line, style_fields.len());
+            let field = |name: &str| style_fields.iter().position(|field| field == name).and_then(|index| fields.get(index)).copied().unwrap_or("");
+            (field("name").trim_start_matches('*').to_string(), parse_style(field, legacy, play_res))
+        })
+        .collect();
+
+    let event_fields = field_names(event_format.unwrap_or(DEFAULT_EVENT_FORMAT));
+    let mut list = CueList::default();
+    for line in event_lines {
+        let fields = split_fields(line, event_fields.len());
+        let field = |name: &str| event_fields.iter().position(|field| field == name).and_then(|index| fields.get(index)).copied().unwrap_or("");
+        let (Some(start), Some(end)) = (parse_time(field("start")), parse_time(field("end"))) else {
+            continue;
+        };
+        if end <= start {
+            continue;
+        }
+        let style_name = field("style").trim_start_matches('*');
+        let style = styles.get(style_name).or_else(|| styles.get("Default")).cloned().unwrap_or_default();
+        let margin_vertical = field("marginv").parse::<f64>().ok().filter(|margin| *margin > 0.0).map(|margin| margin / play_res.1 * 100.0);
+        let (content, alignment, position) = parse_text(field("text"), &style, &styles, play_res);
+        let settings = match position {
+            Some(position) => positioned_settings(alignment, position),
+            None => alignment_settings(alignment, margin_vertical.unwrap_or(style.margin_vertical)),
+        };
+        if content.is_empty() {
+            continue;
+        }
+        list.cues.push(Cue {
+            id: None,
+            start,
+            end,
+            settings,
+            content,
+        });
+    }
+    // Scripts list events in any order
+    list.cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Ok(list)
+}
+
+fn field_names(format: &str) -> Vec<String> {
+    format.split(',').map(|field| field.trim().to_ascii_lowercase()).collect()
+}
+
+/// Splits a line into `count` fields; the last one keeps any further commas.
+fn split_fields(line: &str, count: usize) -> Vec<&str> {
+    line.splitn(count.max(1), ',').map(str::trim).collect()
+}
+
+fn parse_style<'a>(field: impl Fn(&str) -> &'a str, legacy: bool, play_res: (f64, f64)) -> Style {
+    let mut style = Style::default();
+    let flag = |name: &str| field(name).parse::<i32>().is_ok_and(|value| value != 0);
+    style.format.bold = flag("bold");
+    style.format.italic = flag("italic");
+    style.format.underline = flag("underline");
+
+    let text_style = &mut style.format.style;
+    if let Some(color) = parse_color(field("primarycolour")) {
+        text_style.color = Some(color);
+    }
+    let outline_color = parse_color(field("outlinecolour"));
+    if field("borderstyle") == "3" {
+        // An opaque box behind the text, drawn in the outline colour
+        text_style.background = outline_color;
+        text_style.outline = None;
+    } else if field("outline").parse::<f64>().is_ok_and(|width| width == 0.0) {
+        text_style.outline = None;
+    } else if outline_color.is_some() {
+        text_style.outline = outline_color;
+    }
+    let font_family = field("fontname");
+    if !font_family.is_empty() {
+        text_style.font_family = Some(font_family.to_string());
+    }
+    if let Ok(font_size) = field("fontsize").parse::<f64>() {
+        text_style.font_size = Some(font_size / play_res.1 * 100.0);
+    }
+
+    if let Ok(alignment) = field("alignment").parse::<u8>() {
+        style.alignment = if legacy { legacy_alignment(alignment) } else { alignment }.clamp(1, 9);
+    }
+    if let Ok(margin) = field("marginv").parse::<f64>() {
+        style.margin_vertical = margin / play_res.1 * 100.0;
+    }
+    style
+}
+
+/// Parses `H:MM:SS.cc`.
+fn parse_time(value: &str) -> Option<f64> {
+    let mut parts = value.trim().split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || hours < 0.0 || minutes < 0.0 || seconds < 0.0 {
+        return None;
+    }
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+/// Parses `&HAABBGGRR&`, `&HBBGGRR&` or the decimal form SSA uses. ASS alpha counts
+/// transparency, so it is inverted.
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim().trim_end_matches('&');
+    let number = match value.strip_prefix("&H").or_else(|| value.strip_prefix("&h")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => value.parse::<i64>().ok()? as u32,
+    };
+    let [red, green, blue, alpha] = number.to_le_bytes();
+    Some(Color { red, green, blue, alpha: 255 - alpha })
+}
+
+/// Converts SSA's alignment (1-3 bottom, 5-7 top, 9-11 middle) to numpad numbering.
+fn legacy_alignment(alignment: u8) -> u8 {
+    match alignment {
+        9.. => alignment - 5,
+        5.. => alignment + 2,
+        _ => alignment,
+    }
+}
+
+/// Settings for a numpad alignment (1-3 bottom, 4-6 middle, 7-9 top) with the
+/// vertical margin in percent. Bottom cues keep the automatic line so they stack.
+pub fn alignment_settings(alignment: u8, margin_vertical: f64) -> CueSettings {
+    let alignment = alignment.clamp(1, 9);
+    let align = match (alignment - 1) % 3 {
+        0 => TextAlign::Left,
+        1 => TextAlign::Center,
+        _ => TextAlign::Right,
+    };
+    let (line, line_align) = match (alignment - 1) / 3 {
+        0 => (None, LineAlign::Start),
+        1 => (Some(Line::Percent(50.0)), LineAlign::Center),
+        _ => (Some(Line::Percent(margin_vertical.clamp(0.0, 100.0))), LineAlign::Start),
+    };
+    CueSettings {
+        line,
+        line_align,
+        align,
+        ..CueSettings::default()
+    }
+}
+
+/// The numpad alignment closest to a cue's settings, the inverse of `alignment_settings`.
+pub fn settings_alignment(settings: &CueSettings) -> u8 {
+    let column = match settings.align {
+        TextAlign::Left | TextAlign::Start => 1,
+        TextAlign::Center => 2,
+        TextAlign::Right | TextAlign::End => 3,
+    };
+    let row = match (settings.line, settings.line_align) {
+        (None, _) | (Some(_), LineAlign::End) => 0,
+        (Some(_), LineAlign::Center) => 3,
+        (Some(_), LineAlign::Start) => 6,
+    };
+    row + column
+}
+
+/// Settings for a `\pos(x, y)` override, where the alignment picks the anchor point.
+fn positioned_settings(alignment: u8, (x, y): (f64, f64)) -> CueSettings {
+    let mut settings = alignment_settings(alignment, 0.0);
+    settings.position = Some(x.clamp(0.0, 100.0));
+    settings.position_align = Some(match (alignment - 1) % 3 {
+        0 => PositionAlign::LineLeft,
+        1 => PositionAlign::Center,
+        _ => PositionAlign::LineRight,
+    });
+    settings.line = Some(Line::Percent(y.clamp(0.0, 100.0)));
+    settings.line_align = match (alignment - 1) / 3 {
+        0 => LineAlign::End,
+        1 => LineAlign::Center,
+        _ => LineAlign::Start,
+    };
+    settings
+}
+
+/// Splits dialogue text into formatted runs, applying override blocks as they come.
+/// Returns the cue text, the alignment and any `\pos` as percentages of the video.
+fn parse_text(text: &str, style: &Style, styles: &HashMap<String, Style>, play_res: (f64, f64)) -> (Vec<CueNode>, u8, Option<(f64, f64)>) {
+    let mut format = style.format.clone();
+    let mut alignment = style.alignment;
+    let mut position = None;
+    let mut drawing = false;
+    let mut runs: Vec<(RunFormat, String)> = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let (plain, block) = match rest.find('{') {
+            Some(open) => {
+                let close = rest[open..].find('}').map(|close| open + close).unwrap_or(rest.len());
+                let block = rest.get(open + 1..close).unwrap_or("");
+                let plain = &rest[..open];
+                rest = rest.get(close + 1..).unwrap_or("");
+                (plain, Some(block))
+            }
+            None => {
+                let plain = rest;
+                rest = "";
+                (plain, None)
+            }
+        };
+        if !drawing {
+            runs.push((format.clone(), unescape(plain)));
+        }
+        // Blocks without a backslash are comments
+        for tag in block.into_iter().flat_map(|block| block.split('\\')).map(str::trim).filter(|tag| !tag.is_empty()) {
+            apply_tag(tag, style, styles, play_res, &mut format, &mut alignment, &mut position, &mut drawing);
+        }
+    }
+    (nodes_from_runs(runs), alignment, position)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_tag(tag: &str, style: &Style, styles: &HashMap<String, Style>, play_res: (f64, f64), format: &mut RunFormat, alignment: &mut u8, position: &mut Option<(f64, f64)>, drawing: &mut bool) {
+    let flag = |value: &str, default: bool| match value {
+        "" => default,
+        "0" => false,
+        _ => true,
+    };
+    if let Some(arguments) = tag.strip_prefix("pos(") {
+        let coordinates: Vec<f64> = arguments.trim_end_matches(')').split(',').filter_map(|value| value.trim().parse().ok()).collect();
+        if let [x, y] = coordinates[..] {
+            *position = Some((x / play_res.0 * 100.0, y / play_res.1 * 100.0));
+        }
+    } else if let Some(value) = tag.strip_prefix("an") {
+        if let Ok(value) = value.parse::<u8>() {
+            *alignment = value.clamp(1, 9);
+        }
+    } else if let Some(value) = tag.strip_prefix('a').filter(|value| value.bytes().all(|byte| byte.is_ascii_digit())) {
+        if let Ok(value) = value.parse::<u8>() {
+            *alignment = legacy_alignment(value).clamp(1, 9);
+        }
+    } else if let Some(value) = tag.strip_prefix('p').filter(|value| value.bytes().all(|byte| byte.is_ascii_digit())) {
+        *drawing = value.parse::<u32>().is_ok_and(|scale| scale > 0);
+    } else if let Some(value) = tag.strip_prefix("fn") {
+        format.style.font_family = Some(value.to_string()).filter(|value| !value.is_empty()).or_else(|| style.format.style.font_family.clone());
+    } else if let Some(value) = tag.strip_prefix("fs").filter(|value| value.starts_with(|c: char| c.is_ascii_digit())) {
+        if let Ok(size) = value.parse::<f64>() {
+            format.style.font_size = Some(size / play_res.1 * 100.0);
+        }
+    } else if let Some(value) = tag.strip_prefix("1c").or_else(|| tag.strip_prefix('c')) {
+        // Alpha has its own tags, so keep the current one
+        if let Some(color) = parse_color(value) {
+            let alpha = format.style.color.map(|color| color.alpha).unwrap_or(255);
+            format.style.color = Some(Color { alpha, ..color });
+        }
+    } else if let Some(value) = tag.strip_prefix("3c") {
+        if let Some(color) = parse_color(value) {
+            format.style.outline = Some(color);
+        }
+    } else if let Some(value) = tag.strip_prefix('r') {
+        *format = styles.get(value).unwrap_or(style).format.clone();
+    } else if let Some(value) = tag.strip_prefix('i').filter(|value| value.len() <= 1) {
+        format.italic = flag(value, format.italic);
+    } else if let Some(value) = tag.strip_prefix('b').filter(|value| value.bytes().all(|byte| byte.is_ascii_digit())) {
+        // \b also takes a font weight
+        format.bold = value.parse::<u32>().map(|weight| weight == 1 || weight >= 700).unwrap_or(format.bold);
+    } else if let Some(value) = tag.strip_prefix('u').filter(|value| value.len() <= 1) {
+        format.underline = flag(value, format.underline);
+    }
+}
+
+/// Replaces the `\N` hard break, `\n` soft break and `\h` hard space escapes.
+fn unescape(text: &str) -> String {
+    text.replace("\\N", "\n").replace("\\n", " ").replace("\\h", "\u{a0}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::captions::SpanKind;
+
+    #[test]
+    fn test_parse_styles_and_events() {
+        let list = parse_ass(include_str!("fixtures/styled.ass")).unwrap();
+        assert_eq!(list.cues.len(), 4);
+
+        // Events come back sorted, and the Default style applies
+        let first = &list.cues[0];
+        assert_eq!((first.start, first.end), (1.0, 3.5));
+        assert_eq!(first.plain_text(), "Hello there,\nworld");
+        assert_eq!(first.settings, CueSettings::default());
+        let CueNode::Span { style, .. } = &first.content[0] else {
+            panic!("expected a styled span");
+        };
+        assert_eq!(style.color, Some(Color::rgb(255, 255, 255)));
+        assert_eq!(style.outline, Some(Color::rgb(0, 0, 0)));
+        assert_eq!(style.font_family.as_deref(), Some("Arial"));
+        assert_eq!(style.font_size, Some(10.0));
+
+        // Sign style: top right, yellow with a translucent box, bold
+        let sign = &list.cues[1];
+        assert_eq!(sign.settings.align, TextAlign::Right);
+        assert_eq!(sign.settings.line, Some(Line::Percent(5.0)));
+        let CueNode::Span { style, children, .. } = &sign.content[0] else {
+            panic!("expected a styled span");
+        };
+        assert_eq!(style.color, Some(Color::rgb(255, 255, 0)));
+        assert_eq!(style.background, Some(Color { alpha: 127, ..Color::rgb(0, 0, 0) }));
+        assert_eq!(style.outline, None);
+        assert!(matches!(&children[0], CueNode::Span { kind: SpanKind::Bold, .. }));
+    }
+
+    #[test]
+    fn test_override_tags() {
+        let list = parse_ass(include_str!("fixtures/styled.ass")).unwrap();
+
+        // \pos with \an7 anchors the top left corner at the point
+        let positioned = &list.cues[2];
+        assert_eq!(positioned.plain_text(), "Positioned");
+        assert_eq!(positioned.settings.position, Some(25.0));
+        assert_eq!(positioned.settings.position_align, Some(PositionAlign::LineLeft));
+        assert_eq!(positioned.settings.line, Some(Line::Percent(50.0)));
+        assert_eq!(positioned.settings.line_align, LineAlign::Start);
+
+        // Inline italics and colour changes split the text into runs, drawings are dropped
+        let runs = &list.cues[3];
+        assert_eq!(runs.plain_text(), "plain italic red");
+        assert_eq!(runs.content.len(), 3);
+        let CueNode::Span { style, children, .. } = &runs.content[1] else {
+            panic!("expected a styled span");
+        };
+        assert_eq!(style.color, Some(Color::rgb(255, 255, 255)));
+        assert!(matches!(&children[0], CueNode::Span { kind: SpanKind::Italic, .. }));
+        let CueNode::Span { style, .. } = &runs.content[2] else {
+            panic!("expected a styled span");
+        };
+        assert_eq!(style.color, Some(Color::rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn test_parse_colors_and_alignment() {
+        assert_eq!(parse_color("&H00FF8000"), Some(Color::rgb(0, 128, 255)));
+        assert_eq!(parse_color("&HFF0000FF&"), Some(Color { alpha: 0, ..Color::rgb(255, 0, 0) }));
+        assert_eq!(parse_color("65535"), Some(Color::rgb(255, 255, 0)));
+        assert_eq!(legacy_alignment(6), 8);
+        assert_eq!(legacy_alignment(10), 5);
+        for alignment in 1..=9 {
+            assert_eq!(settings_alignment(&alignment_settings(alignment, 5.0)), alignment);
+        }
+        assert!(matches!(parse_ass("[Script Info]\nTitle: nothing"), Err(VideoError::CaptionParseError(_))));
+    }
+}
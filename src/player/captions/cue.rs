@@ -0,0 +1,577 @@
+/// One timed caption, in the form every subtitle format is converted into.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cue {
+    pub id: Option<String>,
+    /// Seconds from the start of the media.
+    pub start: f64,
+    pub end: f64,
+    pub settings: CueSettings,
+    pub content: Vec<CueNode>,
+}
+
+impl Cue {
+    pub fn is_active(&self, time: f64) -> bool {
+        self.start <= time && time < self.end
+    }
+
+    /// The cue's text without markup.
+    pub fn plain_text(&self) -> String {
+        let mut text = String::new();
+        collect_text(&self.content, &mut text);
+        text
+    }
+}
+
+fn collect_text(nodes: &[CueNode], text: &mut String) {
+    for node in nodes {
+        match node {
+            CueNode::Text(value) => text.push_str(value),
+            CueNode::Span { children, .. } => collect_text(children, text),
+            CueNode::Timestamp(_) => {}
+        }
+    }
+}
+
+/// Cue text after markup parsing.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CueNode {
+    Text(String),
+    Span {
+        kind: SpanKind,
+        classes: Vec<String>,
+        /// The voice name of `<v>` or the language of `<lang>`.
+        annotation: Option<String>,
+        /// Styling from formats that carry it inline, empty for WebVTT.
+        style: TextStyle,
+        children: Vec<CueNode>,
+    },
+    /// An intermediate timestamp for karaoke-style reveals.
+    Timestamp(f64),
+}
+
+#[cfg(test)]
+impl CueNode {
+    pub fn text(value: &str) -> CueNode {
+        CueNode::Text(value.to_string())
+    }
+}
+
+impl CueNode {
+    pub fn span(kind: SpanKind, children: Vec<CueNode>) -> CueNode {
+        CueNode::Span {
+            kind,
+            classes: Vec::new(),
+            annotation: None,
+            style: TextStyle::default(),
+            children,
+        }
+    }
+
+    /// A plain span carrying only styling.
+    pub fn styled(style: TextStyle, children: Vec<CueNode>) -> CueNode {
+        CueNode::Span {
+            kind: SpanKind::Class,
+            classes: Vec::new(),
+            annotation: None,
+            style,
+            children,
+        }
+    }
+}
+
+/// An sRGB colour with alpha.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl Color {
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color { red, green, blue, alpha: 255 }
+    }
+
+    /// Parses `#rgb`, `#rrggbb`, `#rrggbbaa`, `rgb()`, `rgba()` and the named colours
+    /// TTML defines, which are the forms subtitle files use.
+    pub fn parse(value: &str) -> Option<Color> {
+        let value = value.trim();
+        if let Some(hex) = value.strip_prefix('#') {
+            let channel = |index: usize, width: usize| u8::from_str_radix(hex.get(index..index + width)?, 16).ok();
+            return match hex.len() {
+                3 => Some(Color::rgb(channel(0, 1)? * 17, channel(1, 1)? * 17, channel(2, 1)? * 17)),
+                6 => Some(Color::rgb(channel(0, 2)?, channel(2, 2)?, channel(4, 2)?)),
+                8 => Some(Color { alpha: channel(6, 2)?, ..Color::rgb(channel(0, 2)?, channel(2, 2)?, channel(4, 2)?) }),
+                _ => None,
+            };
+        }
+        let lower = value.to_ascii_lowercase();
+        if let Some(arguments) = lower.strip_prefix("rgba(").or_else(|| lower.strip_prefix("rgb(")) {
+            let channels = arguments
+                .strip_suffix(')')?
+                .split(',')
+                .map(|channel| channel.trim().parse::<u8>().ok())
+                .collect::<Option<Vec<u8>>>()?;
+            return match channels[..] {
+                [red, green, blue] => Some(Color::rgb(red, green, blue)),
+                [red, green, blue, alpha] => Some(Color { red, green, blue, alpha }),
+                _ => None,
+            };
+        }
+        let (red, green, blue) = match lower.as_str() {
+            "transparent" => return Some(Color { alpha: 0, ..Color::rgb(0, 0, 0) }),
+            "black" => (0, 0, 0),
+            "silver" => (192, 192, 192),
+            "gray" => (128, 128, 128),
+            "white" => (255, 255, 255),
+            "maroon" => (128, 0, 0),
+            "red" => (255, 0, 0),
+            "purple" => (128, 0, 128),
+            "fuchsia" | "magenta" => (255, 0, 255),
+            "green" => (0, 128, 0),
+            "lime" => (0, 255, 0),
+            "olive" => (128, 128, 0),
+            "yellow" => (255, 255, 0),
+            "navy" => (0, 0, 128),
+            "blue" => (0, 0, 255),
+            "teal" => (0, 128, 128),
+            "aqua" | "cyan" => (0, 255, 255),
+            _ => return None,
+        };
+        Some(Color::rgb(red, green, blue))
+    }
+
+    pub fn css(&self) -> String {
+        if self.alpha == 255 {
+            return self.hex();
+        }
+        format!("rgba({}, {}, {}, {})", self.red, self.green, self.blue, round(self.alpha as f64 / 255.0))
+    }
+
+    /// `#rrggbb`, dropping alpha.
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+/// Inline styling that ASS, TTML and SubRip `<font>` tags describe and WebVTT leaves to
+/// style sheets. Unset values inherit from the overlay.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextStyle {
+    pub color: Option<Color>,
+    pub background: Option<Color>,
+    pub outline: Option<Color>,
+    pub font_family: Option<String>,
+    /// Percentage of the video height.
+    pub font_size: Option<f64>,
+}
+
+impl TextStyle {
+    pub fn is_empty(&self) -> bool {
+        *self == TextStyle::default()
+    }
+
+    pub fn css(&self) -> String {
+        let mut declarations = Vec::new();
+        if let Some(color) = self.color {
+            declarations.push(format!("color: {};", color.css()));
+        }
+        if let Some(background) = self.background {
+            declarations.push(format!("background-color: {};", background.css()));
+        }
+        if let Some(outline) = self.outline {
+            let outline = outline.css();
+            declarations.push(format!("text-shadow: -1px -1px 0 {0}, 1px -1px 0 {0}, -1px 1px 0 {0}, 1px 1px 0 {0};", outline));
+        }
+        if let Some(font_family) = &self.font_family {
+            declarations.push(format!("font-family: \"{}\";", font_family.replace(['"', '\\'], "")));
+        }
+        if let Some(font_size) = self.font_size {
+            // The overlay is a size container, so this scales with the video
+            declarations.push(format!("font-size: {}cqh;", round(font_size)));
+        }
+        declarations.join(" ")
+    }
+}
+
+/// Formatting of one run of text, for formats that switch styles between runs rather
+/// than nesting markup.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunFormat {
+    pub italic: bool,
+    pub bold: bool,
+    pub underline: bool,
+    pub style: TextStyle,
+}
+
+/// Builds cue text from formatted runs, merging neighbours that share a format.
+pub fn nodes_from_runs(runs: Vec<(RunFormat, String)>) -> Vec<CueNode> {
+    let mut merged: Vec<(RunFormat, String)> = Vec::new();
+    for (format, text) in runs.into_iter().filter(|(_, text)| !text.is_empty()) {
+        match merged.last_mut() {
+            Some((previous, previous_text)) if *previous == format => previous_text.push_str(&text),
+            _ => merged.push((format, text)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(format, text)| {
+            let mut node = CueNode::Text(text);
+            if format.underline {
+                node = CueNode::span(SpanKind::Underline, vec![node]);
+            }
+            if format.italic {
+                node = CueNode::span(SpanKind::Italic, vec![node]);
+            }
+            if format.bold {
+                node = CueNode::span(SpanKind::Bold, vec![node]);
+            }
+            if !format.style.is_empty() {
+                node = CueNode::styled(format.style, vec![node]);
+            }
+            node
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+    Class,
+    Italic,
+    Bold,
+    Underline,
+    Ruby,
+    RubyText,
+    Voice,
+    Language,
+}
+
+impl SpanKind {
+    pub fn from_tag(tag: &str) -> Option<SpanKind> {
+        match tag {
+            "c" => Some(SpanKind::Class),
+            "i" => Some(SpanKind::Italic),
+            "b" => Some(SpanKind::Bold),
+            "u" => Some(SpanKind::Underline),
+            "ruby" => Some(SpanKind::Ruby),
+            "rt" => Some(SpanKind::RubyText),
+            "v" => Some(SpanKind::Voice),
+            "lang" => Some(SpanKind::Language),
+            _ => None,
+        }
+    }
+
+    /// The HTML element a span renders as.
+    pub fn element_name(&self) -> &'static str {
+        match self {
+            SpanKind::Italic => "i",
+            SpanKind::Bold => "b",
+            SpanKind::Underline => "u",
+            SpanKind::Ruby => "ruby",
+            SpanKind::RubyText => "rt",
+            SpanKind::Class | SpanKind::Voice | SpanKind::Language => "span",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WritingDirection {
+    Horizontal,
+    VerticalRightToLeft,
+    VerticalLeftToRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Line {
+    /// Percentage of the video height.
+    Percent(f64),
+    /// Line number, counted from the bottom when negative.
+    Number(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineAlign {
+    Start,
+    Center,
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionAlign {
+    LineLeft,
+    Center,
+    LineRight,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    Start,
+    #[default]
+    Center,
+    End,
+    Left,
+    Right,
+}
+
+impl TextAlign {
+    pub fn as_css(&self) -> &'static str {
+        match self {
+            TextAlign::Start => "start",
+            TextAlign::Center => "center",
+            TextAlign::End => "end",
+            TextAlign::Left => "left",
+            TextAlign::Right => "right",
+        }
+    }
+}
+
+/// Cue placement; unset values fall back to the WebVTT defaults.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CueSettings {
+    pub vertical: WritingDirection,
+    pub line: Option<Line>,
+    pub line_align: LineAlign,
+    /// Percentage across the video, `None` for auto.
+    pub position: Option<f64>,
+    pub position_align: Option<PositionAlign>,
+    /// Percentage of the video width the cue box takes.
+    pub size: f64,
+    pub align: TextAlign,
+    pub region: Option<String>,
+}
+
+impl Default for CueSettings {
+    fn default() -> Self {
+        CueSettings {
+            vertical: WritingDirection::Horizontal,
+            line: None,
+            line_align: LineAlign::Start,
+            position: None,
+            position_align: None,
+            size: 100.0,
+            align: TextAlign::Center,
+            region: None,
+        }
+    }
+}
+
+impl CueSettings {
+    /// The position used when none is set, derived from the text alignment.
+    pub fn computed_position(&self) -> f64 {
+        self.position.unwrap_or(match self.align {
+            TextAlign::Start | TextAlign::Left => 0.0,
+            TextAlign::End | TextAlign::Right => 100.0,
+            TextAlign::Center => 50.0,
+        })
+    }
+
+    pub fn computed_position_align(&self) -> PositionAlign {
+        self.position_align.unwrap_or(match self.align {
+            TextAlign::Start | TextAlign::Left => PositionAlign::LineLeft,
+            TextAlign::End | TextAlign::Right => PositionAlign::LineRight,
+            TextAlign::Center => PositionAlign::Center,
+        })
+    }
+
+    /// Offset and size of the cue box along the line, in percent.
+    pub fn inline_box(&self) -> (f64, f64) {
+        let position = self.computed_position();
+        // The box cannot extend past the edge its position is measured from
+        let max_size = match self.computed_position_align() {
+            PositionAlign::LineLeft => 100.0 - position,
+            PositionAlign::LineRight => position,
+            PositionAlign::Center => 2.0 * position.min(100.0 - position),
+        };
+        let size = self.size.min(max_size).max(0.0);
+        let offset = match self.computed_position_align() {
+            PositionAlign::LineLeft => position,
+            PositionAlign::LineRight => position - size,
+            PositionAlign::Center => position - size / 2.0,
+        };
+        (offset, size)
+    }
+
+    /// Whether the cue is stacked with others above the bottom edge rather than placed.
+    pub fn is_auto_line(&self) -> bool {
+        self.line.is_none() && self.vertical == WritingDirection::Horizontal
+    }
+
+    /// Inline CSS for a cue stacked at the bottom, which only needs its horizontal box.
+    pub fn stacked_style(&self) -> String {
+        let (offset, size) = self.inline_box();
+        format!("position: relative; left: {}%; width: {}%; text-align: {};", round(offset), round(size), self.align.as_css())
+    }
+
+    /// Inline CSS placing the cue box over the video, following the WebVTT rendering
+    /// rules for horizontal cues and a simplified version of them for vertical ones.
+    pub fn box_style(&self) -> String {
+        let (offset, size) = self.inline_box();
+        let (inline_start, inline_size, block_edge) = match self.vertical {
+            WritingDirection::Horizontal => ("left", "width", "top"),
+            WritingDirection::VerticalRightToLeft => ("top", "height", "right"),
+            WritingDirection::VerticalLeftToRight => ("top", "height", "left"),
+        };
+        let mut style = format!(
+            "position: absolute; {}: {}%; {}: {}%; text-align: {};",
+            inline_start,
+            round(offset),
+            inline_size,
+            round(size),
+            self.align.as_css()
+        );
+        match self.vertical {
+            WritingDirection::Horizontal => {}
+            WritingDirection::VerticalRightToLeft => style.push_str(" writing-mode: vertical-rl;"),
+            WritingDirection::VerticalLeftToRight => style.push_str(" writing-mode: vertical-lr;"),
+        }
+
+        let bottom_edge = match self.vertical {
+            WritingDirection::Horizontal => "bottom",
+            WritingDirection::VerticalRightToLeft => "left",
+            WritingDirection::VerticalLeftToRight => "right",
+        };
+        match self.line {
+            Some(Line::Percent(percent)) => {
+                let translate = match self.line_align {
+                    LineAlign::Start => 0.0,
+                    LineAlign::Center => -50.0,
+                    LineAlign::End => -100.0,
+                };
+                style.push_str(&format!(" {}: {}%;", block_edge, round(percent)));
+                if translate != 0.0 && self.vertical == WritingDirection::Horizontal {
+                    style.push_str(&format!(" transform: translateY({}%);", translate));
+                }
+            }
+            Some(Line::Number(number)) if number >= 0.0 => {
+                style.push_str(&format!(" {}: calc({} * var(--caption-line-height, 1.5em));", block_edge, number));
+            }
+            Some(Line::Number(number)) => {
+                style.push_str(&format!(" {}: calc({} * var(--caption-line-height, 1.5em));", bottom_edge, -number - 1.0));
+            }
+            // Vertical cues without a line start at their block edge
+            None => style.push_str(&format!(" {}: 0;", block_edge)),
+        }
+        style
+    }
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// A WebVTT region: a box cues can be placed in and scrolled up through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Region {
+    pub id: String,
+    /// Percentage of the video width.
+    pub width: f64,
+    pub lines: u32,
+    /// Point of the region (percent of its own size) pinned to `viewport_anchor`.
+    pub region_anchor: (f64, f64),
+    /// Point of the video (percent) the region is pinned to.
+    pub viewport_anchor: (f64, f64),
+    pub scroll_up: bool,
+    /// Where cues sit in the region's box; TTML's `displayAlign`.
+    pub display_align: LineAlign,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region {
+            id: String::new(),
+            width: 100.0,
+            lines: 3,
+            region_anchor: (0.0, 100.0),
+            viewport_anchor: (0.0, 100.0),
+            scroll_up: false,
+            display_align: LineAlign::Start,
+        }
+    }
+}
+
+impl Region {
+    pub fn box_style(&self) -> String {
+        let left = self.viewport_anchor.0 - self.region_anchor.0 * self.width / 100.0;
+        let mut style = format!(
+            "position: absolute; left: {}%; top: {}%; width: {}%; height: calc({} * var(--caption-line-height, 1.5em)); transform: translateY(-{}%);",
+            round(left),
+            round(self.viewport_anchor.1),
+            round(self.width),
+            self.lines,
+            round(self.region_anchor.1)
+        );
+        match self.display_align {
+            LineAlign::Start => {}
+            LineAlign::Center => style.push_str(" display: flex; flex-direction: column; justify-content: center;"),
+            LineAlign::End => style.push_str(" display: flex; flex-direction: column; justify-content: flex-end;"),
+        }
+        style
+    }
+}
+
+/// The cues of one subtitle file.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CueList {
+    pub cues: Vec<Cue>,
+    pub regions: Vec<Region>,
+    /// Style sheets embedded in the file, kept as written.
+    pub styles: Vec<String>,
+}
+
+impl CueList {
+    pub fn active_cues(&self, time: f64) -> Vec<&Cue> {
+        self.cues.iter().filter(|cue| cue.is_active(time)).collect()
+    }
+
+    pub fn active_indices(&self, time: f64) -> Vec<usize> {
+        (0..self.cues.len()).filter(|index| self.cues[*index].is_active(time)).collect()
+    }
+
+    pub fn region(&self, id: &str) -> Option<&Region> {
+        self.regions.iter().find(|region| region.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_box_is_centered_full_width() {
+        let settings = CueSettings::default();
+        assert!(settings.is_auto_line());
+        assert_eq!(settings.stacked_style(), "position: relative; left: 0%; width: 100%; text-align: center;");
+    }
+
+    #[test]
+    fn test_box_style_clamps_size_to_position() {
+        let settings = CueSettings {
+            position: Some(80.0),
+            size: 50.0,
+            align: TextAlign::Start,
+            line: Some(Line::Percent(10.0)),
+            ..CueSettings::default()
+        };
+        assert_eq!(settings.box_style(), "position: absolute; left: 80%; width: 20%; text-align: start; top: 10%;");
+
+        let settings = CueSettings {
+            line: Some(Line::Number(-2.0)),
+            size: 40.0,
+            ..CueSettings::default()
+        };
+        assert!(settings.box_style().starts_with("position: absolute; left: 30%; width: 40%;"));
+        assert!(settings.box_style().ends_with("bottom: calc(1 * var(--caption-line-height, 1.5em));"));
+    }
+
+    #[test]
+    fn test_parse_colors() {
+        assert_eq!(Color::parse("#FF0"), Some(Color::rgb(255, 255, 0)));
+        assert_eq!(Color::parse("#102030"), Some(Color::rgb(16, 32, 48)));
+        assert_eq!(Color::parse("#10203080").map(|color| color.css()), Some("rgba(16, 32, 48, 0.502)".to_string()));
+        assert_eq!(Color::parse(" rgba(0, 0, 0, 128) "), Some(Color { alpha: 128, ..Color::rgb(0, 0, 0) }));
+        assert_eq!(Color::parse("Cyan"), Some(Color::rgb(0, 255, 255)));
+        assert_eq!(Color::parse("#12345"), None);
+        assert_eq!(Color::parse("rgb(300, 0, 0)"), None);
+    }
+}
@@ -0,0 +1,560 @@
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{Document, Element};
+use crate::logger::Logger;
+use crate::player::config::TextTrackSource;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::events::{publish_for, PlayerEvent};
+use crate::player::fetch::fetch_text;
+use crate::player::get_element_by_action;
+use crate::player::menu::hide_menus_for;
+use crate::player::preferences::{preferences_for, update_preferences_for};
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
+
+pub mod ass;
+pub mod cue;
+pub mod srt;
+pub mod ttml;
+pub mod webvtt;
+pub use ass::parse_ass;
+pub use cue::{CueList, CueNode, SpanKind};
+pub use srt::parse_srt;
+pub use ttml::parse_ttml;
+pub use webvtt::parse_webvtt;
+
+// Context menu item that opens the captions menu
+pub const MENU_ACTION_CAPTIONS: &str = "captions";
+// Attribute holding an option's track index, or "off"
+const CAPTION_ATTRIBUTE: &str = "data-caption";
+const CAPTION_OFF: &str = "off";
+
+/// Subtitle formats the captions menu can load.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptionFormat {
+    WebVtt,
+    SubRip,
+    Ttml,
+    Ass,
+}
+
+/// Works out a subtitle file's format from its contents, since file extensions and
+/// content types in the archive cannot be relied on.
+pub fn detect_format(text: &str) -> Option<CaptionFormat> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text).trim_start();
+    if text.starts_with("WEBVTT") {
+        return Some(CaptionFormat::WebVtt);
+    }
+    if text.starts_with('<') {
+        // Skip the XML declaration, comments and doctype to reach the root element
+        let root = text.match_indices('<').map(|(index, _)| &text[index + 1..]).find(|tag| !tag.starts_with(['?', '!']))?;
+        let name = root.split(|c: char| c.is_whitespace() || c == '>' || c == '/').next()?;
+        return (name.rsplit(':').next() == Some("tt")).then_some(CaptionFormat::Ttml);
+    }
+    let head: String = text.chars().take(4096).collect::<String>().to_ascii_lowercase();
+    if head.starts_with("[script info]") || (head.contains("[events]") && head.contains("dialogue:")) {
+        return Some(CaptionFormat::Ass);
+    }
+    // A SubRip block opens with a counter or goes straight to its timing line
+    let mut lines = text.lines().map(str::trim);
+    let first = lines.next()?;
+    let timing = if first.bytes().all(|byte| byte.is_ascii_digit()) { lines.next()? } else { first };
+    timing.contains("-->").then_some(CaptionFormat::SubRip)
+}
+
+/// Parses a subtitle file of any supported format into cues.
+pub fn parse_captions(text: &str) -> Result<CueList, VideoError> {
+    match detect_format(text) {
+        Some(CaptionFormat::WebVtt) => parse_webvtt(text),
+        Some(CaptionFormat::SubRip) => parse_srt(text),
+        Some(CaptionFormat::Ttml) => parse_ttml(text),
+        Some(CaptionFormat::Ass) => parse_ass(text),
+        None => Err(VideoError::CaptionParseError("unrecognised subtitle format".to_string())),
+    }
+}
+
+/// Converts a subtitle file of any supported format to WebVTT (`"vtt"`) or SubRip (`"srt"`).
+#[wasm_bindgen]
+pub fn convert_captions(text: &str, target: &str) -> Result<String, JsValue> {
+    let list = parse_captions(text)?;
+    match target {
+        "vtt" => Ok(webvtt::write_webvtt(&list)),
+        "srt" => Ok(srt::write_srt(&list)),
+        _ => Err(VideoError::CaptionParseError(format!("cannot convert to {}", target)).into()),
+    }
+}
+
+/// A subtitle track and its cues, which stay empty until the file has loaded.
+#[derive(Clone, Debug)]
+pub struct CaptionTrack {
+    pub language: String,
+    pub label: Option<String>,
+    pub cues: CueList,
+}
+
+impl CaptionTrack {
+    pub fn display_label(&self) -> String {
+        self.label.clone().unwrap_or_else(|| self.language.clone())
+    }
+}
+
+/// Caption tracks of one player and what its overlay currently shows.
+#[derive(Default)]
+pub struct CaptionState {
+    pub tracks: Vec<CaptionTrack>,
+    pub overlay: Option<Element>,
+    // Indices of the cues on screen, so unchanged frames are not rebuilt
+    rendered: Option<(usize, Vec<usize>)>,
+}
+
+impl CaptionState {
+    /// Forgets the tracks of the previous media, keeping the overlay for the next.
+    pub fn clear_tracks(&mut self) {
+        self.tracks.clear();
+        self.rendered = None;
+        if let Some(overlay) = &self.overlay {
+            overlay.set_text_content(None);
+        }
+    }
+}
+
+/// The `data-caption` value for a selection.
+pub fn caption_value(track: Option<usize>) -> String {
+    track.map(|track| track.to_string()).unwrap_or_else(|| CAPTION_OFF.to_string())
+}
+
+/// Reads a `data-caption` value back; `None` if it is neither "off" nor an index.
+pub fn parse_caption_value(value: &str) -> Option<Option<usize>> {
+    if value == CAPTION_OFF {
+        return Some(None);
+    }
+    value.parse().ok().map(Some)
+}
+
+#[wasm_bindgen]
+pub fn toggle_captions() -> Result<bool, JsValue> {
+    toggle_captions_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn set_caption_track(track: Option<usize>) -> Result<(), JsValue> {
+    set_caption_track_for(&default_context(), track)
+}
+
+#[wasm_bindgen]
+pub fn get_caption_track() -> Option<usize> {
+    get_caption_track_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn get_caption_text() -> Option<String> {
+    get_caption_text_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn update_caption_active_state(track: Option<usize>) -> Result<(), JsValue> {
+    update_caption_active_state_for(&default_context(), track)
+}
+
+/// The track to show for a preferred language: an exact match of the BCP 47 tag,
+/// ignoring case, or else the first track in the same primary language.
+pub fn preferred_track(languages: &[&str], preferred: &str) -> Option<usize> {
+    let primary = |tag: &str| tag.split(['-', '_']).next().unwrap_or(tag).to_ascii_lowercase();
+    languages
+        .iter()
+        .position(|language| language.eq_ignore_ascii_case(preferred))
+        .or_else(|| languages.iter().position(|language| primary(language) == primary(preferred)))
+}
+
+/// Turns on the track in the language the viewer last chose captions in, if any.
+pub fn apply_caption_preference_for(context: &PlayerContext) -> Result<(), VideoError> {
+    let Some(preferred) = preferences_for(context).caption_language else {
+        return Ok(());
+    };
+    let track = {
+        let captions = context.captions.borrow();
+        let languages: Vec<&str> = captions.tracks.iter().map(|track| track.language.as_str()).collect();
+        preferred_track(&languages, &preferred)
+    };
+    if let Some(track) = track {
+        dispatch_for(context, Action::CaptionTrackSelected { track: Some(track) })?;
+    }
+    Ok(())
+}
+
+/// Registers the configured tracks and loads each of them in the background.
+pub fn load_caption_tracks(context: &Rc<PlayerContext>, sources: &[TextTrackSource]) {
+    let first = {
+        let mut captions = context.captions.borrow_mut();
+        let first = captions.tracks.len();
+        captions.tracks.extend(sources.iter().map(|source| CaptionTrack {
+            language: source.language.clone(),
+            label: source.label.clone(),
+            cues: CueList::default(),
+        }));
+        first
+    };
+    let generation = context.media_generation();
+    for (offset, source) in sources.iter().enumerate() {
+        let weak = Rc::downgrade(context);
+        let url = source.url.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = match fetch_text(&url).await {
+                Ok(text) => parse_captions(&text),
+                Err(error) => Err(error),
+            };
+            // Captions of media the player has since moved on from are dropped
+            let Some(context) = weak.upgrade().filter(|context| context.media_generation() == generation) else {
+                return;
+            };
+            match result {
+                Ok(cues) => {
+                    if let Some(track) = context.captions.borrow_mut().tracks.get_mut(first + offset) {
+                        track.cues = cues;
+                    }
+                    render_captions_for(&context).unwrap_or_default();
+                }
+                Err(error) => {
+                    report_error_for(&context, &error).unwrap_or_default();
+                }
+            }
+        });
+    }
+}
+
+pub fn toggle_captions_for(context: &PlayerContext) -> Result<bool, JsValue> {
+    Logger::trace("Entering toggle_captions()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let state = context.state().inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    let track = match state.caption_track {
+        Some(_) => None,
+        None => Some(state.last_caption_track),
+    };
+    set_caption_track_for(context, track)?;
+    Ok(get_caption_track_for(context).is_some())
+}
+
+/// Shows captions from `track`, or turns them off with `None`.
+pub fn set_caption_track_for(context: &PlayerContext, track: Option<usize>) -> Result<(), JsValue> {
+    Logger::trace("Entering set_caption_track()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let track_count = context.captions.borrow().tracks.len();
+    if track.is_some_and(|track| track >= track_count) {
+        return Ok(());
+    }
+    dispatch_for(context, Action::CaptionTrackSelected { track }).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    let language = track.and_then(|track| context.captions.borrow().tracks.get(track).map(|track| track.language.clone()));
+    update_preferences_for(context, |preferences| preferences.caption_language = language.clone());
+    publish_for(context, PlayerEvent::CaptionChange { track, language });
+    hide_menus_for(context)?;
+    Ok(())
+}
+
+pub fn get_caption_track_for(context: &PlayerContext) -> Option<usize> {
+    context.state().ok().and_then(|state| state.caption_track)
+}
+
+/// Plain text of the cues on screen, one per line, or `None` when captions are off.
+pub fn get_caption_text_for(context: &PlayerContext) -> Option<String> {
+    let track = get_caption_track_for(context)?;
+    let time = context.video_element().ok()?.current_time();
+    let captions = context.captions.borrow();
+    let cues = &captions.tracks.get(track)?.cues;
+    let text: Vec<String> = cues.active_cues(time).iter().map(|cue| cue.plain_text()).collect();
+    Some(text.join("\n"))
+}
+
+/// Draws the cues of the selected track that are active at the current time.
+pub fn render_captions_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let track = get_caption_track_for(context);
+    if track.is_none() && context.captions.borrow().overlay.is_none() {
+        return Ok(());
+    }
+    let video_element = context.video_element()?;
+    let time = video_element.current_time();
+
+    let mut captions = context.captions.borrow_mut();
+    let active: Vec<usize> = track
+        .and_then(|track| captions.tracks.get(track))
+        .map(|track| track.cues.active_indices(time))
+        .unwrap_or_default();
+    let overlay = match captions.overlay.clone() {
+        Some(overlay) => overlay,
+        None => {
+            let overlay = create_overlay(context, &video_element)?;
+            captions.overlay = Some(overlay.clone());
+            overlay
+        }
+    };
+
+    // Keep the overlay over the video's box, which moves with layout and fullscreen
+    overlay.set_attribute("style", &format!(
+        "top: {}px; left: {}px; width: {}px; height: {}px;",
+        video_element.offset_top(),
+        video_element.offset_left(),
+        video_element.offset_width(),
+        video_element.offset_height()
+    ))?;
+
+    let rendered = track.map(|track| (track, active.clone()));
+    if captions.rendered == rendered {
+        return Ok(());
+    }
+    overlay.set_text_content(None);
+    if let Some(track) = track.and_then(|track| captions.tracks.get(track)) {
+        let document = web_sys::window().ok_or(VideoError::WindowNotFound)?.document().ok_or(VideoError::DocumentNotFound)?;
+        render_cues(context, &document, &overlay, &track.cues, &active)?;
+    }
+    captions.rendered = rendered;
+    Ok(())
+}
+
+fn create_overlay(context: &PlayerContext, video_element: &Element) -> Result<Element, JsValue> {
+    let document = web_sys::window().ok_or(VideoError::WindowNotFound)?.document().ok_or(VideoError::DocumentNotFound)?;
+    let overlay = document.create_element("div")?;
+    overlay.set_attribute("class", &context.classes.caption_overlay)?;
+    overlay.set_attribute("aria-live", "polite")?;
+    video_element.after_with_node_1(&overlay)?;
+    context.track_element(overlay.clone());
+    Ok(overlay)
+}
+
+/// Auto-positioned cues stack at the bottom, region cues go into their region's box
+/// and the rest are placed absolutely.
+fn render_cues(context: &PlayerContext, document: &Document, overlay: &Element, list: &CueList, active: &[usize]) -> Result<(), JsValue> {
+    let stack = document.create_element("div")?;
+    stack.set_attribute("class", &context.classes.caption_stack)?;
+    let mut regions: Vec<(String, Element)> = Vec::new();
+
+    for cue in active.iter().map(|index| &list.cues[*index]) {
+        let cue_element = document.create_element("div")?;
+        cue_element.set_attribute("class", &context.classes.caption_cue)?;
+        let text = document.create_element("span")?;
+        append_nodes(document, &text, &cue.content)?;
+        cue_element.append_child(&text)?;
+
+        match cue.settings.region.as_deref().and_then(|id| list.region(id)) {
+            Some(region) => {
+                let region_element = match regions.iter().find(|(id, _)| *id == region.id) {
+                    Some((_, element)) => element.clone(),
+                    None => {
+                        let element = document.create_element("div")?;
+                        element.set_attribute("class", &context.classes.caption_region)?;
+                        element.set_attribute("style", &region.box_style())?;
+                        overlay.append_child(&element)?;
+                        regions.push((region.id.clone(), element.clone()));
+                        element
+                    }
+                };
+                cue_element.set_attribute("style", &format!("text-align: {};", cue.settings.align.as_css()))?;
+                region_element.append_child(&cue_element)?;
+            }
+            None if cue.settings.is_auto_line() => {
+                cue_element.set_attribute("style", &cue.settings.stacked_style())?;
+                stack.append_child(&cue_element)?;
+            }
+            None => {
+                cue_element.set_attribute("style", &cue.settings.box_style())?;
+                overlay.append_child(&cue_element)?;
+            }
+        }
+    }
+    overlay.append_child(&stack)?;
+    Ok(())
+}
+
+fn append_nodes(document: &Document, parent: &Element, nodes: &[CueNode]) -> Result<(), JsValue> {
+    for node in nodes {
+        match node {
+            CueNode::Text(text) => {
+                for (index, line) in text.split('\n').enumerate() {
+                    if index > 0 {
+                        let line_break = document.create_element("br")?;
+                        parent.append_with_node_1(&line_break)?;
+                    }
+                    parent.append_with_str_1(line)?;
+                }
+            }
+            CueNode::Span { kind, classes, annotation, style, children } => {
+                let element = document.create_element(kind.element_name())?;
+                if !classes.is_empty() {
+                    element.set_attribute("class", &classes.join(" "))?;
+                }
+                if !style.is_empty() {
+                    element.set_attribute("style", &style.css())?;
+                }
+                match (kind, annotation) {
+                    (SpanKind::Voice, Some(voice)) => element.set_attribute("data-voice", voice)?,
+                    (SpanKind::Language, Some(language)) => element.set_attribute("lang", language)?,
+                    _ => {}
+                }
+                append_nodes(document, &element, children)?;
+                parent.append_child(&element)?;
+            }
+            CueNode::Timestamp(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Rebuilds the options of the captions menu: "Off" followed by every track.
+pub fn render_captions_menu_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
+    let document = window.document().ok_or(VideoError::DocumentNotFound)?;
+    let captions_menu = context.element(&context.ids.captions_menu)?;
+
+    let mut options = vec![(None, "Off".to_string())];
+    options.extend(context.captions.borrow().tracks.iter().enumerate().map(|(index, track)| (Some(index), track.display_label())));
+
+    captions_menu.set_text_content(None);
+    for (track, label) in options {
+        let element = document.create_element("div")?;
+        element.set_attribute("class", &context.classes.caption_option)?;
+        element.set_attribute(CAPTION_ATTRIBUTE, &caption_value(track))?;
+        element.set_text_content(Some(&label));
+        captions_menu.append_child(&element)?;
+    }
+    update_caption_active_state_for(context, get_caption_track_for(context))
+}
+
+/// Shows the context menu's captions item only when there are tracks to pick from.
+pub fn update_captions_menu_item_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Ok(captions_item) = get_element_by_action(&context.ids.context_menu, MENU_ACTION_CAPTIONS) else {
+        return Ok(());
+    };
+    let available = !context.captions.borrow().tracks.is_empty();
+    captions_item.toggle_attribute_with_force("hidden", !available)?;
+    Ok(())
+}
+
+/// Returns the selection an element inside the captions menu stands for.
+pub fn caption_of(element: &Element) -> Option<Option<usize>> {
+    let option = element.closest(&format!("[{}]", CAPTION_ATTRIBUTE)).ok()??;
+    parse_caption_value(&option.get_attribute(CAPTION_ATTRIBUTE)?)
+}
+
+pub fn update_caption_active_state_for(context: &PlayerContext, track: Option<usize>) -> Result<(), JsValue> {
+    Logger::trace("Entering update_caption_active_state()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let element_classes = &context.classes;
+    let Ok(captions_menu) = context.element(&context.ids.captions_menu) else {
+        return Ok(());
+    };
+    let caption_options = captions_menu.query_selector_all(&format!(".{}", element_classes.caption_option))
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to get caption options: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    let selected = caption_value(track);
+    for i in 0..caption_options.length() {
+        let option = caption_options.get(i)
+            .ok_or_else(|| {
+                let error = VideoError::VideoOperationFailed("Failed to get caption option".to_string());
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?
+            .dyn_into::<Element>()
+            .map_err(|e| {
+                let error = VideoError::VideoOperationFailed(format!("Failed to convert Node to Element: {:?}", e));
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?;
+
+        let class_name = if option.get_attribute(CAPTION_ATTRIBUTE).as_deref() == Some(selected.as_str()) {
+            &element_classes.caption_option_active
+        } else {
+            &element_classes.caption_option
+        };
+        option.set_attribute("class", class_name)
+            .map_err(|e| {
+                let error = VideoError::VideoOperationFailed(format!("Failed to set caption option class: {:?}", e));
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caption_values_round_trip() {
+        for track in [None, Some(0), Some(2)] {
+            assert_eq!(parse_caption_value(&caption_value(track)), Some(track));
+        }
+        assert_eq!(parse_caption_value("on"), None);
+    }
+
+    #[test]
+    fn test_preferred_track() {
+        let languages = ["en", "pt-BR", "pt-PT", "de"];
+        assert_eq!(preferred_track(&languages, "pt-pt"), Some(2));
+        assert_eq!(preferred_track(&languages, "pt"), Some(1));
+        assert_eq!(preferred_track(&languages, "en-GB"), Some(0));
+        assert_eq!(preferred_track(&languages, "fr"), None);
+        assert_eq!(preferred_track(&[], "en"), None);
+    }
+
+    #[test]
+    fn test_detect_format_from_content() {
+        let cases = [
+            (include_str!("fixtures/basic.vtt"), Some(CaptionFormat::WebVtt)),
+            (include_str!("fixtures/basic.srt"), Some(CaptionFormat::SubRip)),
+            (include_str!("fixtures/imsc.ttml"), Some(CaptionFormat::Ttml)),
+            (include_str!("fixtures/styled.ass"), Some(CaptionFormat::Ass)),
+            ("\u{feff}\r\n00:00:01,000 --> 00:00:02,000\r\nNo counter", Some(CaptionFormat::SubRip)),
+            ("<!-- exported --><tt:tt xmlns:tt=\"http://www.w3.org/ns/ttml\"/>", Some(CaptionFormat::Ttml)),
+            ("<html><body>Not found</body></html>", None),
+            ("Just some text", None),
+        ];
+        for (text, format) in cases {
+            assert_eq!(detect_format(text), format, "{}", text);
+        }
+        assert!(matches!(parse_captions("{}"), Err(VideoError::CaptionParseError(_))));
+    }
+
+    #[test]
+    fn test_convert_between_formats() {
+        // SubRip and WebVTT describe the same cues
+        let srt = parse_captions(include_str!("fixtures/basic.srt")).unwrap();
+        let vtt = parse_captions(&webvtt::write_webvtt(&srt)).unwrap();
+        assert_eq!(vtt.cues.len(), srt.cues.len());
+        for (converted, original) in vtt.cues.iter().zip(&srt.cues) {
+            assert_eq!((converted.start, converted.end), (original.start, original.end));
+            assert_eq!(converted.plain_text(), original.plain_text());
+            assert_eq!(converted.settings, original.settings);
+        }
+        assert_eq!(srt::write_srt(&parse_srt(&srt::write_srt(&vtt)).unwrap()), srt::write_srt(&vtt));
+
+        // ASS positions and TTML regions survive the trip through WebVTT
+        let ass = parse_captions(include_str!("fixtures/styled.ass")).unwrap();
+        let converted = parse_webvtt(&webvtt::write_webvtt(&ass)).unwrap();
+        for (converted, original) in converted.cues.iter().zip(&ass.cues) {
+            assert_eq!(converted.plain_text(), original.plain_text());
+            assert_eq!(converted.settings, original.settings);
+        }
+        let ttml = parse_captions(include_str!("fixtures/imsc.ttml")).unwrap();
+        let converted = parse_webvtt(&webvtt::write_webvtt(&ttml)).unwrap();
+        assert_eq!(converted.region("bottom").map(|region| region.viewport_anchor), Some((10.0, 70.0)));
+        assert_eq!(converted.cues[0].settings.region.as_deref(), Some("bottom"));
+        assert_eq!(converted.cues[0].plain_text(), ttml.cues[0].plain_text());
+    }
+}
@@ -0,0 +1,273 @@
+use crate::player::captions::ass::{alignment_settings, settings_alignment};
+use crate::player::captions::cue::{Color, Cue, CueList, CueNode, CueSettings, SpanKind, TextStyle};
+use crate::player::error::VideoError;
+
+const ARROW: &str = "-->";
+
+/// Parses a SubRip file. Counters are optional, commas and dots are both accepted in
+/// timestamps, and `<i>`, `<b>`, `<u>` and `<font color>` tags become cue markup. A
+/// leading `{\anN}` tag, as many players write it, positions the cue.
+pub fn parse_srt(text: &str) -> Result<CueList, VideoError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut list = CueList::default();
+    let mut blocks = 0;
+
+    let mut lines = text.split('\n').peekable();
+    while lines.peek().is_some() {
+        let block: Vec<&str> = lines.by_ref().take_while(|line| !line.trim().is_empty()).collect();
+        if block.is_empty() {
+            continue;
+        }
+        blocks += 1;
+        let Some(timing_line) = block.iter().take(2).position(|line| line.contains(ARROW)) else {
+            continue;
+        };
+        let Some((start, end)) = parse_timing_line(block[timing_line]) else {
+            continue;
+        };
+        let (content, settings) = parse_srt_text(&block[timing_line + 1..].join("\n"));
+        list.cues.push(Cue {
+            id: None,
+            start,
+            end,
+            settings,
+            content,
+        });
+    }
+    if blocks > 0 && list.cues.is_empty() {
+        return Err(VideoError::CaptionParseError("no SubRip cues found".to_string()));
+    }
+    Ok(list)
+}
+
+/// Parses `start --> end`, ignoring the display coordinates some files append.
+fn parse_timing_line(line: &str) -> Option<(f64, f64)> {
+    let (start, rest) = line.split_once(ARROW)?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+/// Parses `hh:mm:ss,ttt`, tolerating a dot separator and short fields.
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let (clock, fraction) = value.split_once([',', '.']).unwrap_or((value, "0"));
+    let parts: Vec<u64> = clock.split(':').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    let [hours, minutes, seconds] = parts[..] else {
+        return None;
+    };
+    if minutes > 59 || seconds > 59 || fraction.is_empty() || fraction.len() > 3 {
+        return None;
+    }
+    let millis: u64 = format!("{:0<3}", fraction).parse().ok()?;
+    Some(hours as f64 * 3600.0 + minutes as f64 * 60.0 + seconds as f64 + millis as f64 / 1000.0)
+}
+
+/// Parses SubRip markup. Tags are matched case-insensitively, unknown ones are
+/// dropped, and a closing tag closes everything opened after its match.
+fn parse_srt_text(text: &str) -> (Vec<CueNode>, CueSettings) {
+    let mut settings = CueSettings::default();
+    let mut rest = text;
+    if let Some((alignment, after)) = rest.strip_prefix("{\\an").and_then(|tag| tag.split_once('}')) {
+        if let Ok(alignment) = alignment.parse::<u8>() {
+            settings = alignment_settings(alignment, 0.0);
+            rest = after;
+        }
+    }
+
+    // Open spans: tag name and the node being built
+    let mut open: Vec<(String, CueNode)> = Vec::new();
+    let mut root: Vec<CueNode> = Vec::new();
+    fn push(open: &mut [(String, CueNode)], root: &mut Vec<CueNode>, node: CueNode) {
+        match open.last_mut() {
+            Some((_, CueNode::Span { children, .. })) => children.push(node),
+            _ => root.push(node),
+        }
+    }
+
+    while let Some(next) = rest.find(['<', '{']) {
+        let close = if rest[next..].starts_with('<') { '>' } else { '}' };
+        let Some(length) = rest[next..].find(close) else {
+            break;
+        };
+        if next > 0 {
+            push(&mut open, &mut root, CueNode::Text(rest[..next].to_string()));
+        }
+        let tag = &rest[next + 1..next + length];
+        rest = &rest[next + length + 1..];
+        // Other ASS override blocks are left over from conversions
+        if close == '}' {
+            continue;
+        }
+
+        let tag = tag.trim();
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_ascii_lowercase();
+            if let Some(index) = open.iter().rposition(|(open_name, _)| *open_name == name) {
+                while open.len() > index {
+                    let (_, node) = open.pop().expect("index is within the stack");
+                    push(&mut open, &mut root, node);
+                }
+            }
+            continue;
+        }
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let name = name.to_ascii_lowercase();
+        let node = match name.as_str() {
+            "i" => CueNode::span(SpanKind::Italic, Vec::new()),
+            "b" => CueNode::span(SpanKind::Bold, Vec::new()),
+            "u" => CueNode::span(SpanKind::Underline, Vec::new()),
+            "font" => CueNode::styled(font_style(attributes), Vec::new()),
+            _ => continue,
+        };
+        open.push((name, node));
+    }
+    if !rest.is_empty() {
+        push(&mut open, &mut root, CueNode::Text(rest.to_string()));
+    }
+    while let Some((_, node)) = open.pop() {
+        push(&mut open, &mut root, node);
+    }
+    (root, settings)
+}
+
+/// Reads the `color` and `face` attributes of a `<font>` tag.
+fn font_style(attributes: &str) -> TextStyle {
+    let mut style = TextStyle::default();
+    let mut rest = attributes;
+    while let Some((name, value)) = rest.split_once('=') {
+        let value = value.trim_start();
+        let (value, remainder) = match value.strip_prefix(['"', '\'']) {
+            Some(quoted) => {
+                let end = quoted.find(['"', '\'']).unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+            }
+            None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "color" => style.color = Color::parse(value),
+            "face" => style.font_family = Some(value.to_string()),
+            _ => {}
+        }
+        rest = remainder;
+    }
+    style
+}
+
+/// Writes cues as SubRip. Only italics, bold, underline, text colour and font face,
+/// and the cue alignment, survive the conversion.
+pub fn write_srt(list: &CueList) -> String {
+    let mut output = String::new();
+    for (index, cue) in list.cues.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        output.push_str(&format!("{}\n{} {} {}\n", index + 1, format_timestamp(cue.start), ARROW, format_timestamp(cue.end)));
+        let alignment = settings_alignment(&cue.settings);
+        if alignment != settings_alignment(&CueSettings::default()) {
+            output.push_str(&format!("{{\\an{}}}", alignment));
+        }
+        let mut text = String::new();
+        write_nodes(&cue.content, &mut text);
+        while text.contains("\n\n") {
+            text = text.replace("\n\n", "\n");
+        }
+        output.push_str(text.trim_end_matches('\n'));
+        output.push('\n');
+    }
+    output
+}
+
+fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02},{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+}
+
+fn write_nodes(nodes: &[CueNode], output: &mut String) {
+    for node in nodes {
+        match node {
+            CueNode::Text(text) => output.push_str(text),
+            CueNode::Span { kind, style, children, .. } => {
+                let tag = match kind {
+                    SpanKind::Italic => Some("i"),
+                    SpanKind::Bold => Some("b"),
+                    SpanKind::Underline => Some("u"),
+                    _ => None,
+                };
+                let mut attributes = String::new();
+                if let Some(color) = style.color {
+                    attributes.push_str(&format!(" color=\"{}\"", color.hex()));
+                }
+                if let Some(font_family) = &style.font_family {
+                    attributes.push_str(&format!(" face=\"{}\"", font_family.replace('"', "")));
+                }
+                if let Some(tag) = tag {
+                    output.push_str(&format!("<{}>", tag));
+                }
+                if !attributes.is_empty() {
+                    output.push_str(&format!("<font{}>", attributes));
+                }
+                write_nodes(children, output);
+                if !attributes.is_empty() {
+                    output.push_str("</font>");
+                }
+                if let Some(tag) = tag {
+                    output.push_str(&format!("</{}>", tag));
+                }
+            }
+            CueNode::Timestamp(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::captions::cue::{Line, LineAlign};
+
+    #[test]
+    fn test_parse_srt() {
+        let list = parse_srt(include_str!("fixtures/basic.srt")).unwrap();
+        assert_eq!(list.cues.len(), 3);
+        assert_eq!((list.cues[0].start, list.cues[0].end), (1.0, 4.5));
+        assert_eq!(list.cues[0].plain_text(), "Welcome to the show.\nIt's a long one & worth it.");
+
+        let second = &list.cues[1];
+        assert_eq!(second.start, 3661.25);
+        assert_eq!(second.settings.line, Some(Line::Percent(0.0)));
+        assert_eq!(second.settings.line_align, LineAlign::Start);
+        assert_eq!(
+            second.content,
+            vec![
+                CueNode::span(SpanKind::Italic, vec![CueNode::text("Much "), CueNode::span(SpanKind::Bold, vec![CueNode::text("later")])]),
+                CueNode::text(" on"),
+            ]
+        );
+
+        let third = &list.cues[2];
+        let CueNode::Span { style, children, .. } = &third.content[0] else {
+            panic!("expected a font span");
+        };
+        assert_eq!(style.color, Some(Color::rgb(255, 255, 0)));
+        assert_eq!(style.font_family.as_deref(), Some("Courier New"));
+        assert_eq!(children, &vec![CueNode::text("Yellow")]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let file = include_str!("fixtures/basic.srt");
+        let list = parse_srt(file).unwrap();
+        assert_eq!(write_srt(&list), file);
+        assert_eq!(parse_srt(&write_srt(&list)).unwrap(), list);
+    }
+
+    #[test]
+    fn test_lenient_timestamps() {
+        assert_eq!(parse_timestamp("00:00:01,5"), Some(1.5));
+        assert_eq!(parse_timestamp("1:02:03.040"), Some(3723.04));
+        assert_eq!(parse_timestamp("00:61:00,000"), None);
+        assert_eq!(parse_timestamp("00:01,000"), None);
+        let list = parse_srt("00:00:01,000 --> 00:00:02,000 X1:40 X2:600 Y1:20 Y2:50\n<B>Loud</b> <blink>text</blink>\n").unwrap();
+        assert_eq!(list.cues[0].plain_text(), "Loud text");
+        assert!(matches!(parse_srt("1\nno timing here\n"), Err(VideoError::CaptionParseError(_))));
+    }
+}
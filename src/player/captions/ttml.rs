@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use roxmltree::{Document, Node};
+use crate::player::captions::cue::{nodes_from_runs, Color, Cue, CueList, CueSettings, LineAlign, Region, RunFormat, TextAlign};
+use crate::player::error::VideoError;
+
+const TTML_NAMESPACE: &str = "http://www.w3.org/ns/ttml";
+const STYLING_NAMESPACE: &str = "http://www.w3.org/ns/ttml#styling";
+const PARAMETER_NAMESPACE: &str = "http://www.w3.org/ns/ttml#parameter";
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+// How deep `style` references may chain before they are taken to be a loop
+const MAX_STYLE_DEPTH: usize = 8;
+
+/// Document parameters that time expressions and lengths are resolved against.
+struct Parameters {
+    frame_rate: f64,
+    tick_rate: f64,
+    /// Cell grid columns and rows.
+    cell_resolution: (f64, f64),
+    /// The root container's size in pixels, when given.
+    extent: Option<(f64, f64)>,
+}
+
+/// Styling properties as written: the `tts` attributes keyed by local name.
+type StyleSet = HashMap<String, String>;
+
+/// Parses a TTML document, including the IMSC text profile. Each `<p>` becomes a cue,
+/// layout regions become cue regions and styles are resolved into inline styling.
+/// Timing on `<span>`s inside a paragraph is ignored.
+pub fn parse_ttml(text: &str) -> Result<CueList, VideoError> {
+    let document = Document::parse(text).map_err(|e| VideoError::CaptionParseError(e.to_string()))?;
+    let root = document.root_element();
+    if !is_element(root, "tt") {
+        return Err(VideoError::CaptionParseError("missing tt root element".to_string()));
+    }
+    let parameters = parse_parameters(root);
+
+    let head = root.children().find(|node| is_element(*node, "head"));
+    let styles: HashMap<&str, Node> = head
+        .into_iter()
+        .flat_map(|head| head.descendants())
+        .filter(|node| is_element(*node, "style"))
+        .filter_map(|node| Some((node.attribute((XML_NAMESPACE, "id"))?, node)))
+        .collect();
+
+    let mut list = CueList::default();
+    let mut region_styles: HashMap<String, StyleSet> = HashMap::new();
+    for node in head.into_iter().flat_map(|head| head.descendants()).filter(|node| is_element(*node, "region")) {
+        let Some(id) = node.attribute((XML_NAMESPACE, "id")) else {
+            continue;
+        };
+        let style = resolve_styles(node, &styles);
+        list.regions.push(parse_region(id, &style, &parameters));
+        region_styles.insert(id.to_string(), style);
+    }
+
+    if let Some(body) = root.children().find(|node| is_element(*node, "body")) {
+        let context = Inherited {
+            begin: 0.0,
+            end: f64::INFINITY,
+            region: None,
+            styles: StyleSet::new(),
+        };
+        collect_paragraphs(body, &context, &styles, &region_styles, &parameters, &mut list.cues);
+    }
+    list.cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+    Ok(list)
+}
+
+fn is_element(node: Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name && node.tag_name().namespace() == Some(TTML_NAMESPACE)
+}
+
+fn parse_parameters(root: Node) -> Parameters {
+    let number = |name: &str| root.attribute((PARAMETER_NAMESPACE, name)).and_then(|value| value.trim().parse::<f64>().ok()).filter(|value| *value > 0.0);
+    let multiplier = root
+        .attribute((PARAMETER_NAMESPACE, "frameRateMultiplier"))
+        .and_then(|value| {
+            let (numerator, denominator) = value.split_once(' ')?;
+            Some(numerator.trim().parse::<f64>().ok()? / denominator.trim().parse::<f64>().ok().filter(|value| *value > 0.0)?)
+        })
+        .unwrap_or(1.0);
+    let frame_rate = number("frameRate").unwrap_or(30.0) * multiplier;
+    let cell_resolution = root
+        .attribute((PARAMETER_NAMESPACE, "cellResolution"))
+        .and_then(|value| {
+            let (columns, rows) = value.trim().split_once(char::is_whitespace)?;
+            Some((columns.parse::<f64>().ok()?, rows.trim().parse::<f64>().ok()?))
+        })
+        .filter(|(columns, rows)| *columns > 0.0 && *rows > 0.0)
+        .unwrap_or((32.0, 15.0));
+    let extent = root.attribute((STYLING_NAMESPACE, "extent")).and_then(|value| {
+        let (width, height) = value.trim().split_once(char::is_whitespace)?;
+        Some((width.strip_suffix("px")?.parse().ok()?, height.trim().strip_suffix("px")?.parse().ok()?))
+    });
+    Parameters {
+        frame_rate,
+        tick_rate: number("tickRate").unwrap_or(if root.has_attribute((PARAMETER_NAMESPACE, "frameRate")) { frame_rate } else { 1.0 }),
+        cell_resolution,
+        extent,
+    }
+}
+
+/// The styles an element specifies: those it references, then its own attributes.
+fn resolve_styles(node: Node, styles: &HashMap<&str, Node>) -> StyleSet {
+    fn resolve_into(node: Node, styles: &HashMap<&str, Node>, depth: usize, set: &mut StyleSet) {
+        if depth > MAX_STYLE_DEPTH {
+            return;
+        }
+        for reference in node.attribute("style").unwrap_or("").split_whitespace() {
+            if let Some(style) = styles.get(reference) {
+                resolve_into(*style, styles, depth + 1, set);
+            }
+        }
+        // Regions may also carry nested style elements
+        for child in node.children().filter(|child| is_element(*child, "style") && !child.has_attribute((XML_NAMESPACE, "id"))) {
+            resolve_into(child, styles, depth + 1, set);
+        }
+        for attribute in node.attributes().filter(|attribute| attribute.namespace() == Some(STYLING_NAMESPACE)) {
+            set.insert(attribute.name().to_string(), attribute.value().to_string());
+        }
+    }
+    let mut set = StyleSet::new();
+    resolve_into(node, styles, 0, &mut set);
+    set
+}
+
+fn parse_region(id: &str, style: &StyleSet, parameters: &Parameters) -> Region {
+    let pair = |name: &str| {
+        let value = style.get(name)?;
+        let (x, y) = value.trim().split_once(char::is_whitespace)?;
+        Some((parse_length(x, 0, parameters)?, parse_length(y.trim(), 1, parameters)?))
+    };
+    let (x, y) = pair("origin").unwrap_or((0.0, 0.0));
+    let (width, height) = pair("extent").unwrap_or((100.0 - x, 100.0 - y));
+    let line_height = 100.0 / parameters.cell_resolution.1;
+    Region {
+        id: id.to_string(),
+        width: width.clamp(0.0, 100.0),
+        lines: (height / line_height).round().max(1.0) as u32,
+        region_anchor: (0.0, 0.0),
+        viewport_anchor: (x.clamp(0.0, 100.0), y.clamp(0.0, 100.0)),
+        scroll_up: false,
+        display_align: match style.get("displayAlign").map(String::as_str) {
+            Some("center") => LineAlign::Center,
+            Some("after") => LineAlign::End,
+            _ => LineAlign::Start,
+        },
+    }
+}
+
+/// A length as a percentage of the root container along `axis` (0 across, 1 down).
+fn parse_length(value: &str, axis: usize, parameters: &Parameters) -> Option<f64> {
+    if let Some(percent) = value.strip_suffix('%') {
+        return percent.parse().ok();
+    }
+    if let Some(cells) = value.strip_suffix('c') {
+        let cells_per_axis = if axis == 0 { parameters.cell_resolution.0 } else { parameters.cell_resolution.1 };
+        return Some(cells.parse::<f64>().ok()? * 100.0 / cells_per_axis);
+    }
+    let pixels: f64 = value.strip_suffix("px")?.parse().ok()?;
+    let (width, height) = parameters.extent?;
+    Some(pixels * 100.0 / if axis == 0 { width } else { height })
+}
+
+/// Parses a clock time (`hh:mm:ss.fff` or `hh:mm:ss:ff`) or an offset time such as
+/// `2.5s`, `90f` or `1000t`.
+fn parse_time(value: &str, parameters: &Parameters) -> Option<f64> {
+    let value = value.trim();
+    if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        let (hours, minutes, seconds) = (parts.first()?.parse::<f64>().ok()?, parts.get(1)?.parse::<f64>().ok()?, parts.get(2)?.parse::<f64>().ok()?);
+        let frames = match parts.get(3) {
+            Some(frames) => frames.parse::<f64>().ok()? / parameters.frame_rate,
+            None => 0.0,
+        };
+        return (parts.len() <= 4).then_some(hours * 3600.0 + minutes * 60.0 + seconds + frames);
+    }
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let (number, metric) = value.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    match metric {
+        "h" => Some(number * 3600.0),
+        "m" => Some(number * 60.0),
+        "s" => Some(number),
+        "ms" => Some(number / 1000.0),
+        "f" => Some(number / parameters.frame_rate),
+        "t" => Some(number / parameters.tick_rate),
+        _ => None,
+    }
+}
+
+/// What an element inherits from its ancestors.
+#[derive(Clone)]
+struct Inherited {
+    begin: f64,
+    end: f64,
+    region: Option<String>,
+    styles: StyleSet,
+}
+
+impl Inherited {
+    /// The context for a child element, resolving its timing against this one.
+    fn child(&self, node: Node, styles: &HashMap<&str, Node>, parameters: &Parameters) -> Inherited {
+        let time = |name: &str| node.attribute(name).and_then(|value| parse_time(value, parameters));
+        let begin = self.begin + time("begin").unwrap_or(0.0);
+        let end = match (time("end"), time("dur")) {
+            (Some(end), _) => self.begin + end,
+            (None, Some(duration)) => begin + duration,
+            (None, None) => self.end,
+        };
+        let mut inherited = self.styles.clone();
+        // Backgrounds paint the element itself rather than passing down
+        inherited.remove("backgroundColor");
+        inherited.extend(resolve_styles(node, styles));
+        Inherited {
+            begin,
+            end: end.min(self.end),
+            region: node.attribute("region").map(str::to_string).or_else(|| self.region.clone()),
+            styles: inherited,
+        }
+    }
+}
+
+fn collect_paragraphs(node: Node, parent: &Inherited, styles: &HashMap<&str, Node>, region_styles: &HashMap<String, StyleSet>, parameters: &Parameters, cues: &mut Vec<Cue>) {
+    let context = parent.child(node, styles, parameters);
+    for child in node.children().filter(|child| child.is_element()) {
+        if is_element(child, "div") {
+            collect_paragraphs(child, &context, styles, region_styles, parameters, cues);
+        } else if is_element(child, "p") {
+            let mut paragraph = context.clone();
+            paragraph.styles.remove("backgroundColor");
+            // Content flowed into a region takes the region's styles as its base
+            let region = child.attribute("region").map(str::to_string).or_else(|| paragraph.region.clone());
+            if let Some(region_style) = region.as_deref().and_then(|id| region_styles.get(id)) {
+                let mut base = region_style.clone();
+                base.remove("backgroundColor");
+                base.extend(paragraph.styles);
+                paragraph.styles = base;
+            }
+            if let Some(cue) = parse_paragraph(child, &paragraph, styles, parameters) {
+                cues.push(cue);
+            }
+        }
+    }
+}
+
+fn parse_paragraph(node: Node, parent: &Inherited, styles: &HashMap<&str, Node>, parameters: &Parameters) -> Option<Cue> {
+    let context = parent.child(node, styles, parameters);
+    if context.end <= context.begin {
+        return None;
+    }
+    let mut runs = Vec::new();
+    collect_runs(node, &context.styles, styles, parameters, &mut runs);
+    let content = nodes_from_runs(trim_runs(runs));
+    if content.is_empty() {
+        return None;
+    }
+
+    let mut settings = CueSettings {
+        region: context.region.clone(),
+        ..CueSettings::default()
+    };
+    settings.align = match context.styles.get("textAlign").map(String::as_str) {
+        Some("left") => TextAlign::Left,
+        Some("right") => TextAlign::Right,
+        Some("start") => TextAlign::Start,
+        Some("end") => TextAlign::End,
+        _ => TextAlign::Center,
+    };
+    Some(Cue {
+        id: node.attribute((XML_NAMESPACE, "id")).map(str::to_string),
+        start: context.begin,
+        end: context.end,
+        settings,
+        content,
+    })
+}
+
+/// Flattens a paragraph into formatted runs, collapsing whitespace as XML text does.
+fn collect_runs(node: Node, inherited: &StyleSet, styles: &HashMap<&str, Node>, parameters: &Parameters, runs: &mut Vec<(RunFormat, String)>) {
+    let format = run_format(inherited, parameters);
+    for child in node.children() {
+        if child.is_text() {
+            let text = child.text().unwrap_or("");
+            let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let leading = if text.starts_with(char::is_whitespace) && !collapsed.is_empty() { " " } else { "" };
+            let trailing = if text.ends_with(char::is_whitespace) { " " } else { "" };
+            runs.push((format.clone(), format!("{}{}{}", leading, collapsed, trailing)));
+        } else if is_element(child, "br") {
+            runs.push((format.clone(), "\n".to_string()));
+        } else if is_element(child, "span") {
+            let mut span_styles = inherited.clone();
+            span_styles.extend(resolve_styles(child, styles));
+            collect_runs(child, &span_styles, styles, parameters, runs);
+        }
+    }
+}
+
+/// Drops the spaces at the start and end of the text and around line breaks.
+fn trim_runs(runs: Vec<(RunFormat, String)>) -> Vec<(RunFormat, String)> {
+    let mut trimmed: Vec<(RunFormat, String)> = Vec::new();
+    let mut at_line_start = true;
+    for (format, text) in runs {
+        let mut text = if at_line_start { text.trim_start_matches(' ').to_string() } else { text };
+        // Collapse spaces split across neighbouring runs
+        if text.starts_with(' ') && trimmed.last().is_some_and(|(_, previous)| previous.ends_with(' ')) {
+            text.remove(0);
+        }
+        if text.starts_with('\n') {
+            if let Some((_, previous)) = trimmed.last_mut() {
+                previous.truncate(previous.trim_end_matches(' ').len());
+            }
+        }
+        if text.is_empty() {
+            continue;
+        }
+        at_line_start = text.ends_with('\n');
+        trimmed.push((format, text));
+    }
+    if let Some((_, last)) = trimmed.last_mut() {
+        last.truncate(last.trim_end_matches(' ').len());
+    }
+    trimmed
+}
+
+fn run_format(styles: &StyleSet, parameters: &Parameters) -> RunFormat {
+    let mut format = RunFormat::default();
+    let style = |name: &str| styles.get(name).map(|value| value.trim());
+    format.italic = matches!(style("fontStyle"), Some("italic" | "oblique"));
+    format.bold = style("fontWeight") == Some("bold");
+    format.underline = style("textDecoration").is_some_and(|value| value.split_whitespace().any(|part| part == "underline"));
+    format.style.color = style("color").and_then(Color::parse);
+    format.style.background = style("backgroundColor").and_then(Color::parse);
+    format.style.font_family = style("fontFamily").map(|value| value.split(',').next().unwrap_or("").trim().trim_matches(['"', '\'']).to_string());
+    format.style.font_size = style("fontSize").and_then(|value| {
+        // With two values the second is the glyph height
+        let value = value.split_whitespace().last()?;
+        let cell_height = 100.0 / parameters.cell_resolution.1;
+        match value.strip_suffix('%') {
+            Some(percent) => Some(percent.parse::<f64>().ok()? / 100.0 * cell_height),
+            None => parse_length(value, 1, parameters),
+        }
+    });
+    format.style.outline = style("textOutline").and_then(|value| {
+        let first = value.split_whitespace().next()?;
+        match first {
+            "none" => None,
+            // Without a colour the outline takes the text colour
+            _ => Color::parse(first).or(format.style.color),
+        }
+    });
+    format
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::captions::{CueNode, SpanKind};
+
+    #[test]
+    fn test_parse_imsc_document() {
+        let list = parse_ttml(include_str!("fixtures/imsc.ttml")).unwrap();
+        assert_eq!(list.cues.len(), 3);
+
+        let bottom = list.region("bottom").unwrap();
+        assert_eq!(bottom.viewport_anchor, (10.0, 70.0));
+        assert_eq!(bottom.width, 80.0);
+        assert_eq!(bottom.lines, 3);
+        assert_eq!(bottom.display_align, LineAlign::End);
+
+        // Timing is relative to the div, whitespace collapses and <br/> breaks lines
+        let first = &list.cues[0];
+        assert_eq!(first.id.as_deref(), Some("c1"));
+        assert_eq!((first.start, first.end), (11.0, 13.5));
+        assert_eq!(first.plain_text(), "Hello, world\nsecond line");
+        assert_eq!(first.settings.region.as_deref(), Some("bottom"));
+        let CueNode::Span { style, .. } = &first.content[0] else {
+            panic!("expected a styled span");
+        };
+        assert_eq!(style.color, Some(Color::rgb(255, 255, 255)));
+        assert_eq!(style.background, Some(Color { alpha: 204, ..Color::rgb(0, 0, 0) }));
+        assert_eq!(style.font_family.as_deref(), Some("proportionalSansSerif"));
+        assert_eq!(style.font_size, Some(100.0 / 15.0));
+
+        // Spans pick up referenced styles on top of the paragraph's
+        let second = &list.cues[1];
+        assert_eq!(second.start, 14.0);
+        assert_eq!(second.end, 15.0);
+        assert_eq!(second.settings.align, TextAlign::Left);
+        assert_eq!(second.plain_text(), "Shouting now!");
+        let CueNode::Span { style, children, .. } = &second.content[1] else {
+            panic!("expected a styled span");
+        };
+        assert_eq!(style.color, Some(Color::rgb(255, 255, 0)));
+        assert!(matches!(&children[0], CueNode::Span { kind: SpanKind::Italic, .. }));
+
+        // Frames and ticks resolve against the document's rates
+        let third = &list.cues[2];
+        assert_eq!(third.settings.region.as_deref(), Some("top"));
+        assert_eq!((third.start, third.end), (20.5, 22.0));
+    }
+
+    #[test]
+    fn test_time_expressions() {
+        let parameters = Parameters {
+            frame_rate: 25.0,
+            tick_rate: 10_000.0,
+            cell_resolution: (32.0, 15.0),
+            extent: Some((1920.0, 1080.0)),
+        };
+        assert_eq!(parse_time("00:00:01.500", &parameters), Some(1.5));
+        assert_eq!(parse_time("00:00:01:05", &parameters), Some(1.2));
+        assert_eq!(parse_time("2.5s", &parameters), Some(2.5));
+        assert_eq!(parse_time("1.5m", &parameters), Some(90.0));
+        assert_eq!(parse_time("250ms", &parameters), Some(0.25));
+        assert_eq!(parse_time("50f", &parameters), Some(2.0));
+        assert_eq!(parse_time("15000t", &parameters), Some(1.5));
+        assert_eq!(parse_time("soon", &parameters), None);
+        assert_eq!(parse_length("960px", 0, &parameters), Some(50.0));
+        assert_eq!(parse_length("3c", 1, &parameters), Some(20.0));
+        assert!(matches!(parse_ttml("<html/>"), Err(VideoError::CaptionParseError(_))));
+        assert!(matches!(parse_ttml("<tt"), Err(VideoError::CaptionParseError(_))));
+    }
+}
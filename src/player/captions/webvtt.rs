@@ -0,0 +1,603 @@
+use crate::player::captions::cue::{Cue, CueList, CueNode, CueSettings, Line, LineAlign, PositionAlign, Region, SpanKind, TextAlign, TextStyle, WritingDirection};
+use crate::player::error::VideoError;
+
+const SIGNATURE: &str = "WEBVTT";
+const ARROW: &str = "-->";
+
+/// Parses a WebVTT file following the parsing algorithm of the W3C WebVTT spec:
+/// invalid cues are skipped rather than failing the whole file, and only a missing
+/// signature is an error.
+pub fn parse_webvtt(text: &str) -> Result<CueList, VideoError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let text = text.replace("\r\n", "\n").replace('\r', "\n").replace('\0', "\u{fffd}");
+    let lines: Vec<&str> = text.split('\n').collect();
+
+    if !is_keyword_line(lines[0], SIGNATURE) {
+        return Err(VideoError::CaptionParseError("missing WEBVTT signature".to_string()));
+    }
+    // Header lines run until the first blank line
+    let mut index = 1;
+    while index < lines.len() && !lines[index].is_empty() {
+        index += 1;
+    }
+
+    let mut list = CueList::default();
+    let mut seen_cue = false;
+    while index < lines.len() {
+        if lines[index].is_empty() {
+            index += 1;
+            continue;
+        }
+        let (block, timing_line) = collect_block(&lines, &mut index);
+        match timing_line {
+            Some(timing_line) => {
+                if let Some(cue) = parse_cue(&block, timing_line, &list.regions) {
+                    list.cues.push(cue);
+                }
+                seen_cue = true;
+            }
+            // Style sheets and regions are only allowed before the first cue
+            None if !seen_cue && is_keyword_line(block[0], "STYLE") => {
+                list.styles.push(block[1..].join("\n"));
+            }
+            None if !seen_cue && is_keyword_line(block[0], "REGION") => {
+                if let Some(region) = parse_region(&block[1..]) {
+                    list.regions.retain(|existing| existing.id != region.id);
+                    list.regions.push(region);
+                }
+            }
+            // NOTE comments and anything unrecognised
+            None => {}
+        }
+    }
+    Ok(list)
+}
+
+/// A line that is `keyword` alone or followed by whitespace.
+fn is_keyword_line(line: &str, keyword: &str) -> bool {
+    line.strip_prefix(keyword)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Collects the lines of one block and the index of its timing line, if any. An arrow
+/// anywhere but the first two lines starts a new block.
+fn collect_block<'a>(lines: &[&'a str], index: &mut usize) -> (Vec<&'a str>, Option<usize>) {
+    let mut block = Vec::new();
+    let mut timing_line = None;
+    while *index < lines.len() && !lines[*index].is_empty() {
+        let line = lines[*index];
+        if line.contains(ARROW) {
+            if timing_line.is_some() || block.len() > 1 {
+                break;
+            }
+            timing_line = Some(block.len());
+        }
+        block.push(line);
+        *index += 1;
+    }
+    (block, timing_line)
+}
+
+fn parse_cue(block: &[&str], timing_line: usize, regions: &[Region]) -> Option<Cue> {
+    let (start, end, settings) = parse_timing_line(block[timing_line])?;
+    Some(Cue {
+        id: (timing_line == 1).then(|| block[0].to_string()),
+        start,
+        end,
+        settings: parse_settings(settings, regions),
+        content: parse_cue_text(&block[timing_line + 1..].join("\n")),
+    })
+}
+
+fn parse_timing_line(line: &str) -> Option<(f64, f64, &str)> {
+    let (start, rest) = line.split_once(ARROW)?;
+    let rest = rest.trim_start_matches([' ', '\t']);
+    let end_length = rest.find([' ', '\t']).unwrap_or(rest.len());
+    let start = parse_timestamp(start.trim_matches([' ', '\t']))?;
+    let end = parse_timestamp(&rest[..end_length])?;
+    Some((start, end, &rest[end_length..]))
+}
+
+/// Parses `[hh:]mm:ss.ttt`, where hours may have any number of digits.
+pub fn parse_timestamp(value: &str) -> Option<f64> {
+    let parts: Vec<&str> = value.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [minutes, seconds] => (0, *minutes, *seconds),
+        [hours, minutes, seconds] => (digits(hours, None)?, *minutes, *seconds),
+        _ => return None,
+    };
+    let (seconds, millis) = seconds.split_once('.')?;
+    let minutes = digits(minutes, Some(2)).filter(|minutes| *minutes <= 59)?;
+    let seconds = digits(seconds, Some(2)).filter(|seconds| *seconds <= 59)?;
+    let millis = digits(millis, Some(3))?;
+    Some(hours as f64 * 3600.0 + minutes as f64 * 60.0 + seconds as f64 + millis as f64 / 1000.0)
+}
+
+fn digits(value: &str, length: Option<usize>) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) || length.is_some_and(|length| value.len() != length) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Parses `N%` or `N.N%` in the range 0-100.
+fn parse_percentage(value: &str) -> Option<f64> {
+    let number = value.strip_suffix('%')?;
+    let (whole, fraction) = number.split_once('.').unwrap_or((number, "0"));
+    digits(whole, None)?;
+    digits(fraction, None)?;
+    number.parse().ok().filter(|percent| (0.0..=100.0).contains(percent))
+}
+
+fn parse_settings(settings: &str, regions: &[Region]) -> CueSettings {
+    let mut parsed = CueSettings::default();
+    for setting in settings.split([' ', '\t']).filter(|setting| !setting.is_empty()) {
+        let Some((name, value)) = setting.split_once(':').filter(|(name, value)| !name.is_empty() && !value.is_empty()) else {
+            continue;
+        };
+        match name {
+            "region" => {
+                parsed.region = regions.iter().any(|region| region.id == value).then(|| value.to_string());
+            }
+            "vertical" => match value {
+                "rl" => parsed.vertical = WritingDirection::VerticalRightToLeft,
+                "lr" => parsed.vertical = WritingDirection::VerticalLeftToRight,
+                _ => {}
+            },
+            "line" => {
+                let (position, align) = value.split_once(',').map(|(position, align)| (position, Some(align))).unwrap_or((value, None));
+                let line_align = match align {
+                    None | Some("start") => LineAlign::Start,
+                    Some("center") => LineAlign::Center,
+                    Some("end") => LineAlign::End,
+                    Some(_) => continue,
+                };
+                let line = if position.ends_with('%') {
+                    parse_percentage(position).map(Line::Percent)
+                } else {
+                    let number = position.strip_prefix('-').unwrap_or(position);
+                    let (whole, fraction) = number.split_once('.').unwrap_or((number, "0"));
+                    digits(whole, None).and(digits(fraction, None)).and(position.parse().ok()).map(Line::Number)
+                };
+                if let Some(line) = line {
+                    parsed.line = Some(line);
+                    parsed.line_align = line_align;
+                }
+            }
+            "position" => {
+                let (position, align) = value.split_once(',').map(|(position, align)| (position, Some(align))).unwrap_or((value, None));
+                let position_align = match align {
+                    None => None,
+                    Some("line-left") => Some(PositionAlign::LineLeft),
+                    Some("center") => Some(PositionAlign::Center),
+                    Some("line-right") => Some(PositionAlign::LineRight),
+                    Some(_) => continue,
+                };
+                if let Some(position) = parse_percentage(position) {
+                    parsed.position = Some(position);
+                    parsed.position_align = position_align;
+                }
+            }
+            "size" => {
+                if let Some(size) = parse_percentage(value) {
+                    parsed.size = size;
+                }
+            }
+            "align" => {
+                parsed.align = match value {
+                    "start" => TextAlign::Start,
+                    "center" => TextAlign::Center,
+                    "end" => TextAlign::End,
+                    "left" => TextAlign::Left,
+                    "right" => TextAlign::Right,
+                    _ => parsed.align,
+                };
+            }
+            _ => {}
+        }
+    }
+    // Regions only hold plain horizontal cues
+    if parsed.vertical != WritingDirection::Horizontal || parsed.line.is_some() || parsed.size != 100.0 {
+        parsed.region = None;
+    }
+    parsed
+}
+
+fn parse_region(lines: &[&str]) -> Option<Region> {
+    let mut region = Region::default();
+    for setting in lines.iter().flat_map(|line| line.split([' ', '\t'])).filter(|setting| !setting.is_empty()) {
+        let Some((name, value)) = setting.split_once(':') else {
+            continue;
+        };
+        match name {
+            "id" if !value.contains(ARROW) => region.id = value.to_string(),
+            "width" => region.width = parse_percentage(value).unwrap_or(region.width),
+            "lines" => region.lines = digits(value, None).and_then(|lines| u32::try_from(lines).ok()).unwrap_or(region.lines),
+            "regionanchor" => region.region_anchor = parse_anchor(value).unwrap_or(region.region_anchor),
+            "viewportanchor" => region.viewport_anchor = parse_anchor(value).unwrap_or(region.viewport_anchor),
+            "scroll" if value == "up" => region.scroll_up = true,
+            _ => {}
+        }
+    }
+    (!region.id.is_empty()).then_some(region)
+}
+
+fn parse_anchor(value: &str) -> Option<(f64, f64)> {
+    let (x, y) = value.split_once(',')?;
+    Some((parse_percentage(x)?, parse_percentage(y)?))
+}
+
+struct OpenSpan {
+    tag: String,
+    kind: SpanKind,
+    classes: Vec<String>,
+    annotation: Option<String>,
+    children: Vec<CueNode>,
+}
+
+/// Parses cue text markup into nodes. Unknown tags are dropped, unmatched end tags
+/// ignored and unclosed spans closed at the end, as the spec's tree builder does.
+pub fn parse_cue_text(text: &str) -> Vec<CueNode> {
+    let mut root = Vec::new();
+    let mut open: Vec<OpenSpan> = Vec::new();
+    let mut buffer = String::new();
+    let mut rest = text;
+
+    fn push(open: &mut [OpenSpan], root: &mut Vec<CueNode>, node: CueNode) {
+        let children = match open.last_mut() {
+            Some(span) => &mut span.children,
+            None => root,
+        };
+        match (children.last_mut(), node) {
+            (Some(CueNode::Text(previous)), CueNode::Text(text)) => previous.push_str(&text),
+            (_, node) => children.push(node),
+        }
+    }
+    fn close(open: &mut Vec<OpenSpan>, root: &mut Vec<CueNode>) {
+        if let Some(span) = open.pop() {
+            let node = CueNode::Span {
+                kind: span.kind,
+                classes: span.classes,
+                annotation: span.annotation,
+                style: TextStyle::default(),
+                children: span.children,
+            };
+            push(open, root, node);
+        }
+    }
+
+    while let Some(next) = rest.find(['<', '&']) {
+        buffer.push_str(&rest[..next]);
+        rest = &rest[next..];
+        if rest.starts_with('&') {
+            let (decoded, length) = decode_entity(rest);
+            buffer.push_str(&decoded);
+            rest = &rest[length..];
+            continue;
+        }
+
+        let end = rest.find('>').unwrap_or(rest.len());
+        let tag = &rest[1..end];
+        rest = rest.get(end + 1..).unwrap_or("");
+        if !buffer.is_empty() {
+            push(&mut open, &mut root, CueNode::Text(std::mem::take(&mut buffer)));
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.split(['.', ' ', '\t', '\n']).next().unwrap_or("");
+            let current = open.last().map(|span| span.tag.as_str());
+            if current == Some(name) {
+                close(&mut open, &mut root);
+            } else if name == "ruby" && current == Some("rt") {
+                close(&mut open, &mut root);
+                close(&mut open, &mut root);
+            }
+        } else if tag.starts_with(|c: char| c.is_ascii_digit()) {
+            if let Some(time) = parse_timestamp(tag.trim()) {
+                push(&mut open, &mut root, CueNode::Timestamp(time));
+            }
+        } else {
+            let (head, annotation) = tag.split_once([' ', '\t', '\n']).unwrap_or((tag, ""));
+            let mut parts = head.split('.');
+            let name = parts.next().unwrap_or("");
+            let Some(kind) = SpanKind::from_tag(name) else {
+                continue;
+            };
+            if kind == SpanKind::RubyText && open.last().map(|span| span.kind) != Some(SpanKind::Ruby) {
+                continue;
+            }
+            let annotation = annotation.split_whitespace().collect::<Vec<_>>().join(" ");
+            open.push(OpenSpan {
+                tag: name.to_string(),
+                kind,
+                classes: parts.filter(|class| !class.is_empty()).map(str::to_string).collect(),
+                annotation: (matches!(kind, SpanKind::Voice | SpanKind::Language) && !annotation.is_empty()).then_some(annotation),
+                children: Vec::new(),
+            });
+        }
+    }
+    buffer.push_str(rest);
+    if !buffer.is_empty() {
+        push(&mut open, &mut root, CueNode::Text(buffer));
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut root);
+    }
+    root
+}
+
+/// Decodes the character reference at the start of `text`, returning the decoded text
+/// and how many bytes it used. Unknown references are kept as written.
+fn decode_entity(text: &str) -> (String, usize) {
+    let Some(end) = text.find(';').filter(|end| *end <= 10) else {
+        return ("&".to_string(), 1);
+    };
+    let name = &text[1..end];
+    let decoded = match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        "lrm" => Some('\u{200e}'),
+        "rlm" => Some('\u{200f}'),
+        _ => name
+            .strip_prefix("#x")
+            .or_else(|| name.strip_prefix("#X"))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .or_else(|| name.strip_prefix('#').and_then(|decimal| decimal.parse().ok()))
+            .and_then(char::from_u32),
+    };
+    match decoded {
+        Some(character) => (character.to_string(), end + 1),
+        None => ("&".to_string(), 1),
+    }
+}
+
+/// Writes cues back out as WebVTT. Inline styling has no WebVTT form and is dropped,
+/// keeping the text it applied to.
+pub fn write_webvtt(list: &CueList) -> String {
+    let mut output = String::from(SIGNATURE);
+    output.push('\n');
+    for style in &list.styles {
+        output.push_str(&format!("\nSTYLE\n{}\n", style));
+    }
+    for region in &list.regions {
+        output.push_str(&format!(
+            "\nREGION\nid:{} width:{}% lines:{} regionanchor:{}%,{}% viewportanchor:{}%,{}%{}\n",
+            region.id,
+            region.width,
+            region.lines,
+            region.region_anchor.0,
+            region.region_anchor.1,
+            region.viewport_anchor.0,
+            region.viewport_anchor.1,
+            if region.scroll_up { " scroll:up" } else { "" }
+        ));
+    }
+    for cue in &list.cues {
+        output.push('\n');
+        if let Some(id) = &cue.id {
+            output.push_str(id);
+            output.push('\n');
+        }
+        output.push_str(&format!("{} {} {}", format_timestamp(cue.start), ARROW, format_timestamp(cue.end)));
+        output.push_str(&write_settings(&cue.settings));
+        output.push('\n');
+        let mut text = String::new();
+        write_nodes(&cue.content, &mut text);
+        // A blank line would end the cue early
+        while text.contains("\n\n") {
+            text = text.replace("\n\n", "\n");
+        }
+        output.push_str(text.trim_end_matches('\n'));
+        output.push('\n');
+    }
+    output
+}
+
+/// Formats seconds as `hh:mm:ss.ttt`.
+pub fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02}.{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+}
+
+fn write_settings(settings: &CueSettings) -> String {
+    let mut output = String::new();
+    match settings.vertical {
+        WritingDirection::Horizontal => {}
+        WritingDirection::VerticalRightToLeft => output.push_str(" vertical:rl"),
+        WritingDirection::VerticalLeftToRight => output.push_str(" vertical:lr"),
+    }
+    if let Some(line) = settings.line {
+        match line {
+            Line::Percent(percent) => output.push_str(&format!(" line:{}%", percent)),
+            Line::Number(number) => output.push_str(&format!(" line:{}", number)),
+        }
+        match settings.line_align {
+            LineAlign::Start => {}
+            LineAlign::Center => output.push_str(",center"),
+            LineAlign::End => output.push_str(",end"),
+        }
+    }
+    if let Some(position) = settings.position {
+        output.push_str(&format!(" position:{}%", position));
+        match settings.position_align {
+            None => {}
+            Some(PositionAlign::LineLeft) => output.push_str(",line-left"),
+            Some(PositionAlign::Center) => output.push_str(",center"),
+            Some(PositionAlign::LineRight) => output.push_str(",line-right"),
+        }
+    }
+    if settings.size != 100.0 {
+        output.push_str(&format!(" size:{}%", settings.size));
+    }
+    if settings.align != TextAlign::Center {
+        output.push_str(&format!(" align:{}", settings.align.as_css()));
+    }
+    if let Some(region) = &settings.region {
+        output.push_str(&format!(" region:{}", region));
+    }
+    output
+}
+
+fn write_nodes(nodes: &[CueNode], output: &mut String) {
+    for node in nodes {
+        match node {
+            CueNode::Text(text) => output.push_str(&text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")),
+            CueNode::Span { kind, classes, annotation, children, .. } => {
+                let tag = match kind {
+                    SpanKind::Class => "c",
+                    SpanKind::Voice => "v",
+                    SpanKind::Language => "lang",
+                    _ => kind.element_name(),
+                };
+                output.push('<');
+                output.push_str(tag);
+                for class in classes {
+                    output.push('.');
+                    output.push_str(class);
+                }
+                if let Some(annotation) = annotation {
+                    output.push(' ');
+                    output.push_str(annotation);
+                }
+                output.push('>');
+                write_nodes(children, output);
+                output.push_str(&format!("</{}>", tag));
+            }
+            CueNode::Timestamp(time) => output.push_str(&format!("<{}>", format_timestamp(*time))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic_file() {
+        let list = parse_webvtt(include_str!("fixtures/basic.vtt")).unwrap();
+        assert_eq!(list.cues.len(), 4);
+
+        let first = &list.cues[0];
+        assert_eq!(first.id.as_deref(), Some("intro"));
+        assert_eq!((first.start, first.end), (1.0, 4.5));
+        assert_eq!(first.plain_text(), "Welcome to the show.\nIt's a long one & worth it.");
+
+        // Hours, no identifier, and a note in between
+        let second = &list.cues[1];
+        assert_eq!(second.id, None);
+        assert_eq!(second.start, 3661.25);
+        // A second arrow line starts a new cue instead of being text
+        assert_eq!(list.cues[2].plain_text(), "First");
+        assert_eq!(list.cues[3].plain_text(), "Second");
+
+        assert_eq!(list.active_cues(2.0).len(), 1);
+        assert!(list.active_cues(4.5).is_empty());
+    }
+
+    #[test]
+    fn test_signature_is_required() {
+        assert!(matches!(parse_webvtt("WEBVTTX\n\n00:01.000 --> 00:02.000\nHi"), Err(VideoError::CaptionParseError(_))));
+        assert!(parse_webvtt("\u{feff}WEBVTT - with a title\r\n\r\n00:01.000 --> 00:02.000\r\nHi").is_ok());
+    }
+
+    #[test]
+    fn test_write_round_trips() {
+        for file in [include_str!("fixtures/basic.vtt"), include_str!("fixtures/settings.vtt")] {
+            let list = parse_webvtt(file).unwrap();
+            let written = write_webvtt(&list);
+            assert_eq!(parse_webvtt(&written).unwrap(), list, "{}", written);
+        }
+        assert_eq!(format_timestamp(3661.25), "01:01:01.250");
+    }
+
+    #[test]
+    fn test_parse_timestamps() {
+        assert_eq!(parse_timestamp("00:01.500"), Some(1.5));
+        assert_eq!(parse_timestamp("1:00:00.000"), Some(3600.0));
+        assert_eq!(parse_timestamp("100:00:00.001"), Some(360_000.001));
+        for invalid in ["1:02.000", "00:60.000", "00:01.5", "00:01", "00:00:01,000", "-00:01.000"] {
+            assert_eq!(parse_timestamp(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_cue_settings_and_regions() {
+        let list = parse_webvtt(include_str!("fixtures/settings.vtt")).unwrap();
+        assert_eq!(list.regions.len(), 2);
+        let fred = list.region("fred").unwrap();
+        assert_eq!(fred.width, 40.0);
+        assert_eq!(fred.lines, 3);
+        assert_eq!(fred.region_anchor, (0.0, 100.0));
+        assert_eq!(fred.viewport_anchor, (10.0, 90.0));
+        assert!(fred.scroll_up);
+        assert_eq!(list.styles, ["::cue(.yellow) { color: yellow; }"]);
+
+        let settings: Vec<&CueSettings> = list.cues.iter().map(|cue| &cue.settings).collect();
+        assert_eq!(settings[0].region.as_deref(), Some("fred"));
+        assert_eq!(settings[0].align, TextAlign::Left);
+
+        assert_eq!(settings[1].line, Some(Line::Percent(10.0)));
+        assert_eq!(settings[1].line_align, LineAlign::Center);
+        assert_eq!(settings[1].position, Some(25.5));
+        assert_eq!(settings[1].position_align, Some(PositionAlign::LineLeft));
+        assert_eq!(settings[1].size, 50.0);
+        // A line setting takes the cue out of its region
+        assert_eq!(settings[1].region, None);
+
+        assert_eq!(settings[2].vertical, WritingDirection::VerticalRightToLeft);
+        assert_eq!(settings[2].line, Some(Line::Number(-1.0)));
+        assert_eq!(settings[2].align, TextAlign::End);
+
+        // Invalid values are ignored one by one
+        assert_eq!(settings[3], &CueSettings { line: Some(Line::Number(0.0)), ..CueSettings::default() });
+        // Unknown regions are dropped
+        assert_eq!(settings[4].region, None);
+    }
+
+    #[test]
+    fn test_cue_text_markup() {
+        let nodes = parse_cue_text("<v.loud Fred Smith>Hi <c.yellow.big>there</c> &lt;3</v> <i><b>bold</i> tail");
+        assert_eq!(
+            nodes,
+            vec![
+                CueNode::Span {
+                    kind: SpanKind::Voice,
+                    classes: vec!["loud".to_string()],
+                    annotation: Some("Fred Smith".to_string()),
+                    style: TextStyle::default(),
+                    children: vec![
+                        CueNode::text("Hi "),
+                        CueNode::Span {
+                            kind: SpanKind::Class,
+                            classes: vec!["yellow".to_string(), "big".to_string()],
+                            annotation: None,
+                            style: TextStyle::default(),
+                            children: vec![CueNode::text("there")],
+                        },
+                        CueNode::text(" <3"),
+                    ],
+                },
+                CueNode::text(" "),
+                // </i> does not close the open <b>, so both run to the end
+                CueNode::span(SpanKind::Italic, vec![CueNode::span(SpanKind::Bold, vec![CueNode::text("bold tail")])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ruby_timestamps_and_unknown_tags() {
+        let nodes = parse_cue_text("<ruby>漢<rt>kan</ruby><00:00:05.000>next <font>x</font> &unknown; <rt>no</rt>");
+        assert_eq!(
+            nodes,
+            vec![
+                CueNode::span(SpanKind::Ruby, vec![CueNode::text("漢"), CueNode::span(SpanKind::RubyText, vec![CueNode::text("kan")])]),
+                CueNode::Timestamp(5.0),
+                CueNode::text("next x &unknown; no"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,629 @@
+use wasm_bindgen::prelude::*;
+use crate::logger::LogLevel;
+use crate::player::error::{Severity, VideoError};
+use crate::player::element_ids::ElementClasses;
+use crate::player::error_overlay::DEFAULT_ERROR_TIMEOUTS;
+use crate::player::abr::AbrStrategyKind;
+use crate::player::keyboard::Keymap;
+use crate::player::playlist::PlaylistItem;
+use crate::player::preferences::DEFAULT_PREFERENCES_KEY;
+use crate::player::resume::ResumeMode;
+use crate::player::watchdog::{DEFAULT_MAX_RETRIES, DEFAULT_STALL_TIMEOUT};
+
+// MIME types the player can hand to a <source> element
+const SUPPORTED_MIME_TYPES: [&str; 10] = [
+    "video/mp4",
+    "video/webm",
+    "video/ogg",
+    "audio/mp4",
+    "audio/mpeg",
+    "audio/webm",
+    "audio/ogg",
+    "application/vnd.apple.mpegurl",
+    "application/x-mpegurl",
+    "application/dash+xml",
+];
+
+// URL schemes accepted for absolute source URLs
+const SUPPORTED_URL_SCHEMES: [&str; 4] = ["http", "https", "blob", "data"];
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preload {
+    None,
+    Metadata,
+    Auto,
+}
+
+impl Preload {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Preload::None => "none",
+            Preload::Metadata => "metadata",
+            Preload::Auto => "auto",
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoSource {
+    pub(crate) url: String,
+    pub(crate) mime_type: String,
+    pub(crate) label: Option<String>,
+    // Copies of the same media elsewhere, such as on another CDN, tried when this one stalls
+    pub(crate) mirrors: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl VideoSource {
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: String, mime_type: String, label: Option<String>) -> VideoSource {
+        VideoSource {
+            url,
+            mime_type,
+            label,
+            mirrors: Vec::new(),
+        }
+    }
+
+    pub fn add_mirror(&mut self, url: String) {
+        self.mirrors.push(url);
+    }
+
+    pub fn mirrors(&self) -> Vec<String> {
+        self.mirrors.clone()
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn mime_type(&self) -> String {
+        self.mime_type.clone()
+    }
+
+    pub fn label(&self) -> Option<String> {
+        self.label.clone()
+    }
+}
+
+/// A subtitle file offered in the captions menu.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextTrackSource {
+    pub(crate) url: String,
+    pub(crate) language: String,
+    pub(crate) label: Option<String>,
+}
+
+#[wasm_bindgen]
+impl TextTrackSource {
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: String, language: String, label: Option<String>) -> TextTrackSource {
+        TextTrackSource {
+            url,
+            language,
+            label,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn language(&self) -> String {
+        self.language.clone()
+    }
+
+    pub fn label(&self) -> Option<String> {
+        self.label.clone()
+    }
+}
+
+impl PlayerConfig {
+    /// The configured sources, captions and poster as the item the player loads first.
+    pub(crate) fn media_item(&self) -> PlaylistItem {
+        match self.playlist.first() {
+            Some(item) => item.clone(),
+            None => PlaylistItem {
+                sources: self.sources.clone(),
+                text_tracks: self.text_tracks.clone(),
+                title: None,
+                poster: self.poster.clone(),
+                content_id: self.content_id.clone(),
+            },
+        }
+    }
+}
+
+impl VideoSource {
+    pub fn validate(&self) -> Result<(), VideoError> {
+        validate_source_url(&self.url)?;
+        for mirror in &self.mirrors {
+            validate_source_url(mirror)?;
+        }
+        validate_mime_type(&self.mime_type)
+    }
+}
+
+/// Adds `url` as a mirror of the last of `sources`.
+pub(crate) fn add_mirror(sources: &mut [VideoSource], url: String) -> Result<(), VideoError> {
+    let source = sources
+        .last_mut()
+        .ok_or_else(|| VideoError::InvalidConfig("add a source before its mirrors".to_string()))?;
+    source.add_mirror(url);
+    Ok(())
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct PlayerConfig {
+    pub(crate) sources: Vec<VideoSource>,
+    pub(crate) text_tracks: Vec<TextTrackSource>,
+    pub(crate) poster: Option<String>,
+    pub(crate) preload: Preload,
+    pub(crate) autoplay: bool,
+    pub(crate) muted_autoplay: bool,
+    pub(crate) loop_playback: bool,
+    pub(crate) start_time: f64,
+    pub(crate) muted: bool,
+    pub(crate) element_classes: ElementClasses,
+    pub(crate) abr_strategy: AbrStrategyKind,
+    pub(crate) keymap: Keymap,
+    pub(crate) preferences_key: Option<String>,
+    pub(crate) resume: ResumeMode,
+    pub(crate) content_id: Option<String>,
+    pub(crate) playlist: Vec<PlaylistItem>,
+    pub(crate) gapless: bool,
+    pub(crate) crossfade: f64,
+    pub(crate) stall_timeout: f64,
+    pub(crate) max_retries: u32,
+    pub(crate) error_timeouts: [Option<f64>; 3],
+    pub(crate) log_level: Option<LogLevel>,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl PlayerConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PlayerConfig {
+        PlayerConfig {
+            sources: Vec::new(),
+            text_tracks: Vec::new(),
+            poster: None,
+            preload: Preload::Metadata,
+            autoplay: false,
+            muted_autoplay: false,
+            loop_playback: false,
+            start_time: 0.0,
+            muted: false,
+            element_classes: ElementClasses::new(),
+            abr_strategy: AbrStrategyKind::Throughput,
+            keymap: Keymap::new(),
+            preferences_key: Some(DEFAULT_PREFERENCES_KEY.to_string()),
+            resume: ResumeMode::Prompt,
+            content_id: None,
+            playlist: Vec::new(),
+            gapless: true,
+            crossfade: 0.0,
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            error_timeouts: DEFAULT_ERROR_TIMEOUTS,
+            log_level: None,
+        }
+    }
+
+    pub fn add_source(&mut self, url: String, mime_type: String, label: Option<String>) {
+        self.sources.push(VideoSource::new(url, mime_type, label));
+    }
+
+    pub fn sources(&self) -> Vec<VideoSource> {
+        self.sources.clone()
+    }
+
+    /// Adds a copy of the source added last, such as the same file on another CDN,
+    /// which playback fails over to when that source keeps stalling.
+    pub fn add_mirror(&mut self, url: String) -> Result<(), VideoError> {
+        add_mirror(&mut self.sources, url)
+    }
+
+    /// Adds a WebVTT, SubRip, TTML or ASS subtitle file; `language` is a BCP 47 tag such as "en" or "pt-BR".
+    pub fn add_text_track(&mut self, url: String, language: String, label: Option<String>) {
+        self.text_tracks.push(TextTrackSource::new(url, language, label));
+    }
+
+    pub fn text_tracks(&self) -> Vec<TextTrackSource> {
+        self.text_tracks.clone()
+    }
+
+    pub fn set_poster(&mut self, poster: Option<String>) {
+        self.poster = poster;
+    }
+
+    pub fn poster(&self) -> Option<String> {
+        self.poster.clone()
+    }
+
+    pub fn set_preload(&mut self, preload: Preload) {
+        self.preload = preload;
+    }
+
+    pub fn preload(&self) -> Preload {
+        self.preload
+    }
+
+    pub fn set_autoplay(&mut self, autoplay: bool) {
+        self.autoplay = autoplay;
+    }
+
+    pub fn autoplay(&self) -> bool {
+        self.autoplay
+    }
+
+    /// When the browser refuses to start playback with sound, start it muted
+    /// instead of showing a click-to-play button.
+    pub fn set_muted_autoplay(&mut self, muted_autoplay: bool) {
+        self.muted_autoplay = muted_autoplay;
+    }
+
+    pub fn muted_autoplay(&self) -> bool {
+        self.muted_autoplay
+    }
+
+    pub fn set_loop(&mut self, loop_playback: bool) {
+        self.loop_playback = loop_playback;
+    }
+
+    pub fn loop_playback(&self) -> bool {
+        self.loop_playback
+    }
+
+    pub fn set_start_time(&mut self, start_time: f64) {
+        self.start_time = start_time;
+    }
+
+    pub fn start_time(&self) -> f64 {
+        self.start_time
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Fails if any class is empty or written as a selector.
+    pub fn set_element_classes(&mut self, element_classes: ElementClasses) -> Result<(), VideoError> {
+        element_classes.validate()?;
+        self.element_classes = element_classes;
+        Ok(())
+    }
+
+    pub fn element_classes(&self) -> ElementClasses {
+        self.element_classes.clone()
+    }
+
+    /// Selects the strategy adaptive (HLS/DASH) streams use to pick renditions.
+    pub fn set_abr_strategy(&mut self, abr_strategy: AbrStrategyKind) {
+        self.abr_strategy = abr_strategy;
+    }
+
+    pub fn abr_strategy(&self) -> AbrStrategyKind {
+        self.abr_strategy
+    }
+
+    /// Replaces the default YouTube-style shortcuts; pass `Keymap.empty()` to turn them off.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn keymap(&self) -> Keymap {
+        self.keymap.clone()
+    }
+
+    /// Sets the `localStorage` key the viewer's volume, speed, caption language and
+    /// quality are remembered under; `None` stops them being saved at all. Players
+    /// sharing a key share their preferences.
+    pub fn set_preferences_key(&mut self, preferences_key: Option<String>) {
+        self.preferences_key = preferences_key;
+    }
+
+    pub fn preferences_key(&self) -> Option<String> {
+        self.preferences_key.clone()
+    }
+
+    /// Chooses whether a partly watched video offers to resume, resumes by itself or starts over.
+    pub fn set_resume(&mut self, resume: ResumeMode) {
+        self.resume = resume;
+    }
+
+    pub fn resume(&self) -> ResumeMode {
+        self.resume
+    }
+
+    /// Names the video in the saved playback positions; defaults to the first source URL,
+    /// which does not suit URLs with expiring tokens.
+    pub fn set_content_id(&mut self, content_id: Option<String>) {
+        self.content_id = content_id;
+    }
+
+    pub fn content_id(&self) -> Option<String> {
+        self.content_id.clone()
+    }
+
+    /// Queues an item in the player's playlist. A config with a playlist starts on its
+    /// first item and must not add sources of its own.
+    pub fn add_playlist_item(&mut self, item: PlaylistItem) {
+        self.playlist.push(item);
+    }
+
+    pub fn playlist_items(&self) -> Vec<PlaylistItem> {
+        self.playlist.clone()
+    }
+
+    /// Preloads the next playlist item in a hidden video element so it starts without
+    /// a gap. Streams are always loaded when their turn comes.
+    pub fn set_gapless(&mut self, gapless: bool) {
+        self.gapless = gapless;
+    }
+
+    pub fn gapless(&self) -> bool {
+        self.gapless
+    }
+
+    /// Seconds the end of one playlist item overlaps the start of the next, fading
+    /// out; 0 cuts straight over. Only applies to gapless transitions.
+    pub fn set_crossfade(&mut self, seconds: f64) {
+        self.crossfade = seconds;
+    }
+
+    pub fn crossfade(&self) -> f64 {
+        self.crossfade
+    }
+
+    /// Seconds playback may stay stuck, neither moving nor receiving data, before
+    /// the player reloads it.
+    pub fn set_stall_timeout(&mut self, seconds: f64) {
+        self.stall_timeout = seconds;
+    }
+
+    pub fn stall_timeout(&self) -> f64 {
+        self.stall_timeout
+    }
+
+    /// Reloads of a stuck source, with growing delays between them, before playback
+    /// fails over to the next source or mirror.
+    pub fn set_max_retries(&mut self, max_retries: u32) {
+        self.max_retries = max_retries;
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Seconds messages of `severity` stay in the error overlay, or `None` to keep
+    /// them until dismissed. Info and warning messages go after 5 and 10 seconds by default.
+    pub fn set_error_timeout(&mut self, severity: Severity, seconds: Option<f64>) {
+        self.error_timeouts[severity as usize] = seconds;
+    }
+
+    pub fn error_timeout(&self, severity: Severity) -> Option<f64> {
+        self.error_timeouts[severity as usize]
+    }
+
+    /// Minimum level of the messages the player logs, shared by every player on the
+    /// page. A `log` query parameter or localStorage entry overrides it; `None`
+    /// leaves the level as it is.
+    pub fn set_log_level(&mut self, level: Option<LogLevel>) {
+        self.log_level = level;
+    }
+
+    pub fn log_level(&self) -> Option<LogLevel> {
+        self.log_level
+    }
+
+    pub fn validate(&self) -> Result<(), VideoError> {
+        if !self.playlist.is_empty() && !self.sources.is_empty() {
+            return Err(VideoError::InvalidConfig("use either sources or a playlist, not both".to_string()));
+        }
+        if self.playlist.is_empty() {
+            self.media_item().validate()?;
+        }
+        for item in &self.playlist {
+            item.validate()?;
+        }
+        if !self.start_time.is_finite() || self.start_time < 0.0 {
+            return Err(VideoError::InvalidConfig(format!(
+                "start time must be a non-negative number, got {}",
+                self.start_time
+            )));
+        }
+        if !self.crossfade.is_finite() || self.crossfade < 0.0 {
+            return Err(VideoError::InvalidConfig(format!(
+                "crossfade must be a non-negative number of seconds, got {}",
+                self.crossfade
+            )));
+        }
+        if !self.stall_timeout.is_finite() || self.stall_timeout <= 0.0 {
+            return Err(VideoError::InvalidConfig(format!(
+                "stall timeout must be a positive number of seconds, got {}",
+                self.stall_timeout
+            )));
+        }
+        if let Some(seconds) = self.error_timeouts.iter().flatten().find(|seconds| !seconds.is_finite() || **seconds <= 0.0) {
+            return Err(VideoError::InvalidConfig(format!(
+                "error timeout must be a positive number of seconds, got {}",
+                seconds
+            )));
+        }
+        if self.preferences_key.as_ref().is_some_and(|key| key.is_empty()) {
+            return Err(VideoError::InvalidConfig("preferences key cannot be empty".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Accepts relative paths and absolute URLs with a scheme the video element can load.
+pub fn validate_source_url(url: &str) -> Result<(), VideoError> {
+    if url.trim().is_empty() {
+        return Err(VideoError::InvalidSourceUrl("URL is empty".to_string()));
+    }
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(VideoError::InvalidSourceUrl(url.to_string()));
+    }
+
+    if let Some((scheme, rest)) = split_scheme(url) {
+        let scheme = scheme.to_ascii_lowercase();
+        if !SUPPORTED_URL_SCHEMES.contains(&scheme.as_str()) {
+            return Err(VideoError::InvalidSourceUrl(url.to_string()));
+        }
+        if scheme == "http" || scheme == "https" {
+            let host = rest
+                .strip_prefix("//")
+                .map(|authority| authority.split(['/', '?', '#']).next().unwrap_or(""))
+                .unwrap_or("");
+            if host.is_empty() {
+                return Err(VideoError::InvalidSourceUrl(url.to_string()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the MIME essence (ignoring parameters such as `codecs`) against the supported list.
+pub fn validate_mime_type(mime_type: &str) -> Result<(), VideoError> {
+    let essence = mime_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    if SUPPORTED_MIME_TYPES.contains(&essence.as_str()) {
+        Ok(())
+    } else {
+        Err(VideoError::UnsupportedSourceType(mime_type.to_string()))
+    }
+}
+
+fn split_scheme(url: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    let first = chars.next()?;
+    if first.is_ascii_alphabetic() && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c)) {
+        Some((scheme, rest))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_source(url: &str, mime_type: &str) -> PlayerConfig {
+        let mut config = PlayerConfig::new();
+        config.add_source(url.to_string(), mime_type.to_string(), None);
+        config
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let mut config = config_with_source("https://example.com/video.mp4", "video/mp4");
+        config.add_source("/media/video.webm".to_string(), "video/webm; codecs=\"vp9\"".to_string(), Some("720p".to_string()));
+        config.set_poster(Some("poster.jpg".to_string()));
+        config.set_start_time(12.5);
+        config.add_text_track("subtitles/en.vtt".to_string(), "en".to_string(), Some("English".to_string()));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_config_without_sources() {
+        assert!(matches!(PlayerConfig::new().validate(), Err(VideoError::NoSources)));
+    }
+
+    #[test]
+    fn test_bad_urls() {
+        for url in ["", "   ", "ftp://example.com/video.mp4", "https://", "http:///video.mp4", "video file.mp4", "javascript:alert(1)"] {
+            let config = config_with_source(url, "video/mp4");
+            assert!(matches!(config.validate(), Err(VideoError::InvalidSourceUrl(_))), "{:?}", url);
+        }
+        let mut config = config_with_source("video.mp4", "video/mp4");
+        config.add_text_track("ftp://example.com/en.vtt".to_string(), "en".to_string(), None);
+        assert!(matches!(config.validate(), Err(VideoError::InvalidSourceUrl(_))));
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let config = config_with_source("video.avi", "video/x-msvideo");
+        assert!(matches!(config.validate(), Err(VideoError::UnsupportedSourceType(_))));
+    }
+
+    #[test]
+    fn test_invalid_start_time() {
+        let mut config = config_with_source("video.mp4", "video/mp4");
+        config.set_start_time(-1.0);
+        assert!(matches!(config.validate(), Err(VideoError::InvalidConfig(_))));
+        config.set_start_time(f64::NAN);
+        assert!(matches!(config.validate(), Err(VideoError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_mirrors_and_stall_timeout() {
+        let mut config = PlayerConfig::new();
+        assert!(matches!(config.add_mirror("https://cdn2.example.com/video.mp4".to_string()), Err(VideoError::InvalidConfig(_))));
+        config.add_source("https://cdn1.example.com/video.mp4".to_string(), "video/mp4".to_string(), None);
+        config.add_mirror("https://cdn2.example.com/video.mp4".to_string()).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.sources()[0].mirrors(), ["https://cdn2.example.com/video.mp4"]);
+        config.add_mirror("ftp://cdn3.example.com/video.mp4".to_string()).unwrap();
+        assert!(matches!(config.validate(), Err(VideoError::InvalidSourceUrl(_))));
+
+        let mut config = config_with_source("video.mp4", "video/mp4");
+        config.set_stall_timeout(0.0);
+        assert!(matches!(config.validate(), Err(VideoError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_error_timeouts() {
+        let mut config = config_with_source("video.mp4", "video/mp4");
+        assert_eq!(config.error_timeout(Severity::Info), Some(5.0));
+        assert_eq!(config.error_timeout(Severity::Fatal), None);
+        config.set_error_timeout(Severity::Fatal, Some(30.0));
+        config.set_error_timeout(Severity::Warning, None);
+        assert!(config.validate().is_ok());
+        config.set_error_timeout(Severity::Info, Some(-1.0));
+        assert!(matches!(config.validate(), Err(VideoError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_preferences_key() {
+        let mut config = config_with_source("video.mp4", "video/mp4");
+        config.set_preferences_key(None);
+        assert!(config.validate().is_ok());
+        config.set_preferences_key(Some(String::new()));
+        assert!(matches!(config.validate(), Err(VideoError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_playlist_config() {
+        let mut config = PlayerConfig::new();
+        config.add_playlist_item(PlaylistItem::new("intro.mp4".to_string(), "video/mp4".to_string(), Some("Intro".to_string())));
+        config.add_playlist_item(PlaylistItem::new("part2.webm".to_string(), "video/webm".to_string(), None));
+        assert!(config.validate().is_ok());
+        assert_eq!(config.media_item().title(), Some("Intro".to_string()));
+        config.set_crossfade(-2.0);
+        assert!(matches!(config.validate(), Err(VideoError::InvalidConfig(_))));
+        config.set_crossfade(2.0);
+        assert!(config.validate().is_ok());
+        config.add_source("video.mp4".to_string(), "video/mp4".to_string(), None);
+        assert!(matches!(config.validate(), Err(VideoError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,368 @@
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlVideoElement};
+use crate::player::abr::AbrStrategyKind;
+use crate::player::captions::CaptionState;
+use crate::player::element_ids::{ElementClasses, ElementIds};
+use crate::player::error::VideoError;
+use crate::player::get_element_by_id;
+use crate::player::error_overlay::ErrorQueue;
+use crate::player::events::EventBus;
+use crate::player::gapless::GaplessState;
+use crate::player::keyboard::Keymap;
+use crate::player::listeners::ListenerRegistry;
+use crate::player::playlist::Queue;
+use crate::player::preferences::Preferences;
+use crate::player::quality::QualitySource;
+use crate::player::resume::ResumeState;
+use crate::player::seek_bar::SeekBar;
+use crate::player::state::{Action, Store, VideoState};
+use crate::player::watchdog::RecoveryState;
+
+thread_local! {
+    // Instance the exported free functions operate on
+    static DEFAULT_CONTEXT: RefCell<Option<Rc<PlayerContext>>> = const { RefCell::new(None) };
+    // Live instances keyed by video element id, used to stop re-init double-binding
+    static LIVE_CONTEXTS: RefCell<HashMap<String, Weak<PlayerContext>>> = RefCell::new(HashMap::new());
+}
+
+/// Everything one player instance owns, so several players can share a page.
+pub struct PlayerContext {
+    /// Ids of the page elements this player drives.
+    pub(crate) ids: ElementIds,
+    /// Classes its controls are drawn with.
+    pub(crate) classes: ElementClasses,
+    /// Playback state, changed only through actions.
+    pub(crate) store: RefCell<Store>,
+    /// Every listener it registered, removed again on teardown.
+    pub(crate) listeners: RefCell<ListenerRegistry>,
+    /// Object URLs it created for streams, revoked on teardown.
+    pub(crate) object_urls: RefCell<Vec<String>>,
+    /// Elements it inserted into the page, such as `<source>`s and prompts.
+    pub(crate) added_elements: RefCell<Vec<Element>>,
+    /// Renditions the quality menu offers for the current media.
+    pub(crate) quality_source: RefCell<Option<QualitySource>>,
+    /// Caption tracks of the current media and the cues showing.
+    pub(crate) captions: RefCell<CaptionState>,
+    /// The custom seek bar, once it is set up.
+    pub(crate) seek_bar: RefCell<Option<SeekBar>>,
+    /// Keyboard shortcuts.
+    pub(crate) keymap: RefCell<Keymap>,
+    /// What the viewer chose on earlier visits.
+    pub(crate) preferences: RefCell<Preferences>,
+    /// localStorage key preferences are saved under, `None` to keep them in memory only.
+    pub(crate) preferences_key: RefCell<Option<String>>,
+    /// Where to resume the current media, and the prompt offering it.
+    pub(crate) resume: RefCell<ResumeState>,
+    /// Playlist items and the one playing.
+    pub(crate) playlist: RefCell<Queue>,
+    /// The next playlist item preloading behind the current one.
+    pub(crate) gapless: RefCell<GaplessState>,
+    /// The watchdog that recovers stuck playback.
+    pub(crate) recovery: RefCell<RecoveryState>,
+    /// Who listens to the player's events.
+    pub(crate) events: RefCell<EventBus>,
+    /// Messages in the error overlay, shared with the timers that take them down.
+    pub(crate) errors: Rc<RefCell<ErrorQueue>>,
+    /// How adaptive streams pick renditions.
+    pub(crate) abr_strategy: Cell<AbrStrategyKind>,
+    /// Whether a refused play() is retried muted rather than waiting for a click.
+    pub(crate) muted_autoplay: Cell<bool>,
+    /// The click-to-play button, while the browser is waiting for the viewer to start playback.
+    pub(crate) click_to_play: RefCell<Option<Element>>,
+    /// Bumped whenever other media is loaded, so work for the previous media stops.
+    pub(crate) media_generation: Cell<u64>,
+}
+
+impl PlayerContext {
+    pub fn new(ids: ElementIds, classes: ElementClasses) -> Rc<PlayerContext> {
+        Rc::new(PlayerContext {
+            ids,
+            classes,
+            store: RefCell::new(Store::default()),
+            listeners: RefCell::new(ListenerRegistry::new()),
+            object_urls: RefCell::new(Vec::new()),
+            added_elements: RefCell::new(Vec::new()),
+            quality_source: RefCell::new(None),
+            captions: RefCell::new(CaptionState::default()),
+            seek_bar: RefCell::new(None),
+            keymap: RefCell::new(Keymap::new()),
+            preferences: RefCell::new(Preferences::default()),
+            preferences_key: RefCell::new(None),
+            resume: RefCell::new(ResumeState::default()),
+            playlist: RefCell::new(Queue::default()),
+            gapless: RefCell::new(GaplessState::default()),
+            recovery: RefCell::new(RecoveryState::default()),
+            events: RefCell::new(EventBus::default()),
+            errors: Rc::new(RefCell::new(ErrorQueue::default())),
+            abr_strategy: Cell::new(AbrStrategyKind::default()),
+            muted_autoplay: Cell::new(false),
+            click_to_play: RefCell::new(None),
+            media_generation: Cell::new(0),
+        })
+    }
+
+    pub fn ids(&self) -> &ElementIds {
+        &self.ids
+    }
+
+    pub fn classes(&self) -> &ElementClasses {
+        &self.classes
+    }
+
+    pub fn video_element(&self) -> Result<HtmlVideoElement, VideoError> {
+        let video_player = &self.ids.video_player;
+        get_element_by_id(video_player)?
+            .dyn_into::<HtmlVideoElement>()
+            .map_err(|_| VideoError::ElementNotFound(format!("#{} is not a <video> element", video_player)))
+    }
+
+    pub fn element(&self, id: &str) -> Result<Element, VideoError> {
+        get_element_by_id(id)
+    }
+
+    /// Applies `action` to the player's state, returning the states before and after.
+    /// Use `store::dispatch_for` so the controls are rendered from the change.
+    pub fn dispatch(&self, action: Action) -> Result<(VideoState, VideoState), VideoError> {
+        let mut store = self.store
+            .try_borrow_mut()
+            .map_err(|e| VideoError::StateError(format!("Failed to borrow state: {:?}", e)))?;
+        let previous = store.dispatch(action);
+        Ok((previous, store.state().clone()))
+    }
+
+    pub fn state(&self) -> Result<VideoState, VideoError> {
+        self.store
+            .try_borrow()
+            .map(|store| store.state().clone())
+            .map_err(|e| VideoError::StateError(format!("Failed to borrow state: {:?}", e)))
+    }
+
+    pub fn listeners_mut(&self) -> Result<RefMut<'_, ListenerRegistry>, VideoError> {
+        self.listeners
+            .try_borrow_mut()
+            .map_err(|e| VideoError::StateError(format!("Failed to borrow listeners: {:?}", e)))
+    }
+
+    /// Records an element the player inserted into the page so teardown can remove it.
+    pub fn track_element(&self, element: Element) {
+        self.added_elements.borrow_mut().push(element);
+    }
+
+    /// Stops tracking an element the player has removed itself.
+    pub fn untrack_element(&self, element: &Element) {
+        self.added_elements.borrow_mut().retain(|tracked| tracked != element);
+    }
+
+    pub fn track_object_url(&self, url: &str) {
+        self.object_urls.borrow_mut().push(url.to_string());
+    }
+
+    pub fn set_quality_source(&self, source: QualitySource) {
+        *self.quality_source.borrow_mut() = Some(source);
+    }
+
+    pub fn quality_source(&self) -> Option<QualitySource> {
+        self.quality_source.borrow().clone()
+    }
+
+    pub fn media_generation(&self) -> u64 {
+        self.media_generation.get()
+    }
+
+    /// Marks the start of new media, leaving streams and loads for the old one to wind down.
+    pub fn next_media_generation(&self) -> u64 {
+        self.media_generation.set(self.media_generation.get() + 1);
+        self.media_generation.get()
+    }
+
+    pub fn revoke_object_url(&self, url: &str) -> Result<(), VideoError> {
+        self.object_urls.borrow_mut().retain(|tracked| tracked != url);
+        web_sys::Url::revoke_object_url(url)
+            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to revoke object URL: {:?}", e)))
+    }
+
+    fn release_resources(&self) {
+        self.listeners.borrow_mut().remove_all();
+        for url in self.object_urls.borrow_mut().drain(..) {
+            web_sys::Url::revoke_object_url(&url).unwrap_or_default();
+        }
+        for element in self.added_elements.borrow_mut().drain(..) {
+            element.remove();
+        }
+        self.quality_source.borrow_mut().take();
+        *self.captions.borrow_mut() = CaptionState::default();
+        self.seek_bar.borrow_mut().take();
+        *self.resume.borrow_mut() = ResumeState::default();
+        *self.playlist.borrow_mut() = Queue::default();
+        *self.gapless.borrow_mut() = GaplessState::default();
+        *self.recovery.borrow_mut() = RecoveryState::default();
+        *self.events.borrow_mut() = EventBus::default();
+        self.errors.borrow_mut().clear();
+        self.click_to_play.borrow_mut().take();
+        self.next_media_generation();
+    }
+
+    /// Removes every listener, revokes object URLs, removes inserted elements and resets state.
+    pub fn destroy(self: &Rc<Self>) {
+        self.release_resources();
+        if let Ok(video_element) = self.video_element() {
+            video_element.pause().unwrap_or_default();
+            video_element.load();
+        }
+        if let Ok(mut store) = self.store.try_borrow_mut() {
+            *store = Store::default();
+        }
+
+        LIVE_CONTEXTS.with(|live| {
+            let mut live = live.borrow_mut();
+            let is_self = live
+                .get(&self.ids.video_player)
+                .is_some_and(|weak| std::ptr::eq(weak.as_ptr(), Rc::as_ptr(self)));
+            if is_self {
+                live.remove(&self.ids.video_player);
+            }
+        });
+        DEFAULT_CONTEXT.with(|default| {
+            let mut default = default.borrow_mut();
+            if default.as_ref().is_some_and(|context| Rc::ptr_eq(context, self)) {
+                *default = None;
+            }
+        });
+    }
+}
+
+// Dropping the last handle to a player releases what it holds even without destroy()
+impl Drop for PlayerContext {
+    fn drop(&mut self) {
+        self.release_resources();
+    }
+}
+
+/// Returns the default instance. Before any player claims it, each call gets a fresh
+/// unconfigured one bound to the stock element ids, which is not kept, so the first
+/// player initialised still becomes the default.
+pub fn default_context() -> Rc<PlayerContext> {
+    DEFAULT_CONTEXT
+        .with(|default| default.borrow().clone())
+        .unwrap_or_else(|| PlayerContext::new(ElementIds::default(), ElementClasses::default()))
+}
+
+/// Makes `context` the default instance unless a player has already claimed it.
+pub fn claim_default_context(context: &Rc<PlayerContext>) {
+    DEFAULT_CONTEXT.with(|default| {
+        let mut default = default.borrow_mut();
+        if default.is_none() {
+            *default = Some(context.clone());
+        }
+    });
+}
+
+/// Whether `context` is the instance the exported free functions operate on.
+pub fn is_default_context(context: &Rc<PlayerContext>) -> bool {
+    DEFAULT_CONTEXT.with(|default| default.borrow().as_ref().is_some_and(|default| Rc::ptr_eq(default, context)))
+}
+
+pub fn set_default_context(context: Rc<PlayerContext>) {
+    DEFAULT_CONTEXT.with(|default| *default.borrow_mut() = Some(context));
+}
+
+/// Registers `context` as the live player for its video element, tearing down
+/// any earlier player bound to the same element so handlers are never bound twice.
+pub fn register_live_context(context: &Rc<PlayerContext>) {
+    let previous = LIVE_CONTEXTS.with(|live| {
+        live.borrow_mut()
+            .insert(context.ids.video_player.clone(), Rc::downgrade(context))
+            .and_then(|weak| weak.upgrade())
+    });
+    if let Some(previous) = previous {
+        if !Rc::ptr_eq(&previous, context) {
+            previous.destroy();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use crate::player::media::fake::{block_on, FakeMedia};
+    use crate::player::media::MediaBackend;
+    use crate::player::mute::get_is_muted;
+
+    fn player(video_player: &str) -> Rc<PlayerContext> {
+        let ids = ElementIds { video_player: video_player.to_string(), ..ElementIds::default() };
+        PlayerContext::new(ids, ElementClasses::default())
+    }
+
+    // Counts `event` on `media` through the player's listener registry
+    fn count_events(context: &PlayerContext, media: &FakeMedia, event: &'static str) -> Rc<Cell<u32>> {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+        context.listeners_mut().unwrap().add_media(media, event, move || counter.set(counter.get() + 1)).unwrap();
+        count
+    }
+
+    #[test]
+    fn test_players_keep_separate_state_and_listeners() {
+        let (first, second) = (player("firstVideo"), player("secondVideo"));
+        let (first_media, second_media) = (FakeMedia::new(10.0), FakeMedia::new(10.0));
+        let first_plays = count_events(&first, &first_media, "play");
+        let second_plays = count_events(&second, &second_media, "play");
+
+        first.dispatch(Action::VolumeChanged { volume: 0.3, muted: true }).unwrap();
+        assert!(first.state().unwrap().is_muted);
+        assert_eq!(second.state().unwrap(), VideoState::default());
+        block_on(first_media.play()).unwrap();
+        assert_eq!((first_plays.get(), second_plays.get()), (1, 0));
+
+        // The first player initialised keeps the free functions
+        claim_default_context(&first);
+        claim_default_context(&second);
+        assert!(is_default_context(&first) && !is_default_context(&second));
+
+        first.destroy();
+        assert!(first.listeners.borrow().is_empty());
+        assert_eq!(second.listeners.borrow().len(), 1);
+        block_on(second_media.play()).unwrap();
+        assert_eq!((first_plays.get(), second_plays.get()), (1, 1));
+        // Tearing down the default player hands the free functions to the next one to claim them
+        claim_default_context(&second);
+        assert!(is_default_context(&second));
+    }
+
+    #[test]
+    fn test_free_functions_before_init_leave_the_default_unclaimed() {
+        assert!(!get_is_muted());
+        assert!(!Rc::ptr_eq(&default_context(), &default_context()));
+
+        let context = player("videoPlayer");
+        claim_default_context(&context);
+        assert!(is_default_context(&context));
+        assert!(Rc::ptr_eq(&default_context(), &context));
+    }
+
+    #[test]
+    fn test_reinitialising_an_element_unbinds_the_previous_player() {
+        let media = FakeMedia::new(10.0);
+        let first = player("videoPlayer");
+        register_live_context(&first);
+        let first_plays = count_events(&first, &media, "play");
+
+        // init_video_player registers the new player before binding its own listeners
+        let second = player("videoPlayer");
+        register_live_context(&second);
+        assert!(first.listeners.borrow().is_empty());
+        let second_plays = count_events(&second, &media, "play");
+
+        block_on(media.play()).unwrap();
+        assert_eq!((first_plays.get(), second_plays.get()), (0, 1));
+        assert_eq!(second.listeners.borrow().len(), 1);
+
+        // Registering the live player again leaves it bound
+        register_live_context(&second);
+        assert_eq!(second.listeners.borrow().len(), 1);
+    }
+}
@@ -0,0 +1,636 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::player::abr::{AbrStrategyKind, RenditionInfo};
+use crate::player::context::PlayerContext;
+use crate::player::error::VideoError;
+use crate::player::fetch::fetch_text;
+use crate::player::mse::start_stream;
+use crate::player::segments::{ByteRange, SegmentKind, SegmentProvider, SegmentRequest, MIN_REFRESH_INTERVAL};
+
+pub mod mpd;
+use mpd::{parse_mpd, ContentType, DashSegment, Mpd, Representation};
+
+pub const DASH_MIME_TYPE: &str = "application/dash+xml";
+
+// Live playback starts this many segments from the newest available one
+const LIVE_EDGE_SEGMENTS: usize = 3;
+// Reload interval for dynamic manifests that do not set minimumUpdatePeriod
+const DEFAULT_UPDATE_PERIOD: f64 = 2.0;
+// Tolerance when comparing segment boundaries computed from different timescales
+const TIME_EPSILON: f64 = 1e-3;
+
+pub fn is_dash_source(url: &str, mime_type: &str) -> bool {
+    let essence = mime_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    essence == DASH_MIME_TYPE || path.to_ascii_lowercase().ends_with(".mpd")
+}
+
+/// Fetches the MPD at `url` and streams its video and audio adaptation sets through
+/// Media Source Extensions, one `SourceBuffer` each, from `start_time` on.
+pub async fn start_dash(context: Rc<PlayerContext>, url: String, abr_strategy: AbrStrategyKind, start_time: f64) -> Result<(), VideoError> {
+    let text = fetch_text(&url).await?;
+    start_stream(&context, dash_tracks(&url, &text, start_time)?, abr_strategy, start_time);
+    Ok(())
+}
+
+/// One provider per video and audio adaptation set of the MPD, each positioned at `start_time`.
+fn dash_tracks(url: &str, text: &str, start_time: f64) -> Result<Vec<Rc<RefCell<dyn SegmentProvider>>>, VideoError> {
+    let mut tracks: Vec<Rc<RefCell<dyn SegmentProvider>>> = Vec::new();
+    for content_type in [ContentType::Video, ContentType::Audio] {
+        let mut provider = DashProvider::new(url, text, content_type)?;
+        if provider.has_content() {
+            if start_time > 0.0 {
+                provider.seek(start_time);
+            }
+            tracks.push(Rc::new(RefCell::new(provider)));
+        }
+    }
+    if tracks.is_empty() {
+        return Err(VideoError::ManifestParseError("MPD has no audio or video adaptation sets".to_string()));
+    }
+    Ok(tracks)
+}
+
+/// One `sidx` reference, i.e. a subsegment of a `SegmentBase` representation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SidxReference {
+    pub byte_range: ByteRange,
+    /// Start relative to the earliest presentation time of the file, in seconds.
+    pub start: f64,
+    pub duration: f64,
+}
+
+/// Parses the `sidx` box found in `data`, which was read starting at byte `offset` of the file.
+pub fn parse_sidx(data: &[u8], offset: u64) -> Result<Vec<SidxReference>, VideoError> {
+    let invalid = |reason: &str| VideoError::ManifestParseError(format!("Invalid sidx box: {}", reason));
+    let read_u32 = |position: usize| -> Result<u64, VideoError> {
+        data.get(position..position + 4)
+            .map(|bytes| u64::from(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])))
+            .ok_or_else(|| invalid("truncated"))
+    };
+    let read_u64 = |position: usize| -> Result<u64, VideoError> { Ok((read_u32(position)? << 32) | read_u32(position + 4)?) };
+
+    let mut position = 0;
+    while position + 8 <= data.len() {
+        let mut size = read_u32(position)? as usize;
+        let mut header = 8;
+        if size == 1 {
+            size = read_u64(position + 8)? as usize;
+            header = 16;
+        }
+        if size < header {
+            return Err(invalid("bad box size"));
+        }
+        if &data[position + 4..position + 8] != b"sidx" {
+            position += size;
+            continue;
+        }
+
+        let mut cursor = position + header;
+        let version = *data.get(cursor).ok_or_else(|| invalid("truncated"))?;
+        cursor += 8; // version, flags and reference_ID
+        let timescale = read_u32(cursor)?.max(1) as f64;
+        cursor += 4;
+        let (earliest_time, first_offset) = if version == 0 {
+            cursor += 8;
+            (read_u32(cursor - 8)?, read_u32(cursor - 4)?)
+        } else {
+            cursor += 16;
+            (read_u64(cursor - 16)?, read_u64(cursor - 8)?)
+        };
+        let count = (read_u32(cursor)? & 0xffff) as usize; // reserved(16) then reference_count(16)
+        cursor += 4;
+
+        // Offsets are relative to the first byte after the sidx box
+        let mut byte_offset = offset + (position + size) as u64 + first_offset;
+        let mut time = earliest_time as f64 / timescale;
+        let mut references = Vec::with_capacity(count);
+        for _ in 0..count {
+            let reference = read_u32(cursor)?;
+            if reference >> 31 == 1 {
+                return Err(invalid("hierarchical sidx is not supported"));
+            }
+            let referenced_size = reference & 0x7fff_ffff;
+            let duration = read_u32(cursor + 4)? as f64 / timescale;
+            references.push(SidxReference {
+                byte_range: ByteRange::from_length(byte_offset, referenced_size),
+                start: time,
+                duration,
+            });
+            byte_offset += referenced_size;
+            time += duration;
+            cursor += 12;
+        }
+        return Ok(references);
+    }
+    Err(invalid("no sidx box in index range"))
+}
+
+/// Walks the representations of one content type across the periods of an MPD.
+pub struct DashProvider {
+    url: String,
+    mpd: Mpd,
+    content_type: ContentType,
+    representation: usize,
+    clock: Box<dyn Fn() -> f64>,
+    // Presentation time of the next segment to hand out, None until playback starts
+    next_time: Option<f64>,
+    // SegmentBase subsegments keyed by representation URL, and the one being fetched
+    indexes: HashMap<String, Vec<DashSegment>>,
+    pending_index: Option<(String, ByteRange)>,
+    last_init: Option<(String, Option<ByteRange>)>,
+    last_period: Option<usize>,
+    // End of the last emitted segment on the element timeline, used for live streams
+    timeline: f64,
+    resync: bool,
+    pending: Option<SegmentRequest>,
+}
+
+impl DashProvider {
+    pub fn new(url: &str, text: &str, content_type: ContentType) -> Result<DashProvider, VideoError> {
+        DashProvider::with_clock(url, text, content_type, Box::new(|| js_sys::Date::now() / 1000.0))
+    }
+
+    /// Like `new`, reading wall-clock time (seconds since the epoch) from `clock`.
+    pub fn with_clock(url: &str, text: &str, content_type: ContentType, clock: Box<dyn Fn() -> f64>) -> Result<DashProvider, VideoError> {
+        Ok(DashProvider {
+            url: url.to_string(),
+            mpd: parse_mpd(text, url)?,
+            content_type,
+            representation: 0,
+            clock,
+            next_time: None,
+            indexes: HashMap::new(),
+            pending_index: None,
+            last_init: None,
+            last_period: None,
+            timeline: 0.0,
+            resync: false,
+            pending: None,
+        })
+    }
+
+    pub fn has_content(&self) -> bool {
+        (0..self.mpd.periods.len()).any(|period| self.representation_in(period).is_some())
+    }
+
+    fn representations_in(&self, period: usize) -> Option<&[Representation]> {
+        let set = self.mpd.periods.get(period)?.adaptation_sets.iter().find(|set| set.content_type == self.content_type)?;
+        Some(&set.representations)
+    }
+
+    fn representation_in(&self, period: usize) -> Option<&Representation> {
+        let representations = self.representations_in(period)?;
+        representations.get(self.representation.min(representations.len().checked_sub(1)?))
+    }
+
+    fn current_period(&self) -> usize {
+        self.next_time.map(|time| self.period_at(time)).unwrap_or(0)
+    }
+
+    fn now(&self) -> Option<f64> {
+        self.mpd.is_dynamic().then(|| (self.clock)())
+    }
+
+    fn period_at(&self, time: f64) -> usize {
+        self.mpd
+            .periods
+            .iter()
+            .rposition(|period| period.start <= time + TIME_EPSILON)
+            .unwrap_or(0)
+    }
+
+    /// Segments of the selected representation in `period`, or `None` while its index is unknown.
+    fn segments_in(&self, period: usize) -> Option<Vec<DashSegment>> {
+        let representation = self.representation_in(period)?;
+        if representation.segment_template.is_some() {
+            return Some(representation.template_segments(&self.mpd, period, self.now()));
+        }
+        let period_start = self.mpd.periods[period].start;
+        self.indexes.get(&representation.base_url).map(|segments| {
+            segments
+                .iter()
+                .cloned()
+                .map(|mut segment| {
+                    segment.start += period_start;
+                    segment
+                })
+                .collect()
+        })
+    }
+
+    fn presentation_end(&self) -> Option<f64> {
+        if let Some(duration) = self.mpd.media_presentation_duration {
+            return Some(duration);
+        }
+        let last = self.mpd.periods.len() - 1;
+        self.mpd.period_duration(last).map(|duration| self.mpd.periods[last].start + duration)
+    }
+
+    fn initial_time(&self) -> f64 {
+        if !self.mpd.is_dynamic() {
+            return self.mpd.periods[0].start;
+        }
+        let last_period = self.mpd.periods.len() - 1;
+        let segments = self.segments_in(last_period).unwrap_or_default();
+        segments
+            .len()
+            .checked_sub(LIVE_EDGE_SEGMENTS)
+            .and_then(|index| segments.get(index))
+            .or(segments.first())
+            .map(|segment| segment.start)
+            .unwrap_or(self.mpd.periods[last_period].start)
+    }
+
+    fn request_for(&mut self, period: usize, segment: &DashSegment) -> SegmentRequest {
+        let start = if self.mpd.is_dynamic() { self.timeline } else { segment.start };
+        self.timeline = start + segment.duration;
+        let discontinuity = std::mem::take(&mut self.resync)
+            || self.last_period.is_some_and(|last| last != period);
+        self.last_period = Some(period);
+        SegmentRequest {
+            url: segment.url.clone(),
+            byte_range: segment.byte_range,
+            kind: SegmentKind::Media,
+            start,
+            duration: segment.duration,
+            discontinuity,
+        }
+    }
+}
+
+impl SegmentProvider for DashProvider {
+    fn mime_type(&self) -> String {
+        match self.representation_in(self.current_period()) {
+            Some(Representation { mime_type, codecs: Some(codecs), .. }) => format!("{}; codecs=\"{}\"", mime_type, codecs),
+            Some(representation) => representation.mime_type.clone(),
+            None => String::new(),
+        }
+    }
+
+    fn manifest_url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn needs_manifest(&self) -> bool {
+        false
+    }
+
+    fn update_manifest(&mut self, text: &str) -> Result<(), VideoError> {
+        self.mpd = parse_mpd(text, &self.url)?;
+        Ok(())
+    }
+
+    fn next_segment(&mut self) -> Option<SegmentRequest> {
+        if let Some(pending) = self.pending.take() {
+            return Some(pending);
+        }
+        if self.pending_index.is_some() {
+            return None;
+        }
+        let mut time = match self.next_time {
+            Some(time) => time,
+            None => self.initial_time(),
+        };
+
+        loop {
+            let period = self.period_at(time);
+            let representation = self.representation_in(period)?.clone();
+
+            // SegmentBase representations list their subsegments in a sidx box
+            let segments = match self.segments_in(period) {
+                Some(segments) => segments,
+                None => {
+                    let index_range = representation.segment_base.as_ref()?.index_range?;
+                    self.next_time = Some(time);
+                    self.pending_index = Some((representation.base_url.clone(), index_range));
+                    return Some(SegmentRequest {
+                        url: representation.base_url.clone(),
+                        byte_range: Some(index_range),
+                        kind: SegmentKind::Index,
+                        start: time,
+                        duration: 0.0,
+                        discontinuity: false,
+                    });
+                }
+            };
+
+            if self.mpd.is_dynamic() {
+                if let Some(first) = segments.first().filter(|first| time < first.start - TIME_EPSILON) {
+                    // The time-shift window moved past us, continue from its oldest segment
+                    time = first.start;
+                    self.resync = true;
+                }
+            }
+
+            let next = segments.iter().find(|segment| segment.start + segment.duration > time + TIME_EPSILON);
+            let Some(segment) = next.cloned() else {
+                // Move on to the next period, or wait for the live manifest to grow
+                match self.mpd.periods.get(period + 1) {
+                    Some(next_period) if !self.mpd.is_dynamic() || next_period.start > time => {
+                        time = next_period.start;
+                        continue;
+                    }
+                    _ => {
+                        self.next_time = Some(time);
+                        return None;
+                    }
+                }
+            };
+
+            self.next_time = Some(segment.start + segment.duration);
+            let request = self.request_for(period, &segment);
+            let init = representation.initialization();
+            if init.is_some() && init != self.last_init {
+                self.last_init = init.clone();
+                let (url, byte_range) = init?;
+                self.pending = Some(request.clone());
+                return Some(SegmentRequest {
+                    url,
+                    byte_range,
+                    kind: SegmentKind::Init,
+                    start: request.start,
+                    duration: 0.0,
+                    discontinuity: request.discontinuity,
+                });
+            }
+            return Some(request);
+        }
+    }
+
+    fn index_loaded(&mut self, data: &[u8]) -> Result<(), VideoError> {
+        let Some((url, range)) = self.pending_index.take() else {
+            return Ok(());
+        };
+        let segments = parse_sidx(data, range.start)?
+            .into_iter()
+            .enumerate()
+            .map(|(number, reference)| DashSegment {
+                url: url.clone(),
+                byte_range: Some(reference.byte_range),
+                number: number as u64,
+                start: reference.start,
+                duration: reference.duration,
+            })
+            .collect();
+        self.indexes.insert(url, segments);
+        Ok(())
+    }
+
+    fn seek(&mut self, time: f64) {
+        if self.mpd.is_dynamic() {
+            return;
+        }
+        self.next_time = Some(time);
+        self.pending = None;
+        self.last_init = None;
+        self.resync = true;
+    }
+
+    fn duration(&self) -> Option<f64> {
+        if self.mpd.is_dynamic() {
+            None
+        } else {
+            self.presentation_end()
+        }
+    }
+
+    fn refresh_interval(&self) -> Option<f64> {
+        self.mpd
+            .is_dynamic()
+            .then(|| self.mpd.minimum_update_period.unwrap_or(DEFAULT_UPDATE_PERIOD).max(MIN_REFRESH_INTERVAL))
+    }
+
+    fn is_complete(&self) -> bool {
+        if self.mpd.is_dynamic() || self.pending.is_some() || self.pending_index.is_some() {
+            return false;
+        }
+        match (self.next_time, self.presentation_end()) {
+            (Some(time), Some(end)) => time >= end - TIME_EPSILON,
+            _ => false,
+        }
+    }
+
+    fn renditions(&self) -> Vec<RenditionInfo> {
+        self.representations_in(self.current_period())
+            .unwrap_or_default()
+            .iter()
+            .map(|representation| RenditionInfo {
+                bandwidth: representation.bandwidth,
+                width: representation.width,
+                height: representation.height,
+            })
+            .collect()
+    }
+
+    fn rendition(&self) -> usize {
+        self.representation
+    }
+
+    fn select_rendition(&mut self, index: usize) {
+        if index == self.representation || index >= self.renditions().len() {
+            return;
+        }
+        self.representation = index;
+        // The new representation's init segment follows because it differs from the last one
+        if !self.mpd.is_dynamic() {
+            self.resync = true;
+        }
+        if let Some(pending) = self.pending.take() {
+            self.next_time = Some(pending.start);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST_URL: &str = "https://origin.example.com/content/manifest.mpd";
+
+    // availabilityStartTime of the dynamic fixtures
+    const AVAILABILITY_START: f64 = 1_704_067_200.0;
+
+    fn provider_at(text: &str, content_type: ContentType, elapsed: f64) -> DashProvider {
+        DashProvider::with_clock(MANIFEST_URL, text, content_type, Box::new(move || AVAILABILITY_START + elapsed)).unwrap()
+    }
+
+    fn provider(text: &str, content_type: ContentType) -> DashProvider {
+        provider_at(text, content_type, 3_600.0)
+    }
+
+    fn drain(provider: &mut DashProvider) -> Vec<SegmentRequest> {
+        std::iter::from_fn(|| provider.next_segment()).collect()
+    }
+
+    /// A version 0 sidx box with two references of 1000 and 2000 bytes lasting 4 s and 5.5 s.
+    fn sidx_box() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(32u32 + 2 * 12).to_be_bytes());
+        data.extend_from_slice(b"sidx");
+        data.extend_from_slice(&[0, 0, 0, 0]); // version 0, flags
+        data.extend_from_slice(&1u32.to_be_bytes()); // reference_ID
+        data.extend_from_slice(&1000u32.to_be_bytes()); // timescale
+        data.extend_from_slice(&0u32.to_be_bytes()); // earliest_presentation_time
+        data.extend_from_slice(&0u32.to_be_bytes()); // first_offset
+        data.extend_from_slice(&2u32.to_be_bytes()); // reserved, reference_count
+        for (size, duration) in [(1000u32, 4000u32), (2000, 5500)] {
+            data.extend_from_slice(&size.to_be_bytes());
+            data.extend_from_slice(&duration.to_be_bytes());
+            data.extend_from_slice(&0x9000_0000u32.to_be_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_is_dash_source() {
+        assert!(is_dash_source("https://a.example.com/manifest.mpd?token=1", ""));
+        assert!(is_dash_source("https://a.example.com/stream", "application/dash+xml"));
+        assert!(!is_dash_source("https://a.example.com/master.m3u8", "application/vnd.apple.mpegurl"));
+    }
+
+    #[test]
+    fn test_parse_sidx() {
+        let references = parse_sidx(&sidx_box(), 862).unwrap();
+        assert_eq!(references.len(), 2);
+        // The box is 56 bytes long, so media starts at 862 + 56
+        assert_eq!(references[0].byte_range, ByteRange { start: 918, end: 1917 });
+        assert_eq!(references[1].byte_range, ByteRange { start: 1918, end: 3917 });
+        assert_eq!(references[1].start, 4.0);
+        assert_eq!(references[1].duration, 5.5);
+        assert!(parse_sidx(b"\0\0\0\x08free", 0).is_err());
+    }
+
+    #[test]
+    fn test_static_template_tracks() {
+        let text = include_str!("fixtures/static_template.mpd");
+        let mut video = provider(text, ContentType::Video);
+        assert_eq!(video.mime_type(), "video/mp4; codecs=\"avc1.4d401e\"");
+        assert_eq!(video.duration(), Some(30.0));
+        assert_eq!(video.refresh_interval(), None);
+
+        let requests = drain(&mut video);
+        assert_eq!(requests.len(), 9);
+        assert_eq!(requests[0].kind, SegmentKind::Init);
+        assert_eq!(requests[8].url, "https://cdn.example.com/movie/360p/seg-00008.m4s");
+        assert!(video.is_complete());
+
+        let mut audio = provider(text, ContentType::Audio);
+        assert_eq!(audio.mime_type(), "audio/mp4; codecs=\"mp4a.40.2\"");
+        assert_eq!(drain(&mut audio)[0].url, "https://cdn.example.com/movie/audio/aac-init.mp4");
+        assert!(!provider(text, ContentType::Text).has_content());
+    }
+
+    #[test]
+    fn test_tracks_start_at_handed_position() {
+        let tracks = dash_tracks(MANIFEST_URL, include_str!("fixtures/static_template.mpd"), 13.0).unwrap();
+        let first_media: Vec<(String, f64)> = tracks
+            .iter()
+            .map(|track| {
+                let mut track = track.borrow_mut();
+                assert_eq!(track.next_segment().map(|request| request.kind), Some(SegmentKind::Init));
+                let request = track.next_segment().unwrap();
+                assert!(request.discontinuity);
+                (request.url, request.start)
+            })
+            .collect();
+        assert_eq!(first_media, [
+            ("https://cdn.example.com/movie/360p/seg-00004.m4s".to_string(), 12.0),
+            ("https://cdn.example.com/movie/audio/aac-4.m4s".to_string(), 12.0),
+        ]);
+    }
+
+    #[test]
+    fn test_switching_representation_continues_at_next_segment() {
+        let mut video = provider(include_str!("fixtures/static_template.mpd"), ContentType::Video);
+        assert_eq!(video.renditions().iter().map(|rendition| rendition.bandwidth).collect::<Vec<_>>(), [800_000, 2_800_000]);
+        video.next_segment();
+        video.next_segment();
+
+        video.select_rendition(1);
+        assert_eq!(video.rendition(), 1);
+        assert_eq!(video.mime_type(), "video/mp4; codecs=\"avc1.4d401f\"");
+        let requests = drain(&mut video);
+        assert_eq!(requests[0].url, "https://cdn.example.com/movie/720p/init.mp4");
+        assert_eq!(requests[1].url, "https://cdn.example.com/movie/720p/seg-00011.m4s");
+        assert_eq!(requests[1].start, 4.0);
+        assert!(requests[1].discontinuity);
+    }
+
+    #[test]
+    fn test_seek_and_period_transition() {
+        let mut video = provider(include_str!("fixtures/timeline.mpd"), ContentType::Video);
+        video.seek(15.0);
+
+        let requests = drain(&mut video);
+        let urls: Vec<&str> = requests.iter().map(|request| request.url.as_str()).collect();
+        assert_eq!(urls, [
+            "https://origin.example.com/content/init-5000000.mp4",
+            "https://origin.example.com/content/chunk-1350000.m4s",
+            "https://origin.example.com/content/chunk-1620000.m4s",
+            "https://origin.example.com/content/credits/init.mp4",
+            "https://origin.example.com/content/credits/0.m4s",
+            "https://origin.example.com/content/credits/1.m4s",
+            "https://origin.example.com/content/credits/2.m4s",
+            "https://origin.example.com/content/credits/3.m4s",
+        ]);
+        assert!(requests[1].discontinuity);
+        assert!(!requests[2].discontinuity);
+        // Crossing into the next period starts a new timeline with its own init segment
+        assert!(requests[3].discontinuity && requests[4].discontinuity);
+        assert_eq!(requests[4].start, 20.0);
+        assert!(video.is_complete());
+    }
+
+    #[test]
+    fn test_segment_base_loads_index_first() {
+        let mut video = provider(include_str!("fixtures/segment_base.mpd"), ContentType::Video);
+        let index = video.next_segment().unwrap();
+        assert_eq!(index.kind, SegmentKind::Index);
+        assert_eq!(index.byte_range, Some(ByteRange { start: 862, end: 1061 }));
+        assert!(video.next_segment().is_none());
+        assert!(!video.is_complete());
+
+        video.index_loaded(&sidx_box()).unwrap();
+        let requests = drain(&mut video);
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].kind, SegmentKind::Init);
+        assert_eq!(requests[0].byte_range, Some(ByteRange { start: 0, end: 861 }));
+        assert_eq!(requests[2].byte_range, Some(ByteRange { start: 1918, end: 3917 }));
+        assert!(video.is_complete());
+    }
+
+    #[test]
+    fn test_dynamic_starts_near_live_edge_and_waits() {
+        let mut video = provider(include_str!("fixtures/dynamic.mpd"), ContentType::Video);
+        assert_eq!(video.duration(), None);
+        assert_eq!(video.refresh_interval(), Some(2.0));
+        let text = include_str!("fixtures/dynamic.mpd").replace("minimumUpdatePeriod=\"PT2S\"", "minimumUpdatePeriod=\"PT0S\"");
+        assert_eq!(provider(&text, ContentType::Video).refresh_interval(), Some(MIN_REFRESH_INTERVAL));
+
+        let requests = drain(&mut video);
+        let urls: Vec<&str> = requests.iter().map(|request| request.url.as_str()).collect();
+        assert_eq!(urls, [
+            "https://origin.example.com/content/live/v1/init.mp4",
+            "https://origin.example.com/content/live/v1/1798.m4s",
+            "https://origin.example.com/content/live/v1/1799.m4s",
+            "https://origin.example.com/content/live/v1/1800.m4s",
+        ]);
+        assert_eq!(requests[3].start, 4.0);
+        assert!(!video.is_complete());
+    }
+
+    #[test]
+    fn test_dynamic_timeline_follows_manifest_updates() {
+        let text = include_str!("fixtures/dynamic_timeline.mpd");
+        let mut video = provider_at(text, ContentType::Video, 120.0);
+        assert_eq!(drain(&mut video).len(), 4);
+
+        video.update_manifest(&text.replace("r=\"4\"", "r=\"6\"")).unwrap();
+        let requests = drain(&mut video);
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].url, "https://origin.example.com/content/t-120000.m4s");
+        assert!(!requests[0].discontinuity);
+    }
+}
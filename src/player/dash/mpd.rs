@@ -0,0 +1,686 @@
+use roxmltree::Node;
+use crate::player::error::VideoError;
+use crate::player::segments::ByteRange;
+use crate::player::url::resolve_url;
+
+// Most segments one timeline entry or template may expand to, so a malformed
+// repeat count or duration cannot exhaust memory
+const MAX_SEGMENTS_PER_ENTRY: u64 = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MpdType {
+    Static,
+    Dynamic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentType {
+    Video,
+    Audio,
+    Text,
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mpd {
+    pub mpd_type: MpdType,
+    pub media_presentation_duration: Option<f64>,
+    pub min_buffer_time: Option<f64>,
+    pub minimum_update_period: Option<f64>,
+    pub time_shift_buffer_depth: Option<f64>,
+    /// Seconds since the Unix epoch.
+    pub availability_start_time: Option<f64>,
+    pub periods: Vec<Period>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Period {
+    pub id: Option<String>,
+    pub start: f64,
+    pub duration: Option<f64>,
+    pub adaptation_sets: Vec<AdaptationSet>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptationSet {
+    pub id: Option<String>,
+    pub content_type: ContentType,
+    pub lang: Option<String>,
+    pub representations: Vec<Representation>,
+}
+
+/// A representation with everything it inherits from its adaptation set, period and MPD
+/// already resolved, so it can be turned into segments on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Representation {
+    pub id: String,
+    pub bandwidth: u64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub mime_type: String,
+    pub codecs: Option<String>,
+    pub base_url: String,
+    pub segment_template: Option<SegmentTemplate>,
+    pub segment_base: Option<SegmentBase>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SegmentTemplate {
+    pub media: Option<String>,
+    pub initialization: Option<String>,
+    pub start_number: Option<u64>,
+    pub timescale: Option<u64>,
+    pub duration: Option<u64>,
+    pub presentation_time_offset: Option<u64>,
+    pub timeline: Vec<TimelineEntry>,
+}
+
+/// One `<S>` element of a `SegmentTimeline`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimelineEntry {
+    pub time: Option<u64>,
+    pub duration: u64,
+    /// Extra repetitions; -1 repeats until the next entry or the end of the period.
+    pub repeat: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SegmentBase {
+    pub index_range: Option<ByteRange>,
+    pub initialization_range: Option<ByteRange>,
+    pub timescale: Option<u64>,
+}
+
+/// A media segment on the presentation timeline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DashSegment {
+    pub url: String,
+    pub byte_range: Option<ByteRange>,
+    pub number: u64,
+    /// Presentation time in seconds.
+    pub start: f64,
+    pub duration: f64,
+}
+
+impl Mpd {
+    pub fn is_dynamic(&self) -> bool {
+        self.mpd_type == MpdType::Dynamic
+    }
+
+    /// Duration of `period`, taken from the period itself, the next period's start or the presentation.
+    pub fn period_duration(&self, index: usize) -> Option<f64> {
+        let period = self.periods.get(index)?;
+        period
+            .duration
+            .or_else(|| self.periods.get(index + 1).map(|next| next.start - period.start))
+            .or_else(|| self.media_presentation_duration.map(|total| total - period.start))
+    }
+}
+
+impl Representation {
+    /// The initialization segment, either from the template or the `SegmentBase` range.
+    pub fn initialization(&self) -> Option<(String, Option<ByteRange>)> {
+        if let Some(template) = &self.segment_template {
+            let initialization = template.initialization.as_ref()?;
+            let url = expand_template(initialization, &self.id, self.bandwidth, 0, 0);
+            return Some((resolve_url(&self.base_url, &url), None));
+        }
+        let segment_base = self.segment_base.as_ref()?;
+        let range = segment_base.initialization_range.or_else(|| {
+            // Without <Initialization>, everything before the index is the init segment
+            let index = segment_base.index_range?;
+            (index.start > 0).then(|| ByteRange { start: 0, end: index.start - 1 })
+        });
+        Some((self.base_url.clone(), range))
+    }
+
+    /// Expands the segment template of this representation within `period`.
+    /// `now` (seconds since the epoch) limits dynamic presentations to available segments.
+    pub fn template_segments(&self, mpd: &Mpd, period_index: usize, now: Option<f64>) -> Vec<DashSegment> {
+        let Some(template) = &self.segment_template else {
+            return Vec::new();
+        };
+        let Some(media) = &template.media else {
+            return Vec::new();
+        };
+        let Some(period) = mpd.periods.get(period_index) else {
+            return Vec::new();
+        };
+        let timescale = template.timescale.unwrap_or(1).max(1) as f64;
+        let offset = template.presentation_time_offset.unwrap_or(0);
+        let start_number = template.start_number.unwrap_or(1);
+        let period_duration = mpd.period_duration(period_index);
+
+        let segment = |number: u64, time: u64, duration: u64| DashSegment {
+            url: resolve_url(&self.base_url, &expand_template(media, &self.id, self.bandwidth, number, time)),
+            byte_range: None,
+            number,
+            start: period.start + (time as f64 - offset as f64) / timescale,
+            duration: duration as f64 / timescale,
+        };
+
+        let mut segments = Vec::new();
+        if !template.timeline.is_empty() {
+            let period_end = period_duration.map(|duration| offset + (duration * timescale) as u64);
+            let mut time = 0;
+            for (index, entry) in template.timeline.iter().enumerate() {
+                time = entry.time.unwrap_or(time);
+                let repeat_end = if entry.repeat >= 0 {
+                    None
+                } else {
+                    template.timeline.get(index + 1).and_then(|next| next.time).or(period_end)
+                };
+                let mut repeat = 0;
+                loop {
+                    match repeat_end {
+                        Some(end) if time >= end => break,
+                        None if repeat > entry.repeat.max(0) => break,
+                        _ if repeat >= MAX_SEGMENTS_PER_ENTRY as i64 => break,
+                        _ => {}
+                    }
+                    segments.push(segment(start_number + segments.len() as u64, time, entry.duration));
+                    time += entry.duration;
+                    repeat += 1;
+                    // r="-1" with nothing to bound it only yields the listed segment
+                    if entry.repeat < 0 && repeat_end.is_none() {
+                        break;
+                    }
+                }
+            }
+        } else if let Some(duration) = template.duration.filter(|duration| *duration > 0) {
+            let segment_duration = duration as f64 / timescale;
+            let (first, last) = match (mpd.is_dynamic(), now, mpd.availability_start_time) {
+                (true, Some(now), Some(availability_start)) => {
+                    // Segment k is available once it has been fully produced
+                    let elapsed = now - availability_start - period.start;
+                    let available = (elapsed / segment_duration).floor() as i64;
+                    let window = mpd
+                        .time_shift_buffer_depth
+                        .map(|depth| (depth / segment_duration).floor() as i64)
+                        .unwrap_or(available);
+                    ((available - window).max(0).max(available - MAX_SEGMENTS_PER_ENTRY as i64), available)
+                }
+                _ => {
+                    let count = period_duration
+                        .map(|duration| (duration / segment_duration - 1e-9).ceil() as i64)
+                        .unwrap_or(0);
+                    (0, count.min(MAX_SEGMENTS_PER_ENTRY as i64))
+                }
+            };
+            for index in first..last {
+                let index = index as u64;
+                segments.push(segment(start_number + index, offset + index * duration, duration));
+            }
+        }
+
+        if let (true, Some(now), Some(availability_start)) = (mpd.is_dynamic(), now, mpd.availability_start_time) {
+            // Drop timeline entries that have fallen out of the time-shift window
+            if let Some(depth) = mpd.time_shift_buffer_depth {
+                let window_start = now - availability_start - depth;
+                segments.retain(|segment| segment.start + segment.duration >= window_start);
+            }
+        }
+        segments
+    }
+}
+
+/// Parses an MPD document, resolving every `BaseURL` against `manifest_url`.
+pub fn parse_mpd(text: &str, manifest_url: &str) -> Result<Mpd, VideoError> {
+    let document = roxmltree::Document::parse(text)
+        .map_err(|e| VideoError::ManifestParseError(format!("Invalid MPD XML: {}", e)))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "MPD" {
+        return Err(VideoError::ManifestParseError("Root element is not <MPD>".to_string()));
+    }
+
+    let mpd_type = match root.attribute("type") {
+        None | Some("static") => MpdType::Static,
+        Some("dynamic") => MpdType::Dynamic,
+        Some(other) => return Err(VideoError::ManifestParseError(format!("Unknown MPD type: {}", other))),
+    };
+    let base_url = base_url(root, manifest_url);
+
+    let mut mpd = Mpd {
+        mpd_type,
+        media_presentation_duration: duration_attribute(root, "mediaPresentationDuration")?,
+        min_buffer_time: duration_attribute(root, "minBufferTime")?,
+        minimum_update_period: duration_attribute(root, "minimumUpdatePeriod")?,
+        time_shift_buffer_depth: duration_attribute(root, "timeShiftBufferDepth")?,
+        availability_start_time: root.attribute("availabilityStartTime").map(parse_datetime).transpose()?,
+        periods: Vec::new(),
+    };
+
+    let mut next_start = 0.0;
+    for period_node in children(root, "Period") {
+        let start = duration_attribute(period_node, "start")?.unwrap_or(next_start);
+        let duration = duration_attribute(period_node, "duration")?;
+        let period = parse_period(period_node, &base_url, start, duration)?;
+        next_start = start + duration.unwrap_or(0.0);
+        mpd.periods.push(period);
+    }
+    if mpd.periods.is_empty() {
+        return Err(VideoError::ManifestParseError("MPD has no periods".to_string()));
+    }
+    Ok(mpd)
+}
+
+fn parse_period(node: Node, parent_url: &str, start: f64, duration: Option<f64>) -> Result<Period, VideoError> {
+    let base_url = base_url(node, parent_url);
+    let template = child(node, "SegmentTemplate").map(|template| parse_segment_template(template, None)).transpose()?;
+    let segment_base = child(node, "SegmentBase").map(|base| parse_segment_base(base, None)).transpose()?;
+
+    let adaptation_sets = children(node, "AdaptationSet")
+        .map(|set| parse_adaptation_set(set, &base_url, template.as_ref(), segment_base.as_ref()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Period {
+        id: node.attribute("id").map(str::to_string),
+        start,
+        duration,
+        adaptation_sets,
+    })
+}
+
+fn parse_adaptation_set(
+    node: Node,
+    parent_url: &str,
+    parent_template: Option<&SegmentTemplate>,
+    parent_base: Option<&SegmentBase>,
+) -> Result<AdaptationSet, VideoError> {
+    let set_url = base_url(node, parent_url);
+    let template = match child(node, "SegmentTemplate") {
+        Some(template) => Some(parse_segment_template(template, parent_template)?),
+        None => parent_template.cloned(),
+    };
+    let segment_base = match child(node, "SegmentBase") {
+        Some(base) => Some(parse_segment_base(base, parent_base)?),
+        None => parent_base.cloned(),
+    };
+    let mime_type = node.attribute("mimeType");
+    let codecs = node.attribute("codecs");
+
+    let representations = children(node, "Representation")
+        .map(|representation| {
+            let representation_template = match child(representation, "SegmentTemplate") {
+                Some(node) => Some(parse_segment_template(node, template.as_ref())?),
+                None => template.clone(),
+            };
+            let representation_base = match child(representation, "SegmentBase") {
+                Some(node) => Some(parse_segment_base(node, segment_base.as_ref())?),
+                None => segment_base.clone(),
+            };
+            Ok(Representation {
+                id: required_attribute(representation, "id")?.to_string(),
+                bandwidth: number_attribute(representation, "bandwidth")?
+                    .ok_or_else(|| VideoError::ManifestParseError("Representation without bandwidth".to_string()))?,
+                width: number_attribute(representation, "width")?,
+                height: number_attribute(representation, "height")?,
+                mime_type: representation
+                    .attribute("mimeType")
+                    .or(mime_type)
+                    .ok_or_else(|| VideoError::ManifestParseError("Representation without mimeType".to_string()))?
+                    .to_string(),
+                codecs: representation.attribute("codecs").or(codecs).map(str::to_string),
+                base_url: base_url(representation, &set_url),
+                segment_template: representation_template,
+                segment_base: representation_base,
+            })
+        })
+        .collect::<Result<Vec<_>, VideoError>>()?;
+
+    let content_type = match node.attribute("contentType") {
+        Some(content_type) => content_type,
+        None => representations
+            .first()
+            .map(|representation| representation.mime_type.as_str())
+            .or(mime_type)
+            .and_then(|mime| mime.split('/').next())
+            .unwrap_or(""),
+    };
+
+    Ok(AdaptationSet {
+        id: node.attribute("id").map(str::to_string),
+        content_type: match content_type {
+            "video" => ContentType::Video,
+            "audio" => ContentType::Audio,
+            "text" | "application" => ContentType::Text,
+            _ => ContentType::Other,
+        },
+        lang: node.attribute("lang").map(str::to_string),
+        representations,
+    })
+}
+
+fn parse_segment_template(node: Node, parent: Option<&SegmentTemplate>) -> Result<SegmentTemplate, VideoError> {
+    let parent = parent.cloned().unwrap_or_default();
+    let timeline = match child(node, "SegmentTimeline") {
+        Some(timeline) => children(timeline, "S")
+            .map(|entry| {
+                let duration = number_attribute(entry, "d")?
+                    .ok_or_else(|| VideoError::ManifestParseError("SegmentTimeline entry without d".to_string()))?;
+                // A zero duration never advances the timeline
+                if duration == 0 {
+                    return Err(VideoError::ManifestParseError("SegmentTimeline entry with d=0".to_string()));
+                }
+                Ok(TimelineEntry {
+                    time: number_attribute(entry, "t")?,
+                    duration,
+                    repeat: number_attribute(entry, "r")?.unwrap_or(0),
+                })
+            })
+            .collect::<Result<Vec<_>, VideoError>>()?,
+        None => parent.timeline,
+    };
+    Ok(SegmentTemplate {
+        media: node.attribute("media").map(str::to_string).or(parent.media),
+        initialization: node.attribute("initialization").map(str::to_string).or(parent.initialization),
+        start_number: number_attribute(node, "startNumber")?.or(parent.start_number),
+        timescale: number_attribute(node, "timescale")?.or(parent.timescale),
+        duration: number_attribute(node, "duration")?.or(parent.duration),
+        presentation_time_offset: number_attribute(node, "presentationTimeOffset")?.or(parent.presentation_time_offset),
+        timeline,
+    })
+}
+
+fn parse_segment_base(node: Node, parent: Option<&SegmentBase>) -> Result<SegmentBase, VideoError> {
+    let parent = parent.cloned().unwrap_or_default();
+    let initialization_range = match child(node, "Initialization").and_then(|init| init.attribute("range")) {
+        Some(range) => Some(parse_range(range)?),
+        None => parent.initialization_range,
+    };
+    Ok(SegmentBase {
+        index_range: node.attribute("indexRange").map(parse_range).transpose()?.or(parent.index_range),
+        initialization_range,
+        timescale: number_attribute(node, "timescale")?.or(parent.timescale),
+    })
+}
+
+/// Expands `$RepresentationID$`, `$Number$`, `$Time$` and `$Bandwidth$`, including
+/// `%0Nd` width formats, and `$$`.
+pub fn expand_template(template: &str, representation_id: &str, bandwidth: u64, number: u64, time: u64) -> String {
+    let mut output = String::new();
+    let mut parts = template.split('$');
+    if let Some(first) = parts.next() {
+        output.push_str(first);
+    }
+    let parts: Vec<&str> = parts.collect();
+    let mut index = 0;
+    while index < parts.len() {
+        let identifier = parts[index];
+        // An identifier needs its closing '$'; otherwise the text is literal
+        if index + 1 >= parts.len() {
+            output.push('$');
+            output.push_str(identifier);
+            break;
+        }
+        let (name, format) = identifier.split_once('%').unwrap_or((identifier, ""));
+        let value = match name {
+            "" => Some("$".to_string()),
+            "RepresentationID" => Some(representation_id.to_string()),
+            "Number" => Some(format_number(number, format)),
+            "Time" => Some(format_number(time, format)),
+            "Bandwidth" => Some(format_number(bandwidth, format)),
+            _ => None,
+        };
+        match value {
+            Some(value) => output.push_str(&value),
+            None => {
+                output.push('$');
+                output.push_str(identifier);
+                output.push('$');
+            }
+        }
+        if let Some(literal) = parts.get(index + 1) {
+            output.push_str(literal);
+        }
+        index += 2;
+    }
+    output
+}
+
+fn format_number(value: u64, format: &str) -> String {
+    let width = format
+        .strip_prefix('0')
+        .and_then(|format| format.strip_suffix('d'))
+        .and_then(|width| width.parse::<usize>().ok())
+        .unwrap_or(0);
+    format!("{:0width$}", value, width = width)
+}
+
+/// Parses an ISO 8601 duration such as `PT1H2M3.5S` into seconds.
+pub fn parse_duration(value: &str) -> Result<f64, VideoError> {
+    let invalid = || VideoError::ManifestParseError(format!("Invalid duration: {}", value));
+    let rest = value.trim().strip_prefix('P').ok_or_else(invalid)?;
+    let (date, time) = rest.split_once('T').unwrap_or((rest, ""));
+
+    let mut seconds = 0.0;
+    for (part, units) in [(date, [('Y', 31_536_000.0), ('M', 2_592_000.0), ('D', 86_400.0)]), (time, [('H', 3_600.0), ('M', 60.0), ('S', 1.0)])] {
+        let mut number = String::new();
+        for character in part.chars() {
+            if character.is_ascii_digit() || character == '.' {
+                number.push(character);
+                continue;
+            }
+            let unit = units.iter().find(|(designator, _)| *designator == character).ok_or_else(invalid)?;
+            seconds += number.parse::<f64>().map_err(|_| invalid())? * unit.1;
+            number.clear();
+        }
+        if !number.is_empty() {
+            return Err(invalid());
+        }
+    }
+    Ok(seconds)
+}
+
+/// Parses an `xs:dateTime` in UTC (`2024-01-01T00:00:00Z`) into seconds since the epoch.
+pub fn parse_datetime(value: &str) -> Result<f64, VideoError> {
+    let invalid = || VideoError::ManifestParseError(format!("Invalid date time: {}", value));
+    let value = value.trim().trim_end_matches('Z');
+    let (date, time) = value.split_once('T').ok_or_else(invalid)?;
+
+    let mut date_parts = date.splitn(3, '-').map(|part| part.parse::<i64>().map_err(|_| invalid()));
+    let (year, month, day) = (
+        date_parts.next().ok_or_else(invalid)??,
+        date_parts.next().ok_or_else(invalid)??,
+        date_parts.next().ok_or_else(invalid)??,
+    );
+    let mut time_parts = time.splitn(3, ':');
+    let hours = time_parts.next().ok_or_else(invalid)?.parse::<f64>().map_err(|_| invalid())?;
+    let minutes = time_parts.next().ok_or_else(invalid)?.parse::<f64>().map_err(|_| invalid())?;
+    let seconds = time_parts.next().unwrap_or("0").parse::<f64>().map_err(|_| invalid())?;
+
+    // Days since 1970-01-01 in the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Ok(days as f64 * 86_400.0 + hours * 3_600.0 + minutes * 60.0 + seconds)
+}
+
+/// Parses an inclusive `first-last` byte range.
+fn parse_range(value: &str) -> Result<ByteRange, VideoError> {
+    let invalid = || VideoError::ManifestParseError(format!("Invalid byte range: {}", value));
+    let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+    Ok(ByteRange {
+        start: start.trim().parse().map_err(|_| invalid())?,
+        end: end.trim().parse().map_err(|_| invalid())?,
+    })
+}
+
+fn base_url(node: Node, parent_url: &str) -> String {
+    match child(node, "BaseURL").and_then(|base| base.text()) {
+        Some(base) => resolve_url(parent_url, base.trim()),
+        None => parent_url.to_string(),
+    }
+}
+
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn required_attribute<'a>(node: Node<'a, '_>, name: &str) -> Result<&'a str, VideoError> {
+    node.attribute(name)
+        .ok_or_else(|| VideoError::ManifestParseError(format!("<{}> without {}", node.tag_name().name(), name)))
+}
+
+fn number_attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<Option<T>, VideoError> {
+    node.attribute(name)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| VideoError::ManifestParseError(format!("Invalid {} value: {}", name, value)))
+        })
+        .transpose()
+}
+
+fn duration_attribute(node: Node, name: &str) -> Result<Option<f64>, VideoError> {
+    node.attribute(name).map(parse_duration).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST_URL: &str = "https://origin.example.com/content/manifest.mpd";
+
+    #[test]
+    fn test_parse_static_template_mpd() {
+        let mpd = parse_mpd(include_str!("fixtures/static_template.mpd"), MANIFEST_URL).unwrap();
+
+        assert_eq!(mpd.mpd_type, MpdType::Static);
+        assert_eq!(mpd.media_presentation_duration, Some(30.0));
+        let period = &mpd.periods[0];
+        assert_eq!(period.adaptation_sets.len(), 2);
+
+        let video = &period.adaptation_sets[0];
+        assert_eq!(video.content_type, ContentType::Video);
+        assert_eq!(video.representations[1].height, Some(720));
+        assert_eq!(video.representations[1].codecs.as_deref(), Some("avc1.4d401f"));
+
+        let low = &video.representations[0];
+        assert_eq!(low.initialization().unwrap().0, "https://cdn.example.com/movie/360p/init.mp4");
+        let segments = low.template_segments(&mpd, 0, None);
+        assert_eq!(segments.len(), 8);
+        assert_eq!(segments[0].url, "https://cdn.example.com/movie/360p/seg-00001.m4s");
+        assert_eq!(segments[7].start, 28.0);
+
+        // The representation's own SegmentTemplate only overrides startNumber
+        let high = video.representations[1].template_segments(&mpd, 0, None);
+        assert_eq!(high[0].url, "https://cdn.example.com/movie/720p/seg-00010.m4s");
+
+        let audio = &period.adaptation_sets[1];
+        assert_eq!(audio.content_type, ContentType::Audio);
+        assert_eq!(audio.lang.as_deref(), Some("en"));
+        let audio_segments = audio.representations[0].template_segments(&mpd, 0, None);
+        assert_eq!(audio_segments[1].url, "https://cdn.example.com/movie/audio/aac-2.m4s");
+        assert_eq!(audio_segments[1].duration, 4.0);
+    }
+
+    #[test]
+    fn test_parse_segment_timeline_and_periods() {
+        let mpd = parse_mpd(include_str!("fixtures/timeline.mpd"), MANIFEST_URL).unwrap();
+        assert_eq!(mpd.periods.len(), 2);
+        assert_eq!(mpd.periods[1].start, 20.0);
+
+        let representation = &mpd.periods[0].adaptation_sets[0].representations[0];
+        assert_eq!(representation.initialization().unwrap().0, "https://origin.example.com/content/init-5000000.mp4");
+        let segments = representation.template_segments(&mpd, 0, None);
+        let starts: Vec<f64> = segments.iter().map(|segment| segment.start).collect();
+        assert_eq!(starts, [0.0, 4.0, 8.0, 12.0, 14.0, 17.0]);
+        assert_eq!(segments[0].url, "https://origin.example.com/content/chunk-90000.m4s");
+        assert_eq!(segments[5].url, "https://origin.example.com/content/chunk-1620000.m4s");
+
+        let credits = mpd.periods[1].adaptation_sets[0].representations[0].template_segments(&mpd, 1, None);
+        assert_eq!(credits.len(), 4);
+        assert_eq!(credits[0].number, 0);
+        assert_eq!(credits[3].start, 50.0);
+    }
+
+    #[test]
+    fn test_parse_segment_base_mpd() {
+        let mpd = parse_mpd(include_str!("fixtures/segment_base.mpd"), MANIFEST_URL).unwrap();
+        let representation = &mpd.periods[0].adaptation_sets[0].representations[0];
+
+        assert_eq!(representation.base_url, "https://origin.example.com/content/video_480p.mp4");
+        let segment_base = representation.segment_base.as_ref().unwrap();
+        assert_eq!(segment_base.index_range, Some(ByteRange { start: 862, end: 1061 }));
+        assert_eq!(representation.initialization(), Some((representation.base_url.clone(), Some(ByteRange { start: 0, end: 861 }))));
+        assert!(representation.template_segments(&mpd, 0, None).is_empty());
+    }
+
+    #[test]
+    fn test_parse_dynamic_mpd() {
+        let mpd = parse_mpd(include_str!("fixtures/dynamic.mpd"), MANIFEST_URL).unwrap();
+        assert!(mpd.is_dynamic());
+        assert_eq!(mpd.minimum_update_period, Some(2.0));
+        let availability_start = mpd.availability_start_time.unwrap();
+        assert_eq!(availability_start, 1_704_067_200.0);
+
+        let representation = &mpd.periods[0].adaptation_sets[0].representations[0];
+        let segments = representation.template_segments(&mpd, 0, Some(availability_start + 3_600.0));
+        // 30 s time-shift window of 2 s segments, newest one finished at the hour
+        assert_eq!(segments.len(), 15);
+        assert_eq!(segments.last().unwrap().number, 1800);
+        assert_eq!(segments.last().unwrap().url, "https://origin.example.com/content/live/v1/1800.m4s");
+    }
+
+    #[test]
+    fn test_parse_dynamic_timeline_mpd() {
+        let mpd = parse_mpd(include_str!("fixtures/dynamic_timeline.mpd"), MANIFEST_URL).unwrap();
+        let availability_start = mpd.availability_start_time.unwrap();
+        let representation = &mpd.periods[0].adaptation_sets[0].representations[0];
+
+        let segments = representation.template_segments(&mpd, 0, Some(availability_start + 120.0));
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[0].url, "https://origin.example.com/content/t-100000.m4s");
+        assert_eq!(segments[4].start, 116.0);
+    }
+
+    #[test]
+    fn test_expand_template() {
+        assert_eq!(expand_template("$RepresentationID$/$Number%05d$.m4s", "v1", 0, 42, 0), "v1/00042.m4s");
+        assert_eq!(expand_template("t-$Time$-$Bandwidth$.m4s", "v1", 800, 0, 9000), "t-9000-800.m4s");
+        assert_eq!(expand_template("cost$$5-$Unknown$.m4s", "v1", 0, 0, 0), "cost$5-$Unknown$.m4s");
+    }
+
+    #[test]
+    fn test_parse_duration_and_datetime() {
+        assert_eq!(parse_duration("PT1H2M3.5S").unwrap(), 3_723.5);
+        assert_eq!(parse_duration("P1DT0.5S").unwrap(), 86_400.5);
+        assert_eq!(parse_duration("PT0S").unwrap(), 0.0);
+        assert!(parse_duration("1H").is_err());
+        assert!(parse_duration("PT5X").is_err());
+        assert_eq!(parse_datetime("1970-01-01T00:00:00Z").unwrap(), 0.0);
+        assert_eq!(parse_datetime("2000-03-01T12:30:15.5Z").unwrap(), 951_913_815.5);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_mpds() {
+        assert!(parse_mpd("<html/>", MANIFEST_URL).is_err());
+        assert!(parse_mpd("<MPD", MANIFEST_URL).is_err());
+        assert!(parse_mpd("<MPD type=\"static\"/>", MANIFEST_URL).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_zero_duration_timeline_entry() {
+        let error = parse_mpd(include_str!("fixtures/zero_duration.mpd"), MANIFEST_URL).unwrap_err();
+        assert!(matches!(error, VideoError::ManifestParseError(message) if message.contains("d=0")));
+    }
+
+    #[test]
+    fn test_segments_per_entry_are_capped() {
+        let mpd = parse_mpd(include_str!("fixtures/huge_repeat.mpd"), MANIFEST_URL).unwrap();
+        for adaptation_set in &mpd.periods[0].adaptation_sets {
+            let segments = adaptation_set.representations[0].template_segments(&mpd, 0, None);
+            assert_eq!(segments.len() as u64, MAX_SEGMENTS_PER_ENTRY);
+        }
+    }
+}
@@ -1,31 +1,56 @@
-use web_sys::{HtmlVideoElement, HtmlSourceElement};
+use web_sys::{Element, HtmlVideoElement, HtmlSourceElement, Window};
 use wasm_bindgen::JsCast;
 use crate::player::VideoError;
 
-pub fn get_video_element() -> Result<HtmlVideoElement, VideoError> {
-    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
-    let document = window.document().ok_or(VideoError::DocumentNotFound)?;
-    let video_element = document
-        .get_element_by_id("videoPlayer")
-        .ok_or_else(|| VideoError::ElementNotFound("videoPlayer".to_string()))?
-        .dyn_into::<HtmlVideoElement>()
-        .map_err(|_| VideoError::ElementNotFound("videoPlayer".to_string()))?;
-    Ok(video_element)
+/// The page's window. Native builds such as `cargo test` have none, so a player
+/// driven there without a page finds no elements rather than panicking.
+pub(crate) fn window() -> Result<Window, VideoError> {
+    #[cfg(target_arch = "wasm32")]
+    return web_sys::window().ok_or(VideoError::WindowNotFound);
+    #[cfg(not(target_arch = "wasm32"))]
+    return Err(VideoError::WindowNotFound);
 }
 
-pub fn get_element_by_id(id: &str) -> Result<web_sys::Element, VideoError> {
-    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
+pub fn get_element_by_id(id: &str) -> Result<Element, VideoError> {
+    let window = window()?;
     let document = window.document().ok_or(VideoError::DocumentNotFound)?;
     let element = document
         .get_element_by_id(id)
-        .ok_or_else(|| VideoError::ElementNotFound(id.to_string()))?;
+        .ok_or_else(|| element_not_found(id))?;
     Ok(element)
 }
 
-pub fn add_video_source(src: &str, type_attr: &str) -> Result<(), VideoError> {
+/// The error for a page without an element `#id`.
+pub(crate) fn element_not_found(id: &str) -> VideoError {
+    VideoError::ElementNotFound(format!("#{}", id))
+}
+
+fn action_selector(action: &str) -> String {
+    format!("[data-action=\"{}\"]", action)
+}
+
+/// The error for `#parent_id` without a descendant for `action`.
+pub(crate) fn action_not_found(parent_id: &str, action: &str) -> VideoError {
+    VideoError::ElementNotFound(format!("#{} {}", parent_id, action_selector(action)))
+}
+
+/// Returns the descendant of `#parent_id` whose `data-action` attribute is `action`.
+///
+/// Menu items and the buttons of the prompts and messages the player draws are
+/// told apart by `data-action` rather than ids. Whatever is drawn more than once
+/// gets a single click listener on its container, which looks at the
+/// `data-action` of the clicked element, so redrawing never needs rebinding.
+pub fn get_element_by_action(parent_id: &str, action: &str) -> Result<Element, VideoError> {
+    let selector = action_selector(action);
+    get_element_by_id(parent_id)?
+        .query_selector(&selector)
+        .map_err(|e| VideoError::VideoOperationFailed(format!("Invalid selector {}: {:?}", selector, e)))?
+        .ok_or_else(|| action_not_found(parent_id, action))
+}
+
+pub fn add_video_source(video_element: &HtmlVideoElement, src: &str, type_attr: &str, label: Option<&str>) -> Result<HtmlSourceElement, VideoError> {
     let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
     let document = window.document().ok_or(VideoError::DocumentNotFound)?;
-    let video_element = get_video_element()?;
 
     let source_element = document
         .create_element("source")
@@ -35,10 +60,29 @@ pub fn add_video_source(src: &str, type_attr: &str) -> Result<(), VideoError> {
 
     source_element.set_src(src);
     source_element.set_type(type_attr);
+    if let Some(label) = label {
+        source_element
+            .set_attribute("data-label", label)
+            .map_err(|js_value| VideoError::VideoOperationFailed(format!("Failed to set source label: {:?}", js_value)))?;
+    }
 
     video_element
         .append_child(&source_element)
         .map_err(|js_value| VideoError::VideoOperationFailed(format!("Failed to append source element: {:?}", js_value)))?;
 
-    Ok(())
-} 
\ No newline at end of file
+    Ok(source_element)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_elements_are_named() {
+        let error = element_not_found("muteButton");
+        assert_eq!(error.code(), "element_not_found");
+        assert_eq!(error.to_string(), "Element not found: #muteButton");
+        let error = action_not_found("contextMenu", "speed");
+        assert_eq!(error.to_string(), "Element not found: #contextMenu [data-action=\"speed\"]");
+    }
+}
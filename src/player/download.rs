@@ -1,45 +1,50 @@
 use wasm_bindgen::prelude::*;
 use crate::logger::Logger;
-use crate::player::error::{show_error, hide_error, VideoError};
-use crate::player::get_video_element;
-use crate::player::menu::hide_menus;
+use std::rc::Rc;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::menu::hide_menus_for;
 
 #[wasm_bindgen]
 pub async fn download_video() -> Result<(), JsValue> {
-    Logger::info("Entering download_video()").map_err(|e| {
+    download_video_for(default_context()).await
+}
+
+pub async fn download_video_for(context: Rc<PlayerContext>) -> Result<(), JsValue> {
+    Logger::trace("Entering download_video()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(&context, &error).unwrap_or_default();
         error
     })?;
-    hide_menus()?;
-    let video_element = get_video_element()?;
+    hide_menus_for(&context)?;
+    let video_element = context.video_element()?;
     let source = video_element.query_selector("source")
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to get source element: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?
         .ok_or_else(|| {
             let error = VideoError::VideoOperationFailed("No source element found".to_string());
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
     let video_url = source.get_attribute("src")
         .ok_or_else(|| {
             let error = VideoError::VideoOperationFailed("No source URL found".to_string());
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
 
     let window = web_sys::window().ok_or_else(|| {
         let error = VideoError::WindowNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(&context, &error).unwrap_or_default();
         error
     })?;
     let document = window.document().ok_or_else(|| {
         let error = VideoError::DocumentNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(&context, &error).unwrap_or_default();
         error
     })?;
     
@@ -51,7 +56,7 @@ pub async fn download_video() -> Result<(), JsValue> {
     let request = web_sys::Request::new_with_str_and_init(&video_url, &init)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to create request: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
@@ -60,14 +65,14 @@ pub async fn download_video() -> Result<(), JsValue> {
         .await
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to fetch video: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
     let response: web_sys::Response = response.dyn_into()
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to convert response: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
@@ -76,14 +81,14 @@ pub async fn download_video() -> Result<(), JsValue> {
         .await
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to get blob: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
     let blob: web_sys::Blob = blob.dyn_into()
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to convert blob: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
@@ -91,15 +96,17 @@ pub async fn download_video() -> Result<(), JsValue> {
     let url = web_sys::Url::create_object_url_with_blob(&blob)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to create object URL: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
+    context.track_object_url(&url);
+    
     // Create anchor element
     let anchor = document.create_element("a")
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to create anchor element: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
@@ -107,13 +114,13 @@ pub async fn download_video() -> Result<(), JsValue> {
     anchor.set_attribute("href", &url)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to set href: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     anchor.set_attribute("download", "video.mp4")
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to set download attribute: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
@@ -121,13 +128,13 @@ pub async fn download_video() -> Result<(), JsValue> {
     document.body()
         .ok_or_else(|| {
             let error = VideoError::VideoOperationFailed("No body element found".to_string());
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?
         .append_child(&anchor)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to append anchor: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
     
@@ -136,25 +143,22 @@ pub async fn download_video() -> Result<(), JsValue> {
     html_anchor.click();
     
     // Clean up
-    web_sys::Url::revoke_object_url(&url)
-        .map_err(|e| {
-            let error = VideoError::VideoOperationFailed(format!("Failed to revoke object URL: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
-            error
+    context.revoke_object_url(&url)
+        .inspect_err(|error| {
+            report_error_for(&context, error).unwrap_or_default();
         })?;
     
     document.body()
         .ok_or_else(|| {
             let error = VideoError::VideoOperationFailed("No body element found".to_string());
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?
         .remove_child(&html_anchor)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to remove anchor: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(&context, &error).unwrap_or_default();
             error
         })?;
-    hide_error()?;
     Ok(())
 } 
\ No newline at end of file
@@ -1,7 +1,8 @@
 use wasm_bindgen::prelude::*;
+use crate::player::error::VideoError;
 
 #[wasm_bindgen]
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct ElementIds {
     pub(crate) video_player: String,
     pub(crate) toggle_button: String,
@@ -13,17 +14,129 @@ pub struct ElementIds {
     pub(crate) error_message: String,
     pub(crate) context_menu: String,
     pub(crate) playback_speed_menu: String,
+    pub(crate) quality_menu: String,
+    pub(crate) captions_menu: String,
+    pub(crate) seek_bar: String,
+    pub(crate) volume_slider: String,
 }
 
 #[wasm_bindgen]
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct ElementClasses {
     pub(crate) context_menu: String,
     pub(crate) context_menu_show: String,
     pub(crate) playback_speed_menu: String,
     pub(crate) playback_speed_menu_show: String,
     pub(crate) speed_option: String,
+    pub(crate) speed_option_active: String,
+    pub(crate) quality_menu: String,
+    pub(crate) quality_menu_show: String,
+    pub(crate) quality_option: String,
+    pub(crate) quality_option_active: String,
+    pub(crate) caption_menu: String,
+    pub(crate) caption_menu_show: String,
+    pub(crate) caption_option: String,
+    pub(crate) caption_option_active: String,
+    pub(crate) caption_overlay: String,
+    pub(crate) caption_cue: String,
+    pub(crate) caption_stack: String,
+    pub(crate) caption_region: String,
+    pub(crate) seek_bar: String,
+    pub(crate) seek_bar_dragging: String,
+    pub(crate) seek_bar_buffered: String,
+    pub(crate) seek_bar_played: String,
+    pub(crate) seek_bar_thumb: String,
+    pub(crate) seek_bar_tooltip: String,
+    pub(crate) seek_bar_tooltip_show: String,
+    pub(crate) resume_prompt: String,
+    pub(crate) click_to_play: String,
     pub(crate) context_menu_item: String,
+    pub(crate) error_message: String,
+    pub(crate) error_message_show: String,
+    pub(crate) error_info: String,
+    pub(crate) error_warning: String,
+    pub(crate) error_fatal: String,
+}
+
+impl ElementClasses {
+    fn class_names(&self) -> [(&'static str, &str); 33] {
+        [
+            ("context_menu", &self.context_menu),
+            ("context_menu_show", &self.context_menu_show),
+            ("playback_speed_menu", &self.playback_speed_menu),
+            ("playback_speed_menu_show", &self.playback_speed_menu_show),
+            ("speed_option", &self.speed_option),
+            ("speed_option_active", &self.speed_option_active),
+            ("quality_menu", &self.quality_menu),
+            ("quality_menu_show", &self.quality_menu_show),
+            ("quality_option", &self.quality_option),
+            ("quality_option_active", &self.quality_option_active),
+            ("caption_menu", &self.caption_menu),
+            ("caption_menu_show", &self.caption_menu_show),
+            ("caption_option", &self.caption_option),
+            ("caption_option_active", &self.caption_option_active),
+            ("caption_overlay", &self.caption_overlay),
+            ("caption_cue", &self.caption_cue),
+            ("caption_stack", &self.caption_stack),
+            ("caption_region", &self.caption_region),
+            ("seek_bar", &self.seek_bar),
+            ("seek_bar_dragging", &self.seek_bar_dragging),
+            ("seek_bar_buffered", &self.seek_bar_buffered),
+            ("seek_bar_played", &self.seek_bar_played),
+            ("seek_bar_thumb", &self.seek_bar_thumb),
+            ("seek_bar_tooltip", &self.seek_bar_tooltip),
+            ("seek_bar_tooltip_show", &self.seek_bar_tooltip_show),
+            ("resume_prompt", &self.resume_prompt),
+            ("click_to_play", &self.click_to_play),
+            ("context_menu_item", &self.context_menu_item),
+            ("error_message", &self.error_message),
+            ("error_message_show", &self.error_message_show),
+            ("error_info", &self.error_info),
+            ("error_warning", &self.error_warning),
+            ("error_fatal", &self.error_fatal),
+        ]
+    }
+
+    /// Every value must name at least one class, given bare as in a `class`
+    /// attribute rather than as a `.selector`.
+    pub(crate) fn validate(&self) -> Result<(), VideoError> {
+        for (name, value) in self.class_names() {
+            let mut classes = value.split_whitespace().peekable();
+            if classes.peek().is_none() {
+                return Err(VideoError::InvalidConfig(format!("element class {} cannot be empty", name)));
+            }
+            if let Some(class) = classes.find(|class| class.starts_with('.')) {
+                return Err(VideoError::InvalidConfig(format!(
+                    "element class {} must be a class name, not a selector, got {}",
+                    name, class
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ElementClasses {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for ElementIds {
+    fn default() -> Self {
+        ElementIds::new(
+            "videoPlayer".to_string(),
+            "toggleButton".to_string(),
+            "muteButton".to_string(),
+            "fullscreenButton".to_string(),
+            "menuButton".to_string(),
+            "currentTime".to_string(),
+            "totalTime".to_string(),
+            "errorMessage".to_string(),
+            "contextMenu".to_string(),
+            "playbackSpeedMenu".to_string(),
+        )
+    }
 }
 
 #[wasm_bindgen]
@@ -36,7 +149,34 @@ impl ElementClasses {
             playback_speed_menu: "playback-speed-menu".to_string(),
             playback_speed_menu_show: "playback-speed-menu show".to_string(),
             speed_option: "speed-option".to_string(),
+            speed_option_active: "speed-option active".to_string(),
+            quality_menu: "quality-menu".to_string(),
+            quality_menu_show: "quality-menu show".to_string(),
+            quality_option: "quality-option".to_string(),
+            quality_option_active: "quality-option active".to_string(),
+            caption_menu: "caption-menu".to_string(),
+            caption_menu_show: "caption-menu show".to_string(),
+            caption_option: "caption-option".to_string(),
+            caption_option_active: "caption-option active".to_string(),
+            caption_overlay: "caption-overlay".to_string(),
+            caption_cue: "caption-cue".to_string(),
+            caption_stack: "caption-stack".to_string(),
+            caption_region: "caption-region".to_string(),
+            seek_bar: "seek-bar".to_string(),
+            seek_bar_dragging: "seek-bar dragging".to_string(),
+            seek_bar_buffered: "seek-bar-buffered".to_string(),
+            seek_bar_played: "seek-bar-played".to_string(),
+            seek_bar_thumb: "seek-bar-thumb".to_string(),
+            seek_bar_tooltip: "seek-bar-tooltip".to_string(),
+            seek_bar_tooltip_show: "seek-bar-tooltip show".to_string(),
+            resume_prompt: "resume-prompt".to_string(),
+            click_to_play: "click-to-play".to_string(),
             context_menu_item: "context-menu-item".to_string(),
+            error_message: "error-message".to_string(),
+            error_message_show: "error-message show".to_string(),
+            error_info: "error-entry info".to_string(),
+            error_warning: "error-entry warning".to_string(),
+            error_fatal: "error-entry fatal".to_string(),
         }
     }
 
@@ -60,14 +200,255 @@ impl ElementClasses {
         self.speed_option.clone()
     }
 
+    pub fn speed_option_active(&self) -> String {
+        self.speed_option_active.clone()
+    }
+
+    pub fn quality_menu(&self) -> String {
+        self.quality_menu.clone()
+    }
+
+    pub fn quality_menu_show(&self) -> String {
+        self.quality_menu_show.clone()
+    }
+
+    pub fn quality_option(&self) -> String {
+        self.quality_option.clone()
+    }
+
+    pub fn quality_option_active(&self) -> String {
+        self.quality_option_active.clone()
+    }
+
+    pub fn caption_menu(&self) -> String {
+        self.caption_menu.clone()
+    }
+
+    pub fn caption_menu_show(&self) -> String {
+        self.caption_menu_show.clone()
+    }
+
+    pub fn caption_option(&self) -> String {
+        self.caption_option.clone()
+    }
+
+    pub fn caption_option_active(&self) -> String {
+        self.caption_option_active.clone()
+    }
+
+    pub fn caption_overlay(&self) -> String {
+        self.caption_overlay.clone()
+    }
+
+    pub fn caption_cue(&self) -> String {
+        self.caption_cue.clone()
+    }
+
+    pub fn caption_stack(&self) -> String {
+        self.caption_stack.clone()
+    }
+
+    pub fn caption_region(&self) -> String {
+        self.caption_region.clone()
+    }
+
+    pub fn seek_bar(&self) -> String {
+        self.seek_bar.clone()
+    }
+
+    pub fn seek_bar_dragging(&self) -> String {
+        self.seek_bar_dragging.clone()
+    }
+
+    pub fn seek_bar_buffered(&self) -> String {
+        self.seek_bar_buffered.clone()
+    }
+
+    pub fn seek_bar_played(&self) -> String {
+        self.seek_bar_played.clone()
+    }
+
+    pub fn seek_bar_thumb(&self) -> String {
+        self.seek_bar_thumb.clone()
+    }
+
+    pub fn seek_bar_tooltip(&self) -> String {
+        self.seek_bar_tooltip.clone()
+    }
+
+    pub fn seek_bar_tooltip_show(&self) -> String {
+        self.seek_bar_tooltip_show.clone()
+    }
+
+    pub fn resume_prompt(&self) -> String {
+        self.resume_prompt.clone()
+    }
+
+    pub fn click_to_play(&self) -> String {
+        self.click_to_play.clone()
+    }
+
     pub fn context_menu_item(&self) -> String {
         self.context_menu_item.clone()
     }
+
+    pub fn error_message(&self) -> String {
+        self.error_message.clone()
+    }
+
+    pub fn error_message_show(&self) -> String {
+        self.error_message_show.clone()
+    }
+
+    pub fn error_info(&self) -> String {
+        self.error_info.clone()
+    }
+
+    pub fn error_warning(&self) -> String {
+        self.error_warning.clone()
+    }
+
+    pub fn error_fatal(&self) -> String {
+        self.error_fatal.clone()
+    }
+
+    pub fn set_context_menu(&mut self, class_name: String) {
+        self.context_menu = class_name;
+    }
+
+    pub fn set_context_menu_show(&mut self, class_name: String) {
+        self.context_menu_show = class_name;
+    }
+
+    pub fn set_playback_speed_menu(&mut self, class_name: String) {
+        self.playback_speed_menu = class_name;
+    }
+
+    pub fn set_playback_speed_menu_show(&mut self, class_name: String) {
+        self.playback_speed_menu_show = class_name;
+    }
+
+    pub fn set_speed_option(&mut self, class_name: String) {
+        self.speed_option = class_name;
+    }
+
+    pub fn set_speed_option_active(&mut self, class_name: String) {
+        self.speed_option_active = class_name;
+    }
+
+    pub fn set_quality_menu(&mut self, class_name: String) {
+        self.quality_menu = class_name;
+    }
+
+    pub fn set_quality_menu_show(&mut self, class_name: String) {
+        self.quality_menu_show = class_name;
+    }
+
+    pub fn set_quality_option(&mut self, class_name: String) {
+        self.quality_option = class_name;
+    }
+
+    pub fn set_quality_option_active(&mut self, class_name: String) {
+        self.quality_option_active = class_name;
+    }
+
+    pub fn set_caption_menu(&mut self, class_name: String) {
+        self.caption_menu = class_name;
+    }
+
+    pub fn set_caption_menu_show(&mut self, class_name: String) {
+        self.caption_menu_show = class_name;
+    }
+
+    pub fn set_caption_option(&mut self, class_name: String) {
+        self.caption_option = class_name;
+    }
+
+    pub fn set_caption_option_active(&mut self, class_name: String) {
+        self.caption_option_active = class_name;
+    }
+
+    pub fn set_caption_overlay(&mut self, class_name: String) {
+        self.caption_overlay = class_name;
+    }
+
+    pub fn set_caption_cue(&mut self, class_name: String) {
+        self.caption_cue = class_name;
+    }
+
+    pub fn set_caption_stack(&mut self, class_name: String) {
+        self.caption_stack = class_name;
+    }
+
+    pub fn set_caption_region(&mut self, class_name: String) {
+        self.caption_region = class_name;
+    }
+
+    pub fn set_seek_bar(&mut self, class_name: String) {
+        self.seek_bar = class_name;
+    }
+
+    pub fn set_seek_bar_dragging(&mut self, class_name: String) {
+        self.seek_bar_dragging = class_name;
+    }
+
+    pub fn set_seek_bar_buffered(&mut self, class_name: String) {
+        self.seek_bar_buffered = class_name;
+    }
+
+    pub fn set_seek_bar_played(&mut self, class_name: String) {
+        self.seek_bar_played = class_name;
+    }
+
+    pub fn set_seek_bar_thumb(&mut self, class_name: String) {
+        self.seek_bar_thumb = class_name;
+    }
+
+    pub fn set_seek_bar_tooltip(&mut self, class_name: String) {
+        self.seek_bar_tooltip = class_name;
+    }
+
+    pub fn set_seek_bar_tooltip_show(&mut self, class_name: String) {
+        self.seek_bar_tooltip_show = class_name;
+    }
+
+    pub fn set_resume_prompt(&mut self, class_name: String) {
+        self.resume_prompt = class_name;
+    }
+
+    pub fn set_click_to_play(&mut self, class_name: String) {
+        self.click_to_play = class_name;
+    }
+
+    pub fn set_context_menu_item(&mut self, class_name: String) {
+        self.context_menu_item = class_name;
+    }
+
+    pub fn set_error_message(&mut self, class_name: String) {
+        self.error_message = class_name;
+    }
+
+    pub fn set_error_message_show(&mut self, class_name: String) {
+        self.error_message_show = class_name;
+    }
+
+    pub fn set_error_info(&mut self, class_name: String) {
+        self.error_info = class_name;
+    }
+
+    pub fn set_error_warning(&mut self, class_name: String) {
+        self.error_warning = class_name;
+    }
+
+    pub fn set_error_fatal(&mut self, class_name: String) {
+        self.error_fatal = class_name;
+    }
 }
 
 #[wasm_bindgen]
 impl ElementIds {
     #[wasm_bindgen(constructor)]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         video_player: String,
         toggle_button: String,
@@ -91,6 +472,10 @@ impl ElementIds {
             error_message,
             context_menu,
             playback_speed_menu,
+            quality_menu: "qualityMenu".to_string(),
+            captions_menu: "captionsMenu".to_string(),
+            seek_bar: "seekBar".to_string(),
+            volume_slider: "volumeSlider".to_string(),
         }
     }
 
@@ -133,4 +518,60 @@ impl ElementIds {
     pub fn playback_speed_menu(&self) -> String {
         self.playback_speed_menu.to_string()
     }
-} 
\ No newline at end of file
+
+    pub fn quality_menu(&self) -> String {
+        self.quality_menu.to_string()
+    }
+
+    /// The quality menu is optional, so it is set separately from the constructor ids.
+    pub fn set_quality_menu(&mut self, quality_menu: String) {
+        self.quality_menu = quality_menu;
+    }
+
+    pub fn captions_menu(&self) -> String {
+        self.captions_menu.to_string()
+    }
+
+    /// Like the quality menu, the captions menu is optional.
+    pub fn set_captions_menu(&mut self, captions_menu: String) {
+        self.captions_menu = captions_menu;
+    }
+
+    pub fn seek_bar(&self) -> String {
+        self.seek_bar.to_string()
+    }
+
+    /// The seek bar is optional too; without it only the time display is updated.
+    pub fn set_seek_bar(&mut self, seek_bar: String) {
+        self.seek_bar = seek_bar;
+    }
+
+    pub fn volume_slider(&self) -> String {
+        self.volume_slider.to_string()
+    }
+
+    /// The volume slider is an optional `<input type="range">`.
+    pub fn set_volume_slider(&mut self, volume_slider: String) {
+        self.volume_slider = volume_slider;
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_names_are_validated() {
+        let mut classes = ElementClasses::new();
+        assert!(classes.validate().is_ok());
+        classes.set_context_menu_show("context-menu  show".to_string());
+        assert!(classes.validate().is_ok());
+
+        classes.set_seek_bar_thumb("  ".to_string());
+        let error = classes.validate().unwrap_err();
+        assert_eq!(error.to_string(), "Invalid player config: element class seek_bar_thumb cannot be empty");
+
+        classes.set_seek_bar_thumb("seek-bar-thumb".to_string());
+        classes.set_error_fatal("error-entry .fatal".to_string());
+        assert!(matches!(classes.validate(), Err(VideoError::InvalidConfig(message)) if message.contains("error_fatal")));
+    }
+}
@@ -1,5 +1,53 @@
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsValue;
+use web_sys::DomException;
+use crate::logger::{LogLevel, Logger};
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error_overlay::{queue_error_for, render_errors_for};
+use crate::player::events::{publish_for, PlayerEvent};
+
+// MediaError.code values
+const MEDIA_ERR_ABORTED: u16 = 1;
+const MEDIA_ERR_NETWORK: u16 = 2;
+const MEDIA_ERR_DECODE: u16 = 3;
+const MEDIA_ERR_SRC_NOT_SUPPORTED: u16 = 4;
+
+#[wasm_bindgen(typescript_custom_section)]
+const VIDEO_ERROR_CODES: &'static str = r#"
+export type VideoErrorCode =
+    | "window_not_found" | "document_not_found" | "element_not_found" | "operation_failed"
+    | "state_error" | "no_sources" | "invalid_source_url" | "unsupported_source_type"
+    | "invalid_config" | "manifest_parse_error" | "network_error" | "media_source_error"
+    | "caption_parse_error" | "playlist_parse_error" | "autoplay_blocked" | "decode_error"
+    | "source_not_supported" | "aborted" | "fullscreen_denied" | "pip_unavailable"
+    | "quota_exceeded" | "playback_stalled" | "source_failover" | "custom";
+"#;
+
+/// How much an error matters to the viewer, which decides how it is shown and
+/// how long it stays.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth knowing, such as playback moving to a mirror; goes away by itself.
+    Info,
+    /// Something did not work, but playback carries on.
+    Warning,
+    /// Playback cannot continue until something is done about it; stays until dismissed.
+    Fatal,
+}
+
+impl Severity {
+    /// The level errors of this severity are logged at.
+    pub fn log_level(self) -> LogLevel {
+        match self {
+            Severity::Info => LogLevel::Info,
+            Severity::Warning => LogLevel::Warn,
+            Severity::Fatal => LogLevel::Error,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum VideoError {
@@ -8,6 +56,138 @@ pub enum VideoError {
     ElementNotFound(String),
     VideoOperationFailed(String),
     StateError(String),
+    NoSources,
+    InvalidSourceUrl(String),
+    UnsupportedSourceType(String),
+    InvalidConfig(String),
+    ManifestParseError(String),
+    NetworkError(String),
+    MediaSourceError(String),
+    CaptionParseError(String),
+    PlaylistParseError(String),
+    /// The browser would not start playback without a user gesture.
+    AutoplayBlocked(String),
+    DecodeError(String),
+    /// None of the sources is in a format the browser can play.
+    SourceNotSupported(String),
+    /// Loading or playback was cut short, usually by newer media being loaded.
+    Aborted(String),
+    FullscreenDenied(String),
+    PipUnavailable(String),
+    /// Storage or a media buffer is full.
+    QuotaExceeded(String),
+    /// Playback stopped moving and is being retried.
+    PlaybackStalled(String),
+    /// The current source kept stalling, so playback moved to another source or mirror.
+    SourceFailover(String),
+}
+
+/// What the player asked the browser to do, which decides what a refusal means:
+/// a `NotAllowedError` from `play()` is blocked autoplay, from `requestFullscreen()` a denial.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    Play,
+    Fullscreen,
+    PictureInPicture,
+    Other,
+}
+
+impl VideoError {
+    /// Stable identifier for the kind of error, for host pages to branch on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            VideoError::WindowNotFound => "window_not_found",
+            VideoError::DocumentNotFound => "document_not_found",
+            VideoError::ElementNotFound(_) => "element_not_found",
+            VideoError::VideoOperationFailed(_) => "operation_failed",
+            VideoError::StateError(_) => "state_error",
+            VideoError::NoSources => "no_sources",
+            VideoError::InvalidSourceUrl(_) => "invalid_source_url",
+            VideoError::UnsupportedSourceType(_) => "unsupported_source_type",
+            VideoError::InvalidConfig(_) => "invalid_config",
+            VideoError::ManifestParseError(_) => "manifest_parse_error",
+            VideoError::NetworkError(_) => "network_error",
+            VideoError::MediaSourceError(_) => "media_source_error",
+            VideoError::CaptionParseError(_) => "caption_parse_error",
+            VideoError::PlaylistParseError(_) => "playlist_parse_error",
+            VideoError::AutoplayBlocked(_) => "autoplay_blocked",
+            VideoError::DecodeError(_) => "decode_error",
+            VideoError::SourceNotSupported(_) => "source_not_supported",
+            VideoError::Aborted(_) => "aborted",
+            VideoError::FullscreenDenied(_) => "fullscreen_denied",
+            VideoError::PipUnavailable(_) => "pip_unavailable",
+            VideoError::QuotaExceeded(_) => "quota_exceeded",
+            VideoError::PlaybackStalled(_) => "playback_stalled",
+            VideoError::SourceFailover(_) => "source_failover",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            VideoError::AutoplayBlocked(_) | VideoError::Aborted(_) | VideoError::SourceFailover(_) => Severity::Info,
+            VideoError::NoSources
+            | VideoError::InvalidSourceUrl(_)
+            | VideoError::UnsupportedSourceType(_)
+            | VideoError::InvalidConfig(_)
+            | VideoError::ManifestParseError(_)
+            | VideoError::NetworkError(_)
+            | VideoError::MediaSourceError(_)
+            | VideoError::DecodeError(_)
+            | VideoError::SourceNotSupported(_) => Severity::Fatal,
+            _ => Severity::Warning,
+        }
+    }
+
+    /// Whether loading the media again may help, which earns the message a Retry button.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            VideoError::NetworkError(_)
+                | VideoError::MediaSourceError(_)
+                | VideoError::ManifestParseError(_)
+                | VideoError::DecodeError(_)
+                | VideoError::PlaybackStalled(_)
+        )
+    }
+
+    /// The error for a `MediaError` the video element reported.
+    pub fn from_media_error(code: u16, message: &str) -> VideoError {
+        let message = message.to_string();
+        match code {
+            MEDIA_ERR_ABORTED => VideoError::Aborted(message),
+            MEDIA_ERR_NETWORK => VideoError::NetworkError(message),
+            MEDIA_ERR_DECODE => VideoError::DecodeError(message),
+            MEDIA_ERR_SRC_NOT_SUPPORTED => VideoError::SourceNotSupported(message),
+            _ => VideoError::VideoOperationFailed(format!("Media error {}: {}", code, message)),
+        }
+    }
+
+    /// The error for a `DOMException` named `name` that `operation` failed with.
+    pub fn from_dom_exception(operation: Operation, name: &str, message: &str) -> VideoError {
+        let message = message.to_string();
+        match (name, operation) {
+            ("AbortError", _) => VideoError::Aborted(message),
+            ("QuotaExceededError", _) => VideoError::QuotaExceeded(message),
+            ("NetworkError", _) => VideoError::NetworkError(message),
+            ("EncodingError", _) => VideoError::DecodeError(message),
+            (_, Operation::Fullscreen) => VideoError::FullscreenDenied(message),
+            (_, Operation::PictureInPicture) => VideoError::PipUnavailable(message),
+            ("NotAllowedError", Operation::Play) => VideoError::AutoplayBlocked(message),
+            ("NotSupportedError", Operation::Play) => VideoError::SourceNotSupported(message),
+            _ => VideoError::VideoOperationFailed(format!("{}: {}", name, message)),
+        }
+    }
+
+    /// The error for whatever `operation` threw or rejected with.
+    pub fn from_js(operation: Operation, value: &JsValue) -> VideoError {
+        if let Some(exception) = value.dyn_ref::<DomException>() {
+            return VideoError::from_dom_exception(operation, &exception.name(), &exception.message());
+        }
+        if let Some(error) = value.dyn_ref::<js_sys::Error>() {
+            return VideoError::from_dom_exception(operation, &String::from(error.name()), &String::from(error.message()));
+        }
+        VideoError::VideoOperationFailed(format!("{:?}", value))
+    }
 }
 
 impl std::fmt::Display for VideoError {
@@ -18,42 +198,122 @@ impl std::fmt::Display for VideoError {
             VideoError::ElementNotFound(id) => write!(f, "Element not found: {}", id),
             VideoError::VideoOperationFailed(msg) => write!(f, "Video operation failed: {}", msg),
             VideoError::StateError(msg) => write!(f, "State error: {}", msg),
+            VideoError::NoSources => write!(f, "No video sources configured"),
+            VideoError::InvalidSourceUrl(url) => write!(f, "Invalid source URL: {}", url),
+            VideoError::UnsupportedSourceType(mime) => write!(f, "Unsupported source type: {}", mime),
+            VideoError::InvalidConfig(msg) => write!(f, "Invalid player config: {}", msg),
+            VideoError::ManifestParseError(msg) => write!(f, "Failed to parse manifest: {}", msg),
+            VideoError::NetworkError(msg) => write!(f, "Network error: {}", msg),
+            VideoError::MediaSourceError(msg) => write!(f, "Media source error: {}", msg),
+            VideoError::CaptionParseError(msg) => write!(f, "Failed to parse captions: {}", msg),
+            VideoError::PlaylistParseError(msg) => write!(f, "Failed to parse playlist: {}", msg),
+            VideoError::AutoplayBlocked(msg) => write!(f, "Autoplay was blocked: {}", msg),
+            VideoError::DecodeError(msg) => write!(f, "Failed to decode video: {}", msg),
+            VideoError::SourceNotSupported(msg) => write!(f, "Video source not supported: {}", msg),
+            VideoError::Aborted(msg) => write!(f, "Video loading aborted: {}", msg),
+            VideoError::FullscreenDenied(msg) => write!(f, "Fullscreen was denied: {}", msg),
+            VideoError::PipUnavailable(msg) => write!(f, "Picture-in-picture is unavailable: {}", msg),
+            VideoError::QuotaExceeded(msg) => write!(f, "Storage quota exceeded: {}", msg),
+            VideoError::PlaybackStalled(msg) => write!(f, "Playback stalled: {}", msg),
+            VideoError::SourceFailover(msg) => write!(f, "Switched source: {}", msg),
         }
     }
 }
 
 impl std::error::Error for VideoError {}
 
+/// JavaScript sees an `Error` whose `code` property is `VideoError::code`.
 impl From<VideoError> for JsValue {
     fn from(error: VideoError) -> Self {
-        JsValue::from_str(&error.to_string())
+        let js_error = js_sys::Error::new(&error.to_string());
+        js_sys::Reflect::set(&js_error, &JsValue::from_str("code"), &JsValue::from_str(error.code())).unwrap_or_default();
+        js_error.into()
     }
 }
 
 #[wasm_bindgen]
 pub fn show_error(message: &str) -> Result<(), JsValue> {
-    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
-    let document = window.document().ok_or(VideoError::DocumentNotFound)?;
-    
-    let error_element = document
-        .get_element_by_id("errorMessage")
-        .ok_or(VideoError::ElementNotFound("errorMessage".to_string()))?;
-    
-    error_element.set_text_content(Some(message));
-    error_element.set_attribute("class", "error-message show")?;
-    Ok(())
+    show_error_for(&default_context(), message)
 }
 
 #[wasm_bindgen]
 pub fn hide_error() -> Result<(), JsValue> {
-    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
-    let document = window.document().ok_or(VideoError::DocumentNotFound)?;
-    
-    let error_element = document
-        .get_element_by_id("errorMessage")
-        .ok_or(VideoError::ElementNotFound("errorMessage".to_string()))?;
-    
-    error_element.set_text_content(None);
-    error_element.set_attribute("class", "error-message")?;
+    hide_error_for(&default_context())
+}
+
+/// Shows a message of the page's own until it is dismissed, reported to `error`
+/// handlers with the code `custom`.
+pub fn show_error_for(context: &PlayerContext, message: &str) -> Result<(), JsValue> {
+    show_message_for(context, message, Severity::Fatal)
+}
+
+/// Shows a message of the page's own with `severity`, which decides how long it stays.
+pub fn show_message_for(context: &PlayerContext, message: &str, severity: Severity) -> Result<(), JsValue> {
+    publish_for(context, PlayerEvent::Error { code: "custom".to_string(), message: message.to_string(), severity });
+    queue_error_for(context, "custom", message, severity, false)
+}
+
+/// Adds `error` to the error overlay and reports it, with its code and severity,
+/// to `error` handlers.
+pub fn report_error_for(context: &PlayerContext, error: &VideoError) -> Result<(), JsValue> {
+    let message = error.to_string();
+    Logger::log(error.severity().log_level(), &format!("{}: {}", error.code(), message)).unwrap_or_default();
+    publish_for(context, PlayerEvent::Error { code: error.code().to_string(), message: message.clone(), severity: error.severity() });
+    queue_error_for(context, error.code(), &message, error.severity(), error.is_retryable())
+}
+
+/// Removes the messages with any of `codes`, for when whatever went wrong has been put right.
+/// Messages about anything else stay.
+pub fn resolve_errors_for(context: &PlayerContext, codes: &[&str]) -> Result<(), JsValue> {
+    if context.errors.borrow_mut().resolve(codes) {
+        render_errors_for(context)?;
+    }
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// Removes every message.
+pub fn hide_error_for(context: &PlayerContext) -> Result<(), JsValue> {
+    context.errors.borrow_mut().clear();
+    render_errors_for(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_error_codes() {
+        let errors = [1, 2, 3, 4].map(|code| VideoError::from_media_error(code, "MEDIA_ELEMENT_ERROR: Format error"));
+        assert_eq!(errors.each_ref().map(|error| error.code()), ["aborted", "network_error", "decode_error", "source_not_supported"]);
+        // The browser's message is kept
+        assert_eq!(errors[3].to_string(), "Video source not supported: MEDIA_ELEMENT_ERROR: Format error");
+        assert_eq!(VideoError::from_media_error(9, "").code(), "operation_failed");
+    }
+
+    #[test]
+    fn test_severity_and_retry() {
+        assert_eq!(VideoError::AutoplayBlocked(String::new()).severity(), Severity::Info);
+        assert_eq!(VideoError::FullscreenDenied(String::new()).severity(), Severity::Warning);
+        assert_eq!(VideoError::PlaybackStalled(String::new()).severity(), Severity::Warning);
+        assert_eq!(VideoError::from_media_error(2, "").severity(), Severity::Fatal);
+        assert!(VideoError::from_media_error(2, "").is_retryable());
+        assert!(!VideoError::NoSources.is_retryable());
+        assert!(Severity::Fatal > Severity::Warning && Severity::Warning > Severity::Info);
+    }
+
+    #[test]
+    fn test_dom_exceptions_depend_on_the_operation() {
+        let refused = |operation| VideoError::from_dom_exception(operation, "NotAllowedError", "denied").code();
+        assert_eq!(refused(Operation::Play), "autoplay_blocked");
+        assert_eq!(refused(Operation::Fullscreen), "fullscreen_denied");
+        assert_eq!(refused(Operation::PictureInPicture), "pip_unavailable");
+        assert_eq!(refused(Operation::Other), "operation_failed");
+        assert_eq!(VideoError::from_dom_exception(Operation::Play, "NotSupportedError", "").code(), "source_not_supported");
+        assert_eq!(VideoError::from_dom_exception(Operation::Play, "AbortError", "").code(), "aborted");
+        assert_eq!(VideoError::from_dom_exception(Operation::Other, "QuotaExceededError", "").code(), "quota_exceeded");
+        assert!(matches!(
+            VideoError::from_dom_exception(Operation::Play, "NotAllowedError", "play() can only be initiated by a user gesture"),
+            VideoError::AutoplayBlocked(message) if message == "play() can only be initiated by a user gesture"
+        ));
+    }
+}
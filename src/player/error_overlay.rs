@@ -0,0 +1,303 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+use web_sys::Element;
+use crate::logger::Logger;
+use crate::player::context::PlayerContext;
+use crate::player::element_ids::ElementClasses;
+use crate::player::error::{report_error_for, Severity, VideoError};
+use crate::player::fetch::sleep_ms;
+use crate::player::watchdog::retry_playback_for;
+
+// Messages shown at once; beyond this the oldest of the least severe goes
+const MAX_ERRORS: usize = 5;
+/// Seconds info, warning and fatal messages stay unless configured otherwise;
+/// fatal ones stay until dismissed.
+pub const DEFAULT_ERROR_TIMEOUTS: [Option<f64>; 3] = [Some(5.0), Some(10.0), None];
+
+// Message buttons
+const ERROR_ACTION_RETRY: &str = "retry";
+const ERROR_ACTION_DISMISS: &str = "dismiss";
+// Attribute holding the id of the message an element belongs to
+const ERROR_ID_ATTRIBUTE: &str = "data-error-id";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorEntry {
+    pub id: u64,
+    pub code: String,
+    pub message: String,
+    pub severity: Severity,
+    /// How many times the same message was reported while it was showing.
+    pub count: u32,
+    /// Whether it offers to load the media again.
+    pub retry: bool,
+    expires_at: Option<f64>,
+}
+
+/// The messages in the error overlay: one entry per distinct message, most severe
+/// first and otherwise oldest first. Times are seconds on whatever clock the caller uses.
+#[derive(Clone, Debug)]
+pub struct ErrorQueue {
+    entries: Vec<ErrorEntry>,
+    next_id: u64,
+    // Seconds each severity stays, by `Severity as usize`; `None` until dismissed
+    timeouts: [Option<f64>; 3],
+}
+
+impl Default for ErrorQueue {
+    fn default() -> Self {
+        ErrorQueue { entries: Vec::new(), next_id: 0, timeouts: DEFAULT_ERROR_TIMEOUTS }
+    }
+}
+
+impl ErrorQueue {
+    pub fn entries(&self) -> &[ErrorEntry] {
+        &self.entries
+    }
+
+    pub fn set_timeout(&mut self, severity: Severity, seconds: Option<f64>) {
+        self.timeouts[severity as usize] = seconds;
+    }
+
+    pub fn timeout(&self, severity: Severity) -> Option<f64> {
+        self.timeouts[severity as usize]
+    }
+
+    /// Adds a message at `now`, or restarts the timer of the same message already
+    /// showing. Returns when it should go away by itself.
+    pub fn push(&mut self, code: &str, message: &str, severity: Severity, retry: bool, now: f64) -> Option<f64> {
+        let expires_at = self.timeout(severity).map(|timeout| now + timeout);
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.code == code && entry.message == message) {
+            entry.count += 1;
+            entry.expires_at = expires_at;
+            return expires_at;
+        }
+        self.entries.push(ErrorEntry {
+            id: self.next_id,
+            code: code.to_string(),
+            message: message.to_string(),
+            severity,
+            count: 1,
+            retry,
+            expires_at,
+        });
+        self.next_id += 1;
+        self.entries.sort_by_key(|entry| (std::cmp::Reverse(entry.severity), entry.id));
+        if self.entries.len() > MAX_ERRORS {
+            if let Some(index) = (0..self.entries.len()).min_by_key(|index| (self.entries[*index].severity, self.entries[*index].id)) {
+                self.entries.remove(index);
+            }
+        }
+        expires_at
+    }
+
+    /// Removes a message; `false` if it was not showing.
+    pub fn dismiss(&mut self, id: u64) -> bool {
+        self.retain(|entry| entry.id != id)
+    }
+
+    /// Removes the messages with any of `codes`.
+    pub fn resolve(&mut self, codes: &[&str]) -> bool {
+        self.retain(|entry| !codes.contains(&entry.code.as_str()))
+    }
+
+    pub fn clear(&mut self) -> bool {
+        self.retain(|_| false)
+    }
+
+    /// Removes the messages whose time is up at `now`.
+    pub fn expire(&mut self, now: f64) -> bool {
+        self.retain(|entry| entry.expires_at.is_none_or(|expires_at| expires_at > now))
+    }
+
+    fn retain<F: FnMut(&ErrorEntry) -> bool>(&mut self, keep: F) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(keep);
+        self.entries.len() != count
+    }
+}
+
+fn now() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+/// Makes the error element a live region, so screen readers announce new messages.
+pub fn setup_error_overlay_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let container = context.element(&context.ids.error_message)?;
+    container.set_attribute("role", "status")?;
+    container.set_attribute("aria-live", "polite")?;
+    container.set_attribute("aria-atomic", "false")?;
+    render_errors_for(context)
+}
+
+/// Shows a message, and takes it down again once its severity's time is up.
+pub fn queue_error_for(context: &PlayerContext, code: &str, message: &str, severity: Severity, retry: bool) -> Result<(), JsValue> {
+    let expires_at = context.errors.borrow_mut().push(code, message, severity, retry, now());
+    if let Some(expires_at) = expires_at {
+        let errors = Rc::downgrade(&context.errors);
+        let container = context.element(&context.ids.error_message)?;
+        let classes = context.classes.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let delay_ms = ((expires_at - now()) * 1000.0).ceil().max(0.0) as i32;
+            if sleep_ms(delay_ms).await.is_ok() {
+                expire_errors(&errors, &container, &classes).unwrap_or_default();
+            }
+        });
+    }
+    render_errors_for(context)
+}
+
+fn expire_errors(errors: &Weak<RefCell<ErrorQueue>>, container: &Element, classes: &ElementClasses) -> Result<(), JsValue> {
+    let Some(errors) = errors.upgrade() else {
+        return Ok(());
+    };
+    let expired = errors.borrow_mut().expire(now());
+    if expired {
+        render_queue(&errors.borrow(), container, classes)?;
+    }
+    Ok(())
+}
+
+/// Redraws the error overlay from the queue.
+pub fn render_errors_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let container = context.element(&context.ids.error_message)?;
+    render_queue(&context.errors.borrow(), &container, &context.classes)
+}
+
+fn render_queue(errors: &ErrorQueue, container: &Element, classes: &ElementClasses) -> Result<(), JsValue> {
+    let document = container.owner_document().ok_or(VideoError::DocumentNotFound)?;
+    container.set_text_content(None);
+    for entry in errors.entries() {
+        let item = document.create_element("div")?;
+        let class_name = match entry.severity {
+            Severity::Info => &classes.error_info,
+            Severity::Warning => &classes.error_warning,
+            Severity::Fatal => &classes.error_fatal,
+        };
+        item.set_attribute("class", class_name)?;
+        item.set_attribute(ERROR_ID_ATTRIBUTE, &entry.id.to_string())?;
+        item.set_attribute("data-code", &entry.code)?;
+        let message = document.create_element("span")?;
+        let text = match entry.count {
+            1 => entry.message.clone(),
+            count => format!("{} ({}\u{d7})", entry.message, count),
+        };
+        message.set_text_content(Some(&text));
+        item.append_child(&message)?;
+        if entry.retry {
+            item.append_child(&action_button(&document, ERROR_ACTION_RETRY, "Retry", "Retry")?.into())?;
+        }
+        item.append_child(&action_button(&document, ERROR_ACTION_DISMISS, "\u{d7}", "Dismiss")?.into())?;
+        container.append_child(&item)?;
+    }
+    // Fatal messages interrupt whatever a screen reader is saying
+    let fatal = errors.entries().iter().any(|entry| entry.severity == Severity::Fatal);
+    container.set_attribute("aria-live", if fatal { "assertive" } else { "polite" })?;
+    let class_name = if errors.entries().is_empty() { &classes.error_message } else { &classes.error_message_show };
+    container.set_attribute("class", class_name)?;
+    Ok(())
+}
+
+fn action_button(document: &web_sys::Document, action: &str, text: &str, label: &str) -> Result<Element, JsValue> {
+    let button = document.create_element("button")?;
+    button.set_attribute("type", "button")?;
+    button.set_attribute("data-action", action)?;
+    button.set_attribute("aria-label", label)?;
+    button.set_text_content(Some(text));
+    Ok(button)
+}
+
+/// Handles the Retry and Dismiss buttons of a message. Clicks anywhere else are ignored.
+pub fn error_overlay_click_for(context: &Rc<PlayerContext>, target: &Element) -> Result<(), JsValue> {
+    let Some(button) = target.closest("[data-action]")? else {
+        return Ok(());
+    };
+    let Some(id) = target
+        .closest(&format!("[{}]", ERROR_ID_ATTRIBUTE))?
+        .and_then(|entry| entry.get_attribute(ERROR_ID_ATTRIBUTE))
+        .and_then(|id| id.parse::<u64>().ok())
+    else {
+        return Ok(());
+    };
+    let action = button.get_attribute("data-action");
+    if !matches!(action.as_deref(), Some(ERROR_ACTION_RETRY | ERROR_ACTION_DISMISS)) {
+        return Ok(());
+    }
+    context.errors.borrow_mut().dismiss(id);
+    render_errors_for(context)?;
+    if action.as_deref() == Some(ERROR_ACTION_RETRY) {
+        Logger::info("Retrying playback").unwrap_or_default();
+        retry_playback_for(context).inspect_err(|error| {
+            report_error_for(context, error).unwrap_or_default();
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(queue: &ErrorQueue) -> Vec<&str> {
+        queue.entries().iter().map(|entry| entry.code.as_str()).collect()
+    }
+
+    #[test]
+    fn test_repeated_messages_are_counted_once() {
+        let mut queue = ErrorQueue::default();
+        queue.push("network_error", "Network error: timeout", Severity::Fatal, true, 0.0);
+        queue.push("network_error", "Network error: timeout", Severity::Fatal, true, 1.0);
+        queue.push("network_error", "Network error: HTTP 404", Severity::Fatal, true, 2.0);
+        assert_eq!(queue.entries().len(), 2);
+        assert_eq!(queue.entries()[0].count, 2);
+        assert!(queue.entries()[0].retry);
+    }
+
+    #[test]
+    fn test_most_severe_first_and_least_severe_dropped() {
+        let mut queue = ErrorQueue::default();
+        queue.push("autoplay_blocked", "blocked", Severity::Info, false, 0.0);
+        queue.push("fullscreen_denied", "denied", Severity::Warning, false, 0.0);
+        queue.push("decode_error", "corrupt", Severity::Fatal, true, 0.0);
+        assert_eq!(codes(&queue), ["decode_error", "fullscreen_denied", "autoplay_blocked"]);
+
+        for index in 0..3 {
+            queue.push("custom", &format!("warning {}", index), Severity::Warning, false, 0.0);
+        }
+        assert_eq!(queue.entries().len(), MAX_ERRORS);
+        assert!(!codes(&queue).contains(&"autoplay_blocked"));
+    }
+
+    #[test]
+    fn test_timeouts_depend_on_severity() {
+        let mut queue = ErrorQueue::default();
+        queue.set_timeout(Severity::Warning, Some(3.0));
+        assert_eq!(queue.push("aborted", "aborted", Severity::Info, false, 0.0), Some(5.0));
+        assert_eq!(queue.push("state_error", "busy", Severity::Warning, false, 0.0), Some(3.0));
+        assert_eq!(queue.push("no_sources", "none", Severity::Fatal, false, 0.0), None);
+        // Reporting a message again restarts its timer
+        assert_eq!(queue.push("state_error", "busy", Severity::Warning, false, 2.0), Some(5.0));
+
+        assert!(!queue.expire(4.0));
+        assert!(queue.expire(5.0));
+        assert_eq!(codes(&queue), ["no_sources"]);
+        assert!(!queue.expire(1000.0));
+    }
+
+    #[test]
+    fn test_resolving_leaves_unrelated_messages() {
+        let mut queue = ErrorQueue::default();
+        queue.push("fullscreen_denied", "denied", Severity::Warning, false, 0.0);
+        queue.push("playback_stalled", "stalled", Severity::Warning, true, 0.0);
+        queue.push("custom", "from the page", Severity::Fatal, false, 0.0);
+        assert!(queue.resolve(&["playback_stalled", "network_error"]));
+        assert_eq!(codes(&queue), ["custom", "fullscreen_denied"]);
+        assert!(!queue.resolve(&["autoplay_blocked"]));
+
+        let id = queue.entries()[1].id;
+        assert!(queue.dismiss(id));
+        assert!(!queue.dismiss(id));
+        assert!(queue.clear());
+        assert!(queue.entries().is_empty());
+    }
+}
@@ -1,363 +1,532 @@
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::{
-    HtmlVideoElement,
-    Event,
-};
+use web_sys::{Event, KeyboardEvent, PointerEvent, WheelEvent};
 use wasm_bindgen_futures::spawn_local;
-use crate::player::play_pause::{play_video, set_toggle_play};
-use crate::player::mute::{toggle_mute, update_mute_button_text};
-use crate::player::fullscreen::{toggle_fullscreen, update_fullscreen_button_text};
-use crate::player::error::VideoError;
-use crate::player::time::update_time_display;
-use crate::player::download::download_video;
-use crate::player::menu::{position_playback_speed_menu, position_context_menu};
-use crate::player::picture_in_picture::toggle_picture_in_picture;
-use crate::player::playback_speed::set_playback_speed;
-use crate::player::ElementIds;
+use crate::player::play_pause::{click_to_play_click_for, dismiss_click_to_play_for, toggle_play_for, set_toggle_play_for};
+use crate::player::mute::toggle_mute_for;
+use crate::player::events::{publish_for, subscriber, PlayerEvent};
+use crate::player::fullscreen::{is_fullscreen_for, toggle_fullscreen_for, update_fullscreen_button_text_for};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::error_overlay::{error_overlay_click_for, setup_error_overlay_for};
+use crate::player::time::update_time_display_for;
+use crate::player::captions::{caption_of, render_captions_for, set_caption_track_for, MENU_ACTION_CAPTIONS};
+use crate::player::download::download_video_for;
+use crate::player::gapless::gapless_time_update_for;
+use crate::player::keyboard::{keyboard_shortcut_for, mark_active_player_for};
+use crate::player::menu::{position_playback_speed_menu_for, position_context_menu_for, position_quality_menu_for, position_captions_menu_for};
+use crate::player::picture_in_picture::toggle_picture_in_picture_for;
+use crate::player::playback_speed::set_playback_speed_for;
+use crate::player::playlist::playlist_ended_for;
+use crate::player::quality::{quality_of, set_quality_for, MENU_ACTION_QUALITY};
+use crate::player::state::Action;
+use crate::player::store::{dispatch_for, render_state_for};
+use crate::player::resume::{dismiss_resume_prompt_for, forget_position_for, record_position_for, resume_prompt_click_for};
+use crate::player::volume::{setup_volume_slider_for, sync_volume_for, volume_slider_input_for, volume_slider_key_down_for, volume_slider_wheel_for};
+use crate::player::watchdog::{watchdog_signal_for, Signal};
+use crate::player::seek_bar::{
+    render_seek_bar_for, seek_bar_key_down_for, seek_bar_pointer_down_for, seek_bar_pointer_leave_for,
+    seek_bar_pointer_move_for, seek_bar_pointer_up_for, setup_seek_bar_for,
+};
+use crate::player::context::{register_live_context, set_default_context, PlayerContext};
 use crate::player::element_ids::ElementClasses;
+use crate::player::{ElementIds, get_element_by_id, get_element_by_action};
 
 // Event name constants
 const EVENT_CLICK: &str = "click";
 const EVENT_TIMEUPDATE: &str = "timeupdate";
 const EVENT_LOADEDMETADATA: &str = "loadedmetadata";
 const EVENT_DURATIONCHANGE: &str = "durationchange";
+const EVENT_PROGRESS: &str = "progress";
+const EVENT_WAITING: &str = "waiting";
+const EVENT_STALLED: &str = "stalled";
+const EVENT_POINTERDOWN: &str = "pointerdown";
+const EVENT_POINTERMOVE: &str = "pointermove";
+const EVENT_POINTERUP: &str = "pointerup";
+const EVENT_POINTERCANCEL: &str = "pointercancel";
+const EVENT_POINTERLEAVE: &str = "pointerleave";
+const EVENT_KEYDOWN: &str = "keydown";
+const EVENT_FOCUSIN: &str = "focusin";
+const EVENT_INPUT: &str = "input";
+const EVENT_WHEEL: &str = "wheel";
 const EVENT_PLAY: &str = "play";
+const EVENT_PLAYING: &str = "playing";
 const EVENT_PAUSE: &str = "pause";
+const EVENT_ENDED: &str = "ended";
+const EVENT_SEEKED: &str = "seeked";
+const EVENT_RATECHANGE: &str = "ratechange";
+const EVENT_ENTERPICTUREINPICTURE: &str = "enterpictureinpicture";
+const EVENT_LEAVEPICTUREINPICTURE: &str = "leavepictureinpicture";
+const EVENT_PAGEHIDE: &str = "pagehide";
 const EVENT_VOLUMECHANGE: &str = "volumechange";
 const EVENT_FULLSCREENCHANGE: &str = "fullscreenchange";
+const EVENT_FULLSCREENERROR: &str = "fullscreenerror";
+const EVENT_ERROR: &str = "error";
 
 // Button text constants
 const BUTTON_TEXT_PLAY: &str = "Play";
 const BUTTON_TEXT_PAUSE: &str = "Pause";
 
+// Context menu items
+const MENU_ACTION_DOWNLOAD: &str = "download";
+const MENU_ACTION_PLAYBACK_SPEED: &str = "playback-speed";
+const MENU_ACTION_PIP: &str = "picture-in-picture";
+
 // Error message constants
-const ERROR_DOWNLOAD_BUTTON_NOT_FOUND: &str = "download button";
-const ERROR_PLAYBACK_SPEED_BUTTON_NOT_FOUND: &str = "playback speed button";
-const ERROR_PIP_BUTTON_NOT_FOUND: &str = "pip button";
 const ERROR_SPEED_OPTION_NOT_FOUND: &str = "Failed to get speed option";
 const ERROR_NODE_TO_ELEMENT_CONVERSION: &str = "Failed to convert Node to Element";
 const ERROR_NO_TEXT_CONTENT: &str = "No text content found";
 const ERROR_SPEED_PARSE: &str = "Failed to parse speed";
 
+/// Binds the default player instance to `element_ids` and wires up its listeners.
 #[wasm_bindgen]
 pub fn setup_event_listeners(element_ids: ElementIds) -> Result<(), JsValue> {
+    let context = PlayerContext::new(element_ids, ElementClasses::default());
+    register_live_context(&context);
+    set_default_context(context.clone());
+    setup_event_listeners_for(&context)
+}
+
+/// Wraps a handler so it only holds a weak reference to the player. The registry lives
+/// inside the context, so a strong reference here would keep the player alive forever.
+pub(crate) fn bind<F>(context: &Rc<PlayerContext>, handler: F) -> impl FnMut(Event) + 'static
+where
+    F: Fn(&Rc<PlayerContext>, Event) + 'static,
+{
+    let context = Rc::downgrade(context);
+    move |event: Event| {
+        if let Some(context) = context.upgrade() {
+            handler(&context, event);
+        }
+    }
+}
+
+/// `bind` for handlers registered with `ListenerRegistry::add_media`, which get no event.
+pub(crate) fn bind_media<F>(context: &Rc<PlayerContext>, handler: F) -> impl FnMut() + 'static
+where
+    F: Fn(&Rc<PlayerContext>) + 'static,
+{
+    let context = Rc::downgrade(context);
+    move || {
+        if let Some(context) = context.upgrade() {
+            handler(&context);
+        }
+    }
+}
+
+pub fn setup_event_listeners_for(context: &Rc<PlayerContext>) -> Result<(), JsValue> {
     let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
     let document = window.document().ok_or(VideoError::DocumentNotFound)?;
-    
-    let video_player = document
-        .get_element_by_id(&element_ids.video_player())
-        .ok_or(VideoError::ElementNotFound(element_ids.video_player()))?
-        .dyn_into::<HtmlVideoElement>()?;
+
+    let element_ids = context.ids().clone();
+    let element_classes = context.classes().clone();
+
+    let video_player = context.video_element()?;
 
     // Get menu elements
-    let context_menu = document
-        .get_element_by_id(&element_ids.context_menu())
-        .ok_or(VideoError::ElementNotFound(element_ids.context_menu()))?;
-    
-    let playback_speed_menu = document
-        .get_element_by_id(&element_ids.playback_speed_menu())
-        .ok_or(VideoError::ElementNotFound(element_ids.playback_speed_menu()))?;
+    let context_menu = context.element(&element_ids.context_menu())?;
+
+    let playback_speed_menu = context.element(&element_ids.playback_speed_menu())?;
+
+    // The quality and captions menus are optional
+    let quality_menu = context.element(&element_ids.quality_menu()).ok();
+    let captions_menu = context.element(&element_ids.captions_menu()).ok();
+
+    // The player's own controls follow its events the way a page would
+    {
+        let mut events = context.events.borrow_mut();
+        events.clear_player_subscriptions();
+        events.subscribe(EVENT_PLAY, subscriber(context, |context, _| {
+            // Playing without answering the resume prompt starts from where playback already is
+            dismiss_resume_prompt_for(context);
+            dismiss_click_to_play_for(context);
+        }));
+        events.subscribe(EVENT_PAUSE, subscriber(context, |context, _| {
+            set_toggle_play_for(context, BUTTON_TEXT_PLAY).unwrap_or_default();
+        }));
+        // Keeps the state, slider and mute button in sync
+        events.subscribe(EVENT_VOLUMECHANGE, subscriber(context, |context, _| {
+            sync_volume_for(context).unwrap_or_default();
+        }));
+        // Also covers the page setting playbackRate on the element directly
+        events.subscribe(EVENT_RATECHANGE, subscriber(context, |context, event| {
+            if let PlayerEvent::RateChange { playback_speed } = event {
+                dispatch_for(context, Action::PlaybackSpeedChanged { speed: *playback_speed }).unwrap_or_default();
+            }
+        }));
+        // A finished video starts over next time, and the playlist moves on
+        events.subscribe(EVENT_ENDED, subscriber(context, |context, _| {
+            forget_position_for(context);
+            playlist_ended_for(context).unwrap_or_default();
+        }));
+        events.subscribe(EVENT_FULLSCREENCHANGE, subscriber(context, |context, _| {
+            update_fullscreen_button_text_for(context).unwrap_or_default();
+        }));
+    }
 
-    let element_classes = ElementClasses::new();
+    let mut listeners = context.listeners_mut()?;
+    // Re-running setup on the same player replaces its handlers rather than adding more
+    listeners.remove_all();
 
     // Click outside listener to close menus
     {
         let context_menu_clone = context_menu.clone();
         let playback_speed_menu_clone = playback_speed_menu.clone();
+        let optional_menus_clone = [
+            (quality_menu.clone(), element_classes.quality_menu()),
+            (captions_menu.clone(), element_classes.caption_menu()),
+        ];
         let element_classes_clone = element_classes.clone();
-        
-        let closure = Closure::wrap(Box::new(move |event: Event| {
+
+        listeners.add(&document, EVENT_CLICK, move |event: Event| {
             if let Some(target) = event.target() {
                 if let Ok(target_element) = target.dyn_into::<web_sys::Element>() {
-                    if !context_menu_clone.contains(Some(&target_element)) && 
-                       !playback_speed_menu_clone.contains(Some(&target_element)) {
+                    let in_optional_menu = optional_menus_clone.iter()
+                        .any(|(menu, _)| menu.as_ref().is_some_and(|menu| menu.contains(Some(&target_element))));
+                    if !context_menu_clone.contains(Some(&target_element)) &&
+                       !playback_speed_menu_clone.contains(Some(&target_element)) &&
+                       !in_optional_menu {
                         context_menu_clone.set_attribute("class", &element_classes_clone.context_menu()).unwrap_or_default();
                         playback_speed_menu_clone.set_attribute("class", &element_classes_clone.playback_speed_menu()).unwrap_or_default();
+                        for (menu, class_name) in &optional_menus_clone {
+                            if let Some(menu) = menu {
+                                menu.set_attribute("class", class_name).unwrap_or_default();
+                            }
+                        }
                     }
                 }
             }
-        }) as Box<dyn FnMut(_)>);
+        })?;
+    }
 
-        document.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+    // Time update, metadata loaded and duration change event listeners
+    for event_name in [EVENT_TIMEUPDATE, EVENT_LOADEDMETADATA, EVENT_DURATIONCHANGE] {
+        listeners.add(&video_player, event_name, bind(context, |context, _| {
+            update_time_display_for(context).unwrap_or_default();
+            render_seek_bar_for(context).unwrap_or_default();
+            render_captions_for(context).unwrap_or_default();
+        }))?;
     }
 
-    // Time update event listener
+    // Playback position is saved every few seconds, and once more as the page goes away
+    listeners.add(&video_player, EVENT_TIMEUPDATE, bind(context, |context, _| {
+        record_position_for(context, false).unwrap_or_default();
+    }))?;
+    // Near the end of a playlist item the next one loads behind it
+    listeners.add(&video_player, EVENT_TIMEUPDATE, bind(context, |context, _| {
+        gapless_time_update_for(context).unwrap_or_default();
+    }))?;
+    listeners.add(&window, EVENT_PAGEHIDE, bind(context, |context, _| {
+        record_position_for(context, true).unwrap_or_default();
+    }))?;
+    listeners.add(&video_player, EVENT_ENDED, bind(context, |context, _| {
+        publish_for(context, PlayerEvent::Ended);
+    }))?;
+    // Resume prompt and click-to-play button click event listener
     {
-        let closure = Closure::wrap(Box::new(move || {
-            let update_time = update_time_display();
-            spawn_local(async move {
-                update_time.await.unwrap_or_default();
-            });
-        }) as Box<dyn FnMut()>);
-        video_player.add_event_listener_with_callback(
-            EVENT_TIMEUPDATE,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        let container = video_player.parent_element().unwrap_or_else(|| video_player.clone().into());
+        listeners.add(&container, EVENT_CLICK, bind(context, |context, event| {
+            if let Some(target) = event.target().and_then(|target| target.dyn_into::<web_sys::Element>().ok()) {
+                resume_prompt_click_for(context, &target).unwrap_or_default();
+                click_to_play_click_for(context, &target).unwrap_or_default();
+            }
+        }))?;
     }
 
-    // Metadata loaded event listener
+    // Error message button click event listener
     {
-        let closure = Closure::wrap(Box::new(move || {
-            let update_time = update_time_display();
-            spawn_local(async move {
-                update_time.await.unwrap_or_default();
-            });
-        }) as Box<dyn FnMut()>);
-        video_player.add_event_listener_with_callback(
-            EVENT_LOADEDMETADATA,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        let error_overlay = context.element(&element_ids.error_message())?;
+        setup_error_overlay_for(context)?;
+        listeners.add(&error_overlay, EVENT_CLICK, bind(context, |context, event| {
+            if let Some(target) = event.target().and_then(|target| target.dyn_into::<web_sys::Element>().ok()) {
+                error_overlay_click_for(context, &target).unwrap_or_default();
+            }
+        }))?;
     }
 
-    // Duration change event listener
-    {
-        let closure = Closure::wrap(Box::new(move || {
-            let update_time = update_time_display();
-            spawn_local(async move {
-                update_time.await.unwrap_or_default();
-            });
-        }) as Box<dyn FnMut()>);
-        video_player.add_event_listener_with_callback(
-            EVENT_DURATIONCHANGE,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+    // Download progress redraws the seek bar's buffered ranges
+    listeners.add(&video_player, EVENT_PROGRESS, bind(context, |context, _| {
+        render_seek_bar_for(context).unwrap_or_default();
+    }))?;
+
+    // The stall watchdog follows playback and data arriving to notice when both stop
+    listeners.add_media(&video_player, EVENT_TIMEUPDATE, bind_media(context, |context| {
+        if let Ok(video_element) = context.video_element() {
+            watchdog_signal_for(context, Signal::TimeUpdate(video_element.current_time()));
+        }
+    }))?;
+    listeners.add_media(&video_player, EVENT_PROGRESS, bind_media(context, |context| {
+        watchdog_signal_for(context, Signal::Progress);
+    }))?;
+    for event_name in [EVENT_WAITING, EVENT_STALLED] {
+        listeners.add_media(&video_player, event_name, bind_media(context, |context| {
+            watchdog_signal_for(context, Signal::Starving);
+        }))?;
     }
 
-    // Play event listener
-    {
-        let closure = Closure::wrap(Box::new(move || {
-            let set_text = set_toggle_play(BUTTON_TEXT_PAUSE);
-            spawn_local(async move {
-                set_text.await.unwrap_or_default();
-            });
-        }) as Box<dyn FnMut()>);
-        video_player.add_event_listener_with_callback(
-            EVENT_PLAY,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+    // Seek bar pointer, hover and keyboard listeners; the seek bar is optional
+    setup_seek_bar_for(context)?;
+    if let Some(seek_bar) = context.seek_bar.borrow().as_ref().map(|seek_bar| seek_bar.root.clone()) {
+        listeners.add(&seek_bar, EVENT_POINTERDOWN, bind(context, |context, event| {
+            if let Ok(event) = event.dyn_into::<PointerEvent>() {
+                seek_bar_pointer_down_for(context, &event).unwrap_or_default();
+            }
+        }))?;
+        listeners.add(&seek_bar, EVENT_POINTERMOVE, bind(context, |context, event| {
+            if let Ok(event) = event.dyn_into::<PointerEvent>() {
+                seek_bar_pointer_move_for(context, &event).unwrap_or_default();
+            }
+        }))?;
+        for event_name in [EVENT_POINTERUP, EVENT_POINTERCANCEL] {
+            listeners.add(&seek_bar, event_name, bind(context, |context, event| {
+                if let Ok(event) = event.dyn_into::<PointerEvent>() {
+                    seek_bar_pointer_up_for(context, &event).unwrap_or_default();
+                }
+            }))?;
+        }
+        listeners.add(&seek_bar, EVENT_POINTERLEAVE, bind(context, |context, _| {
+            seek_bar_pointer_leave_for(context).unwrap_or_default();
+        }))?;
+        listeners.add(&seek_bar, EVENT_KEYDOWN, bind(context, |context, event| {
+            if let Ok(event) = event.dyn_into::<KeyboardEvent>() {
+                seek_bar_key_down_for(context, &event).unwrap_or_default();
+            }
+        }))?;
     }
 
-    // Pause event listener
-    {
-        let closure = Closure::wrap(Box::new(move || {
-            let set_text = set_toggle_play(BUTTON_TEXT_PLAY);
-            spawn_local(async move {
-                set_text.await.unwrap_or_default();
-            });
-        }) as Box<dyn FnMut()>);
-        video_player.add_event_listener_with_callback(
-            EVENT_PAUSE,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+    // Media element events are published on the player's event bus
+    listeners.add(&video_player, EVENT_PLAY, bind(context, |context, _| {
+        if let Ok(video_element) = context.video_element() {
+            publish_for(context, PlayerEvent::Play { current_time: video_element.current_time() });
+        }
+    }))?;
+    // A play request only shows as playing once the browser has actually started
+    listeners.add(&video_player, EVENT_PLAYING, bind(context, |context, _| {
+        set_toggle_play_for(context, BUTTON_TEXT_PAUSE).unwrap_or_default();
+    }))?;
+    listeners.add(&video_player, EVENT_PAUSE, bind(context, |context, _| {
+        if let Ok(video_element) = context.video_element() {
+            publish_for(context, PlayerEvent::Pause { current_time: video_element.current_time() });
+        }
+    }))?;
+    listeners.add(&video_player, EVENT_SEEKED, bind(context, |context, _| {
+        if let Ok(video_element) = context.video_element() {
+            publish_for(context, PlayerEvent::Seeked { current_time: video_element.current_time() });
+        }
+    }))?;
+    listeners.add(&video_player, EVENT_RATECHANGE, bind(context, |context, _| {
+        if let Ok(video_element) = context.video_element() {
+            publish_for(context, PlayerEvent::RateChange { playback_speed: video_element.playback_rate() });
+        }
+    }))?;
+    listeners.add(&video_player, EVENT_VOLUMECHANGE, bind(context, |context, _| {
+        if let Ok(video_element) = context.video_element() {
+            publish_for(context, PlayerEvent::VolumeChange { volume: video_element.volume(), muted: video_element.muted() });
+        }
+    }))?;
+    // The element failed to load or play its media
+    listeners.add(&video_player, EVENT_ERROR, bind(context, |context, _| {
+        if let Some(media_error) = context.video_element().ok().and_then(|video_element| video_element.error()) {
+            report_error_for(context, &VideoError::from_media_error(media_error.code(), &media_error.message())).unwrap_or_default();
+        }
+    }))?;
+    // requestFullscreen() can be refused after it returns, which is reported here
+    listeners.add(&video_player, EVENT_FULLSCREENERROR, bind(context, |context, _| {
+        let error = VideoError::FullscreenDenied("the browser refused to enter fullscreen".to_string());
+        report_error_for(context, &error).unwrap_or_default();
+    }))?;
+    for (event_name, active) in [(EVENT_ENTERPICTUREINPICTURE, true), (EVENT_LEAVEPICTUREINPICTURE, false)] {
+        listeners.add(&video_player, event_name, bind(context, move |context, _| {
+            publish_for(context, PlayerEvent::PipChange { active });
+        }))?;
     }
 
-    // Volume change event listener
-    {
-        let closure = Closure::wrap(Box::new(move || {
-            update_mute_button_text().unwrap_or_default();
-        }) as Box<dyn FnMut()>);
-        video_player.add_event_listener_with_callback(
-            EVENT_VOLUMECHANGE,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+    // Volume slider input, wheel and keyboard listeners; the slider is optional
+    setup_volume_slider_for(context)?;
+    if let Ok(volume_slider) = context.element(&element_ids.volume_slider()) {
+        listeners.add(&volume_slider, EVENT_INPUT, bind(context, |context, _| {
+            volume_slider_input_for(context).unwrap_or_default();
+        }))?;
+        listeners.add(&volume_slider, EVENT_WHEEL, bind(context, |context, event| {
+            if let Ok(event) = event.dyn_into::<WheelEvent>() {
+                volume_slider_wheel_for(context, &event).unwrap_or_default();
+            }
+        }))?;
+        listeners.add(&volume_slider, EVENT_KEYDOWN, bind(context, |context, event| {
+            if let Ok(event) = event.dyn_into::<KeyboardEvent>() {
+                volume_slider_key_down_for(context, &event).unwrap_or_default();
+            }
+        }))?;
     }
 
-    // Fullscreen change event listener
-    {
-        let closure = Closure::wrap(Box::new(move || {
-            update_fullscreen_button_text().unwrap_or_default();
-        }) as Box<dyn FnMut()>);
-        document.add_event_listener_with_callback(
-            EVENT_FULLSCREENCHANGE,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+    // Keyboard shortcut listeners
+    for event_name in [EVENT_POINTERDOWN, EVENT_FOCUSIN] {
+        let container = video_player.parent_element().unwrap_or_else(|| video_player.clone().into());
+        listeners.add(&container, event_name, bind(context, |context, _| {
+            mark_active_player_for(context);
+        }))?;
     }
+    listeners.add(&document, EVENT_KEYDOWN, bind(context, |context, event| {
+        if let Ok(event) = event.dyn_into::<KeyboardEvent>() {
+            keyboard_shortcut_for(context, &event).unwrap_or_default();
+        }
+    }))?;
+
+    // Fullscreen change event listener
+    listeners.add(&document, EVENT_FULLSCREENCHANGE, bind(context, |context, _| {
+        if let Ok(fullscreen) = is_fullscreen_for(context) {
+            publish_for(context, PlayerEvent::FullscreenChange { fullscreen });
+        }
+    }))?;
 
     // Toggle button click event listener
     {
-        let closure = Closure::wrap(Box::new(move || {
-            let play_video = play_video();
+        let toggle_button = get_element_by_id(&element_ids.toggle_button())?;
+
+        listeners.add(&toggle_button, EVENT_CLICK, bind(context, |context, _| {
+            let play_video = toggle_play_for(context.clone());
             spawn_local(async move {
                 play_video.await.unwrap_or_default();
             });
-        }) as Box<dyn FnMut()>);
-        
-        let toggle_button = document
-            .get_element_by_id(&element_ids.toggle_button())
-            .ok_or(VideoError::ElementNotFound(element_ids.toggle_button()))?;
-            
-        toggle_button.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        }))?;
     }
 
     // Mute button click event listener
     {
-        let closure = Closure::wrap(Box::new(move || {
-            toggle_mute().unwrap_or_default();
-        }) as Box<dyn FnMut()>);
-        
-        let mute_button = document
-            .get_element_by_id(&element_ids.mute_button())
-            .ok_or(VideoError::ElementNotFound(element_ids.mute_button()))?;
-            
-        mute_button.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        let mute_button = get_element_by_id(&element_ids.mute_button())?;
+
+        listeners.add(&mute_button, EVENT_CLICK, bind(context, |context, _| {
+            toggle_mute_for(context).unwrap_or_default();
+        }))?;
     }
 
     // Fullscreen button click event listener
     {
-        let closure = Closure::wrap(Box::new(move || {
-            toggle_fullscreen().unwrap_or_default();
-        }) as Box<dyn FnMut()>);
-        
-        let fullscreen_button = document
-            .get_element_by_id(&element_ids.fullscreen_button())
-            .ok_or(VideoError::ElementNotFound(element_ids.fullscreen_button()))?;
-            
-        fullscreen_button.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        let fullscreen_button = get_element_by_id(&element_ids.fullscreen_button())?;
+
+        listeners.add(&fullscreen_button, EVENT_CLICK, bind(context, |context, _| {
+            toggle_fullscreen_for(context).unwrap_or_default();
+        }))?;
     }
 
     // Context menu download button click event listener
     {
-        let closure = Closure::wrap(Box::new(move || {
-            let download = download_video();
+        let download_button = get_element_by_action(&element_ids.context_menu(), MENU_ACTION_DOWNLOAD)?;
+
+        listeners.add(&download_button, EVENT_CLICK, bind(context, |context, _| {
+            let download = download_video_for(context.clone());
             spawn_local(async move {
                 download.await.unwrap_or_default();
             });
-        }) as Box<dyn FnMut()>);
-        
-        let download_button = document
-            .get_element_by_id(&element_ids.context_menu())
-            .ok_or(VideoError::ElementNotFound(element_ids.context_menu()))?
-            .query_selector(&format!(".{}", element_classes.context_menu_item()))
-            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to get download button: {:?}", e)))?
-            .ok_or(VideoError::ElementNotFound(ERROR_DOWNLOAD_BUTTON_NOT_FOUND.to_string()))?;
-            
-        download_button.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        }))?;
     }
 
     // Context menu playback speed button click event listener
     {
-        let closure = Closure::wrap(Box::new(move |event: Event| {
+        let playback_speed_button = get_element_by_action(&element_ids.context_menu(), MENU_ACTION_PLAYBACK_SPEED)?;
+
+        listeners.add(&playback_speed_button, EVENT_CLICK, bind(context, |context, event| {
             if let Some(target) = event.target() {
                 if let Ok(target_element) = target.dyn_into::<web_sys::Element>() {
                     let rect = target_element.get_bounding_client_rect();
-                    let _ = position_playback_speed_menu(rect.right(), rect.bottom());
+                    let _ = position_playback_speed_menu_for(context, rect.right(), rect.bottom());
                 }
             }
-        }) as Box<dyn FnMut(Event)>);
-        
-        let playback_speed_button = document
-            .get_element_by_id(&element_ids.context_menu())
-            .ok_or(VideoError::ElementNotFound(element_ids.context_menu()))?
-            .query_selector_all(&format!(".{}", element_classes.context_menu_item()))
-            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to get playback speed button: {:?}", e)))?
-            .get(1)
-            .ok_or(VideoError::ElementNotFound(ERROR_PLAYBACK_SPEED_BUTTON_NOT_FOUND.to_string()))?;
-            
-        playback_speed_button.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        }))?;
     }
 
     // Context menu picture-in-picture button click event listener
     {
-        let closure = Closure::wrap(Box::new(move || {
-            toggle_picture_in_picture().unwrap_or_default();
-        }) as Box<dyn FnMut()>);
-        
-        let pip_button = document
-            .get_element_by_id(&element_ids.context_menu())
-            .ok_or(VideoError::ElementNotFound(element_ids.context_menu()))?
-            .query_selector_all(&format!(".{}", element_classes.context_menu_item()))
-            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to get pip button: {:?}", e)))?
-            .get(2)
-            .ok_or(VideoError::ElementNotFound(ERROR_PIP_BUTTON_NOT_FOUND.to_string()))?;
-            
-        pip_button.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        let pip_button = get_element_by_action(&element_ids.context_menu(), MENU_ACTION_PIP)?;
+
+        listeners.add(&pip_button, EVENT_CLICK, bind(context, |context, _| {
+            toggle_picture_in_picture_for(context).unwrap_or_default();
+        }))?;
+    }
+
+    // Context menu quality button and quality option click event listeners
+    if let Some(quality_menu) = &quality_menu {
+        if let Ok(quality_button) = get_element_by_action(&element_ids.context_menu(), MENU_ACTION_QUALITY) {
+            listeners.add(&quality_button, EVENT_CLICK, bind(context, |context, event| {
+                if let Some(target) = event.target() {
+                    if let Ok(target_element) = target.dyn_into::<web_sys::Element>() {
+                        let rect = target_element.get_bounding_client_rect();
+                        let _ = position_quality_menu_for(context, rect.right(), rect.bottom());
+                    }
+                }
+            }))?;
+        }
+
+        listeners.add(quality_menu, EVENT_CLICK, bind(context, |context, event| {
+            let quality = event.target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .and_then(|target_element| quality_of(&target_element));
+            if let Some(quality) = quality {
+                set_quality_for(context, quality).unwrap_or_default();
+            }
+        }))?;
+    }
+
+    // Context menu captions button and caption option click event listeners
+    if let Some(captions_menu) = &captions_menu {
+        if let Ok(captions_button) = get_element_by_action(&element_ids.context_menu(), MENU_ACTION_CAPTIONS) {
+            listeners.add(&captions_button, EVENT_CLICK, bind(context, |context, event| {
+                if let Some(target) = event.target() {
+                    if let Ok(target_element) = target.dyn_into::<web_sys::Element>() {
+                        let rect = target_element.get_bounding_client_rect();
+                        let _ = position_captions_menu_for(context, rect.right(), rect.bottom());
+                    }
+                }
+            }))?;
+        }
+
+        listeners.add(captions_menu, EVENT_CLICK, bind(context, |context, event| {
+            let track = event.target()
+                .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                .and_then(|target_element| caption_of(&target_element));
+            if let Some(track) = track {
+                set_caption_track_for(context, track).unwrap_or_default();
+            }
+        }))?;
     }
 
     // Speed options click event listeners
     {
         let speed_options = playback_speed_menu.query_selector_all(&format!(".{}", element_classes.speed_option()))
             .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to get speed options: {:?}", e)))?;
-        
+
         for i in 0..speed_options.length() {
             let option = speed_options.get(i)
                 .ok_or_else(|| VideoError::ElementNotFound(ERROR_SPEED_OPTION_NOT_FOUND.to_string()))?
                 .dyn_into::<web_sys::Element>()
                 .map_err(|e| VideoError::VideoOperationFailed(format!("{}: {:?}", ERROR_NODE_TO_ELEMENT_CONVERSION, e)))?;
-            
+
             let text_content = option.text_content()
                 .ok_or_else(|| VideoError::VideoOperationFailed(ERROR_NO_TEXT_CONTENT.to_string()))?;
-            
+
             let speed = text_content.replace('x', "").parse::<f64>()
                 .map_err(|e| VideoError::VideoOperationFailed(format!("{}: {:?}", ERROR_SPEED_PARSE, e)))?;
-            
-            let closure = Closure::wrap(Box::new(move || {
-                set_playback_speed(speed).unwrap_or_default();
-            }) as Box<dyn FnMut()>);
-            
-            option.add_event_listener_with_callback(
-                EVENT_CLICK,
-                closure.as_ref().unchecked_ref(),
-            )?;
-            closure.forget();
+
+            listeners.add(&option, EVENT_CLICK, bind(context, move |context, _| {
+                set_playback_speed_for(context, speed).unwrap_or_default();
+            }))?;
         }
     }
 
     // Menu button click event listener
     {
-        let closure = Closure::wrap(Box::new(move |event: Event| {
-            if let Some(mouse_event) = event.dyn_into::<web_sys::MouseEvent>().ok() {
+        let menu_button = get_element_by_id(&element_ids.menu_button())?;
+
+        listeners.add(&menu_button, EVENT_CLICK, bind(context, |context, event| {
+            if let Ok(mouse_event) = event.dyn_into::<web_sys::MouseEvent>() {
                 // Stop event propagation
                 mouse_event.stop_propagation();
                 // Show context menu at button position
-                let _ = position_context_menu(mouse_event.client_x() as f64, mouse_event.client_y() as f64);
+                let _ = position_context_menu_for(context, mouse_event.client_x() as f64, mouse_event.client_y() as f64);
             }
-        }) as Box<dyn FnMut(Event)>);
-        
-        let menu_button = document
-            .get_element_by_id(&element_ids.menu_button())
-            .ok_or(VideoError::ElementNotFound(element_ids.menu_button()))?;
-            
-        menu_button.add_event_listener_with_callback(
-            EVENT_CLICK,
-            closure.as_ref().unchecked_ref(),
-        )?;
-        closure.forget();
+        }))?;
     }
 
+    // Draws every control from the state, whatever element the listeners were bound to
+    render_state_for(context, None, &context.state()?);
     Ok(())
-} 
\ No newline at end of file
+}
@@ -0,0 +1,261 @@
+use std::rc::{Rc, Weak};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use js_sys::Function;
+use crate::logger::Logger;
+use crate::player::context::PlayerContext;
+use crate::player::error::{Severity, VideoError};
+use crate::player::watchdog::RecoveryStep;
+
+/// Names `Player.on` accepts, one per `PlayerEvent` variant.
+pub const EVENT_NAMES: [&str; 12] = [
+    "play",
+    "pause",
+    "seeked",
+    "ratechange",
+    "volumechange",
+    "qualitychange",
+    "error",
+    "ended",
+    "fullscreenchange",
+    "pipchange",
+    "captionchange",
+    "recovery",
+];
+
+#[wasm_bindgen(typescript_custom_section)]
+const PLAYER_EVENT_TYPES: &'static str = r#"
+export interface PlayerEventMap {
+    play: { type: "play"; current_time: number };
+    pause: { type: "pause"; current_time: number };
+    seeked: { type: "seeked"; current_time: number };
+    ratechange: { type: "ratechange"; playback_speed: number };
+    volumechange: { type: "volumechange"; volume: number; muted: boolean };
+    qualitychange: { type: "qualitychange"; quality?: number; automatic: boolean; bandwidth?: number; height?: number };
+    error: { type: "error"; code: VideoErrorCode; message: string; severity: "info" | "warning" | "fatal" };
+    ended: { type: "ended" };
+    fullscreenchange: { type: "fullscreenchange"; fullscreen: boolean };
+    pipchange: { type: "pipchange"; active: boolean };
+    captionchange: { type: "captionchange"; track?: number; language?: string };
+    recovery: { type: "recovery"; step: RecoveryStep };
+}
+
+export type RecoveryStep =
+    | { kind: "stalled"; attempt: number; delay: number }
+    | { kind: "retry"; attempt: number; position: number }
+    | { kind: "failover"; source: number; position: number }
+    | { kind: "recovered" }
+    | { kind: "failed" };
+"#;
+
+/// Something a player did, as handed to `Player.on` callbacks. The variant name,
+/// lowercased, is both the event name and the payload's `type`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PlayerEvent {
+    Play { current_time: f64 },
+    Pause { current_time: f64 },
+    Seeked { current_time: f64 },
+    RateChange { playback_speed: f64 },
+    VolumeChange { volume: f64, muted: bool },
+    /// `quality` is the rendition playing or picked; `automatic` when ABR chose it.
+    QualityChange { quality: Option<usize>, automatic: bool, bandwidth: Option<u64>, height: Option<u32> },
+    /// `code` is `VideoError::code`, or `custom` for a message the page showed.
+    Error { code: String, message: String, severity: Severity },
+    Ended,
+    FullscreenChange { fullscreen: bool },
+    PipChange { active: bool },
+    /// `track` is `None` when captions were turned off.
+    CaptionChange { track: Option<usize>, language: Option<String> },
+    /// What the stall watchdog did about playback that stopped moving.
+    Recovery { step: RecoveryStep },
+}
+
+impl PlayerEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlayerEvent::Play { .. } => "play",
+            PlayerEvent::Pause { .. } => "pause",
+            PlayerEvent::Seeked { .. } => "seeked",
+            PlayerEvent::RateChange { .. } => "ratechange",
+            PlayerEvent::VolumeChange { .. } => "volumechange",
+            PlayerEvent::QualityChange { .. } => "qualitychange",
+            PlayerEvent::Error { .. } => "error",
+            PlayerEvent::Ended => "ended",
+            PlayerEvent::FullscreenChange { .. } => "fullscreenchange",
+            PlayerEvent::PipChange { .. } => "pipchange",
+            PlayerEvent::CaptionChange { .. } => "captionchange",
+            PlayerEvent::Recovery { .. } => "recovery",
+        }
+    }
+}
+
+/// The static name for `name`, or an error naming the events there are.
+pub fn event_name(name: &str) -> Result<&'static str, VideoError> {
+    EVENT_NAMES
+        .iter()
+        .find(|known| **known == name)
+        .copied()
+        .ok_or_else(|| VideoError::InvalidConfig(format!("unknown event {:?}, expected one of {}", name, EVENT_NAMES.join(", "))))
+}
+
+/// A handler inside the player, such as the one keeping the play button's text current.
+pub type EventHandler = Rc<dyn Fn(&PlayerEvent)>;
+
+#[derive(Clone)]
+enum Handler {
+    Host(Function),
+    Player(EventHandler),
+}
+
+struct Subscription {
+    event: &'static str,
+    handler: Handler,
+}
+
+/// Who is listening to one player's events: the host page through `Player.on`,
+/// and the player's own controls.
+#[derive(Default)]
+pub struct EventBus {
+    subscriptions: Vec<Subscription>,
+}
+
+impl EventBus {
+    /// Adds a page callback; adding the same callback twice for one event has no effect.
+    pub fn on(&mut self, event: &str, callback: Function) -> Result<(), VideoError> {
+        let event = event_name(event)?;
+        let exists = self.subscriptions.iter().any(|subscription| {
+            subscription.event == event && matches!(&subscription.handler, Handler::Host(existing) if *existing == callback)
+        });
+        if !exists {
+            self.subscriptions.push(Subscription { event, handler: Handler::Host(callback) });
+        }
+        Ok(())
+    }
+
+    /// Removes a page callback; `false` if it was not subscribed to `event`.
+    pub fn off(&mut self, event: &str, callback: &Function) -> bool {
+        let count = self.subscriptions.len();
+        self.subscriptions.retain(|subscription| {
+            subscription.event != event || !matches!(&subscription.handler, Handler::Host(existing) if existing == callback)
+        });
+        self.subscriptions.len() != count
+    }
+
+    pub fn subscribe(&mut self, event: &'static str, handler: EventHandler) {
+        self.subscriptions.push(Subscription { event, handler: Handler::Player(handler) });
+    }
+
+    /// Drops the player's own handlers, which are subscribed again with its listeners.
+    pub fn clear_player_subscriptions(&mut self) {
+        self.subscriptions.retain(|subscription| matches!(subscription.handler, Handler::Host(_)));
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    // Copied out so handlers can subscribe and unsubscribe while the event is delivered
+    fn handlers(&self, event: &str) -> Vec<Handler> {
+        self.subscriptions
+            .iter()
+            .filter(|subscription| subscription.event == event)
+            .map(|subscription| subscription.handler.clone())
+            .collect()
+    }
+}
+
+/// Calls every handler in order. Page callbacks get the event serialized once; one
+/// that throws is logged and the rest still run.
+fn dispatch(handlers: Vec<Handler>, event: &PlayerEvent) {
+    let mut payload = None;
+    for handler in handlers {
+        match handler {
+            Handler::Player(handler) => handler(event),
+            Handler::Host(callback) => {
+                let payload = payload.get_or_insert_with(|| serde_wasm_bindgen::to_value(event).unwrap_or(JsValue::NULL));
+                if let Err(e) = callback.call1(&JsValue::NULL, payload) {
+                    Logger::error(&format!("{} handler failed: {:?}", event.name(), e)).unwrap_or_default();
+                }
+            }
+        }
+    }
+}
+
+/// Delivers `event` to everyone subscribed to it on this player.
+pub fn publish_for(context: &PlayerContext, event: PlayerEvent) {
+    let Ok(events) = context.events.try_borrow() else {
+        return;
+    };
+    let handlers = events.handlers(event.name());
+    drop(events);
+    dispatch(handlers, &event);
+}
+
+/// `event_listeners::bind` for handlers on the player's own event bus.
+pub fn subscriber<F>(context: &Rc<PlayerContext>, handler: F) -> EventHandler
+where
+    F: Fn(&Rc<PlayerContext>, &PlayerEvent) + 'static,
+{
+    let context: Weak<PlayerContext> = Rc::downgrade(context);
+    Rc::new(move |event: &PlayerEvent| {
+        if let Some(context) = context.upgrade() {
+            handler(&context, event);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_event_names_and_payloads() {
+        let events = [
+            PlayerEvent::Play { current_time: 1.0 },
+            PlayerEvent::Pause { current_time: 1.0 },
+            PlayerEvent::Seeked { current_time: 1.0 },
+            PlayerEvent::RateChange { playback_speed: 1.5 },
+            PlayerEvent::VolumeChange { volume: 0.5, muted: false },
+            PlayerEvent::QualityChange { quality: Some(2), automatic: true, bandwidth: Some(3_000_000), height: Some(720) },
+            PlayerEvent::Error { code: "network_error".to_string(), message: "Network error".to_string(), severity: Severity::Fatal },
+            PlayerEvent::Ended,
+            PlayerEvent::FullscreenChange { fullscreen: true },
+            PlayerEvent::PipChange { active: false },
+            PlayerEvent::CaptionChange { track: None, language: None },
+            PlayerEvent::Recovery { step: RecoveryStep::Stalled { attempt: 2, delay: 2.0 } },
+        ];
+        // Each payload's type is the name it is published under
+        for (event, name) in events.iter().zip(EVENT_NAMES) {
+            assert_eq!(event.name(), name);
+            assert_eq!(serde_json::to_value(event).unwrap()["type"], name);
+        }
+        assert_eq!(
+            serde_json::to_value(&events[3]).unwrap(),
+            serde_json::json!({ "type": "ratechange", "playback_speed": 1.5 })
+        );
+        assert_eq!(
+            serde_json::to_value(&events[11]).unwrap(),
+            serde_json::json!({ "type": "recovery", "step": { "kind": "stalled", "attempt": 2, "delay": 2.0 } })
+        );
+        assert_eq!(event_name("volumechange").unwrap(), "volumechange");
+        assert!(matches!(event_name("timeupdate"), Err(VideoError::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_handlers_run_in_order_for_their_event() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = EventBus::default();
+        for (label, event) in [("first", "play"), ("second", "play"), ("other", "pause")] {
+            let received = received.clone();
+            bus.subscribe(event, Rc::new(move |event: &PlayerEvent| received.borrow_mut().push((label, event.name()))));
+        }
+        dispatch(bus.handlers("play"), &PlayerEvent::Play { current_time: 0.0 });
+        assert_eq!(*received.borrow(), [("first", "play"), ("second", "play")]);
+
+        bus.clear_player_subscriptions();
+        assert!(bus.is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, RequestMode, Response};
+use js_sys::{ArrayBuffer, Promise};
+use crate::player::error::VideoError;
+use crate::player::segments::ByteRange;
+
+async fn fetch_response(url: &str, byte_range: Option<ByteRange>) -> Result<Response, VideoError> {
+    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
+
+    let init = RequestInit::new();
+    init.set_method("GET");
+    init.set_mode(RequestMode::Cors);
+
+    let request = Request::new_with_str_and_init(url, &init)
+        .map_err(|e| VideoError::NetworkError(format!("Failed to create request for {}: {:?}", url, e)))?;
+    if let Some(range) = byte_range {
+        request
+            .headers()
+            .set("Range", &range.header_value())
+            .map_err(|e| VideoError::NetworkError(format!("Failed to set Range header: {:?}", e)))?;
+    }
+
+    let response: Response = JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(|e| VideoError::NetworkError(format!("Failed to fetch {}: {:?}", url, e)))?
+        .dyn_into()
+        .map_err(|e| VideoError::NetworkError(format!("Failed to convert response: {:?}", e)))?;
+
+    if !response.ok() {
+        return Err(VideoError::NetworkError(format!("{} returned HTTP {}", url, response.status())));
+    }
+    Ok(response)
+}
+
+pub async fn fetch_text(url: &str) -> Result<String, VideoError> {
+    let response = fetch_response(url, None).await?;
+    let text = response
+        .text()
+        .map_err(|e| VideoError::NetworkError(format!("Failed to read {}: {:?}", url, e)))?;
+    JsFuture::from(text)
+        .await
+        .map_err(|e| VideoError::NetworkError(format!("Failed to read {}: {:?}", url, e)))?
+        .as_string()
+        .ok_or_else(|| VideoError::NetworkError(format!("{} did not return text", url)))
+}
+
+pub async fn fetch_bytes(url: &str, byte_range: Option<ByteRange>) -> Result<ArrayBuffer, VideoError> {
+    let response = fetch_response(url, byte_range).await?;
+    let buffer = response
+        .array_buffer()
+        .map_err(|e| VideoError::NetworkError(format!("Failed to read {}: {:?}", url, e)))?;
+    JsFuture::from(buffer)
+        .await
+        .map_err(|e| VideoError::NetworkError(format!("Failed to read {}: {:?}", url, e)))?
+        .dyn_into()
+        .map_err(|e| VideoError::NetworkError(format!("{} did not return binary data: {:?}", url, e)))
+}
+
+/// Resolves after `milliseconds` via `setTimeout`.
+pub async fn sleep_ms(milliseconds: i32) -> Result<(), VideoError> {
+    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
+    let mut result = Ok(());
+    let promise = Promise::new(&mut |resolve, _reject| {
+        result = window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, milliseconds)
+            .map(|_| ());
+    });
+    result.map_err(|e| VideoError::VideoOperationFailed(format!("Failed to set timeout: {:?}", e)))?;
+    JsFuture::from(promise)
+        .await
+        .map(|_| ())
+        .map_err(|e| VideoError::VideoOperationFailed(format!("Timer failed: {:?}", e)))
+}
@@ -1,98 +1,115 @@
 use wasm_bindgen::prelude::*;
 use crate::logger::Logger;
-use crate::player::{get_video_element, get_element_by_id};
-use crate::player::error::{show_error, hide_error, VideoError};
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, resolve_errors_for, Operation, VideoError};
 
 #[wasm_bindgen]
 pub fn toggle_fullscreen() -> Result<bool, JsValue> {
-    Logger::info("Entering toggle_fullscreen()").map_err(|e| {
+    toggle_fullscreen_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn is_fullscreen() -> Result<bool, JsValue> {
+    is_fullscreen_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn set_fullscreen_button_text() -> Result<(), JsValue> {
+    set_fullscreen_button_text_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn update_fullscreen_button_text() -> Result<(), JsValue> {
+    update_fullscreen_button_text_for(&default_context())
+}
+
+pub fn toggle_fullscreen_for(context: &PlayerContext) -> Result<bool, JsValue> {
+    Logger::trace("Entering toggle_fullscreen()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let window = web_sys::window().ok_or_else(|| {
         let error = VideoError::WindowNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let document = window.document().ok_or_else(|| {
         let error = VideoError::DocumentNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let video_element = get_video_element()?;
-    
+    let video_element = context.video_element()?;
+
     if document.fullscreen_element().is_some() {
-        Logger::info("Exiting fullscreen mode")
+        Logger::debug("Exiting fullscreen mode")
             .map_err(|e| {
                 let error = VideoError::VideoOperationFailed(e.to_string());
-                show_error(&error.to_string()).unwrap_or_default();
+                report_error_for(context, &error).unwrap_or_default();
                 error
             })?;
         document.exit_fullscreen();
-        hide_error()?;
         Ok(false)
     } else {
-        Logger::info("Entering fullscreen mode")
+        Logger::trace("Entering fullscreen mode")
             .map_err(|e| {
                 let error = VideoError::VideoOperationFailed(e.to_string());
-                show_error(&error.to_string()).unwrap_or_default();
+                report_error_for(context, &error).unwrap_or_default();
                 error
             })?;
         video_element.request_fullscreen().map_err(|e| {
-            let error = VideoError::VideoOperationFailed(format!("Failed to enter fullscreen: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            let error = VideoError::from_js(Operation::Fullscreen, &e);
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
-        hide_error()?;
+        resolve_errors_for(context, &["fullscreen_denied"])?;
         Ok(true)
     }
 }
 
-#[wasm_bindgen]
-pub fn is_fullscreen() -> Result<bool, JsValue> {
-    Logger::info("Entering is_fullscreen()").map_err(|e| {
+/// True only when this player's video element is the one in fullscreen.
+pub fn is_fullscreen_for(context: &PlayerContext) -> Result<bool, JsValue> {
+    Logger::trace("Entering is_fullscreen()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let window = web_sys::window().ok_or_else(|| {
         let error = VideoError::WindowNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let document = window.document().ok_or_else(|| {
         let error = VideoError::DocumentNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    Ok(document.fullscreen_element().is_some())
+    let video_element = context.video_element()?;
+    Ok(document
+        .fullscreen_element()
+        .is_some_and(|element| element == ***video_element))
 }
 
-#[wasm_bindgen]
-pub fn set_fullscreen_button_text() -> Result<(), JsValue> {
-    Logger::info("Entering set_fullscreen_button_text()").map_err(|e| {
+pub fn set_fullscreen_button_text_for(context: &PlayerContext) -> Result<(), JsValue> {
+    Logger::trace("Entering set_fullscreen_button_text()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let is_fullscreen = toggle_fullscreen()?;
-    let button = get_element_by_id("fullscreenButton")?;
+    let is_fullscreen = toggle_fullscreen_for(context)?;
+    let button = context.element(&context.ids.fullscreen_button)?;
     button.set_text_content(Some(if is_fullscreen { "Exit Fullscreen" } else { "Fullscreen" }));
-    hide_error()?;
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn update_fullscreen_button_text() -> Result<(), JsValue> {
-    Logger::info("Entering update_fullscreen_button_text()").map_err(|e| {
+pub fn update_fullscreen_button_text_for(context: &PlayerContext) -> Result<(), JsValue> {
+    Logger::trace("Entering update_fullscreen_button_text()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let is_fullscreen = is_fullscreen()?;
-    let button = get_element_by_id("fullscreenButton")?;
+    let is_fullscreen = is_fullscreen_for(context)?;
+    let button = context.element(&context.ids.fullscreen_button)?;
     button.set_text_content(Some(if is_fullscreen { "Exit Fullscreen" } else { "Fullscreen" }));
-    hide_error()?;
     Ok(())
-} 
\ No newline at end of file
+}
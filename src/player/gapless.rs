@@ -0,0 +1,297 @@
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, HtmlVideoElement};
+use crate::logger::Logger;
+use crate::player::captions::{apply_caption_preference_for, load_caption_tracks};
+use crate::player::context::PlayerContext;
+use crate::player::dash::is_dash_source;
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::event_listeners::setup_event_listeners_for;
+use crate::player::fetch::sleep_ms;
+use crate::player::hls::is_hls_source;
+use crate::player::playlist::{dispatch_playlist_change, PlaylistItem};
+use crate::player::play_pause::start_video_for;
+use crate::player::quality::{apply_quality_preference_for, QualitySource};
+use crate::player::resume::{record_position_for, restore_position_for};
+use crate::player::watchdog::watch_media_for;
+use crate::player::dom::add_video_source;
+use crate::player::loader::forget_media_for;
+
+// Seconds before the end of an item at which the next one starts loading
+const PRELOAD_LEAD: f64 = 15.0;
+// HTMLMediaElement.HAVE_FUTURE_DATA: enough is buffered to start playing
+const HAVE_FUTURE_DATA: u16 = 3;
+// Volume steps the outgoing video fades over
+const CROSSFADE_STEPS: u32 = 20;
+
+/// The next playlist item, loading in a hidden video element so it can take over
+/// without a gap when the current one ends.
+struct Preloaded {
+    index: usize,
+    item: PlaylistItem,
+    element: HtmlVideoElement,
+}
+
+/// Gapless playback settings and the item preloaded for one player.
+#[derive(Default)]
+pub struct GaplessState {
+    pub enabled: bool,
+    /// Seconds the outgoing and incoming videos overlap, 0 for a straight cut.
+    pub crossfade: f64,
+    preloaded: Option<Preloaded>,
+}
+
+/// Whether an item can load in a second element. Streams go through MSE on the
+/// visible element, so they are loaded the ordinary way when their turn comes.
+pub fn is_preloadable(item: &PlaylistItem) -> bool {
+    !item.sources.is_empty()
+        && !item.sources.iter().any(|source| is_hls_source(&source.url, &source.mime_type) || is_dash_source(&source.url, &source.mime_type))
+}
+
+/// Whether the next item should start loading, `remaining` seconds before the end.
+pub fn should_preload(remaining: f64, crossfade: f64) -> bool {
+    remaining.is_finite() && remaining <= PRELOAD_LEAD + crossfade
+}
+
+/// Whether a crossfade of `crossfade` seconds should start, `remaining` seconds before the end.
+pub fn crossfade_due(remaining: f64, crossfade: f64) -> bool {
+    crossfade > 0.0 && remaining.is_finite() && remaining <= crossfade
+}
+
+fn remaining_time(video_element: &HtmlVideoElement) -> f64 {
+    video_element.duration() - video_element.current_time()
+}
+
+/// The item that plays after the current one, unless it is the current one again.
+fn upcoming_item(context: &PlayerContext) -> Option<(usize, PlaylistItem)> {
+    let playlist = context.playlist.borrow();
+    let index = playlist.ended_index().filter(|index| Some(*index) != playlist.current())?;
+    Some((index, playlist.get(index)?.clone()))
+}
+
+/// Called on `timeupdate`: starts loading the next item near the end of the current
+/// one, and starts the crossfade once it is due and the next item can play.
+pub fn gapless_time_update_for(context: &Rc<PlayerContext>) -> Result<(), JsValue> {
+    let (enabled, crossfade) = {
+        let gapless = context.gapless.borrow();
+        (gapless.enabled, gapless.crossfade)
+    };
+    let video_element = context.video_element()?;
+    if !enabled || video_element.loop_() {
+        return Ok(());
+    }
+    let remaining = remaining_time(&video_element);
+    if !should_preload(remaining, crossfade) {
+        return Ok(());
+    }
+    let Some((index, item)) = upcoming_item(context) else {
+        discard_preloaded_for(context);
+        return Ok(());
+    };
+    preload_for(context, &video_element, index, &item)?;
+
+    let ready = context.gapless.borrow().preloaded.as_ref()
+        .is_some_and(|preloaded| preloaded.element.ready_state() >= HAVE_FUTURE_DATA);
+    if ready && crossfade_due(remaining, crossfade) {
+        swap_to_preloaded_for(context, index)?;
+    }
+    Ok(())
+}
+
+/// Loads `item` into a hidden copy of the video element, replacing anything
+/// preloaded for an item that is no longer next.
+fn preload_for(context: &PlayerContext, video_element: &HtmlVideoElement, index: usize, item: &PlaylistItem) -> Result<(), JsValue> {
+    let current = context.gapless.borrow().preloaded.as_ref()
+        .is_some_and(|preloaded| preloaded.index == index && preloaded.item == *item);
+    if current {
+        return Ok(());
+    }
+    discard_preloaded_for(context);
+    if !is_preloadable(item) {
+        return Ok(());
+    }
+
+    Logger::debug(&format!("Preloading playlist item {}", index)).map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    // A shallow copy keeps the page's classes and attributes, but none of the children
+    let element = video_element.clone_node()?.dyn_into::<HtmlVideoElement>()?;
+    element.remove_attribute("id")?;
+    element.remove_attribute("src")?;
+    element.set_hidden(true);
+    element.set_autoplay(false);
+    element.set_preload("auto");
+    match &item.poster {
+        Some(poster) => element.set_poster(poster),
+        None => element.remove_attribute("poster")?,
+    }
+    for source in &item.sources {
+        let source_element = add_video_source(&element, &source.url, &source.mime_type, source.label.as_deref())?;
+        context.track_element(source_element.into());
+    }
+    video_element.after_with_node_1(&element)?;
+    context.track_element(element.clone().into());
+    element.load();
+    context.gapless.borrow_mut().preloaded = Some(Preloaded { index, item: item.clone(), element });
+    Ok(())
+}
+
+/// Removes the hidden element of a preloaded item, if there is one.
+pub fn discard_preloaded_for(context: &PlayerContext) {
+    let preloaded = context.gapless.borrow_mut().preloaded.take();
+    if let Some(preloaded) = preloaded {
+        preloaded.element.pause().unwrap_or_default();
+        untrack_sources(context, &preloaded.element);
+        preloaded.element.remove();
+        context.untrack_element(&preloaded.element);
+    }
+}
+
+fn untrack_sources(context: &PlayerContext, video_element: &HtmlVideoElement) {
+    let Ok(sources) = video_element.query_selector_all("source") else {
+        return;
+    };
+    for index in 0..sources.length() {
+        if let Some(source) = sources.get(index).and_then(|node| node.dyn_into::<Element>().ok()) {
+            context.untrack_element(&source);
+        }
+    }
+}
+
+/// Makes the preloaded element the player's video element and starts it, if item
+/// `index` is what was preloaded. Returns `Ok(false)` when the item has to be loaded
+/// the ordinary way. The player's listeners are moved over to the new element.
+pub fn swap_to_preloaded_for(context: &Rc<PlayerContext>, index: usize) -> Result<bool, JsValue> {
+    let preloaded = context.gapless.borrow_mut().preloaded.take();
+    let Some(preloaded) = preloaded else {
+        return Ok(false);
+    };
+    let still_next = context.playlist.borrow().get(index) == Some(&preloaded.item);
+    if preloaded.index != index || !still_next {
+        context.gapless.borrow_mut().preloaded = Some(preloaded);
+        discard_preloaded_for(context);
+        return Ok(false);
+    }
+
+    Logger::debug(&format!("Switching gaplessly to playlist item {}", index)).map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let Preloaded { item, element, .. } = preloaded;
+    let previous = context.video_element()?;
+    record_position_for(context, true)?;
+    context.playlist.borrow_mut().set_current(index);
+    forget_media_for(context)?;
+
+    element.set_volume(previous.volume());
+    element.set_muted(previous.muted());
+    element.set_default_playback_rate(previous.default_playback_rate());
+    element.set_playback_rate(previous.playback_rate());
+    context.untrack_element(&element);
+    untrack_sources(context, &previous);
+    previous.remove_attribute("id")?;
+    element.set_id(&context.ids.video_player);
+    element.set_hidden(false);
+
+    let crossfade = context.gapless.borrow().crossfade;
+    if crossfade > 0.0 {
+        fade_out(context, previous, crossfade)?;
+    } else {
+        retire(context, &previous);
+    }
+    // Every handler is bound to the element it was registered on
+    setup_event_listeners_for(context)?;
+
+    let playable: Vec<_> = item.sources.iter()
+        .filter(|source| !element.can_play_type(&source.mime_type).is_empty())
+        .cloned()
+        .collect();
+    if playable.len() > 1 {
+        context.set_quality_source(QualitySource::Progressive(playable));
+        apply_quality_preference_for(context)?;
+    }
+    watch_media_for(context, &item.sources)?;
+    load_caption_tracks(context, &item.text_tracks);
+    apply_caption_preference_for(context)?;
+    restore_position_for(context, item.resume_id(), 0.0)?;
+    dispatch_playlist_change(context, index, &item)?;
+    start_video_for(context)?;
+    Ok(true)
+}
+
+/// Lays the outgoing video over the incoming one and fades its picture and sound
+/// out over `duration` seconds before removing it.
+fn fade_out(context: &Rc<PlayerContext>, previous: HtmlVideoElement, duration: f64) -> Result<(), JsValue> {
+    let geometry = format!(
+        "position: absolute; top: {}px; left: {}px; width: {}px; height: {}px; pointer-events: none; transition: opacity {}s linear;",
+        previous.offset_top(),
+        previous.offset_left(),
+        previous.offset_width(),
+        previous.offset_height(),
+        duration
+    );
+    previous.set_attribute("style", &format!("{} opacity: 1;", geometry))?;
+    // Reading layout commits the starting opacity, so the change below is animated
+    previous.offset_width();
+    previous.set_attribute("style", &format!("{} opacity: 0;", geometry))?;
+
+    let context = Rc::downgrade(context);
+    wasm_bindgen_futures::spawn_local(async move {
+        let start = previous.volume();
+        let step_ms = (duration * 1000.0 / CROSSFADE_STEPS as f64).round() as i32;
+        for step in 1..=CROSSFADE_STEPS {
+            if sleep_ms(step_ms).await.is_err() {
+                break;
+            }
+            previous.set_volume(start * (1.0 - step as f64 / CROSSFADE_STEPS as f64));
+        }
+        match context.upgrade() {
+            Some(context) => retire(&context, &previous),
+            None => previous.remove(),
+        }
+    });
+    Ok(())
+}
+
+/// Stops and removes an element that is no longer the player's video, releasing
+/// the object URL of a stream it was playing.
+fn retire(context: &PlayerContext, previous: &HtmlVideoElement) {
+    previous.pause().unwrap_or_default();
+    if let Some(src) = previous.get_attribute("src") {
+        if context.object_urls.borrow().contains(&src) {
+            context.revoke_object_url(&src).unwrap_or_default();
+        }
+    }
+    previous.remove();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preload_and_crossfade_timing() {
+        assert!(!should_preload(60.0, 0.0));
+        assert!(should_preload(15.0, 0.0));
+        assert!(should_preload(18.0, 5.0));
+        // Unknown or live durations never preload
+        assert!(!should_preload(f64::NAN, 0.0));
+        assert!(!should_preload(f64::INFINITY, 0.0));
+        assert!(!crossfade_due(1.0, 0.0));
+        assert!(!crossfade_due(3.5, 3.0));
+        assert!(crossfade_due(2.75, 3.0));
+    }
+
+    #[test]
+    fn test_streams_are_not_preloaded() {
+        let mut item = PlaylistItem::new("https://example.com/a.mp4".to_string(), "video/mp4".to_string(), None);
+        assert!(is_preloadable(&item));
+        item.add_source("https://example.com/a.m3u8".to_string(), "application/vnd.apple.mpegurl".to_string(), None);
+        assert!(!is_preloadable(&item));
+        let dash = PlaylistItem::new("https://example.com/a.mpd".to_string(), "application/dash+xml".to_string(), None);
+        assert!(!is_preloadable(&dash));
+    }
+}
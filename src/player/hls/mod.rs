@@ -0,0 +1,425 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::player::abr::{AbrStrategyKind, RenditionInfo};
+use crate::player::context::PlayerContext;
+use crate::player::error::VideoError;
+use crate::player::fetch::fetch_text;
+use crate::player::mse::start_stream;
+use crate::player::segments::{SegmentKind, SegmentProvider, SegmentRequest, MIN_REFRESH_INTERVAL};
+
+pub mod playlist;
+use playlist::{parse_media_playlist, parse_playlist, InitSection, MasterPlaylist, MediaPlaylist, MediaSegment, Playlist};
+
+pub const HLS_MIME_TYPES: [&str; 2] = ["application/vnd.apple.mpegurl", "application/x-mpegurl"];
+
+// Used when a playlist does not advertise CODECS
+const DEFAULT_CODECS: &str = "avc1.42E01E,mp4a.40.2";
+// Live playback starts this many segments from the end of the playlist
+const LIVE_EDGE_SEGMENTS: u64 = 3;
+
+pub fn is_hls_source(url: &str, mime_type: &str) -> bool {
+    let essence = mime_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    HLS_MIME_TYPES.contains(&essence.as_str()) || path.to_ascii_lowercase().ends_with(".m3u8")
+}
+
+/// Fetches `url` and streams it through Media Source Extensions from `start_time` on.
+pub async fn start_hls(context: Rc<PlayerContext>, url: String, abr_strategy: AbrStrategyKind, start_time: f64) -> Result<(), VideoError> {
+    let text = fetch_text(&url).await?;
+    let provider = hls_provider(&url, &text, start_time)?;
+    start_stream(&context, vec![Rc::new(RefCell::new(provider))], abr_strategy, start_time);
+    Ok(())
+}
+
+/// The provider for the playlist at `url`, positioned at `start_time`. Live streams
+/// ignore the position and start near the edge.
+fn hls_provider(url: &str, text: &str, start_time: f64) -> Result<HlsProvider, VideoError> {
+    let mut provider = HlsProvider::new(url, text)?;
+    if start_time > 0.0 {
+        provider.seek(start_time);
+    }
+    Ok(provider)
+}
+
+/// Walks an HLS presentation segment by segment. Alternate audio renditions are not
+/// fetched, so variants are expected to carry muxed audio.
+pub struct HlsProvider {
+    url: String,
+    master: Option<MasterPlaylist>,
+    variant: usize,
+    media: Option<MediaPlaylist>,
+    live: Option<bool>,
+    next_sequence: u64,
+    // Where to resume once the media playlist of a newly selected variant arrives
+    resume_time: Option<f64>,
+    last_init: Option<InitSection>,
+    last_discontinuity: Option<u64>,
+    // End of the last emitted segment on the element timeline, used for live streams
+    timeline: f64,
+    resync: bool,
+    pending: Option<SegmentRequest>,
+}
+
+impl HlsProvider {
+    /// Builds a provider from the first playlist fetched from `url`, master or media.
+    pub fn new(url: &str, text: &str) -> Result<HlsProvider, VideoError> {
+        let mut provider = HlsProvider {
+            url: url.to_string(),
+            master: None,
+            variant: 0,
+            media: None,
+            live: None,
+            next_sequence: 0,
+            resume_time: None,
+            last_init: None,
+            last_discontinuity: None,
+            timeline: 0.0,
+            resync: false,
+            pending: None,
+        };
+        match parse_playlist(text, url)? {
+            Playlist::Master(master) => provider.master = Some(master),
+            Playlist::Media(media) => provider.apply_media_playlist(media)?,
+        }
+        Ok(provider)
+    }
+
+    fn apply_media_playlist(&mut self, media: MediaPlaylist) -> Result<(), VideoError> {
+        if media.segments.iter().any(|segment| segment.encrypted) {
+            return Err(VideoError::UnsupportedSourceType("encrypted HLS (EXT-X-KEY)".to_string()));
+        }
+        let first = media.segments.first().map(|segment| segment.sequence).unwrap_or(media.media_sequence);
+        let last = media.last_sequence().unwrap_or(first);
+
+        match self.live {
+            None => {
+                let live = media.is_live();
+                self.live = Some(live);
+                self.next_sequence = if live {
+                    last.saturating_sub(LIVE_EDGE_SEGMENTS - 1).max(first)
+                } else {
+                    first
+                };
+            }
+            Some(_) if self.next_sequence < first => {
+                // The live window moved past us, continue from its oldest segment
+                self.next_sequence = first;
+                self.resync = true;
+            }
+            Some(_) => {}
+        }
+        if let Some(time) = self.resume_time.take() {
+            if let Some(index) = media.segment_index_at(time) {
+                self.next_sequence = media.segments[index].sequence;
+            }
+        }
+        self.media = Some(media);
+        Ok(())
+    }
+
+    fn is_live(&self) -> bool {
+        self.live.unwrap_or(false)
+    }
+
+    fn media_request(&mut self, segment: &MediaSegment) -> SegmentRequest {
+        let start = if self.is_live() { self.timeline } else { segment.start };
+        self.timeline = start + segment.duration;
+        let discontinuity = std::mem::take(&mut self.resync)
+            || self.last_discontinuity.is_some_and(|last| last != segment.discontinuity_sequence);
+        self.last_discontinuity = Some(segment.discontinuity_sequence);
+        SegmentRequest {
+            url: segment.uri.clone(),
+            byte_range: segment.byte_range,
+            kind: SegmentKind::Media,
+            start,
+            duration: segment.duration,
+            discontinuity,
+        }
+    }
+}
+
+impl SegmentProvider for HlsProvider {
+    fn mime_type(&self) -> String {
+        let fragmented = self
+            .media
+            .as_ref()
+            .and_then(|media| media.segments.first())
+            .is_some_and(|segment| segment.init.is_some());
+        let container = if fragmented { "video/mp4" } else { "video/mp2t" };
+        let codecs = self
+            .master
+            .as_ref()
+            .and_then(|master| master.variants.get(self.variant))
+            .and_then(|variant| variant.codecs.clone())
+            .unwrap_or_else(|| DEFAULT_CODECS.to_string());
+        format!("{}; codecs=\"{}\"", container, codecs)
+    }
+
+    fn manifest_url(&self) -> String {
+        match &self.master {
+            Some(master) => master.variants[self.variant].uri.clone(),
+            None => self.url.clone(),
+        }
+    }
+
+    fn needs_manifest(&self) -> bool {
+        self.media.is_none()
+    }
+
+    fn update_manifest(&mut self, text: &str) -> Result<(), VideoError> {
+        let media = parse_media_playlist(text, &self.manifest_url())?;
+        self.apply_media_playlist(media)
+    }
+
+    fn next_segment(&mut self) -> Option<SegmentRequest> {
+        if let Some(pending) = self.pending.take() {
+            return Some(pending);
+        }
+        let segment = self.media.as_ref()?.segment_by_sequence(self.next_sequence)?.clone();
+        self.next_sequence += 1;
+
+        let request = self.media_request(&segment);
+        if segment.init.is_some() && segment.init != self.last_init {
+            self.last_init = segment.init.clone();
+            let init = segment.init?;
+            self.pending = Some(request.clone());
+            return Some(SegmentRequest {
+                url: init.uri,
+                byte_range: init.byte_range,
+                kind: SegmentKind::Init,
+                start: request.start,
+                duration: 0.0,
+                discontinuity: request.discontinuity,
+            });
+        }
+        Some(request)
+    }
+
+    fn seek(&mut self, time: f64) {
+        if self.is_live() {
+            return;
+        }
+        self.pending = None;
+        self.last_init = None;
+        self.resync = true;
+        match &self.media {
+            Some(media) => {
+                if let Some(index) = media.segment_index_at(time) {
+                    self.next_sequence = media.segments[index].sequence;
+                }
+            }
+            None => self.resume_time = Some(time),
+        }
+    }
+
+    fn duration(&self) -> Option<f64> {
+        match &self.media {
+            Some(media) if !self.is_live() => Some(media.total_duration()),
+            _ => None,
+        }
+    }
+
+    fn refresh_interval(&self) -> Option<f64> {
+        self.media
+            .as_ref()
+            .filter(|media| !media.end_list)
+            .map(|media| media.target_duration.max(MIN_REFRESH_INTERVAL))
+    }
+
+    fn is_complete(&self) -> bool {
+        self.pending.is_none()
+            && self.media.as_ref().is_some_and(|media| {
+                media.end_list && media.last_sequence().is_none_or(|last| self.next_sequence > last)
+            })
+    }
+
+    fn renditions(&self) -> Vec<RenditionInfo> {
+        let Some(master) = &self.master else {
+            return Vec::new();
+        };
+        master
+            .variants
+            .iter()
+            .map(|variant| RenditionInfo {
+                bandwidth: variant.bandwidth,
+                width: variant.resolution.map(|(width, _)| width),
+                height: variant.resolution.map(|(_, height)| height),
+            })
+            .collect()
+    }
+
+    fn rendition(&self) -> usize {
+        self.variant
+    }
+
+    fn select_rendition(&mut self, index: usize) {
+        let variants = self.master.as_ref().map(|master| master.variants.len()).unwrap_or(0);
+        if index == self.variant || index >= variants {
+            return;
+        }
+        self.variant = index;
+        // Live variants share sequence numbers; on-demand ones are matched by time
+        if !self.is_live() {
+            self.resume_time = Some(self.pending.as_ref().map(|pending| pending.start).unwrap_or(self.timeline));
+            self.resync = true;
+        }
+        self.pending = None;
+        self.last_init = None;
+        self.media = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://media.example.com/show/index.m3u8";
+
+    fn drain(provider: &mut HlsProvider) -> Vec<SegmentRequest> {
+        std::iter::from_fn(|| provider.next_segment()).collect()
+    }
+
+    #[test]
+    fn test_is_hls_source() {
+        assert!(is_hls_source("https://a.example.com/master.m3u8?token=1", "video/mp4"));
+        assert!(is_hls_source("https://a.example.com/stream", "application/vnd.apple.mpegurl"));
+        assert!(is_hls_source("https://a.example.com/stream", "Application/X-MpegURL"));
+        assert!(!is_hls_source("https://a.example.com/video.mp4", "video/mp4"));
+    }
+
+    #[test]
+    fn test_master_playlist_needs_media_playlist() {
+        let mut provider = HlsProvider::new(BASE, include_str!("fixtures/master.m3u8")).unwrap();
+
+        assert!(provider.needs_manifest());
+        assert_eq!(provider.manifest_url(), "https://media.example.com/show/360p/index.m3u8");
+        assert!(provider.next_segment().is_none());
+
+        provider.update_manifest(include_str!("fixtures/vod_fmp4.m3u8")).unwrap();
+        assert_eq!(provider.mime_type(), "video/mp4; codecs=\"avc1.4d401e,mp4a.40.2\"");
+        assert_eq!(provider.manifest_url(), "https://media.example.com/show/360p/index.m3u8");
+    }
+
+    #[test]
+    fn test_switching_variant_resumes_at_same_position() {
+        let mut provider = HlsProvider::new(BASE, include_str!("fixtures/master.m3u8")).unwrap();
+        assert_eq!(provider.renditions().len(), 3);
+        assert_eq!(provider.renditions()[1].height, Some(720));
+        provider.update_manifest(include_str!("fixtures/vod_fmp4.m3u8")).unwrap();
+        provider.next_segment();
+        provider.next_segment();
+        provider.next_segment();
+
+        provider.select_rendition(1);
+        assert_eq!(provider.rendition(), 1);
+        assert!(provider.needs_manifest());
+        assert_eq!(provider.manifest_url(), "https://media.example.com/show/720p/index.m3u8");
+        provider.update_manifest(include_str!("fixtures/vod_fmp4.m3u8")).unwrap();
+
+        let requests = drain(&mut provider);
+        assert_eq!(requests[0].kind, SegmentKind::Init);
+        assert_eq!(requests[1].url, "https://media.example.com/show/720p/seg2.m4s");
+        assert_eq!(requests[1].start, 12.0);
+        assert!(requests[1].discontinuity);
+    }
+
+    #[test]
+    fn test_vod_emits_init_then_segments_until_end() {
+        let mut provider = HlsProvider::new(BASE, include_str!("fixtures/vod_fmp4.m3u8")).unwrap();
+        assert_eq!(provider.duration(), Some(22.5));
+        assert_eq!(provider.refresh_interval(), None);
+
+        let requests = drain(&mut provider);
+        let kinds: Vec<SegmentKind> = requests.iter().map(|request| request.kind).collect();
+        assert_eq!(kinds, [SegmentKind::Init, SegmentKind::Media, SegmentKind::Media, SegmentKind::Media, SegmentKind::Media]);
+        assert_eq!(requests[0].url, "https://media.example.com/show/init.mp4");
+        assert_eq!(requests[4].start, 18.0);
+        assert!(provider.is_complete());
+    }
+
+    #[test]
+    fn test_vod_seek_restarts_with_init_segment() {
+        let mut provider = HlsProvider::new(BASE, include_str!("fixtures/vod_fmp4.m3u8")).unwrap();
+        drain(&mut provider);
+
+        provider.seek(13.0);
+        assert!(!provider.is_complete());
+        let requests = drain(&mut provider);
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].kind, SegmentKind::Init);
+        assert_eq!(requests[1].url, "https://media.example.com/show/seg2.m4s");
+        assert!(requests[1].discontinuity);
+        assert!(!requests[2].discontinuity);
+    }
+
+    #[test]
+    fn test_stream_starts_at_handed_position() {
+        let mut provider = hls_provider(BASE, include_str!("fixtures/vod_fmp4.m3u8"), 13.0).unwrap();
+        let requests = drain(&mut provider);
+        assert_eq!(requests[0].kind, SegmentKind::Init);
+        assert_eq!(requests[1].url, "https://media.example.com/show/seg2.m4s");
+        assert_eq!(requests[1].start, 12.0);
+        assert!(requests[1].discontinuity);
+
+        // A master playlist holds the position until its media playlist arrives
+        let mut provider = hls_provider(BASE, include_str!("fixtures/master.m3u8"), 13.0).unwrap();
+        provider.update_manifest(include_str!("fixtures/vod_fmp4.m3u8")).unwrap();
+        assert_eq!(drain(&mut provider)[1].url, "https://media.example.com/show/360p/seg2.m4s");
+    }
+
+    #[test]
+    fn test_live_starts_near_edge_and_follows_reloads() {
+        let mut provider = HlsProvider::new(BASE, include_str!("fixtures/live_1.m3u8")).unwrap();
+        assert_eq!(provider.duration(), None);
+        assert_eq!(provider.refresh_interval(), Some(4.0));
+        let text = include_str!("fixtures/live_1.m3u8").replace("#EXT-X-TARGETDURATION:4", "#EXT-X-TARGETDURATION:0");
+        assert_eq!(HlsProvider::new(BASE, &text).unwrap().refresh_interval(), Some(MIN_REFRESH_INTERVAL));
+        assert_eq!(provider.mime_type(), format!("video/mp2t; codecs=\"{}\"", DEFAULT_CODECS));
+
+        let first = drain(&mut provider);
+        let urls: Vec<&str> = first.iter().map(|request| request.url.as_str()).collect();
+        assert_eq!(urls, ["https://media.example.com/show/live102.ts", "https://media.example.com/show/live103.ts", "https://media.example.com/show/live104.ts"]);
+        assert!(!provider.is_complete());
+
+        // The reload slides the window, adds a segment and a discontinuity, then ends the stream
+        provider.update_manifest(include_str!("fixtures/live_2.m3u8")).unwrap();
+        let second = drain(&mut provider);
+        assert_eq!(second.len(), 2);
+        assert_eq!(second[0].url, "https://media.example.com/show/live105.ts");
+        assert_eq!(second[0].start, 12.0);
+        assert!(!second[0].discontinuity);
+        assert_eq!(second[1].url, "https://media.example.com/show/ad0.ts");
+        assert!(second[1].discontinuity);
+        assert_eq!(second[1].start, 16.0);
+        assert!(provider.is_complete());
+        assert_eq!(provider.refresh_interval(), None);
+    }
+
+    #[test]
+    fn test_live_window_overtaking_player_resyncs() {
+        let mut provider = HlsProvider::new(BASE, include_str!("fixtures/live_1.m3u8")).unwrap();
+        provider.next_segment();
+
+        let moved_on = include_str!("fixtures/live_1.m3u8").replace("MEDIA-SEQUENCE:100", "MEDIA-SEQUENCE:200");
+        provider.update_manifest(&moved_on).unwrap();
+        let request = provider.next_segment().unwrap();
+        assert!(request.discontinuity);
+    }
+
+    #[test]
+    fn test_discontinuities_and_byte_ranges() {
+        let mut provider = HlsProvider::new(BASE, include_str!("fixtures/discontinuity_byterange.m3u8")).unwrap();
+        let requests = drain(&mut provider);
+
+        let discontinuities: Vec<bool> = requests.iter().map(|request| request.discontinuity).collect();
+        assert_eq!(discontinuities, [false, false, true, true]);
+        assert_eq!(requests[1].byte_range.unwrap().header_value(), "bytes=1000-2999");
+        assert_eq!(requests[3].start, 28.5);
+    }
+
+    #[test]
+    fn test_encrypted_playlists_are_rejected() {
+        let encrypted = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n#EXTINF:4,\na.ts\n";
+        assert!(HlsProvider::new(BASE, encrypted).is_err());
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use crate::player::error::VideoError;
+use crate::player::segments::ByteRange;
+use crate::player::url::resolve_url;
+
+const TAG_HEADER: &str = "#EXTM3U";
+const TAG_STREAM_INF: &str = "#EXT-X-STREAM-INF:";
+const TAG_MEDIA: &str = "#EXT-X-MEDIA:";
+const TAG_TARGET_DURATION: &str = "#EXT-X-TARGETDURATION:";
+const TAG_MEDIA_SEQUENCE: &str = "#EXT-X-MEDIA-SEQUENCE:";
+const TAG_DISCONTINUITY_SEQUENCE: &str = "#EXT-X-DISCONTINUITY-SEQUENCE:";
+const TAG_PLAYLIST_TYPE: &str = "#EXT-X-PLAYLIST-TYPE:";
+const TAG_INF: &str = "#EXTINF:";
+const TAG_BYTERANGE: &str = "#EXT-X-BYTERANGE:";
+const TAG_MAP: &str = "#EXT-X-MAP:";
+const TAG_KEY: &str = "#EXT-X-KEY:";
+const TAG_DISCONTINUITY: &str = "#EXT-X-DISCONTINUITY";
+const TAG_ENDLIST: &str = "#EXT-X-ENDLIST";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Playlist {
+    Master(MasterPlaylist),
+    Media(MediaPlaylist),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MasterPlaylist {
+    pub variants: Vec<Variant>,
+    pub renditions: Vec<Rendition>,
+}
+
+/// One `#EXT-X-STREAM-INF` entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Variant {
+    pub uri: String,
+    pub bandwidth: u64,
+    pub average_bandwidth: Option<u64>,
+    pub codecs: Option<String>,
+    pub resolution: Option<(u32, u32)>,
+    pub frame_rate: Option<f64>,
+    pub audio: Option<String>,
+    pub subtitles: Option<String>,
+}
+
+/// One `#EXT-X-MEDIA` entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rendition {
+    pub media_type: String,
+    pub group_id: String,
+    pub name: String,
+    pub language: Option<String>,
+    pub uri: Option<String>,
+    pub default: bool,
+    pub autoselect: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistType {
+    Vod,
+    Event,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaPlaylist {
+    pub target_duration: f64,
+    pub media_sequence: u64,
+    pub discontinuity_sequence: u64,
+    pub playlist_type: Option<PlaylistType>,
+    pub end_list: bool,
+    pub segments: Vec<MediaSegment>,
+}
+
+/// The `#EXT-X-MAP` initialization section a segment depends on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitSection {
+    pub uri: String,
+    pub byte_range: Option<ByteRange>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaSegment {
+    pub uri: String,
+    pub duration: f64,
+    pub title: Option<String>,
+    pub sequence: u64,
+    pub discontinuity: bool,
+    pub discontinuity_sequence: u64,
+    pub byte_range: Option<ByteRange>,
+    pub init: Option<InitSection>,
+    pub encrypted: bool,
+    /// Start time relative to the first segment of this playlist.
+    pub start: f64,
+}
+
+impl MediaPlaylist {
+    pub fn is_live(&self) -> bool {
+        !self.end_list
+    }
+
+    pub fn total_duration(&self) -> f64 {
+        self.segments.iter().map(|segment| segment.duration).sum()
+    }
+
+    /// Index of the segment covering `time`, clamped to the last segment.
+    pub fn segment_index_at(&self, time: f64) -> Option<usize> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        let index = self
+            .segments
+            .iter()
+            .position(|segment| time < segment.start + segment.duration)
+            .unwrap_or(self.segments.len() - 1);
+        Some(index)
+    }
+
+    pub fn segment_by_sequence(&self, sequence: u64) -> Option<&MediaSegment> {
+        let first = self.segments.first()?.sequence;
+        let index = sequence.checked_sub(first)?;
+        self.segments.get(usize::try_from(index).ok()?)
+    }
+
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.segments.last().map(|segment| segment.sequence)
+    }
+}
+
+/// Parses an m3u8 document, resolving every URI against `base_url`.
+pub fn parse_playlist(text: &str, base_url: &str) -> Result<Playlist, VideoError> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    if lines.next() != Some(TAG_HEADER) {
+        return Err(VideoError::ManifestParseError("Playlist does not start with #EXTM3U".to_string()));
+    }
+
+    if text.contains(TAG_STREAM_INF) {
+        parse_master_playlist(text, base_url).map(Playlist::Master)
+    } else {
+        parse_media_playlist(text, base_url).map(Playlist::Media)
+    }
+}
+
+pub fn parse_master_playlist(text: &str, base_url: &str) -> Result<MasterPlaylist, VideoError> {
+    let mut playlist = MasterPlaylist::default();
+    let mut pending_variant: Option<Variant> = None;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(attributes) = line.strip_prefix(TAG_STREAM_INF) {
+            let attributes = parse_attributes(attributes);
+            let bandwidth = attributes
+                .get("BANDWIDTH")
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| VideoError::ManifestParseError("EXT-X-STREAM-INF without BANDWIDTH".to_string()))?;
+            pending_variant = Some(Variant {
+                uri: String::new(),
+                bandwidth,
+                average_bandwidth: attributes.get("AVERAGE-BANDWIDTH").and_then(|value| value.parse().ok()),
+                codecs: attributes.get("CODECS").cloned(),
+                resolution: attributes.get("RESOLUTION").and_then(|value| parse_resolution(value)),
+                frame_rate: attributes.get("FRAME-RATE").and_then(|value| value.parse().ok()),
+                audio: attributes.get("AUDIO").cloned(),
+                subtitles: attributes.get("SUBTITLES").cloned(),
+            });
+        } else if let Some(attributes) = line.strip_prefix(TAG_MEDIA) {
+            let attributes = parse_attributes(attributes);
+            let required = |name: &str| {
+                attributes
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| VideoError::ManifestParseError(format!("EXT-X-MEDIA without {}", name)))
+            };
+            playlist.renditions.push(Rendition {
+                media_type: required("TYPE")?,
+                group_id: required("GROUP-ID")?,
+                name: required("NAME")?,
+                language: attributes.get("LANGUAGE").cloned(),
+                uri: attributes.get("URI").map(|uri| resolve_url(base_url, uri)),
+                default: attributes.get("DEFAULT").is_some_and(|value| value == "YES"),
+                autoselect: attributes.get("AUTOSELECT").is_some_and(|value| value == "YES"),
+            });
+        } else if !line.starts_with('#') {
+            if let Some(mut variant) = pending_variant.take() {
+                variant.uri = resolve_url(base_url, line);
+                playlist.variants.push(variant);
+            }
+        }
+    }
+
+    if playlist.variants.is_empty() {
+        return Err(VideoError::ManifestParseError("Master playlist has no variants".to_string()));
+    }
+    Ok(playlist)
+}
+
+pub fn parse_media_playlist(text: &str, base_url: &str) -> Result<MediaPlaylist, VideoError> {
+    let mut playlist = MediaPlaylist::default();
+    let mut target_duration = None;
+
+    // Tags that apply to the next URI line
+    let mut duration: Option<f64> = None;
+    let mut title: Option<String> = None;
+    let mut discontinuity = false;
+    let mut byte_range: Option<(u64, Option<u64>)> = None;
+    // Tags that apply until they are replaced
+    let mut init: Option<InitSection> = None;
+    let mut encrypted = false;
+    let mut discontinuity_sequence = 0;
+    // Where the previous sub-range of each URI ended, for EXT-X-BYTERANGE without an offset
+    let mut range_ends: HashMap<String, u64> = HashMap::new();
+    let mut start = 0.0;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(value) = line.strip_prefix(TAG_TARGET_DURATION) {
+            target_duration = Some(parse_number::<f64>(value, "EXT-X-TARGETDURATION")?);
+        } else if let Some(value) = line.strip_prefix(TAG_MEDIA_SEQUENCE) {
+            playlist.media_sequence = parse_number(value, "EXT-X-MEDIA-SEQUENCE")?;
+        } else if let Some(value) = line.strip_prefix(TAG_DISCONTINUITY_SEQUENCE) {
+            playlist.discontinuity_sequence = parse_number(value, "EXT-X-DISCONTINUITY-SEQUENCE")?;
+            discontinuity_sequence = playlist.discontinuity_sequence;
+        } else if let Some(value) = line.strip_prefix(TAG_PLAYLIST_TYPE) {
+            playlist.playlist_type = match value {
+                "VOD" => Some(PlaylistType::Vod),
+                "EVENT" => Some(PlaylistType::Event),
+                other => return Err(VideoError::ManifestParseError(format!("Unknown playlist type: {}", other))),
+            };
+        } else if let Some(value) = line.strip_prefix(TAG_INF) {
+            let (length, segment_title) = value.split_once(',').unwrap_or((value, ""));
+            duration = Some(parse_number(length, "EXTINF")?);
+            title = Some(segment_title.to_string()).filter(|title| !title.is_empty());
+        } else if let Some(value) = line.strip_prefix(TAG_BYTERANGE) {
+            byte_range = Some(parse_byte_range(value)?);
+        } else if let Some(attributes) = line.strip_prefix(TAG_MAP) {
+            let attributes = parse_attributes(attributes);
+            let uri = attributes
+                .get("URI")
+                .ok_or_else(|| VideoError::ManifestParseError("EXT-X-MAP without URI".to_string()))?;
+            let byte_range = match attributes.get("BYTERANGE") {
+                Some(value) => match parse_byte_range(value)? {
+                    (length, Some(offset)) => Some(ByteRange::from_length(offset, length)),
+                    (_, None) => return Err(VideoError::ManifestParseError("EXT-X-MAP BYTERANGE needs an offset".to_string())),
+                },
+                None => None,
+            };
+            init = Some(InitSection {
+                uri: resolve_url(base_url, uri),
+                byte_range,
+            });
+        } else if let Some(attributes) = line.strip_prefix(TAG_KEY) {
+            let attributes = parse_attributes(attributes);
+            encrypted = attributes.get("METHOD").is_some_and(|method| method != "NONE");
+        } else if line == TAG_DISCONTINUITY {
+            discontinuity = true;
+            discontinuity_sequence += 1;
+        } else if line == TAG_ENDLIST {
+            playlist.end_list = true;
+        } else if !line.starts_with('#') {
+            let segment_duration = duration
+                .take()
+                .ok_or_else(|| VideoError::ManifestParseError(format!("Segment {} has no EXTINF", line)))?;
+            let uri = resolve_url(base_url, line);
+            let segment_range = byte_range.take().map(|(length, offset)| {
+                let offset = offset.unwrap_or_else(|| range_ends.get(&uri).copied().unwrap_or(0));
+                range_ends.insert(uri.clone(), offset + length);
+                ByteRange::from_length(offset, length)
+            });
+            playlist.segments.push(MediaSegment {
+                uri,
+                duration: segment_duration,
+                title: title.take(),
+                sequence: playlist.media_sequence + playlist.segments.len() as u64,
+                discontinuity: std::mem::take(&mut discontinuity),
+                discontinuity_sequence,
+                byte_range: segment_range,
+                init: init.clone(),
+                encrypted,
+                start,
+            });
+            start += segment_duration;
+        }
+    }
+
+    playlist.target_duration = target_duration
+        .ok_or_else(|| VideoError::ManifestParseError("Media playlist has no EXT-X-TARGETDURATION".to_string()))?;
+    Ok(playlist)
+}
+
+/// Parses an attribute list such as `BANDWIDTH=800000,CODECS="avc1.4d401e,mp4a.40.2"`,
+/// stripping quotes from quoted values.
+pub fn parse_attributes(input: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = input.trim();
+
+    while !rest.is_empty() {
+        let Some((name, after_name)) = rest.split_once('=') else {
+            break;
+        };
+        let (value, after_value) = if let Some(quoted) = after_name.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            }
+        } else {
+            match after_name.find(',') {
+                Some(end) => (&after_name[..end], &after_name[end..]),
+                None => (after_name, ""),
+            }
+        };
+        attributes.insert(name.trim().to_string(), value.to_string());
+        rest = after_value.trim_start_matches(',').trim_start();
+    }
+
+    attributes
+}
+
+fn parse_resolution(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Parses `<length>[@<offset>]`.
+fn parse_byte_range(value: &str) -> Result<(u64, Option<u64>), VideoError> {
+    match value.split_once('@') {
+        Some((length, offset)) => Ok((parse_number(length, "BYTERANGE")?, Some(parse_number(offset, "BYTERANGE")?))),
+        None => Ok((parse_number(value, "BYTERANGE")?, None)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, tag: &str) -> Result<T, VideoError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| VideoError::ManifestParseError(format!("Invalid {} value: {}", tag, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://media.example.com/show/master.m3u8";
+
+    fn media(text: &str) -> MediaPlaylist {
+        match parse_playlist(text, BASE).unwrap() {
+            Playlist::Media(playlist) => playlist,
+            Playlist::Master(_) => panic!("expected a media playlist"),
+        }
+    }
+
+    #[test]
+    fn test_parse_master_playlist() {
+        let playlist = match parse_playlist(include_str!("fixtures/master.m3u8"), BASE).unwrap() {
+            Playlist::Master(playlist) => playlist,
+            Playlist::Media(_) => panic!("expected a master playlist"),
+        };
+
+        assert_eq!(playlist.variants.len(), 3);
+        let first = &playlist.variants[0];
+        assert_eq!(first.uri, "https://media.example.com/show/360p/index.m3u8");
+        assert_eq!(first.bandwidth, 800_000);
+        assert_eq!(first.average_bandwidth, Some(700_000));
+        assert_eq!(first.resolution, Some((640, 360)));
+        assert_eq!(first.frame_rate, Some(25.0));
+        assert_eq!(first.codecs.as_deref(), Some("avc1.4d401e,mp4a.40.2"));
+        assert_eq!(first.subtitles.as_deref(), Some("subs"));
+        // The I-frame entry must not swallow the next variant's URI
+        assert_eq!(playlist.variants[2].uri, "https://cdn.example.com/1080p/index.m3u8");
+
+        assert_eq!(playlist.renditions.len(), 2);
+        assert_eq!(playlist.renditions[0].uri.as_deref(), Some("https://media.example.com/show/audio/en/index.m3u8"));
+        assert!(playlist.renditions[0].default);
+        assert_eq!(playlist.renditions[1].name, "Deutsch, Untertitel");
+    }
+
+    #[test]
+    fn test_parse_vod_fmp4_playlist() {
+        let playlist = media(include_str!("fixtures/vod_fmp4.m3u8"));
+
+        assert_eq!(playlist.target_duration, 6.0);
+        assert_eq!(playlist.playlist_type, Some(PlaylistType::Vod));
+        assert!(!playlist.is_live());
+        assert_eq!(playlist.segments.len(), 4);
+        assert_eq!(playlist.total_duration(), 22.5);
+        assert_eq!(playlist.segments[3].start, 18.0);
+        assert_eq!(playlist.segments[1].init.as_ref().unwrap().uri, "https://media.example.com/show/init.mp4");
+        assert_eq!(playlist.segment_index_at(13.0), Some(2));
+        assert_eq!(playlist.segment_index_at(99.0), Some(3));
+    }
+
+    #[test]
+    fn test_parse_live_playlist() {
+        let playlist = media(include_str!("fixtures/live_1.m3u8"));
+
+        assert!(playlist.is_live());
+        assert_eq!(playlist.media_sequence, 100);
+        assert_eq!(playlist.last_sequence(), Some(104));
+        assert_eq!(playlist.segment_by_sequence(102).unwrap().uri, "https://media.example.com/show/live102.ts");
+        assert!(playlist.segment_by_sequence(99).is_none());
+    }
+
+    #[test]
+    fn test_parse_discontinuities_and_byte_ranges() {
+        let playlist = media(include_str!("fixtures/discontinuity_byterange.m3u8"));
+        let segments = &playlist.segments;
+
+        assert_eq!(segments[0].sequence, 7);
+        assert_eq!(segments[0].title.as_deref(), Some("Intro"));
+        assert_eq!(segments[0].byte_range, Some(ByteRange { start: 0, end: 999 }));
+        // No offset continues from the end of the previous range of the same URI
+        assert_eq!(segments[1].byte_range, Some(ByteRange { start: 1000, end: 2999 }));
+        assert!(segments[2].discontinuity);
+        assert_eq!(segments[2].discontinuity_sequence, 3);
+        assert_eq!(segments[2].byte_range, None);
+        assert!(segments[3].discontinuity);
+        assert_eq!(segments[3].discontinuity_sequence, 4);
+        assert_eq!(segments[3].byte_range, Some(ByteRange { start: 3000, end: 4499 }));
+        assert_eq!(segments[3].start, 28.5);
+    }
+
+    #[test]
+    fn test_parse_attributes_with_quoted_commas() {
+        let attributes = parse_attributes(r#"BANDWIDTH=1,CODECS="a,b",NAME="x""#);
+        assert_eq!(attributes["BANDWIDTH"], "1");
+        assert_eq!(attributes["CODECS"], "a,b");
+        assert_eq!(attributes["NAME"], "x");
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_playlists() {
+        assert!(parse_playlist("not a playlist", BASE).is_err());
+        assert!(parse_playlist("#EXTM3U\n#EXTINF:4,\na.ts\n", BASE).is_err());
+        assert!(parse_playlist("#EXTM3U\n#EXT-X-TARGETDURATION:4\nno-extinf.ts\n", BASE).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use js_sys::{Function, Promise};
+use crate::player::captions::{toggle_captions_for, set_caption_track_for, get_caption_track_for, get_caption_text_for};
+use crate::player::context::PlayerContext;
+use crate::player::download::download_video_for;
+use crate::player::error::{show_error_for, show_message_for, hide_error_for, Severity, VideoError};
+use crate::player::fullscreen::{toggle_fullscreen_for, is_fullscreen_for};
+use crate::player::keyboard::Keymap;
+use crate::player::mute::{toggle_mute_for, is_video_muted_for};
+use crate::player::picture_in_picture::toggle_picture_in_picture_for;
+use crate::player::play_pause::{toggle_play_for, play_for, pause_video_for, is_video_playing_for};
+use crate::player::playlist::Playlist;
+use crate::player::playback_speed::{set_playback_speed_for, get_playback_speed_for};
+use crate::player::quality::{set_quality_for, get_quality_for};
+use crate::player::seek_bar::seek_to_for;
+use crate::player::store::{action_log_for, enable_action_log_for, travel_to_for};
+use crate::player::volume::{set_volume_for, get_volume_for};
+use crate::player::time::{get_video_time_for, get_video_duration_for};
+use crate::player::ElementIds;
+
+/// Handle to one player instance, returned by `init_video_player`.
+#[wasm_bindgen]
+pub struct Player {
+    context: Rc<PlayerContext>,
+}
+
+impl Player {
+    pub fn new(context: Rc<PlayerContext>) -> Player {
+        Player { context }
+    }
+
+    pub fn context(&self) -> &Rc<PlayerContext> {
+        &self.context
+    }
+}
+
+#[wasm_bindgen]
+impl Player {
+    pub fn element_ids(&self) -> ElementIds {
+        self.context.ids().clone()
+    }
+
+    /// Resolves once playback has started, or rejects with why it could not.
+    pub fn play(&self) -> Promise {
+        let context = self.context.clone();
+        future_to_promise(async move {
+            play_for(context).await?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    pub fn pause(&self) -> Result<(), JsValue> {
+        pause_video_for(&self.context)
+    }
+
+    pub fn toggle_play(&self) -> Promise {
+        let context = self.context.clone();
+        future_to_promise(async move {
+            toggle_play_for(context).await?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    pub fn is_playing(&self) -> Result<bool, JsValue> {
+        is_video_playing_for(&self.context)
+    }
+
+    pub fn toggle_mute(&self) -> Result<bool, JsValue> {
+        toggle_mute_for(&self.context)
+    }
+
+    pub fn is_muted(&self) -> Result<bool, JsValue> {
+        is_video_muted_for(&self.context)
+    }
+
+    pub fn toggle_fullscreen(&self) -> Result<bool, JsValue> {
+        toggle_fullscreen_for(&self.context)
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, JsValue> {
+        is_fullscreen_for(&self.context)
+    }
+
+    pub fn set_volume(&self, volume: f64) -> Result<(), JsValue> {
+        set_volume_for(&self.context, volume)
+    }
+
+    pub fn volume(&self) -> Result<f64, JsValue> {
+        get_volume_for(&self.context)
+    }
+
+    pub fn set_playback_speed(&self, speed: f64) -> Result<(), JsValue> {
+        set_playback_speed_for(&self.context, speed)
+    }
+
+    pub fn playback_speed(&self) -> Result<f64, JsValue> {
+        get_playback_speed_for(&self.context)
+    }
+
+    /// Pins a rendition from the quality menu's list, or restores automatic selection with `None`.
+    pub fn set_quality(&self, index: Option<usize>) -> Result<(), JsValue> {
+        set_quality_for(&self.context, index)
+    }
+
+    pub fn quality(&self) -> Option<usize> {
+        get_quality_for(&self.context)
+    }
+
+    /// Turns captions off, or back on with the last track shown. Returns whether they are on.
+    pub fn toggle_captions(&self) -> Result<bool, JsValue> {
+        toggle_captions_for(&self.context)
+    }
+
+    pub fn set_caption_track(&self, track: Option<usize>) -> Result<(), JsValue> {
+        set_caption_track_for(&self.context, track)
+    }
+
+    pub fn caption_track(&self) -> Option<usize> {
+        get_caption_track_for(&self.context)
+    }
+
+    pub fn caption_text(&self) -> Option<String> {
+        get_caption_text_for(&self.context)
+    }
+
+    pub fn toggle_picture_in_picture(&self) -> Result<(), JsValue> {
+        toggle_picture_in_picture_for(&self.context)
+    }
+
+    pub fn download(&self) -> Promise {
+        let context = self.context.clone();
+        future_to_promise(async move {
+            download_video_for(context).await?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    pub fn current_time(&self) -> Result<f64, JsValue> {
+        get_video_time_for(&self.context)
+    }
+
+    pub fn seek(&self, seconds: f64) -> Result<(), JsValue> {
+        seek_to_for(&self.context, seconds)
+    }
+
+    pub fn duration(&self) -> Result<f64, JsValue> {
+        get_video_duration_for(&self.context)
+    }
+
+    /// Swaps the keyboard shortcuts while the player is running.
+    pub fn set_keymap(&self, keymap: Keymap) {
+        *self.context.keymap.borrow_mut() = keymap;
+    }
+
+    pub fn keymap(&self) -> Keymap {
+        self.context.keymap.borrow().clone()
+    }
+
+    /// The player's playlist, to queue, reorder and skip between videos.
+    pub fn playlist(&self) -> Playlist {
+        Playlist::new(self.context.clone())
+    }
+
+    /// Calls `callback` with the payload of every `event` this player publishes; see
+    /// `PlayerEventMap` for the names and payloads.
+    pub fn on(&self, event: &str, callback: Function) -> Result<(), VideoError> {
+        self.context.events.borrow_mut().on(event, callback)
+    }
+
+    /// Removes a callback added with `on`; returns whether it was subscribed.
+    pub fn off(&self, event: &str, callback: &Function) -> bool {
+        self.context.events.borrow_mut().off(event, callback)
+    }
+
+    /// Starts recording the player's state changes, keeping the last `capacity`
+    /// actions for `action_log` and `travel_to`.
+    pub fn enable_action_log(&self, capacity: usize) {
+        enable_action_log_for(&self.context, capacity);
+    }
+
+    pub fn disable_action_log(&self) {
+        self.context.store.borrow_mut().disable_log();
+    }
+
+    /// The recorded actions, oldest first, each an object with a `type`.
+    pub fn action_log(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&action_log_for(&self.context))?)
+    }
+
+    /// Returns the player to its state after the first `count` recorded actions.
+    /// The log is kept, so travelling forward again works too.
+    pub fn travel_to(&self, count: usize) -> Result<(), JsValue> {
+        travel_to_for(&self.context, count)
+    }
+
+    pub fn show_error(&self, message: &str) -> Result<(), JsValue> {
+        show_error_for(&self.context, message)
+    }
+
+    /// Shows `message` in the error overlay alongside any others, dismissed after the
+    /// timeout configured for `severity`.
+    pub fn show_message(&self, message: &str, severity: Severity) -> Result<(), JsValue> {
+        show_message_for(&self.context, message, severity)
+    }
+
+    pub fn hide_error(&self) -> Result<(), JsValue> {
+        hide_error_for(&self.context)
+    }
+
+    /// Removes every listener this player registered, revokes its object URLs,
+    /// removes the sources it added and resets its state.
+    pub fn destroy(&self) {
+        self.context.destroy();
+    }
+}
@@ -0,0 +1,366 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::{Rc, Weak};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, HtmlElement, KeyboardEvent};
+use wasm_bindgen_futures::spawn_local;
+use crate::logger::Logger;
+use crate::player::captions::toggle_captions_for;
+use crate::player::context::{is_default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::fullscreen::toggle_fullscreen_for;
+use crate::player::mute::toggle_mute_for;
+use crate::player::play_pause::toggle_play_for;
+use crate::player::playback_speed::{get_current_playback_speed_for, set_playback_speed_for};
+use crate::player::seek_bar::seek_to_for;
+use crate::player::volume::step_volume_for;
+
+// Speeds `<` and `>` step through
+const SPEED_STEPS: [f64; 8] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+// <input> types that do not take typed text
+const NON_TEXT_INPUT_TYPES: [&str; 10] = ["button", "checkbox", "color", "file", "hidden", "image", "radio", "range", "reset", "submit"];
+
+thread_local! {
+    // Player the user last clicked or focused, which gets keys pressed while nothing has focus
+    static ACTIVE_PLAYER: RefCell<Weak<PlayerContext>> = const { RefCell::new(Weak::new()) };
+}
+
+/// What a shortcut does. In JSON, actions without a value are plain strings such as
+/// `"toggle_play"`; the others are objects such as `{"seek": -10}`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    TogglePlay,
+    /// Moves playback by this many seconds.
+    Seek(f64),
+    /// Jumps to this percentage of the duration.
+    SeekPercent(f64),
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    ToggleFullscreen,
+    ToggleCaptions,
+    SpeedUp,
+    SpeedDown,
+}
+
+/// Maps `KeyboardEvent.key` values to actions. `new()` gives YouTube-style bindings,
+/// which can be changed one key at a time or overridden from JSON.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: BTreeMap<String, KeyAction>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl Keymap {
+    /// Space/k play and pause, j/l seek 10 seconds, the left and right arrows 5,
+    /// the up and down arrows change the volume, m mutes, f toggles fullscreen,
+    /// c toggles captions, `<` and `>` change the speed, 0-9 jump to 0-90% of the
+    /// video and Home/End to its start and end.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Keymap {
+        let mut keymap = Keymap::empty();
+        for (key, action) in [
+            (" ", KeyAction::TogglePlay),
+            ("k", KeyAction::TogglePlay),
+            ("j", KeyAction::Seek(-10.0)),
+            ("l", KeyAction::Seek(10.0)),
+            ("ArrowLeft", KeyAction::Seek(-5.0)),
+            ("ArrowRight", KeyAction::Seek(5.0)),
+            ("ArrowUp", KeyAction::VolumeUp),
+            ("ArrowDown", KeyAction::VolumeDown),
+            ("m", KeyAction::ToggleMute),
+            ("f", KeyAction::ToggleFullscreen),
+            ("c", KeyAction::ToggleCaptions),
+            ("<", KeyAction::SpeedDown),
+            (">", KeyAction::SpeedUp),
+            ("Home", KeyAction::SeekPercent(0.0)),
+            ("End", KeyAction::SeekPercent(100.0)),
+        ] {
+            keymap.bind_action(key, action);
+        }
+        for digit in 0..10u8 {
+            keymap.bind_action(&digit.to_string(), KeyAction::SeekPercent(digit as f64 * 10.0));
+        }
+        keymap
+    }
+
+    /// A keymap with no bindings, for pages that want only their own shortcuts or none.
+    pub fn empty() -> Keymap {
+        Keymap { bindings: BTreeMap::new() }
+    }
+
+    /// Applies a JSON object of key to action on top of the default bindings, for
+    /// example `{"k": null, "p": "toggle_play", "ArrowRight": {"seek": 15}}`.
+    /// A `null` action removes the key's binding.
+    pub fn from_json(json: &str) -> Result<Keymap, VideoError> {
+        let overrides: BTreeMap<String, Option<KeyAction>> = serde_json::from_str(json)
+            .map_err(|e| VideoError::InvalidConfig(format!("Invalid keymap: {}", e)))?;
+        let mut keymap = Keymap::new();
+        for (key, action) in overrides {
+            match action {
+                Some(action) => keymap.bind_action(&key, action),
+                None => keymap.unbind(&key),
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// The full set of bindings in the format `from_json` reads.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.bindings).unwrap_or_default()
+    }
+
+    /// Binds `key` to an action without a value, such as `"toggle_mute"`.
+    pub fn bind(&mut self, key: &str, action: &str) -> Result<(), VideoError> {
+        let action = serde_json::from_value(serde_json::Value::String(action.to_string()))
+            .map_err(|_| VideoError::InvalidConfig(format!("Unknown keyboard action: {}", action)))?;
+        self.bind_action(key, action);
+        Ok(())
+    }
+
+    /// Binds `key` to moving playback by `seconds`, backwards when negative.
+    pub fn bind_seek(&mut self, key: &str, seconds: f64) {
+        self.bind_action(key, KeyAction::Seek(seconds));
+    }
+
+    /// Binds `key` to jumping to `percent` of the duration.
+    pub fn bind_seek_percent(&mut self, key: &str, percent: f64) {
+        self.bind_action(key, KeyAction::SeekPercent(percent));
+    }
+
+    pub fn unbind(&mut self, key: &str) {
+        self.bindings.remove(&normalize_key(key));
+    }
+}
+
+impl Keymap {
+    pub fn bind_action(&mut self, key: &str, action: KeyAction) {
+        self.bindings.insert(normalize_key(key), action);
+    }
+
+    pub fn action(&self, key: &str) -> Option<KeyAction> {
+        self.bindings.get(&normalize_key(key)).copied()
+    }
+}
+
+/// Folds the spellings of a key together: single letters are matched regardless of
+/// Shift or Caps Lock, and "Space" and the legacy "Spacebar" mean " ".
+pub fn normalize_key(key: &str) -> String {
+    match key {
+        "Space" | "Spacebar" => " ".to_string(),
+        _ if key.chars().count() == 1 => key.to_lowercase(),
+        _ => key.to_string(),
+    }
+}
+
+/// The next speed up or down from `current`, or `None` at either end of the range.
+pub fn next_speed(current: f64, faster: bool) -> Option<f64> {
+    if faster {
+        SPEED_STEPS.iter().copied().find(|speed| *speed > current + f64::EPSILON)
+    } else {
+        SPEED_STEPS.iter().rev().copied().find(|speed| *speed < current - f64::EPSILON)
+    }
+}
+
+/// The time `percent` of the way through, once the duration is known.
+pub fn percent_target(percent: f64, duration: f64) -> Option<f64> {
+    if !duration.is_finite() || duration <= 0.0 {
+        return None;
+    }
+    Some(duration * percent.clamp(0.0, 100.0) / 100.0)
+}
+
+/// Whether an `<input>` of this type takes typed text, so keys must reach it untouched.
+pub fn is_text_input_type(input_type: &str) -> bool {
+    !NON_TEXT_INPUT_TYPES.contains(&input_type.to_ascii_lowercase().as_str())
+}
+
+/// Whether `key` pressed on `target` belongs to the element itself: typing in a text
+/// field, or Space and Enter activating a button or checkbox.
+fn target_handles_key(target: &Element, key: &str) -> bool {
+    if target.dyn_ref::<HtmlElement>().is_some_and(|element| element.is_content_editable()) {
+        return true;
+    }
+    let activation_key = key == " " || key == "Enter";
+    match target.tag_name().to_ascii_uppercase().as_str() {
+        "TEXTAREA" | "SELECT" => true,
+        "INPUT" => {
+            let input_type = target.get_attribute("type").unwrap_or_else(|| "text".to_string());
+            is_text_input_type(&input_type) || (activation_key && input_type != "range")
+        }
+        "BUTTON" | "SUMMARY" => activation_key,
+        "A" => activation_key && target.has_attribute("href"),
+        _ => false,
+    }
+}
+
+/// Records `context` as the player keys go to while nothing on the page has focus.
+pub fn mark_active_player_for(context: &Rc<PlayerContext>) {
+    ACTIVE_PLAYER.with(|active| *active.borrow_mut() = Rc::downgrade(context));
+}
+
+/// Whether keys pressed now are meant for this player: focus is inside its container,
+/// or nothing has focus and it was the last player used (the default player until
+/// another one is clicked).
+fn has_keyboard_focus(context: &Rc<PlayerContext>) -> Result<bool, VideoError> {
+    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
+    let document = window.document().ok_or(VideoError::DocumentNotFound)?;
+    let video_element = context.video_element()?;
+    let container = video_element.parent_element().unwrap_or_else(|| video_element.clone().into());
+
+    let focused = document.active_element()
+        .filter(|element| document.body().is_none_or(|body| AsRef::<Element>::as_ref(&body) != element))
+        .filter(|element| document.document_element().as_ref() != Some(element));
+    if let Some(focused) = focused {
+        return Ok(container.contains(Some(&focused)));
+    }
+    Ok(ACTIVE_PLAYER.with(|active| match active.borrow().upgrade() {
+        Some(active) => Rc::ptr_eq(&active, context),
+        None => is_default_context(context),
+    }))
+}
+
+/// Runs the shortcut bound to a `keydown`, if the key is meant for this player.
+/// Keys another control already handled, keys with Ctrl, Alt or Meta held and keys
+/// typed into form fields are left alone.
+pub fn keyboard_shortcut_for(context: &Rc<PlayerContext>, event: &KeyboardEvent) -> Result<(), JsValue> {
+    if event.default_prevented() || event.is_composing() || event.ctrl_key() || event.alt_key() || event.meta_key() {
+        return Ok(());
+    }
+    let key = event.key();
+    let Some(action) = context.keymap.borrow().action(&key) else {
+        return Ok(());
+    };
+    let target = event.target().and_then(|target| target.dyn_into::<Element>().ok());
+    if target.is_some_and(|target| target_handles_key(&target, &key)) || !has_keyboard_focus(context)? {
+        return Ok(());
+    }
+    // Space would otherwise scroll the page and the arrows move the native controls
+    event.prevent_default();
+    run_key_action_for(context, action)
+}
+
+pub fn run_key_action_for(context: &Rc<PlayerContext>, action: KeyAction) -> Result<(), JsValue> {
+    Logger::debug(&format!("Running keyboard action {:?}", action)).map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    match action {
+        KeyAction::TogglePlay => {
+            let play_video = toggle_play_for(context.clone());
+            spawn_local(async move {
+                play_video.await.unwrap_or_default();
+            });
+        }
+        KeyAction::Seek(seconds) => {
+            let video_element = context.video_element()?;
+            seek_to_for(context, video_element.current_time() + seconds)?;
+        }
+        KeyAction::SeekPercent(percent) => {
+            let video_element = context.video_element()?;
+            if let Some(target) = percent_target(percent, video_element.duration()) {
+                seek_to_for(context, target)?;
+            }
+        }
+        KeyAction::VolumeUp => step_volume_for(context, 1.0)?,
+        KeyAction::VolumeDown => step_volume_for(context, -1.0)?,
+        KeyAction::ToggleMute => {
+            toggle_mute_for(context)?;
+        }
+        KeyAction::ToggleFullscreen => {
+            toggle_fullscreen_for(context)?;
+        }
+        KeyAction::ToggleCaptions => {
+            toggle_captions_for(context)?;
+        }
+        KeyAction::SpeedUp | KeyAction::SpeedDown => {
+            let current = get_current_playback_speed_for(context);
+            if let Some(speed) = next_speed(current, action == KeyAction::SpeedUp) {
+                set_playback_speed_for(context, speed)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_bindings() {
+        let keymap = Keymap::new();
+        assert_eq!(keymap.action(" "), Some(KeyAction::TogglePlay));
+        assert_eq!(keymap.action("K"), Some(KeyAction::TogglePlay));
+        assert_eq!(keymap.action("Spacebar"), Some(KeyAction::TogglePlay));
+        assert_eq!(keymap.action("j"), Some(KeyAction::Seek(-10.0)));
+        assert_eq!(keymap.action("ArrowRight"), Some(KeyAction::Seek(5.0)));
+        assert_eq!(keymap.action("7"), Some(KeyAction::SeekPercent(70.0)));
+        assert_eq!(keymap.action(">"), Some(KeyAction::SpeedUp));
+        assert_eq!(keymap.action("x"), None);
+        assert_eq!(Keymap::empty().action("k"), None);
+    }
+
+    #[test]
+    fn test_remap_from_json_and_builder() {
+        let keymap = Keymap::from_json(r#"{"k": null, "p": "toggle_play", "Space": "toggle_mute", "ArrowRight": {"seek": 15}, "5": {"seek_percent": 25}}"#).unwrap();
+        assert_eq!(keymap.action("k"), None);
+        assert_eq!(keymap.action("P"), Some(KeyAction::TogglePlay));
+        assert_eq!(keymap.action(" "), Some(KeyAction::ToggleMute));
+        assert_eq!(keymap.action("ArrowRight"), Some(KeyAction::Seek(15.0)));
+        assert_eq!(keymap.action("5"), Some(KeyAction::SeekPercent(25.0)));
+        assert_eq!(keymap.action("m"), Some(KeyAction::ToggleMute));
+        assert!(matches!(Keymap::from_json(r#"{"k": "dance"}"#), Err(VideoError::InvalidConfig(_))));
+        assert!(matches!(Keymap::from_json("[]"), Err(VideoError::InvalidConfig(_))));
+
+        let mut keymap = Keymap::empty();
+        keymap.bind("s", "speed_up").unwrap();
+        keymap.bind_seek("b", -30.0);
+        keymap.bind_seek_percent("e", 100.0);
+        assert!(keymap.bind("z", "seek").is_err());
+        assert_eq!(keymap.to_json(), r#"{"b":{"seek":-30.0},"e":{"seek_percent":100.0},"s":"speed_up"}"#);
+        keymap.unbind("S");
+        assert_eq!(keymap.action("s"), None);
+
+        let defaults = Keymap::new();
+        let mut round_trip = Keymap::empty();
+        for (key, action) in serde_json::from_str::<BTreeMap<String, KeyAction>>(&defaults.to_json()).unwrap() {
+            round_trip.bind_action(&key, action);
+        }
+        assert_eq!(round_trip, defaults);
+    }
+
+    #[test]
+    fn test_speed_and_percent_steps() {
+        assert_eq!(next_speed(1.0, true), Some(1.25));
+        assert_eq!(next_speed(1.0, false), Some(0.75));
+        assert_eq!(next_speed(1.1, true), Some(1.25));
+        assert_eq!(next_speed(1.1, false), Some(1.0));
+        assert_eq!(next_speed(2.0, true), None);
+        assert_eq!(next_speed(0.25, false), None);
+        assert_eq!(percent_target(50.0, 120.0), Some(60.0));
+        assert_eq!(percent_target(150.0, 120.0), Some(120.0));
+        assert_eq!(percent_target(50.0, f64::NAN), None);
+        assert_eq!(percent_target(50.0, f64::INFINITY), None);
+    }
+
+    #[test]
+    fn test_text_input_types() {
+        assert!(is_text_input_type("text"));
+        assert!(is_text_input_type("Search"));
+        assert!(is_text_input_type("number"));
+        assert!(!is_text_input_type("range"));
+        assert!(!is_text_input_type("checkbox"));
+    }
+}
@@ -0,0 +1,113 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{Event, EventTarget};
+use crate::player::error::VideoError;
+use crate::player::media::{MediaBackend, MediaSubscription};
+
+struct RegisteredListener {
+    target: EventTarget,
+    event: &'static str,
+    closure: Closure<dyn FnMut(Event)>,
+}
+
+/// Keeps the `Closure` handles of every listener a player registers so they can be
+/// removed again instead of being leaked with `forget()`, along with the
+/// subscriptions it holds on its media.
+#[derive(Default)]
+pub struct ListenerRegistry {
+    listeners: Vec<RegisteredListener>,
+    subscriptions: Vec<MediaSubscription>,
+}
+
+impl ListenerRegistry {
+    pub fn new() -> ListenerRegistry {
+        ListenerRegistry::default()
+    }
+
+    pub fn add<F>(&mut self, target: &EventTarget, event: &'static str, handler: F) -> Result<(), VideoError>
+    where
+        F: FnMut(Event) + 'static,
+    {
+        let closure = Closure::wrap(Box::new(handler) as Box<dyn FnMut(Event)>);
+        target
+            .add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to add {} listener: {:?}", event, e)))?;
+        self.listeners.push(RegisteredListener {
+            target: target.clone(),
+            event,
+            closure,
+        });
+        Ok(())
+    }
+
+    /// Listens to `media` through its backend, for handlers that need no event object.
+    pub fn add_media<M, F>(&mut self, media: &M, event: &'static str, handler: F) -> Result<(), VideoError>
+    where
+        M: MediaBackend + ?Sized,
+        F: FnMut() + 'static,
+    {
+        self.subscriptions.push(media.listen(event, Box::new(handler))?);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.listeners.len() + self.subscriptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.listeners.is_empty() && self.subscriptions.is_empty()
+    }
+
+    pub fn remove_all(&mut self) {
+        // Media subscriptions outlive a dispatch on their own
+        self.subscriptions.clear();
+        let listeners = std::mem::take(&mut self.listeners);
+        for listener in &listeners {
+            listener
+                .target
+                .remove_event_listener_with_callback(listener.event, listener.closure.as_ref().unchecked_ref())
+                .unwrap_or_default();
+        }
+        // A handler may be the one tearing the player down, so its closure
+        // must outlive the current dispatch before it is dropped.
+        if !listeners.is_empty() {
+            wasm_bindgen_futures::spawn_local(async move {
+                drop(listeners);
+            });
+        }
+    }
+}
+
+impl Drop for ListenerRegistry {
+    fn drop(&mut self) {
+        self.remove_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_remove_all_unbinds_listeners() {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let target = document.create_element("div").unwrap();
+        let calls = Rc::new(Cell::new(0));
+
+        let mut registry = ListenerRegistry::new();
+        let calls_clone = calls.clone();
+        registry.add(&target, "ping", move |_| calls_clone.set(calls_clone.get() + 1)).unwrap();
+        assert_eq!(registry.len(), 1);
+
+        target.dispatch_event(&Event::new("ping").unwrap()).unwrap();
+        registry.remove_all();
+        target.dispatch_event(&Event::new("ping").unwrap()).unwrap();
+
+        assert!(registry.is_empty());
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,173 @@
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, HtmlVideoElement};
+use crate::player::captions::{apply_caption_preference_for, load_caption_tracks};
+use crate::player::config::VideoSource;
+use crate::player::context::PlayerContext;
+use crate::player::dash::{is_dash_source, start_dash};
+use crate::player::dom::add_video_source;
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::gapless::discard_preloaded_for;
+use crate::player::hls::{is_hls_source, start_hls, HLS_MIME_TYPES};
+use crate::player::media::listen_once;
+use crate::player::mse::is_media_source_supported;
+use crate::player::playlist::PlaylistItem;
+use crate::player::play_pause::start_video_for;
+use crate::player::quality::{apply_quality_preference_for, QualitySource};
+use crate::player::resume::restore_position_for;
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
+use crate::player::watchdog::watch_media_for;
+
+// HTMLMediaElement.HAVE_METADATA
+const HAVE_METADATA: u16 = 1;
+
+/// Loads an item's sources, captions and poster into the player, seeking to
+/// `start_time` or offering to resume where the viewer left it.
+pub(crate) fn load_media_for(context: &Rc<PlayerContext>, item: &PlaylistItem, start_time: f64) -> Result<(), VideoError> {
+    let video_element = context.video_element()?;
+    load_sources_for(context, &item.sources, start_time)?;
+    watch_media_for(context, &item.sources)?;
+    load_caption_tracks(context, &item.text_tracks);
+    apply_caption_preference_for(context)?;
+    match &item.poster {
+        Some(poster) => video_element.set_poster(poster),
+        None => video_element.remove_attribute("poster").unwrap_or_default(),
+    }
+    if start_time > 0.0 {
+        set_start_time(&video_element, start_time)?;
+    }
+    restore_position_for(context, item.resume_id(), start_time)
+}
+
+fn load_sources_for(context: &Rc<PlayerContext>, sources: &[VideoSource], start_time: f64) -> Result<(), VideoError> {
+    let video_element = context.video_element()?;
+    let abr_strategy = context.abr_strategy.get();
+    let mut playable_sources = Vec::new();
+    for (index, source) in sources.iter().enumerate() {
+        if is_dash_source(&source.url, &source.mime_type) {
+            // Browsers cannot play DASH natively, so it is only usable through MSE
+            if index == 0 && is_media_source_supported() {
+                start_stream_source(context, start_dash(context.clone(), source.url.clone(), abr_strategy, start_time));
+                break;
+            }
+            continue;
+        }
+        if is_hls_source(&source.url, &source.mime_type) {
+            // A leading HLS source is streamed through MSE and takes over the element
+            if index == 0 && is_media_source_supported() {
+                start_stream_source(context, start_hls(context.clone(), source.url.clone(), abr_strategy, start_time));
+                break;
+            }
+            // Otherwise only browsers that play HLS natively can use it as a <source>
+            if video_element.can_play_type(HLS_MIME_TYPES[0]).is_empty() {
+                continue;
+            }
+        }
+        let source_element = add_video_source(&video_element, &source.url, &source.mime_type, source.label.as_deref()).map_err(|e|
+            VideoError::VideoOperationFailed(format!("Failed to add video source: {:?}", e)))?;
+        context.track_element(source_element.into());
+        if !video_element.can_play_type(&source.mime_type).is_empty() {
+            playable_sources.push(source.clone());
+        }
+    }
+    // Several playable files are offered as alternative qualities
+    if playable_sources.len() > 1 {
+        context.set_quality_source(QualitySource::Progressive(playable_sources));
+        apply_quality_preference_for(context).map_err(|e|
+            VideoError::VideoOperationFailed(format!("Failed to apply quality preference: {:?}", e)))?;
+    }
+    Ok(())
+}
+
+/// Empties the video element of the current media: stops its stream, removes its
+/// `<source>` elements and forgets its renditions and caption tracks.
+pub(crate) fn unload_media_for(context: &PlayerContext) -> Result<(), VideoError> {
+    discard_preloaded_for(context);
+    let video_element = context.video_element()?;
+    video_element.pause().unwrap_or_default();
+    let sources = video_element.query_selector_all("source")
+        .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to get video sources: {:?}", e)))?;
+    for index in 0..sources.length() {
+        if let Some(source) = sources.get(index).and_then(|node| node.dyn_into::<Element>().ok()) {
+            source.remove();
+            context.untrack_element(&source);
+        }
+    }
+    if let Some(src) = video_element.get_attribute("src") {
+        if context.object_urls.borrow().contains(&src) {
+            context.revoke_object_url(&src)?;
+        }
+        video_element.remove_attribute("src").unwrap_or_default();
+    }
+    video_element.load();
+    forget_media_for(context)
+}
+
+/// Forgets the renditions and caption tracks of the current media, and stops any
+/// stream or caption load still running for it.
+pub(crate) fn forget_media_for(context: &PlayerContext) -> Result<(), VideoError> {
+    context.next_media_generation();
+    context.quality_source.borrow_mut().take();
+    context.captions.borrow_mut().clear_tracks();
+    dispatch_for(context, Action::MediaUnloaded)
+}
+
+/// Runs the setup of a stream, reporting failures unless the player has moved on.
+fn start_stream_source<F>(context: &Rc<PlayerContext>, start: F)
+where
+    F: std::future::Future<Output = Result<(), VideoError>> + 'static,
+{
+    let generation = context.media_generation();
+    let context = Rc::downgrade(context);
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(error) = start.await {
+            if let Some(context) = context.upgrade().filter(|context| context.media_generation() == generation) {
+                report_error_for(&context, &error).unwrap_or_default();
+            }
+        }
+    });
+}
+
+/// Loads `source` into the element afresh, continuing from `position`: the same
+/// source again when it stalled, or a mirror or alternative to fail over to.
+pub(crate) fn reload_source_for(context: &Rc<PlayerContext>, source: &VideoSource, position: f64) -> Result<(), VideoError> {
+    let video_element = context.video_element()?;
+    let dash = is_dash_source(&source.url, &source.mime_type);
+    let hls = is_hls_source(&source.url, &source.mime_type);
+    if (dash || hls) && is_media_source_supported() {
+        // The old stream stops once the generation moves on, and a new one takes over the element
+        context.next_media_generation();
+        if let Some(src) = video_element.get_attribute("src") {
+            if context.object_urls.borrow().contains(&src) {
+                context.revoke_object_url(&src)?;
+            }
+        }
+        // The new stream fetches from `position` and moves the element there once it has metadata
+        let abr_strategy = context.abr_strategy.get();
+        if dash {
+            start_stream_source(context, start_dash(context.clone(), source.url.clone(), abr_strategy, position));
+        } else {
+            start_stream_source(context, start_hls(context.clone(), source.url.clone(), abr_strategy, position));
+        }
+    } else {
+        video_element.set_src(&source.url);
+        video_element.load();
+        set_start_time(&video_element, position)?;
+    }
+    start_video_for(context).map_err(|e|
+        VideoError::VideoOperationFailed(format!("Failed to restart playback: {:?}", e)))
+}
+
+pub(crate) fn set_start_time(video_element: &HtmlVideoElement, start_time: f64) -> Result<(), VideoError> {
+    // Seeking is only honoured once metadata is available
+    if video_element.ready_state() >= HAVE_METADATA {
+        video_element.set_current_time(start_time);
+        return Ok(());
+    }
+
+    let video_element_clone = video_element.clone();
+    listen_once(video_element, "loadedmetadata", move || {
+        video_element_clone.set_current_time(start_time);
+    })
+}
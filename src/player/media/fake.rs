@@ -0,0 +1,268 @@
+use std::cell::{Cell, RefCell};
+use std::future::{ready, Future};
+use std::pin::pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+use crate::player::error::{Operation, VideoError};
+use crate::player::media::{MediaBackend, MediaSubscription};
+
+type Handlers = Rc<RefCell<Vec<(u64, &'static str, Rc<RefCell<Box<dyn FnMut()>>>)>>>;
+
+/// An in-memory media element for native tests. Time only moves when `advance` is
+/// called, and events fire synchronously the way a browser would queue them.
+pub struct FakeMedia {
+    paused: Cell<bool>,
+    ended: Cell<bool>,
+    current_time: Cell<f64>,
+    playback_rate: Cell<f64>,
+    volume: Cell<f64>,
+    muted: Cell<bool>,
+    duration: Cell<f64>,
+    buffered: RefCell<Vec<(f64, f64)>>,
+    // DOMException name `play` rejects with, standing in for a browser refusing to play
+    play_error: RefCell<Option<String>>,
+    // Refuse to play with sound, as browsers do before the viewer has interacted
+    block_unmuted: Cell<bool>,
+    fired: RefCell<Vec<&'static str>>,
+    handlers: Handlers,
+    next_handler: Cell<u64>,
+}
+
+impl FakeMedia {
+    /// A paused element at the start of media `duration` seconds long.
+    pub fn new(duration: f64) -> FakeMedia {
+        FakeMedia {
+            paused: Cell::new(true),
+            ended: Cell::new(false),
+            current_time: Cell::new(0.0),
+            playback_rate: Cell::new(1.0),
+            volume: Cell::new(1.0),
+            muted: Cell::new(false),
+            duration: Cell::new(duration),
+            buffered: RefCell::new(Vec::new()),
+            play_error: RefCell::new(None),
+            block_unmuted: Cell::new(false),
+            fired: RefCell::new(Vec::new()),
+            handlers: Rc::new(RefCell::new(Vec::new())),
+            next_handler: Cell::new(0),
+        }
+    }
+
+    /// Runs the virtual clock forward by `seconds` of wall time, moving playback on
+    /// at the playback rate and ending it at the duration.
+    pub fn advance(&self, seconds: f64) {
+        if self.paused.get() {
+            return;
+        }
+        let time = self.current_time.get() + seconds * self.playback_rate.get();
+        let duration = self.duration.get();
+        if duration.is_finite() && time >= duration {
+            self.current_time.set(duration);
+            self.fire("timeupdate");
+            self.paused.set(true);
+            self.ended.set(true);
+            self.fire("pause");
+            self.fire("ended");
+        } else {
+            self.current_time.set(time);
+            self.fire("timeupdate");
+        }
+    }
+
+    pub fn set_buffered(&self, ranges: Vec<(f64, f64)>) {
+        *self.buffered.borrow_mut() = ranges;
+        self.fire("progress");
+    }
+
+    /// Makes every later `play` reject with the `DOMException` named `name`, or
+    /// succeed again with `None`.
+    pub fn fail_play(&self, name: Option<&str>) {
+        *self.play_error.borrow_mut() = name.map(str::to_string);
+    }
+
+    /// Rejects `play` with `NotAllowedError` unless the element is muted.
+    pub fn block_unmuted_autoplay(&self, block: bool) {
+        self.block_unmuted.set(block);
+    }
+
+    /// Events fired since the last call, oldest first.
+    pub fn take_events(&self) -> Vec<&'static str> {
+        std::mem::take(&mut *self.fired.borrow_mut())
+    }
+
+    fn fire(&self, event: &'static str) {
+        self.fired.borrow_mut().push(event);
+        // Copied out so a handler can subscribe or unsubscribe while it runs
+        let handlers: Vec<_> = self.handlers.borrow()
+            .iter()
+            .filter(|(_, name, _)| *name == event)
+            .map(|(_, _, handler)| handler.clone())
+            .collect();
+        for handler in handlers {
+            (handler.borrow_mut())();
+        }
+    }
+}
+
+/// Runs a future that completes without waiting, as everything `FakeMedia` returns does.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("future did not complete"),
+    }
+}
+
+impl MediaBackend for FakeMedia {
+    fn play(&self) -> impl Future<Output = Result<(), VideoError>> {
+        let refusal = self.play_error.borrow().clone()
+            .or_else(|| (self.block_unmuted.get() && !self.muted.get()).then(|| "NotAllowedError".to_string()));
+        if let Some(name) = refusal {
+            return ready(Err(VideoError::from_dom_exception(Operation::Play, &name, "play() was refused")));
+        }
+        if self.ended.get() {
+            self.ended.set(false);
+            self.set_current_time(0.0);
+        }
+        if self.paused.get() {
+            self.paused.set(false);
+            self.fire("play");
+            self.fire("playing");
+        }
+        ready(Ok(()))
+    }
+
+    fn pause(&self) -> Result<(), VideoError> {
+        if !self.paused.get() {
+            self.paused.set(true);
+            self.fire("pause");
+        }
+        Ok(())
+    }
+
+    fn paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    fn ended(&self) -> bool {
+        self.ended.get()
+    }
+
+    fn current_time(&self) -> f64 {
+        self.current_time.get()
+    }
+
+    fn set_current_time(&self, seconds: f64) {
+        let duration = self.duration.get();
+        let seconds = if duration.is_finite() { seconds.clamp(0.0, duration) } else { seconds.max(0.0) };
+        self.current_time.set(seconds);
+        self.ended.set(false);
+        self.fire("seeking");
+        self.fire("seeked");
+    }
+
+    fn playback_rate(&self) -> f64 {
+        self.playback_rate.get()
+    }
+
+    fn set_playback_rate(&self, rate: f64) {
+        if self.playback_rate.replace(rate) != rate {
+            self.fire("ratechange");
+        }
+    }
+
+    fn volume(&self) -> f64 {
+        self.volume.get()
+    }
+
+    fn set_volume(&self, volume: f64) {
+        if self.volume.replace(volume) != volume {
+            self.fire("volumechange");
+        }
+    }
+
+    fn muted(&self) -> bool {
+        self.muted.get()
+    }
+
+    fn set_muted(&self, muted: bool) {
+        if self.muted.replace(muted) != muted {
+            self.fire("volumechange");
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration.get()
+    }
+
+    fn buffered(&self) -> Vec<(f64, f64)> {
+        self.buffered.borrow().clone()
+    }
+
+    fn listen(&self, event: &'static str, handler: Box<dyn FnMut()>) -> Result<MediaSubscription, VideoError> {
+        let id = self.next_handler.get();
+        self.next_handler.set(id + 1);
+        self.handlers.borrow_mut().push((id, event, Rc::new(RefCell::new(handler))));
+        let handlers = Rc::downgrade(&self.handlers);
+        Ok(MediaSubscription::new(move || {
+            if let Some(handlers) = handlers.upgrade() {
+                handlers.borrow_mut().retain(|(handler, _, _)| *handler != id);
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::media::listen_once;
+
+    #[test]
+    fn test_clock_moves_playback_until_the_end() {
+        let media = FakeMedia::new(10.0);
+        media.advance(5.0);
+        assert_eq!(media.current_time(), 0.0);
+
+        block_on(media.play()).unwrap();
+        media.set_playback_rate(2.0);
+        media.advance(3.0);
+        assert_eq!(media.current_time(), 6.0);
+        media.advance(3.0);
+        assert_eq!(media.current_time(), 10.0);
+        assert!(media.paused() && media.ended());
+        assert_eq!(media.take_events(), ["play", "playing", "ratechange", "timeupdate", "timeupdate", "pause", "ended"]);
+
+        // Playing again after the end starts over
+        block_on(media.play()).unwrap();
+        assert_eq!(media.current_time(), 0.0);
+        assert!(!media.ended());
+    }
+
+    #[test]
+    fn test_subscriptions_end_when_dropped() {
+        let media = FakeMedia::new(10.0);
+        let changes = Rc::new(Cell::new(0));
+        let counter = changes.clone();
+        let subscription = media.listen("volumechange", Box::new(move || counter.set(counter.get() + 1))).unwrap();
+        media.set_volume(0.5);
+        media.set_volume(0.5);
+        media.set_muted(true);
+        assert_eq!(changes.get(), 2);
+
+        drop(subscription);
+        media.set_muted(false);
+        assert_eq!(changes.get(), 2);
+
+        let seeks = Rc::new(Cell::new(0));
+        let counter = seeks.clone();
+        listen_once(&media, "seeked", move || counter.set(counter.get() + 1)).unwrap();
+        media.set_current_time(1.0);
+        media.set_current_time(2.0);
+        assert_eq!(seeks.get(), 1);
+
+        media.block_unmuted_autoplay(true);
+        assert!(matches!(block_on(media.play()), Err(VideoError::AutoplayBlocked(_))));
+        assert!(media.paused());
+        media.set_muted(true);
+        assert!(block_on(media.play()).is_ok());
+    }
+}
@@ -0,0 +1,165 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Event, HtmlMediaElement, HtmlVideoElement, TimeRanges};
+use crate::player::error::{Operation, VideoError};
+
+#[cfg(test)]
+pub mod fake;
+
+/// What the player needs from a media element. `HtmlVideoElement` is the real one;
+/// tests use `fake::FakeMedia`, whose clock they advance by hand.
+pub trait MediaBackend {
+    /// Asks to start playback; resolves once it has started, or with why it could not.
+    fn play(&self) -> impl Future<Output = Result<(), VideoError>>;
+    fn pause(&self) -> Result<(), VideoError>;
+    fn paused(&self) -> bool;
+    fn ended(&self) -> bool;
+    fn current_time(&self) -> f64;
+    fn set_current_time(&self, seconds: f64);
+    fn playback_rate(&self) -> f64;
+    fn set_playback_rate(&self, rate: f64);
+    fn volume(&self) -> f64;
+    fn set_volume(&self, volume: f64);
+    fn muted(&self) -> bool;
+    fn set_muted(&self, muted: bool);
+    /// NaN until the metadata has loaded, infinite for live streams.
+    fn duration(&self) -> f64;
+    /// The buffered ranges as `(start, end)` seconds, in order.
+    fn buffered(&self) -> Vec<(f64, f64)>;
+    /// Calls `handler` each time the element fires `event`, until the returned
+    /// subscription is dropped.
+    fn listen(&self, event: &'static str, handler: Box<dyn FnMut()>) -> Result<MediaSubscription, VideoError>;
+}
+
+/// Keeps a `MediaBackend::listen` handler registered; dropping it removes the handler.
+pub struct MediaSubscription {
+    release: Option<Box<dyn FnOnce()>>,
+}
+
+impl MediaSubscription {
+    pub fn new<F>(release: F) -> MediaSubscription
+    where
+        F: FnOnce() + 'static,
+    {
+        MediaSubscription { release: Some(Box::new(release)) }
+    }
+}
+
+impl Drop for MediaSubscription {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+/// Calls `handler` the next time `media` fires `event`, and then stops listening.
+pub fn listen_once<M, F>(media: &M, event: &'static str, handler: F) -> Result<(), VideoError>
+where
+    M: MediaBackend + ?Sized,
+    F: FnOnce() + 'static,
+{
+    // The subscription is kept alive by its own handler until the first call releases it
+    let subscription: Rc<RefCell<Option<MediaSubscription>>> = Rc::new(RefCell::new(None));
+    let pending = subscription.clone();
+    let mut handler = Some(handler);
+    let listening = media.listen(event, Box::new(move || {
+        let released = pending.borrow_mut().take();
+        if let Some(handler) = handler.take() {
+            handler();
+        }
+        drop(released);
+    }))?;
+    *subscription.borrow_mut() = Some(listening);
+    Ok(())
+}
+
+pub fn time_ranges(ranges: &TimeRanges) -> Vec<(f64, f64)> {
+    (0..ranges.length())
+        .filter_map(|index| Some((ranges.start(index).ok()?, ranges.end(index).ok()?)))
+        .collect()
+}
+
+impl MediaBackend for HtmlVideoElement {
+    fn play(&self) -> impl Future<Output = Result<(), VideoError>> {
+        let promise = HtmlMediaElement::play(self);
+        async move {
+            let promise = promise.map_err(|e| VideoError::from_js(Operation::Play, &e))?;
+            JsFuture::from(promise)
+                .await
+                .map(|_| ())
+                .map_err(|e| VideoError::from_js(Operation::Play, &e))
+        }
+    }
+
+    fn pause(&self) -> Result<(), VideoError> {
+        HtmlMediaElement::pause(self).map_err(|e| VideoError::VideoOperationFailed(format!("Failed to pause video: {:?}", e)))
+    }
+
+    fn paused(&self) -> bool {
+        HtmlMediaElement::paused(self)
+    }
+
+    fn ended(&self) -> bool {
+        HtmlMediaElement::ended(self)
+    }
+
+    fn current_time(&self) -> f64 {
+        HtmlMediaElement::current_time(self)
+    }
+
+    fn set_current_time(&self, seconds: f64) {
+        HtmlMediaElement::set_current_time(self, seconds)
+    }
+
+    fn playback_rate(&self) -> f64 {
+        HtmlMediaElement::playback_rate(self)
+    }
+
+    fn set_playback_rate(&self, rate: f64) {
+        HtmlMediaElement::set_playback_rate(self, rate)
+    }
+
+    fn volume(&self) -> f64 {
+        HtmlMediaElement::volume(self)
+    }
+
+    fn set_volume(&self, volume: f64) {
+        HtmlMediaElement::set_volume(self, volume)
+    }
+
+    fn muted(&self) -> bool {
+        HtmlMediaElement::muted(self)
+    }
+
+    fn set_muted(&self, muted: bool) {
+        HtmlMediaElement::set_muted(self, muted)
+    }
+
+    fn duration(&self) -> f64 {
+        HtmlMediaElement::duration(self)
+    }
+
+    fn buffered(&self) -> Vec<(f64, f64)> {
+        time_ranges(&HtmlMediaElement::buffered(self))
+    }
+
+    fn listen(&self, event: &'static str, mut handler: Box<dyn FnMut()>) -> Result<MediaSubscription, VideoError> {
+        let closure = Closure::wrap(Box::new(move |_: Event| handler()) as Box<dyn FnMut(Event)>);
+        self.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to add {} listener: {:?}", event, e)))?;
+        let element = self.clone();
+        Ok(MediaSubscription::new(move || {
+            element
+                .remove_event_listener_with_callback(event, closure.as_ref().unchecked_ref())
+                .unwrap_or_default();
+            // The handler may be what dropped the subscription, so it has to outlive this dispatch
+            wasm_bindgen_futures::spawn_local(async move {
+                drop(closure);
+            });
+        }))
+    }
+}
@@ -1,27 +1,63 @@
 use wasm_bindgen::prelude::*;
 use crate::logger::Logger;
-use crate::player::error::{show_error, hide_error, VideoError};
-use crate::player::get_element_by_id;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::captions::{render_captions_menu_for, update_captions_menu_item_for};
+use crate::player::quality::{render_quality_menu_for, update_quality_menu_item_for};
 
 #[wasm_bindgen]
 pub fn position_context_menu(event_x: f64, event_y: f64) -> Result<(), JsValue> {
-    Logger::info("Entering position_context_menu()").map_err(|e| {
+    position_context_menu_for(&default_context(), event_x, event_y)
+}
+
+#[wasm_bindgen]
+pub fn position_playback_speed_menu(event_x: f64, event_y: f64) -> Result<(), JsValue> {
+    position_playback_speed_menu_for(&default_context(), event_x, event_y)
+}
+
+#[wasm_bindgen]
+pub fn position_quality_menu(event_x: f64, event_y: f64) -> Result<(), JsValue> {
+    position_quality_menu_for(&default_context(), event_x, event_y)
+}
+
+#[wasm_bindgen]
+pub fn position_captions_menu(event_x: f64, event_y: f64) -> Result<(), JsValue> {
+    position_captions_menu_for(&default_context(), event_x, event_y)
+}
+
+#[wasm_bindgen]
+pub fn toggle_context_menu(x: f64, y: f64) -> Result<(), JsValue> {
+    toggle_context_menu_for(&default_context(), x, y)
+}
+
+#[wasm_bindgen]
+pub fn toggle_playback_speed_menu(x: f64, y: f64) -> Result<(), JsValue> {
+    toggle_playback_speed_menu_for(&default_context(), x, y)
+}
+
+#[wasm_bindgen]
+pub fn hide_menus() -> Result<(), JsValue> {
+    hide_menus_for(&default_context())
+}
+
+pub fn position_context_menu_for(context: &PlayerContext, event_x: f64, event_y: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering position_context_menu()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let window = web_sys::window().ok_or_else(|| {
         let error = VideoError::WindowNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let _document = window.document().ok_or_else(|| {
         let error = VideoError::DocumentNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     
-    let context_menu = get_element_by_id("contextMenu")?;
+    let context_menu = context.element(&context.ids.context_menu)?;
     
     // Position the menu at the event coordinates
     context_menu.set_attribute("style", &format!(
@@ -30,158 +66,251 @@ pub fn position_context_menu(event_x: f64, event_y: f64) -> Result<(), JsValue>
         event_x
     )).map_err(|e| {
         let error = VideoError::VideoOperationFailed(format!("Failed to position menu: {:?}", e));
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     
     // Toggle show class and hide playback speed menu
-    context_menu.set_attribute("class", "context-menu show")
+    context_menu.set_attribute("class", &context.classes.context_menu_show)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to show context menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
     
-    let playback_speed_menu = get_element_by_id("playbackSpeedMenu")?;
-    playback_speed_menu.set_attribute("class", "playback-speed-menu")
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
+    playback_speed_menu.set_attribute("class", &context.classes.playback_speed_menu)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to hide playback speed menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
+    hide_optional_menus_for(context)?;
+    update_quality_menu_item_for(context)?;
+    update_captions_menu_item_for(context)?;
     
-    hide_error()?;
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn position_playback_speed_menu(event_x: f64, event_y: f64) -> Result<(), JsValue> {
-    Logger::info("Entering position_playback_speed_menu()").map_err(|e| {
+pub fn position_playback_speed_menu_for(context: &PlayerContext, event_x: f64, event_y: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering position_playback_speed_menu()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let window = web_sys::window().ok_or_else(|| {
         let error = VideoError::WindowNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     let _document = window.document().ok_or_else(|| {
         let error = VideoError::DocumentNotFound;
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     
-    let playback_speed_menu = get_element_by_id("playbackSpeedMenu")?;
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
     
     // Position the menu at the event coordinates with a small offset
     playback_speed_menu.set_attribute("style", &format!("top: {}px; left: {}px", event_y, event_x + 5.0))
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to position menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
     
-    playback_speed_menu.set_attribute("class", "playback-speed-menu show")
+    playback_speed_menu.set_attribute("class", &context.classes.playback_speed_menu_show)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to show menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
+    hide_optional_menus_for(context)?;
     
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn toggle_context_menu(x: f64, y: f64) -> Result<(), JsValue> {
-    Logger::info("Entering toggle_context_menu()").map_err(|e| {
+/// Fills the quality menu with the current renditions and shows it next to the context menu.
+pub fn position_quality_menu_for(context: &PlayerContext, event_x: f64, event_y: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering position_quality_menu()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    render_quality_menu_for(context)?;
+    hide_optional_menus_for(context)?;
+    let quality_menu = context.element(&context.ids.quality_menu)?;
+
+    quality_menu.set_attribute("style", &format!("top: {}px; left: {}px", event_y, event_x + 5.0))
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to position menu: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    quality_menu.set_attribute("class", &context.classes.quality_menu_show)
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to show menu: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
+    playback_speed_menu.set_attribute("class", &context.classes.playback_speed_menu)
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to hide playback speed menu: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    Ok(())
+}
+
+/// Fills the captions menu with the loaded tracks and shows it next to the context menu.
+pub fn position_captions_menu_for(context: &PlayerContext, event_x: f64, event_y: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering position_captions_menu()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    render_captions_menu_for(context)?;
+    hide_optional_menus_for(context)?;
+    let captions_menu = context.element(&context.ids.captions_menu)?;
+
+    captions_menu.set_attribute("style", &format!("top: {}px; left: {}px", event_y, event_x + 5.0))
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to position menu: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    captions_menu.set_attribute("class", &context.classes.caption_menu_show)
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to show menu: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
+    playback_speed_menu.set_attribute("class", &context.classes.playback_speed_menu)
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to hide playback speed menu: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    Ok(())
+}
+
+/// Hides the quality and captions menus. Both are optional, so a page without them is left alone.
+fn hide_optional_menus_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let menus = [
+        (&context.ids.quality_menu, &context.classes.quality_menu),
+        (&context.ids.captions_menu, &context.classes.caption_menu),
+    ];
+    for (id, class_name) in menus {
+        let Ok(menu) = context.element(id) else {
+            continue;
+        };
+        menu.set_attribute("class", class_name)
+            .map_err(|e| {
+                let error = VideoError::VideoOperationFailed(format!("Failed to hide #{}: {:?}", id, e));
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?;
+    }
+    Ok(())
+}
+
+pub fn toggle_context_menu_for(context: &PlayerContext, x: f64, y: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering toggle_context_menu()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     
-    let context_menu = get_element_by_id("contextMenu")?;
+    let context_menu = context.element(&context.ids.context_menu)?;
     
     // Position the menu at the event coordinates
     context_menu.set_attribute("style", &format!("left: {}px; top: {}px;", x, y))
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to position menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
     
     // Toggle show class and hide playback speed menu
-    context_menu.set_attribute("class", "context-menu show")
+    context_menu.set_attribute("class", &context.classes.context_menu_show)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to show context menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
     
-    let playback_speed_menu = get_element_by_id("playbackSpeedMenu")?;
-    playback_speed_menu.set_attribute("class", "playback-speed-menu")
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
+    playback_speed_menu.set_attribute("class", &context.classes.playback_speed_menu)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to hide playback speed menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
+    hide_optional_menus_for(context)?;
+    update_quality_menu_item_for(context)?;
+    update_captions_menu_item_for(context)?;
     
-    hide_error()?;
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn toggle_playback_speed_menu(x: f64, y: f64) -> Result<(), JsValue> {
-    Logger::info("Entering toggle_playback_speed_menu()").map_err(|e| {
+pub fn toggle_playback_speed_menu_for(context: &PlayerContext, x: f64, y: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering toggle_playback_speed_menu()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
     
-    let playback_speed_menu = get_element_by_id("playbackSpeedMenu")?;
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
     
     // Position the menu at the event coordinates
     playback_speed_menu.set_attribute("style", &format!("left: {}px; top: {}px;", x, y))
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to position menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
     
-    playback_speed_menu.set_attribute("class", "playback-speed-menu show")
+    playback_speed_menu.set_attribute("class", &context.classes.playback_speed_menu_show)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to show menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
+    hide_optional_menus_for(context)?;
     
-    hide_error()?;
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn hide_menus() -> Result<(), JsValue> {
-    Logger::info("Entering hide_menus()").map_err(|e| {
+pub fn hide_menus_for(context: &PlayerContext) -> Result<(), JsValue> {
+    Logger::trace("Entering hide_menus()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let playback_speed_menu = get_element_by_id("playbackSpeedMenu")?;
-    let context_menu = get_element_by_id("contextMenu")?;
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
+    let context_menu = context.element(&context.ids.context_menu)?;
     
-    playback_speed_menu.set_attribute("class", "playback-speed-menu")
+    playback_speed_menu.set_attribute("class", &context.classes.playback_speed_menu)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to hide playback speed menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
-    context_menu.set_attribute("class", "context-menu")
+    context_menu.set_attribute("class", &context.classes.context_menu)
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to hide context menu: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
+    hide_optional_menus_for(context)?;
     
-    hide_error()?;
     Ok(())
 } 
\ No newline at end of file
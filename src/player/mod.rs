@@ -1,28 +1,143 @@
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use crate::player::error::VideoError;
-use crate::player::event_listeners::setup_event_listeners;
+use crate::logger::{page_log_level, Logger};
+use crate::player::context::{claim_default_context, default_context, register_live_context, PlayerContext};
+use crate::player::error::{Severity, VideoError};
+use crate::player::event_listeners::setup_event_listeners_for;
+use crate::player::loader::load_media_for;
+use crate::player::playlist::Queue;
+use crate::player::play_pause::start_video_for;
+use crate::player::preferences::load_preferences;
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
+use crate::player::watchdog::start_watchdog_for;
 mod dom;
-pub use dom::{get_video_element, get_element_by_id,add_video_source};
+pub use dom::{get_element_by_id, get_element_by_action};
 mod element_ids;
 pub use element_ids::ElementIds;
+pub use config::PlayerConfig;
+pub use instance::Player;
 
+/// Creates an independent player bound to `element_ids`.
+/// The first player initialised on a page also backs the exported free functions.
 #[wasm_bindgen]
-pub fn init_video_player(element_ids: ElementIds) -> Result<(), VideoError> {
-    add_video_source("https://storage.googleapis.com/gtv-videos-bucket/sample/BigBuckBunny.mp4", "video/mp4").map_err(|e| 
-        VideoError::VideoOperationFailed(format!("Failed to add video source: {:?}", e)))?;
-    setup_event_listeners(element_ids).map_err(|e| 
+pub fn init_video_player(element_ids: ElementIds, config: PlayerConfig) -> Result<Player, VideoError> {
+    config.validate()?;
+    let context = PlayerContext::new(element_ids, config.element_classes.clone());
+    // Tears down any player previously bound to the same video element
+    register_live_context(&context);
+    apply_config(&context, &config)?;
+    setup_event_listeners_for(&context).map_err(|e|
         VideoError::VideoOperationFailed(format!("Failed to setup event listeners: {:?}", e)))?;
+    claim_default_context(&context);
+    start_watchdog_for(&context);
+    if config.autoplay {
+        start_video_for(&context).map_err(|e|
+            VideoError::VideoOperationFailed(format!("Failed to start playback: {:?}", e)))?;
+    }
+    Ok(Player::new(context))
+}
+
+/// Tears down the default player instance.
+#[wasm_bindgen]
+pub fn destroy_video_player() {
+    default_context().destroy();
+}
+
+fn apply_config(context: &Rc<PlayerContext>, config: &PlayerConfig) -> Result<(), VideoError> {
+    // The page's own setting wins, so logging can be turned up on a live site
+    if let Some(level) = page_log_level().or(config.log_level) {
+        Logger::set_level(level);
+    }
+    let video_element = context.video_element()?;
+    // What the viewer chose on earlier visits; storage that is unavailable gives none
+    let preferences = config.preferences_key.as_deref().map(load_preferences).unwrap_or_default();
+    *context.preferences.borrow_mut() = preferences.clone();
+    *context.preferences_key.borrow_mut() = config.preferences_key.clone();
+    context.abr_strategy.set(config.abr_strategy);
+    {
+        let mut recovery = context.recovery.borrow_mut();
+        recovery.policy.stall_timeout = config.stall_timeout;
+        recovery.policy.max_retries = config.max_retries;
+    }
+    {
+        let mut errors = context.errors.borrow_mut();
+        for severity in [Severity::Info, Severity::Warning, Severity::Fatal] {
+            errors.set_timeout(severity, config.error_timeout(severity));
+        }
+    }
+    context.resume.borrow_mut().mode = config.resume;
+    {
+        let mut gapless = context.gapless.borrow_mut();
+        gapless.enabled = config.gapless;
+        gapless.crossfade = config.crossfade;
+    }
+
+    // A playlist starts on its first item
+    if !config.playlist.is_empty() {
+        let mut playlist = Queue::new(config.playlist.clone());
+        playlist.set_current(0);
+        *context.playlist.borrow_mut() = playlist;
+    }
+    load_media_for(context, &config.media_item(), config.start_time)?;
+    video_element.set_preload(config.preload.as_str());
+    // Autoplay goes through play() rather than the attribute, so a refusal can be handled
+    video_element.set_autoplay(false);
+    context.muted_autoplay.set(config.muted_autoplay);
+    video_element.set_loop(config.loop_playback);
+    // The level and mute state from the last visit, unless the page asks to start muted
+    let muted = config.muted || preferences.muted.unwrap_or(false);
+    if let Some(volume) = preferences.volume {
+        video_element.set_volume(volume);
+    }
+    video_element.set_default_muted(config.muted);
+    video_element.set_muted(muted);
+    dispatch_for(context, Action::VolumeChanged { volume: video_element.volume(), muted })?;
+    if let Some(speed) = preferences.playback_speed {
+        // The default rate survives the reload a source switch does
+        video_element.set_default_playback_rate(speed);
+        video_element.set_playback_rate(speed);
+        dispatch_for(context, Action::PlaybackSpeedChanged { speed })?;
+    }
+
+    *context.keymap.borrow_mut() = config.keymap.clone();
     Ok(())
 }
 
+pub mod abr;
+pub mod captions;
+pub mod config;
+pub mod context;
+pub mod dash;
 pub mod error;
+pub mod error_overlay;
+pub mod events;
 pub mod fullscreen;
+pub mod gapless;
+pub mod instance;
+pub mod keyboard;
+pub mod listeners;
+pub mod loader;
+pub mod media;
 pub mod menu;
 pub mod mute;
 pub mod picture_in_picture;
 pub mod play_pause;
 pub mod state;
+pub mod store;
 pub mod time;
 pub mod download;
 pub mod event_listeners;
-pub mod playback_speed;
\ No newline at end of file
+pub mod playback_speed;
+pub mod playlist;
+pub mod preferences;
+pub mod quality;
+pub mod resume;
+pub mod seek_bar;
+pub mod fetch;
+pub mod hls;
+pub mod mse;
+pub mod segments;
+pub mod url;
+pub mod volume;
+pub mod watchdog;
@@ -0,0 +1,328 @@
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AddEventListenerOptions, CustomEvent, CustomEventInit, Event, EventTarget, HtmlVideoElement, MediaSource, MediaSourceReadyState, SourceBuffer, SourceBufferAppendMode, TimeRanges};
+use js_sys::{ArrayBuffer, Promise, Uint8Array};
+use crate::player::abr::{AbrController, AbrStrategyKind, AbrSwitch, SwitchReason};
+use crate::player::context::PlayerContext;
+use crate::player::error::{report_error_for, Operation, VideoError};
+use crate::player::events::{publish_for, PlayerEvent};
+use crate::player::quality::{apply_quality_preference_for, QualitySource};
+use crate::player::fetch::{fetch_bytes, fetch_text, sleep_ms};
+use crate::player::loader::set_start_time;
+use crate::player::segments::{SegmentKind, SegmentProvider};
+
+// Stop fetching once this many seconds are buffered ahead of the playhead
+const MAX_BUFFER_AHEAD: f64 = 30.0;
+// Keep this many seconds behind the playhead when the buffer is full
+const BACK_BUFFER: f64 = 10.0;
+// How long the pipeline idles when it has nothing to do
+const IDLE_INTERVAL_MS: i32 = 250;
+
+pub fn is_media_source_supported() -> bool {
+    js_sys::Reflect::has(&js_sys::global(), &JsValue::from_str("MediaSource")).unwrap_or(false)
+}
+
+/// Resolves with the next `event` fired on `target`.
+pub async fn wait_for_event(target: &EventTarget, event: &str) -> Result<Event, VideoError> {
+    let options = AddEventListenerOptions::new();
+    options.set_once(true);
+    let mut result = Ok(());
+    let promise = Promise::new(&mut |resolve, _reject| {
+        result = target.add_event_listener_with_callback_and_add_event_listener_options(event, &resolve, &options);
+    });
+    result.map_err(|e| VideoError::MediaSourceError(format!("Failed to wait for {}: {:?}", event, e)))?;
+    JsFuture::from(promise)
+        .await
+        .map_err(|e| VideoError::MediaSourceError(format!("Failed to wait for {}: {:?}", event, e)))?
+        .dyn_into()
+        .map_err(|e| VideoError::MediaSourceError(format!("{} did not deliver an Event: {:?}", event, e)))
+}
+
+/// Runs every track provider through its own `SourceBuffer` on one `MediaSource` attached
+/// to the player's video element, reporting failures in the player's error element.
+/// The element is moved to `start_time`, where the providers are expected to begin.
+pub fn start_stream(context: &Rc<PlayerContext>, tracks: Vec<Rc<RefCell<dyn SegmentProvider>>>, abr_strategy: AbrStrategyKind, start_time: f64) {
+    let weak = Rc::downgrade(context);
+    let generation = context.media_generation();
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(error) = run_stream(weak.clone(), generation, tracks, abr_strategy, start_time).await {
+            report(&weak, generation, &error);
+        }
+    });
+}
+
+/// Opens the `MediaSource` and starts one feed loop per track. The stream is ended once
+/// every track has handed out its last segment. The first track offering more than one
+/// rendition is driven by an `AbrController`. Only weak references to the player are
+/// held so a dropped player stops streaming, and loading other media into the player
+/// (a new `generation`) stops it too.
+pub async fn run_stream(context: Weak<PlayerContext>, generation: u64, tracks: Vec<Rc<RefCell<dyn SegmentProvider>>>, abr_strategy: AbrStrategyKind, start_time: f64) -> Result<(), VideoError> {
+    let video_element = upgrade(&context)?.video_element()?;
+
+    let mut mime_types = Vec::with_capacity(tracks.len());
+    for provider in &tracks {
+        if provider.borrow().needs_manifest() {
+            load_manifest(provider).await?;
+        }
+        let mime_type = provider.borrow().mime_type();
+        if !MediaSource::is_type_supported(&mime_type) {
+            return Err(VideoError::UnsupportedSourceType(mime_type));
+        }
+        mime_types.push(mime_type);
+    }
+    // Other media may have been loaded while the manifests were on their way
+    if !is_current(&context, generation) {
+        return Ok(());
+    }
+
+    let media_source = MediaSource::new()
+        .map_err(|e| VideoError::MediaSourceError(format!("Failed to create MediaSource: {:?}", e)))?;
+    let object_url = web_sys::Url::create_object_url_with_source(&media_source)
+        .map_err(|e| VideoError::MediaSourceError(format!("Failed to create object URL: {:?}", e)))?;
+    upgrade(&context)?.track_object_url(&object_url);
+    video_element.set_src(&object_url);
+    // A new src starts the element at 0, so the position is applied once its metadata is in
+    if start_time > 0.0 {
+        set_start_time(&video_element, start_time)?;
+    }
+    wait_for_event(&media_source, "sourceopen").await?;
+    if !is_current(&context, generation) {
+        return Ok(());
+    }
+
+    // Every SourceBuffer has to exist before the first append
+    let mut source_buffers = Vec::with_capacity(tracks.len());
+    for mime_type in &mime_types {
+        let source_buffer = media_source
+            .add_source_buffer(mime_type)
+            .map_err(|e| VideoError::MediaSourceError(format!("Failed to add SourceBuffer for {}: {:?}", mime_type, e)))?;
+        // Segments are placed one after another; timestampOffset moves the group on seeks and discontinuities
+        source_buffer.set_mode(SourceBufferAppendMode::Sequence);
+        source_buffers.push(source_buffer);
+    }
+    let duration = tracks[0].borrow().duration();
+    media_source.set_duration(duration.unwrap_or(f64::INFINITY));
+
+    let adaptive = tracks.iter().position(|provider| provider.borrow().renditions().len() > 1);
+    let remaining = Rc::new(Cell::new(tracks.len()));
+    for (index, (provider, source_buffer)) in tracks.into_iter().zip(source_buffers).enumerate() {
+        let abr = if Some(index) == adaptive {
+            let mut controller = abr_strategy.controller();
+            controller.set_current(provider.borrow().rendition());
+            let abr = Rc::new(RefCell::new(controller));
+            let player = upgrade(&context)?;
+            player.set_quality_source(QualitySource::Adaptive {
+                provider: provider.clone(),
+                abr: abr.clone(),
+            });
+            apply_quality_preference_for(&player).unwrap_or_default();
+            Some(abr)
+        } else {
+            None
+        };
+        let context = context.clone();
+        let video_element = video_element.clone();
+        let media_source = media_source.clone();
+        let remaining = remaining.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match feed_track(&context, generation, &video_element, &media_source, &source_buffer, provider, abr).await {
+                Ok(()) => {
+                    remaining.set(remaining.get() - 1);
+                    if remaining.get() == 0 && media_source.ready_state() == MediaSourceReadyState::Open {
+                        media_source.end_of_stream().unwrap_or_default();
+                    }
+                }
+                Err(error) => report(&context, generation, &error),
+            }
+        });
+    }
+    Ok(())
+}
+
+async fn feed_track(
+    context: &Weak<PlayerContext>,
+    generation: u64,
+    video_element: &HtmlVideoElement,
+    media_source: &MediaSource,
+    source_buffer: &SourceBuffer,
+    provider: Rc<RefCell<dyn SegmentProvider>>,
+    abr: Option<Rc<RefCell<AbrController>>>,
+) -> Result<(), VideoError> {
+    let mut mime_type = provider.borrow().mime_type();
+    let mut reset_timeline = false;
+    let mut handled_seek: Option<f64> = None;
+
+    loop {
+        if !is_current(context, generation) || media_source.ready_state() != MediaSourceReadyState::Open {
+            return Ok(());
+        }
+        let buffered = source_buffer
+            .buffered()
+            .map_err(|e| VideoError::MediaSourceError(format!("Failed to read buffered ranges: {:?}", e)))?;
+
+        // A seek outside the buffered ranges restarts segment selection at the new position
+        let current_time = video_element.current_time();
+        if video_element.seeking() && handled_seek != Some(current_time) && !is_buffered(&buffered, current_time) {
+            handled_seek = Some(current_time);
+            provider.borrow_mut().seek(current_time);
+            source_buffer.abort().unwrap_or_default();
+            reset_timeline = true;
+        } else if !video_element.seeking() {
+            handled_seek = None;
+        }
+
+        if buffered_ahead(&buffered, current_time) >= MAX_BUFFER_AHEAD {
+            trim_back_buffer(source_buffer, current_time).await?;
+            sleep_ms(IDLE_INTERVAL_MS).await?;
+            continue;
+        }
+
+        if provider.borrow().needs_manifest() {
+            load_manifest(&provider).await?;
+            continue;
+        }
+
+        let next_segment = provider.borrow_mut().next_segment();
+        match next_segment {
+            Some(segment) if segment.kind == SegmentKind::Index => {
+                let data = fetch_bytes(&segment.url, segment.byte_range).await?;
+                provider.borrow_mut().index_loaded(&Uint8Array::new(&data).to_vec())?;
+            }
+            Some(segment) => {
+                let requested_at = js_sys::Date::now();
+                let data = fetch_bytes(&segment.url, segment.byte_range).await?;
+                if segment.kind == SegmentKind::Init {
+                    // A rendition switch may change codecs, which the SourceBuffer has to be told about
+                    let next_mime_type = provider.borrow().mime_type();
+                    if next_mime_type != mime_type {
+                        source_buffer
+                            .change_type(&next_mime_type)
+                            .map_err(|e| VideoError::MediaSourceError(format!("Failed to change SourceBuffer type to {}: {:?}", next_mime_type, e)))?;
+                        mime_type = next_mime_type;
+                    }
+                }
+                if segment.kind == SegmentKind::Media && (segment.discontinuity || reset_timeline) {
+                    source_buffer.set_timestamp_offset(segment.start);
+                    reset_timeline = false;
+                }
+                append(media_source, source_buffer, &data, current_time).await?;
+
+                if let (Some(abr), SegmentKind::Media) = (&abr, segment.kind) {
+                    abr.borrow_mut().record_download(data.byte_length() as u64, js_sys::Date::now() - requested_at);
+                    let buffer_level = source_buffer
+                        .buffered()
+                        .map(|buffered| buffered_ahead(&buffered, video_element.current_time()))
+                        .unwrap_or(0.0);
+                    let renditions = provider.borrow().renditions();
+                    let switch = abr.borrow_mut().choose(&renditions, buffer_level, segment.duration, js_sys::Date::now() / 1000.0);
+                    if let Some(switch) = switch {
+                        provider.borrow_mut().select_rendition(switch.index);
+                        dispatch_quality_change(&*upgrade(context)?, &switch);
+                    }
+                }
+            }
+            None if provider.borrow().is_complete() => return Ok(()),
+            None => {
+                let refresh_interval = provider.borrow().refresh_interval();
+                match refresh_interval {
+                    Some(interval) => {
+                        sleep_ms((interval * 1000.0) as i32).await?;
+                        load_manifest(&provider).await?;
+                    }
+                    None => sleep_ms(IDLE_INTERVAL_MS).await?,
+                }
+            }
+        }
+    }
+}
+
+/// Whether the player still exists and still plays the media this stream was started for.
+fn is_current(context: &Weak<PlayerContext>, generation: u64) -> bool {
+    context.upgrade().is_some_and(|context| context.media_generation() == generation)
+}
+
+// Failures of a stream the player has already moved on from are not worth showing
+fn report(context: &Weak<PlayerContext>, generation: u64, error: &VideoError) {
+    if let Some(context) = context.upgrade().filter(|context| context.media_generation() == generation) {
+        report_error_for(&context, error).unwrap_or_default();
+    }
+}
+
+/// Tells the page about a rendition switch, on the player's event bus and with a
+/// `qualitychange` event on the video element.
+pub(crate) fn dispatch_quality_change(context: &PlayerContext, switch: &AbrSwitch) {
+    publish_for(context, PlayerEvent::QualityChange {
+        quality: Some(switch.index),
+        automatic: switch.reason == SwitchReason::Abr,
+        bandwidth: Some(switch.bandwidth),
+        height: switch.height,
+    });
+    let init = CustomEventInit::new();
+    init.set_detail(&serde_wasm_bindgen::to_value(switch).unwrap_or(JsValue::NULL));
+    if let (Ok(event), Ok(video_element)) = (CustomEvent::new_with_event_init_dict("qualitychange", &init), context.video_element()) {
+        video_element.dispatch_event(&event).unwrap_or_default();
+    }
+}
+
+fn upgrade(context: &Weak<PlayerContext>) -> Result<Rc<PlayerContext>, VideoError> {
+    context
+        .upgrade()
+        .ok_or_else(|| VideoError::StateError("Player was dropped while streaming".to_string()))
+}
+
+async fn load_manifest(provider: &Rc<RefCell<dyn SegmentProvider>>) -> Result<(), VideoError> {
+    let url = provider.borrow().manifest_url();
+    let text = fetch_text(&url).await?;
+    provider.borrow_mut().update_manifest(&text)
+}
+
+async fn append(media_source: &MediaSource, source_buffer: &SourceBuffer, data: &ArrayBuffer, current_time: f64) -> Result<(), VideoError> {
+    if media_source.ready_state() != MediaSourceReadyState::Open {
+        return Ok(());
+    }
+    if source_buffer.append_buffer_with_array_buffer(data).is_err() {
+        // QuotaExceededError: drop what has already been played and try once more
+        trim_back_buffer(source_buffer, current_time).await?;
+        source_buffer
+            .append_buffer_with_array_buffer(data)
+            .map_err(|e| match VideoError::from_js(Operation::Other, &e) {
+                quota @ VideoError::QuotaExceeded(_) => quota,
+                _ => VideoError::MediaSourceError(format!("Failed to append segment: {:?}", e)),
+            })?;
+    }
+    wait_for_event(source_buffer, "updateend").await?;
+    Ok(())
+}
+
+async fn trim_back_buffer(source_buffer: &SourceBuffer, current_time: f64) -> Result<(), VideoError> {
+    let Ok(buffered) = source_buffer.buffered() else {
+        return Ok(());
+    };
+    let trim_end = current_time - BACK_BUFFER;
+    if buffered.length() == 0 || trim_end <= buffered.start(0).unwrap_or(trim_end) {
+        return Ok(());
+    }
+    source_buffer
+        .remove(0.0, trim_end)
+        .map_err(|e| VideoError::MediaSourceError(format!("Failed to trim buffer: {:?}", e)))?;
+    wait_for_event(source_buffer, "updateend").await?;
+    Ok(())
+}
+
+fn is_buffered(ranges: &TimeRanges, time: f64) -> bool {
+    (0..ranges.length()).any(|index| {
+        let start = ranges.start(index).unwrap_or(f64::MAX);
+        let end = ranges.end(index).unwrap_or(f64::MIN);
+        start <= time && time < end
+    })
+}
+
+fn buffered_ahead(ranges: &TimeRanges, time: f64) -> f64 {
+    (0..ranges.length())
+        .filter_map(|index| Some((ranges.start(index).ok()?, ranges.end(index).ok()?)))
+        .find(|(start, end)| *start <= time && time < *end)
+        .map(|(_, end)| end - time)
+        .unwrap_or(0.0)
+}
@@ -1,95 +1,151 @@
 use wasm_bindgen::prelude::*;
 use crate::logger::Logger;
-use crate::player::{get_video_element, get_element_by_id};
-use crate::player::error::{show_error, hide_error, VideoError};
-use crate::player::state::VIDEO_STATE;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::media::MediaBackend;
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
+use crate::player::volume::{remember_volume_for, restore_volume_for};
 
 #[wasm_bindgen]
 pub fn toggle_mute() -> Result<bool, JsValue> {
-    Logger::info("Entering toggle_mute()").map_err(|e| {
+    toggle_mute_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn is_video_muted() -> Result<bool, JsValue> {
+    is_video_muted_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn set_mute_button_text(is_muted: bool) -> Result<(), JsValue> {
+    set_mute_button_text_for(&default_context(), is_muted)
+}
+
+#[wasm_bindgen]
+pub fn update_mute_button_text() -> Result<(), JsValue> {
+    update_mute_button_text_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn get_is_muted() -> bool {
+    get_is_muted_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn set_is_muted(value: bool) -> Result<(), JsValue> {
+    set_is_muted_for(&default_context(), value)
+}
+
+pub fn toggle_mute_for(context: &PlayerContext) -> Result<bool, JsValue> {
+    Logger::trace("Entering toggle_mute()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let video_element = get_video_element()?;
+    let video_element = context.video_element()?;
     let muted = !video_element.muted();
-    
-    Logger::info(&format!("Toggling mute state to: {}", muted))
+
+    Logger::debug(&format!("Toggling mute state to: {}", muted))
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(e.to_string());
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
-    video_element.set_muted(muted);
-    set_mute_button_text(muted)?;
-    
-    hide_error()?;
+    apply_muted_for(context, muted)?;
+
     Ok(video_element.muted())
 }
 
-#[wasm_bindgen]
-pub fn is_video_muted() -> Result<bool, JsValue> {
-    Logger::info("Entering is_video_muted()").map_err(|e| {
+/// Mutes or unmutes `media`, unmuting at `restore_volume` if the level is at zero,
+/// and returns the action recording the result.
+pub fn apply_muted(media: &impl MediaBackend, muted: bool, restore_volume: f64) -> Action {
+    // Unmuting at zero volume would stay silent, so bring back the last level
+    if !muted && media.volume() == 0.0 {
+        media.set_volume(restore_volume);
+    }
+    media.set_muted(muted);
+    Action::VolumeChanged { volume: media.volume(), muted: media.muted() }
+}
+
+/// Mutes or unmutes the element and records the result in the player's state.
+fn apply_muted_for(context: &PlayerContext, muted: bool) -> Result<(), JsValue> {
+    let video_element = context.video_element()?;
+    let action = apply_muted(&video_element, muted, restore_volume_for(context));
+    remember_volume_for(context, &action);
+    dispatch_for(context, action)?;
+    Ok(())
+}
+
+pub fn is_video_muted_for(context: &PlayerContext) -> Result<bool, JsValue> {
+    Logger::trace("Entering is_video_muted()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let video_element = get_video_element()?;
+    let video_element = context.video_element()?;
     Ok(video_element.muted())
 }
 
-#[wasm_bindgen]
-pub fn set_mute_button_text(is_muted: bool) -> Result<(), JsValue> {
-    Logger::info("Entering set_mute_button_text()").map_err(|e| {
+pub fn set_mute_button_text_for(context: &PlayerContext, is_muted: bool) -> Result<(), JsValue> {
+    Logger::trace("Entering set_mute_button_text()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let button = get_element_by_id("muteButton")?;
+    let button = context.element(&context.ids.mute_button)?;
     button.set_text_content(Some(if is_muted { "Unmute" } else { "Mute" }));
-    hide_error()?;
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn update_mute_button_text() -> Result<(), JsValue> {
-    Logger::info("Entering update_mute_button_text()").map_err(|e| {
+pub fn update_mute_button_text_for(context: &PlayerContext) -> Result<(), JsValue> {
+    Logger::trace("Entering update_mute_button_text()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let is_muted = is_video_muted()?;
-    let button = get_element_by_id("muteButton")?;
+    let is_muted = is_video_muted_for(context)?;
+    let button = context.element(&context.ids.mute_button)?;
     button.set_text_content(Some(if is_muted { "Unmute" } else { "Mute" }));
-    hide_error()?;
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn get_is_muted() -> bool {
-    Logger::info("Entering get_is_muted()").map_err(|e| {
+pub fn get_is_muted_for(context: &PlayerContext) -> bool {
+    Logger::trace("Entering get_is_muted()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     }).unwrap_or_default();
-    VIDEO_STATE.lock().unwrap().is_muted
+    context.state().map(|state| state.is_muted).unwrap_or_default()
 }
 
-#[wasm_bindgen]
-pub fn set_is_muted(value: bool) -> Result<(), JsValue> {
-    Logger::info("Entering set_is_muted()").map_err(|e| {
+pub fn set_is_muted_for(context: &PlayerContext, value: bool) -> Result<(), JsValue> {
+    Logger::trace("Entering set_is_muted()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let mut result = VIDEO_STATE.lock().map_err(|e| {
-        let error = VideoError::StateError(format!("Failed to lock state: {:?}", e));
-        show_error(&error.to_string()).unwrap_or_default();
-        error
-    })?;
-    if result.is_muted != value {
-        result.is_muted = value;
-    }
-    hide_error()?;
+    apply_muted_for(context, value)?;
     Ok(())
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::media::fake::FakeMedia;
+    use crate::player::state::{reduce, VideoState};
+
+    #[test]
+    fn test_mute_keeps_element_and_state_together() {
+        let media = FakeMedia::new(10.0);
+        let state = reduce(&VideoState::default(), &apply_muted(&media, true, 1.0));
+        assert!(media.muted() && state.is_muted);
+
+        // Unmuting at zero brings back the level to restore
+        media.set_volume(0.0);
+        let state = reduce(&state, &apply_muted(&media, false, 0.6));
+        assert!(!media.muted() && !state.is_muted);
+        assert_eq!((media.volume(), state.volume), (0.6, 0.6));
+        assert_eq!(media.take_events(), ["volumechange", "volumechange", "volumechange", "volumechange"]);
+    }
+}
@@ -1,16 +1,18 @@
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::HtmlVideoElement;
-use crate::player::error::VideoError;
+use wasm_bindgen_futures::JsFuture;
+use js_sys::Promise;
+use crate::player::error::{report_error_for, Operation, VideoError};
+use crate::player::context::{default_context, PlayerContext};
 
 #[wasm_bindgen]
 pub fn toggle_picture_in_picture() -> Result<(), JsValue> {
+    toggle_picture_in_picture_for(&default_context())
+}
+
+pub fn toggle_picture_in_picture_for(context: &Rc<PlayerContext>) -> Result<(), JsValue> {
     let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
-    let document = window.document().ok_or(VideoError::DocumentNotFound)?;
-    
-    let video_player = document
-        .get_element_by_id("videoPlayer")
-        .ok_or(VideoError::ElementNotFound("videoPlayer".to_string()))?
-        .dyn_into::<HtmlVideoElement>()?;
+    let video_player = context.video_element()?;
 
     // Use JavaScript interop for Picture-in-Picture API
     let js_value = js_sys::Reflect::get(
@@ -24,29 +26,50 @@ pub fn toggle_picture_in_picture() -> Result<(), JsValue> {
         &JsValue::from_str("pictureInPictureElement"),
     )?;
 
-    if !pip_element.is_null() {
+    let request = if !pip_element.is_null() {
         let exit_pip = js_sys::Reflect::get(
             &document,
             &JsValue::from_str("exitPictureInPicture"),
         )?;
         let exit_pip_fn = exit_pip.dyn_into::<js_sys::Function>()?;
         // Call the function with document as this context
-        exit_pip_fn.call1(&document, &JsValue::null())?;
+        exit_pip_fn.call1(&document, &JsValue::null())
     } else {
         let pip_enabled = js_sys::Reflect::get(
             &document,
             &JsValue::from_str("pictureInPictureEnabled"),
         )?;
-        if pip_enabled.as_bool().unwrap_or(false) {
-            // Get the requestPictureInPicture function
-            let request_pip = js_sys::Reflect::get(
-                &video_player,
-                &JsValue::from_str("requestPictureInPicture"),
-            )?;
-            let request_pip_fn = request_pip.dyn_into::<js_sys::Function>()?;
-            // Call the function with video_player as this context
-            request_pip_fn.call1(&video_player, &JsValue::null())?;
+        if !pip_enabled.as_bool().unwrap_or(false) {
+            let error = VideoError::PipUnavailable("the browser does not support it or it is disabled".to_string());
+            report_error_for(context, &error).unwrap_or_default();
+            return Err(error.into());
+        }
+        // Get the requestPictureInPicture function
+        let request_pip = js_sys::Reflect::get(
+            &video_player,
+            &JsValue::from_str("requestPictureInPicture"),
+        )?;
+        let request_pip_fn = request_pip.dyn_into::<js_sys::Function>()?;
+        // Call the function with video_player as this context
+        request_pip_fn.call1(&video_player, &JsValue::null())
+    };
+    let promise = request.map_err(|e| {
+        let error = VideoError::from_js(Operation::PictureInPicture, &e);
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+
+    // The browser can still refuse once the request is made, for example without a user gesture
+    let context = Rc::downgrade(context);
+    wasm_bindgen_futures::spawn_local(async move {
+        let Ok(promise) = promise.dyn_into::<Promise>() else {
+            return;
+        };
+        if let Err(e) = JsFuture::from(promise).await {
+            if let Some(context) = context.upgrade() {
+                report_error_for(&context, &VideoError::from_js(Operation::PictureInPicture, &e)).unwrap_or_default();
+            }
         }
-    }
+    });
     Ok(())
-} 
\ No newline at end of file
+}
@@ -1,70 +1,219 @@
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
+use web_sys::Element;
 use crate::logger::Logger;
-use crate::player::{get_video_element, get_element_by_id};
-use crate::player::error::{show_error, hide_error, VideoError};
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, resolve_errors_for, VideoError};
+use crate::player::media::MediaBackend;
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
 
 #[wasm_bindgen]
 pub async fn set_toggle_play(text: &str) -> Result<(), JsValue> {
-    Logger::info("Entering set_toggle_play()").map_err(|e| {
+    set_toggle_play_for(&default_context(), text)
+}
+
+#[wasm_bindgen]
+pub async fn play_video() -> Result<(), JsValue> {
+    toggle_play_for(default_context()).await
+}
+
+#[wasm_bindgen]
+pub fn is_video_playing() -> Result<bool, JsValue> {
+    is_video_playing_for(&default_context())
+}
+
+/// Whether `media` is playing rather than paused or finished.
+pub fn is_playing(media: &impl MediaBackend) -> bool {
+    !media.paused() && !media.ended()
+}
+
+/// Starts `media` and waits until it plays. If the browser refuses to play with
+/// sound and `muted_fallback` is set, it tries again muted. Returns whether it had
+/// to mute; a muted attempt that is refused as well is unmuted again.
+pub async fn play_media(media: &impl MediaBackend, muted_fallback: bool) -> Result<bool, VideoError> {
+    match media.play().await {
+        Ok(()) => Ok(false),
+        Err(VideoError::AutoplayBlocked(_)) if muted_fallback && !media.muted() => {
+            media.set_muted(true);
+            media.play().await.inspect_err(|_| media.set_muted(false))?;
+            Ok(true)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+pub fn set_toggle_play_for(context: &PlayerContext, text: &str) -> Result<(), JsValue> {
+    Logger::trace("Entering set_toggle_play()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let button = get_element_by_id("toggleButton")?;
+    let button = context.element(&context.ids.toggle_button)?;
     button.set_text_content(Some(text));
     Ok(())
 }
 
-#[wasm_bindgen]
-pub async fn play_video() -> Result<(), JsValue> {
-    Logger::info("Entering play_video()").map_err(|e| {
+pub async fn toggle_play_for(context: Rc<PlayerContext>) -> Result<(), JsValue> {
+    Logger::trace("Entering play_video()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(&context, &error).unwrap_or_default();
         error
     })?;
-    let video_element = get_video_element()?;
-    let is_video_playing = is_video_playing()?;
-    
-    if is_video_playing {
-        Logger::info("Pausing video").map_err(|e| {
-            let error = VideoError::VideoOperationFailed(e.to_string());
-            show_error(&error.to_string()).unwrap_or_default();
-            error
-        })?;
-        let _ = video_element.pause().map_err(|e| {
-            let error = VideoError::VideoOperationFailed(format!("Failed to pause video: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
-            error
-        })?;
-        set_toggle_play("Play").await?;
+    if is_video_playing_for(&context)? {
+        pause_video_for(&context)
     } else {
-        Logger::info("Playing video").map_err(|e| {
-            let error = VideoError::VideoOperationFailed(e.to_string());
-            show_error(&error.to_string()).unwrap_or_default();
-            error
-        })?;
-        let _ = video_element.play().map_err(|e| {
-            let error = VideoError::VideoOperationFailed(format!("Failed to play video: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
-            error
-        })?;
-        set_toggle_play("Pause").await?;
+        play_for(context).await
     }
-    
-    hide_error()?;
+}
+
+/// Starts playback without waiting for it; `play_for` reports how it went.
+pub fn start_video_for(context: &Rc<PlayerContext>) -> Result<(), JsValue> {
+    let play = play_for(context.clone());
+    wasm_bindgen_futures::spawn_local(async move {
+        play.await.unwrap_or_default();
+    });
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn is_video_playing() -> Result<bool, JsValue> {
-    Logger::info("Entering is_video_playing()").map_err(|e| {
+/// Starts playback and waits until it is under way before the play button
+/// changes. When the browser refuses to play with sound, the player either
+/// plays muted, if configured to, or shows a click-to-play button over the video.
+pub async fn play_for(context: Rc<PlayerContext>) -> Result<(), JsValue> {
+    Logger::debug("Playing video").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(&context, &error).unwrap_or_default();
         error
     })?;
-    let video_element = get_video_element()?;
-    Ok(!video_element.paused())
-} 
+    let video_element = context.video_element()?;
+    let generation = context.media_generation();
+    let result = play_media(&video_element, context.muted_autoplay.get()).await;
+    // Other media was loaded meanwhile, and is started by whoever loaded it
+    if context.media_generation() != generation {
+        return Ok(());
+    }
+    match result {
+        Ok(muted) => {
+            if muted {
+                dispatch_for(&context, Action::VolumeChanged { volume: video_element.volume(), muted })?;
+            }
+            dismiss_click_to_play_for(&context);
+            set_toggle_play_for(&context, "Pause")?;
+            resolve_errors_for(&context, &["autoplay_blocked"])?;
+            Ok(())
+        }
+        // A pause or a new source cut the request short, which is not a failure
+        Err(VideoError::Aborted(_)) => Ok(()),
+        Err(error) => {
+            if matches!(error, VideoError::AutoplayBlocked(_)) {
+                show_click_to_play_for(&context)?;
+            }
+            set_toggle_play_for(&context, "Play")?;
+            report_error_for(&context, &error).unwrap_or_default();
+            Err(error.into())
+        }
+    }
+}
 
+/// Lays a play button over the video, for a viewer to start what the browser
+/// would not start on its own.
+fn show_click_to_play_for(context: &PlayerContext) -> Result<(), JsValue> {
+    if context.click_to_play.borrow().is_some() {
+        return Ok(());
+    }
+    let document = web_sys::window().ok_or(VideoError::WindowNotFound)?.document().ok_or(VideoError::DocumentNotFound)?;
+    let video_element = context.video_element()?;
 
+    let button = document.create_element("button")?;
+    button.set_attribute("type", "button")?;
+    button.set_attribute("class", &context.classes.click_to_play)?;
+    button.set_attribute("aria-label", "Play video")?;
+    button.set_text_content(Some("Click to play"));
+    video_element.after_with_node_1(&button)?;
+    context.track_element(button.clone());
+    *context.click_to_play.borrow_mut() = Some(button);
+    Ok(())
+}
 
+/// Starts playback when the click-to-play button is clicked. Clicks anywhere else are ignored.
+pub fn click_to_play_click_for(context: &Rc<PlayerContext>, target: &Element) -> Result<(), JsValue> {
+    let on_button = context.click_to_play.borrow().as_ref().is_some_and(|button| button.contains(Some(target)));
+    if !on_button {
+        return Ok(());
+    }
+    dismiss_click_to_play_for(context);
+    start_video_for(context)
+}
+
+/// Removes the click-to-play button, if it is showing.
+pub fn dismiss_click_to_play_for(context: &PlayerContext) {
+    let button = context.click_to_play.borrow_mut().take();
+    if let Some(button) = button {
+        button.remove();
+        context.untrack_element(&button);
+    }
+}
+
+pub fn pause_video_for(context: &PlayerContext) -> Result<(), JsValue> {
+    Logger::debug("Pausing video").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let video_element = context.video_element()?;
+    MediaBackend::pause(&video_element).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    set_toggle_play_for(context, "Play")?;
+    Ok(())
+}
+
+pub fn is_video_playing_for(context: &PlayerContext) -> Result<bool, JsValue> {
+    Logger::trace("Entering is_video_playing()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let video_element = context.video_element()?;
+    Ok(is_playing(&video_element))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::media::fake::{block_on, FakeMedia};
+
+    #[test]
+    fn test_play_waits_for_playback() {
+        let media = FakeMedia::new(4.0);
+        assert!(!block_on(play_media(&media, false)).unwrap());
+        assert!(is_playing(&media));
+        media.advance(5.0);
+        // Finished media is not playing, and plays again from the start
+        assert!(!is_playing(&media));
+        block_on(play_media(&media, false)).unwrap();
+        assert_eq!(media.current_time(), 0.0);
+    }
+
+    #[test]
+    fn test_blocked_autoplay_falls_back_to_muted() {
+        let media = FakeMedia::new(4.0);
+        media.block_unmuted_autoplay(true);
+        assert!(matches!(block_on(play_media(&media, false)), Err(VideoError::AutoplayBlocked(_))));
+        assert!(!is_playing(&media) && !media.muted());
+
+        assert!(block_on(play_media(&media, true)).unwrap());
+        assert!(is_playing(&media) && media.muted());
+    }
+
+    #[test]
+    fn test_refused_muted_play_is_unmuted_again() {
+        let media = FakeMedia::new(4.0);
+        media.fail_play(Some("NotAllowedError"));
+        assert!(matches!(block_on(play_media(&media, true)), Err(VideoError::AutoplayBlocked(_))));
+        assert!(!media.muted());
+        media.fail_play(Some("NotSupportedError"));
+        assert!(matches!(block_on(play_media(&media, true)), Err(VideoError::SourceNotSupported(_))));
+        assert_eq!(media.take_events(), ["volumechange", "volumechange"]);
+    }
+}
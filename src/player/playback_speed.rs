@@ -1,112 +1,158 @@
 use wasm_bindgen::prelude::*;
 use crate::logger::Logger;
-use crate::player::error::{show_error, hide_error, VideoError};
-use crate::player::state::VIDEO_STATE;
-use crate::player::{get_video_element, get_element_by_id};
-use crate::player::menu::hide_menus;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::media::MediaBackend;
+use crate::player::menu::hide_menus_for;
+use crate::player::preferences::update_preferences_for;
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
 
 #[wasm_bindgen]
 pub fn get_playback_speed() -> Result<f64, JsValue> {
-    Logger::info("Entering get_playback_speed()").map_err(|e| {
+    get_playback_speed_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn set_playback_speed(speed: f64) -> Result<(), JsValue> {
+    set_playback_speed_for(&default_context(), speed)
+}
+
+#[wasm_bindgen]
+pub fn get_current_playback_speed() -> f64 {
+    get_current_playback_speed_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn update_playback_speed_active_state(speed: f64) -> Result<(), JsValue> {
+    update_playback_speed_active_state_for(&default_context(), speed)
+}
+
+pub fn get_playback_speed_for(context: &PlayerContext) -> Result<f64, JsValue> {
+    Logger::trace("Entering get_playback_speed()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let video_element = get_video_element()?;
+    let video_element = context.video_element()?;
     Ok(video_element.playback_rate())
 }
 
-#[wasm_bindgen]
-pub fn set_playback_speed(speed: f64) -> Result<(), JsValue> {
-    Logger::info("Entering set_playback_speed()").map_err(|e| {
+/// Sets the playback rate of `media`, returning the action recording it. Speeds
+/// the element would reject are refused.
+pub fn apply_playback_speed(media: &impl MediaBackend, speed: f64) -> Result<Action, VideoError> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(VideoError::VideoOperationFailed(format!("Invalid playback speed: {}", speed)));
+    }
+    media.set_playback_rate(speed);
+    Ok(Action::PlaybackSpeedChanged { speed: media.playback_rate() })
+}
+
+pub fn set_playback_speed_for(context: &PlayerContext, speed: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering set_playback_speed()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let video_element = get_video_element()?;
-    video_element.set_playback_rate(speed);
-    let mut state = VIDEO_STATE.lock().map_err(|e| {
-        let error = VideoError::StateError(format!("Failed to lock state: {:?}", e));
-        show_error(&error.to_string()).unwrap_or_default();
-        error
+    let video_element = context.video_element()?;
+    let action = apply_playback_speed(&video_element, speed).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    dispatch_for(context, action).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
     })?;
-    state.playback_speed = speed;
-    update_playback_speed_active_state(speed)?;
-    hide_menus()?;
-    hide_error()?;
+    update_preferences_for(context, |preferences| preferences.playback_speed = Some(speed));
+    hide_menus_for(context)?;
     Ok(())
 }
 
-#[wasm_bindgen]
-pub fn get_current_playback_speed() -> f64 {
-    Logger::info("Entering get_current_playback_speed()").map_err(|e| {
+pub fn get_current_playback_speed_for(context: &PlayerContext) -> f64 {
+    Logger::trace("Entering get_current_playback_speed()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     }).unwrap_or_default();
-    VIDEO_STATE.lock().unwrap().playback_speed
+    context.state().map(|state| state.playback_speed).unwrap_or(1.0)
 }
 
-#[wasm_bindgen]
-pub fn update_playback_speed_active_state(speed: f64) -> Result<(), JsValue> {
-    Logger::info("Entering update_playback_speed_active_state()").map_err(|e| {
+pub fn update_playback_speed_active_state_for(context: &PlayerContext, speed: f64) -> Result<(), JsValue> {
+    Logger::trace("Entering update_playback_speed_active_state()").map_err(|e| {
         let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
+        report_error_for(context, &error).unwrap_or_default();
         error
     })?;
-    let playback_speed_menu = get_element_by_id("playbackSpeedMenu")?;
-    let speed_options = playback_speed_menu.query_selector_all(".speed-option")
+    let element_classes = &context.classes;
+    let playback_speed_menu = context.element(&context.ids.playback_speed_menu)?;
+    let speed_options = playback_speed_menu.query_selector_all(&format!(".{}", element_classes.speed_option))
         .map_err(|e| {
             let error = VideoError::VideoOperationFailed(format!("Failed to get speed options: {:?}", e));
-            show_error(&error.to_string()).unwrap_or_default();
+            report_error_for(context, &error).unwrap_or_default();
             error
         })?;
-    
+
     for i in 0..speed_options.length() {
         let option = speed_options.get(i)
             .ok_or_else(|| {
                 let error = VideoError::VideoOperationFailed("Failed to get speed option".to_string());
-                show_error(&error.to_string()).unwrap_or_default();
+                report_error_for(context, &error).unwrap_or_default();
                 error
             })?
             .dyn_into::<web_sys::Element>()
             .map_err(|e| {
                 let error = VideoError::VideoOperationFailed(format!("Failed to convert Node to Element: {:?}", e));
-                show_error(&error.to_string()).unwrap_or_default();
+                report_error_for(context, &error).unwrap_or_default();
                 error
             })?;
-        
+
         let text_content = option.text_content()
             .ok_or_else(|| {
                 let error = VideoError::VideoOperationFailed("No text content found".to_string());
-                show_error(&error.to_string()).unwrap_or_default();
+                report_error_for(context, &error).unwrap_or_default();
                 error
             })?;
-        
+
         let option_speed = text_content.replace('x', "").parse::<f64>()
             .map_err(|e| {
                 let error = VideoError::VideoOperationFailed(format!("Failed to parse speed: {:?}", e));
-                show_error(&error.to_string()).unwrap_or_default();
+                report_error_for(context, &error).unwrap_or_default();
                 error
             })?;
-        
+
         if option_speed == speed {
-            option.set_attribute("class", "speed-option active")
+            option.set_attribute("class", &element_classes.speed_option_active)
                 .map_err(|e| {
                     let error = VideoError::VideoOperationFailed(format!("Failed to set active class: {:?}", e));
-                    show_error(&error.to_string()).unwrap_or_default();
+                    report_error_for(context, &error).unwrap_or_default();
                     error
                 })?;
         } else {
-            option.set_attribute("class", "speed-option")
+            option.set_attribute("class", &element_classes.speed_option)
                 .map_err(|e| {
                     let error = VideoError::VideoOperationFailed(format!("Failed to remove active class: {:?}", e));
-                    show_error(&error.to_string()).unwrap_or_default();
+                    report_error_for(context, &error).unwrap_or_default();
                     error
                 })?;
         }
     }
-    
-    hide_error()?;
+
     Ok(())
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::media::fake::{block_on, FakeMedia};
+
+    #[test]
+    fn test_apply_playback_speed() {
+        let media = FakeMedia::new(10.0);
+        assert_eq!(apply_playback_speed(&media, 1.5).unwrap(), Action::PlaybackSpeedChanged { speed: 1.5 });
+        block_on(media.play()).unwrap();
+        media.advance(2.0);
+        assert_eq!(media.current_time(), 3.0);
+        for speed in [0.0, -1.0, f64::INFINITY] {
+            assert!(apply_playback_speed(&media, speed).is_err());
+        }
+        assert_eq!(media.playback_rate(), 1.5);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::player::error::VideoError;
+use crate::player::playlist::{mime_type_for_url, PlaylistItem};
+use crate::player::url::resolve_url;
+
+/// Parses a plain or extended M3U/M3U8 playlist of media files. `#EXTINF` titles
+/// and `tvg-logo` attributes, and `#EXTIMG` lines, give the next entry its title
+/// and poster. HLS media playlists are rejected; they belong in a source instead.
+pub fn parse_m3u(text: &str, base_url: &str) -> Result<Vec<PlaylistItem>, VideoError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut items = Vec::new();
+    let mut title: Option<String> = None;
+    let mut poster: Option<String> = None;
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if line.starts_with("#EXT-X-") {
+            return Err(VideoError::PlaylistParseError("this is an HLS playlist; add it as a video source".to_string()));
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let (attributes, name) = split_extinf(info);
+            title = Some(name.to_string()).filter(|name| !name.is_empty());
+            poster = attribute(attributes, "tvg-logo").map(|logo| resolve_url(base_url, logo));
+            continue;
+        }
+        if let Some(image) = line.strip_prefix("#EXTIMG:") {
+            poster = Some(resolve_url(base_url, image.trim()));
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let url = resolve_url(base_url, line);
+        let mut item = PlaylistItem::new(url.clone(), mime_type_for_url(&url).to_string(), title.take());
+        item.poster = poster.take();
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Splits `-1 tvg-logo="a.png",Title` into its attributes and title. The title
+/// starts after the first comma outside quotes.
+fn split_extinf(info: &str) -> (&str, &str) {
+    let mut quoted = false;
+    for (index, character) in info.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            ',' if !quoted => return (&info[..index], info[index + 1..].trim()),
+            _ => {}
+        }
+    }
+    (info, "")
+}
+
+fn attribute<'a>(attributes: &'a str, name: &str) -> Option<&'a str> {
+    let start = attributes.find(&format!("{}=\"", name))? + name.len() + 2;
+    let length = attributes[start..].find('"')?;
+    Some(&attributes[start..start + length]).filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_extended_m3u() {
+        let items = parse_m3u(include_str!("fixtures/course.m3u"), "https://example.com/course/list.m3u").unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].title.as_deref(), Some("Lesson 1, the basics"));
+        assert_eq!(items[0].url(), "https://example.com/course/video/lesson1.mp4");
+        assert_eq!(items[0].sources[0].mime_type, "video/mp4");
+        assert_eq!(items[0].poster.as_deref(), Some("https://example.com/course/thumbs/1.jpg"));
+        assert_eq!(items[1].title.as_deref(), Some("Lesson 2"));
+        assert_eq!(items[1].sources[0].mime_type, "video/webm");
+        assert_eq!(items[1].poster.as_deref(), Some("https://cdn.example.com/2.jpg"));
+        assert_eq!(items[2].title, None);
+        assert_eq!(items[2].poster, None);
+        assert_eq!(items[2].url(), "https://media.example.com/live/stream.m3u8");
+        assert_eq!(items[2].sources[0].mime_type, "application/vnd.apple.mpegurl");
+    }
+
+    #[test]
+    fn test_plain_m3u_and_hls_rejection() {
+        let items = parse_m3u("intro.mp4\r\n\r\n# comment\r\nmusic/song.mp3\r\n", "https://example.com/a/").unwrap();
+        let urls: Vec<String> = items.iter().map(PlaylistItem::url).collect();
+        assert_eq!(urls, ["https://example.com/a/intro.mp4", "https://example.com/a/music/song.mp3"]);
+        assert_eq!(items[1].sources[0].mime_type, "audio/mpeg");
+        assert!(matches!(
+            parse_m3u("#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6,\nsegment0.ts\n", "https://example.com/"),
+            Err(VideoError::PlaylistParseError(_))
+        ));
+    }
+}
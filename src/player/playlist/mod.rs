@@ -0,0 +1,683 @@
+use std::rc::Rc;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+use web_sys::{CustomEvent, CustomEventInit};
+use js_sys::Promise;
+use crate::logger::Logger;
+use crate::player::config::{add_mirror, validate_source_url, TextTrackSource, VideoSource};
+use crate::player::context::PlayerContext;
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::fetch::fetch_text;
+use crate::player::gapless::swap_to_preloaded_for;
+use crate::player::play_pause::start_video_for;
+use crate::player::resume::record_position_for;
+use crate::player::loader::{load_media_for, unload_media_for};
+
+pub mod m3u;
+pub mod xspf;
+pub use m3u::parse_m3u;
+pub use xspf::parse_xspf;
+
+// Seconds into an item after which "previous" restarts it instead of going back
+const PREVIOUS_RESTART_THRESHOLD: f64 = 3.0;
+
+/// One entry of a playlist: its sources (alternatives for the same video), caption
+/// tracks, title and poster.
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaylistItem {
+    pub(crate) sources: Vec<VideoSource>,
+    pub(crate) text_tracks: Vec<TextTrackSource>,
+    pub(crate) title: Option<String>,
+    pub(crate) poster: Option<String>,
+    pub(crate) content_id: Option<String>,
+}
+
+#[wasm_bindgen]
+impl PlaylistItem {
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: String, mime_type: String, title: Option<String>) -> PlaylistItem {
+        PlaylistItem {
+            sources: vec![VideoSource::new(url, mime_type, None)],
+            text_tracks: Vec::new(),
+            title,
+            poster: None,
+            content_id: None,
+        }
+    }
+
+    /// Adds an alternative source, as `PlayerConfig.add_source` does.
+    pub fn add_source(&mut self, url: String, mime_type: String, label: Option<String>) {
+        self.sources.push(VideoSource::new(url, mime_type, label));
+    }
+
+    pub fn sources(&self) -> Vec<VideoSource> {
+        self.sources.clone()
+    }
+
+    /// Adds a mirror of the source added last, as `PlayerConfig.add_mirror` does.
+    pub fn add_mirror(&mut self, url: String) -> Result<(), VideoError> {
+        add_mirror(&mut self.sources, url)
+    }
+
+    pub fn add_text_track(&mut self, url: String, language: String, label: Option<String>) {
+        self.text_tracks.push(TextTrackSource::new(url, language, label));
+    }
+
+    pub fn text_tracks(&self) -> Vec<TextTrackSource> {
+        self.text_tracks.clone()
+    }
+
+    /// URL of the first source.
+    pub fn url(&self) -> String {
+        self.sources.first().map(|source| source.url.clone()).unwrap_or_default()
+    }
+
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.title = title;
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.title.clone()
+    }
+
+    pub fn set_poster(&mut self, poster: Option<String>) {
+        self.poster = poster;
+    }
+
+    pub fn poster(&self) -> Option<String> {
+        self.poster.clone()
+    }
+
+    /// Names the item in the saved playback positions instead of its first source URL.
+    pub fn set_content_id(&mut self, content_id: Option<String>) {
+        self.content_id = content_id;
+    }
+
+    pub fn content_id(&self) -> Option<String> {
+        self.content_id.clone()
+    }
+
+    pub fn validate(&self) -> Result<(), VideoError> {
+        if self.sources.is_empty() {
+            return Err(VideoError::NoSources);
+        }
+        for source in &self.sources {
+            source.validate()?;
+        }
+        for track in &self.text_tracks {
+            validate_source_url(&track.url)?;
+        }
+        if let Some(poster) = &self.poster {
+            validate_source_url(poster)?;
+        }
+        Ok(())
+    }
+}
+
+impl PlaylistItem {
+    /// The id playback positions are saved under: the content id, or else the first
+    /// source URL without its fragment.
+    pub fn resume_id(&self) -> Option<String> {
+        self.content_id.clone().or_else(|| {
+            let url = &self.sources.first()?.url;
+            Some(url.split('#').next().unwrap_or(url).to_string())
+        })
+    }
+}
+
+/// Guesses a MIME type from a URL's file extension, for playlist formats that do
+/// not carry one. Unknown extensions are assumed to be MP4, which browsers sniff anyway.
+pub fn mime_type_for_url(url: &str) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let extension = file.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "webm" => "video/webm",
+        "ogv" | "ogg" => "video/ogg",
+        "mp3" => "audio/mpeg",
+        "m4a" | "aac" => "audio/mp4",
+        "oga" | "opus" => "audio/ogg",
+        "m3u8" => "application/vnd.apple.mpegurl",
+        "mpd" => "application/dash+xml",
+        _ => "video/mp4",
+    }
+}
+
+/// Parses an M3U/M3U8 or XSPF playlist file, telling the two apart by content.
+/// Relative entries are resolved against `base_url`, the playlist's own URL.
+#[wasm_bindgen]
+pub fn parse_playlist(text: &str, base_url: &str) -> Result<Vec<PlaylistItem>, VideoError> {
+    let content = text.strip_prefix('\u{feff}').unwrap_or(text).trim_start();
+    if content.starts_with('<') {
+        parse_xspf(content, base_url)
+    } else {
+        parse_m3u(content, base_url)
+    }
+}
+
+/// What happens when an item ends, and whether next/previous wrap around.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RepeatMode {
+    /// Stop after the last item.
+    #[default]
+    Off,
+    /// Play the current item again.
+    One,
+    /// Go back to the first item after the last.
+    All,
+}
+
+/// SplitMix64, a small generator whose output is fully determined by its seed.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// The order `len` items are played in when shuffled with `seed`. The same seed
+/// and length always give the same order.
+pub fn shuffle_order(len: usize, seed: u64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    let mut rng = SplitMix64(seed);
+    for index in (1..len).rev() {
+        let other = (rng.next() % (index as u64 + 1)) as usize;
+        order.swap(index, other);
+    }
+    order
+}
+
+/// The ordered items of a player's playlist and which one is playing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Queue {
+    items: Vec<PlaylistItem>,
+    current: Option<usize>,
+    // Where playback continues after the current item was removed
+    detached: Option<usize>,
+    repeat: RepeatMode,
+    shuffle: Option<u64>,
+}
+
+impl Queue {
+    pub fn new(items: Vec<PlaylistItem>) -> Queue {
+        Queue { items, ..Queue::default() }
+    }
+
+    pub fn items(&self) -> &[PlaylistItem] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&PlaylistItem> {
+        self.items.get(index)
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn set_current(&mut self, index: usize) -> bool {
+        if index >= self.items.len() {
+            return false;
+        }
+        self.current = Some(index);
+        self.detached = None;
+        true
+    }
+
+    pub fn push(&mut self, item: PlaylistItem) {
+        self.items.push(item);
+    }
+
+    /// Inserts `item` before `index`, or at the end when `index` is past it.
+    pub fn insert(&mut self, index: usize, item: PlaylistItem) {
+        let index = index.min(self.items.len());
+        self.items.insert(index, item);
+        shift_up(&mut self.current, index);
+        shift_up(&mut self.detached, index);
+    }
+
+    /// Removes an item. Removing the playing item leaves it playing, and playback
+    /// continues with the item that took its place.
+    pub fn remove(&mut self, index: usize) -> Option<PlaylistItem> {
+        if index >= self.items.len() {
+            return None;
+        }
+        let item = self.items.remove(index);
+        if self.current == Some(index) {
+            self.current = None;
+            self.detached = Some(index);
+        } else {
+            shift_down(&mut self.current, index);
+            shift_down(&mut self.detached, index);
+        }
+        Some(item)
+    }
+
+    /// Moves the item at `from` to `to`, keeping track of the playing item.
+    pub fn move_item(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.items.len() || to >= self.items.len() {
+            return false;
+        }
+        let item = self.items.remove(from);
+        self.items.insert(to, item);
+        for index in [&mut self.current, &mut self.detached].into_iter().flatten() {
+            *index = if *index == from {
+                to
+            } else if from < *index && *index <= to {
+                *index - 1
+            } else if to <= *index && *index < from {
+                *index + 1
+            } else {
+                *index
+            };
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+        self.current = None;
+        self.detached = None;
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    pub fn shuffle(&self) -> Option<u64> {
+        self.shuffle
+    }
+
+    /// Shuffles the play order with `seed`, or restores list order with `None`.
+    pub fn set_shuffle(&mut self, seed: Option<u64>) {
+        self.shuffle = seed;
+    }
+
+    /// Item indices in the order they play.
+    pub fn play_order(&self) -> Vec<usize> {
+        match self.shuffle {
+            Some(seed) => shuffle_order(self.items.len(), seed),
+            None => (0..self.items.len()).collect(),
+        }
+    }
+
+    /// The item "next" moves to, wrapping around only with `RepeatMode::All`.
+    pub fn next_index(&self) -> Option<usize> {
+        let order = self.play_order();
+        let Some(current) = self.current else {
+            // After the playing item was removed, the one that took its place is next
+            return self.detached.filter(|index| *index < self.items.len()).or_else(|| order.first().copied());
+        };
+        let position = order.iter().position(|index| *index == current)?;
+        match order.get(position + 1) {
+            Some(index) => Some(*index),
+            None if self.repeat == RepeatMode::All => order.first().copied(),
+            None => None,
+        }
+    }
+
+    /// The item "previous" moves to, wrapping around only with `RepeatMode::All`.
+    pub fn previous_index(&self) -> Option<usize> {
+        let order = self.play_order();
+        let current = self.current.or(self.detached)?;
+        let position = order.iter().position(|index| *index == current).unwrap_or(order.len());
+        match position.checked_sub(1).and_then(|position| order.get(position)) {
+            Some(index) => Some(*index),
+            None if self.repeat == RepeatMode::All => order.last().copied(),
+            None => None,
+        }
+    }
+
+    /// The item to play when the current one ends.
+    pub fn ended_index(&self) -> Option<usize> {
+        match (self.repeat, self.current) {
+            (RepeatMode::One, Some(current)) => Some(current),
+            _ => self.next_index(),
+        }
+    }
+}
+
+fn shift_up(index: &mut Option<usize>, inserted: usize) {
+    if let Some(index) = index.as_mut().filter(|index| **index >= inserted) {
+        *index += 1;
+    }
+}
+
+fn shift_down(index: &mut Option<usize>, removed: usize) {
+    if let Some(index) = index.as_mut().filter(|index| **index > removed) {
+        *index -= 1;
+    }
+}
+
+/// Detail of the `playlistchange` event fired on the video element.
+#[derive(Serialize)]
+struct PlaylistChange {
+    index: usize,
+    title: Option<String>,
+}
+
+pub(crate) fn dispatch_playlist_change(context: &PlayerContext, index: usize, item: &PlaylistItem) -> Result<(), VideoError> {
+    let init = CustomEventInit::new();
+    let detail = PlaylistChange { index, title: item.title.clone() };
+    init.set_detail(&serde_wasm_bindgen::to_value(&detail).unwrap_or(JsValue::NULL));
+    if let Ok(event) = CustomEvent::new_with_event_init_dict("playlistchange", &init) {
+        context.video_element()?.dispatch_event(&event).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Loads playlist item `index` into the player, saving where the previous one was
+/// left, and starts it if `play` is set.
+pub fn play_item_for(context: &Rc<PlayerContext>, index: usize, play: bool) -> Result<(), JsValue> {
+    Logger::debug(&format!("Playing playlist item {}", index)).map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let item = {
+        let mut playlist = context.playlist.borrow_mut();
+        if !playlist.set_current(index) {
+            let error = VideoError::InvalidConfig(format!("no playlist item with index {}", index));
+            report_error_for(context, &error).unwrap_or_default();
+            return Err(error.into());
+        }
+        playlist.get(index).cloned().expect("index was just checked")
+    };
+    record_position_for(context, true)?;
+    unload_media_for(context).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    load_media_for(context, &item, 0.0).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    dispatch_playlist_change(context, index, &item)?;
+    if play {
+        start_video_for(context)?;
+    }
+    Ok(())
+}
+
+/// Moves to the next item; `Ok(false)` at the end of the playlist.
+pub fn playlist_next_for(context: &Rc<PlayerContext>) -> Result<bool, JsValue> {
+    let Some(index) = context.playlist.borrow().next_index() else {
+        return Ok(false);
+    };
+    let playing = !context.video_element()?.paused();
+    play_item_for(context, index, playing)?;
+    Ok(true)
+}
+
+/// Restarts the current item when it has played for a few seconds, and otherwise
+/// moves to the previous one; `Ok(false)` when there is nothing before it.
+pub fn playlist_previous_for(context: &Rc<PlayerContext>) -> Result<bool, JsValue> {
+    let video_element = context.video_element()?;
+    let has_current = context.playlist.borrow().current().is_some();
+    if has_current && video_element.current_time() > PREVIOUS_RESTART_THRESHOLD {
+        video_element.set_current_time(0.0);
+        return Ok(true);
+    }
+    let Some(index) = context.playlist.borrow().previous_index() else {
+        return Ok(false);
+    };
+    play_item_for(context, index, !video_element.paused())?;
+    Ok(true)
+}
+
+/// Moves on when an item plays to the end, as the repeat mode says.
+pub fn playlist_ended_for(context: &Rc<PlayerContext>) -> Result<(), JsValue> {
+    let (index, current) = {
+        let playlist = context.playlist.borrow();
+        (playlist.ended_index(), playlist.current())
+    };
+    match index {
+        // Repeating one item needs no reload
+        Some(index) if Some(index) == current => {
+            let video_element = context.video_element()?;
+            video_element.set_current_time(0.0);
+            start_video_for(context)
+        }
+        // The next item takes over gaplessly when it was preloaded
+        Some(index) => {
+            if !swap_to_preloaded_for(context, index)? {
+                play_item_for(context, index, true)?;
+            }
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Handle to a player's playlist, returned by `Player.playlist()`.
+#[wasm_bindgen]
+pub struct Playlist {
+    context: Rc<PlayerContext>,
+}
+
+impl Playlist {
+    pub fn new(context: Rc<PlayerContext>) -> Playlist {
+        Playlist { context }
+    }
+}
+
+#[wasm_bindgen]
+impl Playlist {
+    pub fn len(&self) -> usize {
+        self.context.playlist.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.context.playlist.borrow().is_empty()
+    }
+
+    pub fn items(&self) -> Vec<PlaylistItem> {
+        self.context.playlist.borrow().items().to_vec()
+    }
+
+    pub fn item(&self, index: usize) -> Option<PlaylistItem> {
+        self.context.playlist.borrow().get(index).cloned()
+    }
+
+    /// Index of the item playing, `None` before the first one is played.
+    pub fn current_index(&self) -> Option<usize> {
+        self.context.playlist.borrow().current()
+    }
+
+    pub fn add(&self, item: PlaylistItem) -> Result<(), VideoError> {
+        item.validate()?;
+        self.context.playlist.borrow_mut().push(item);
+        Ok(())
+    }
+
+    pub fn insert(&self, index: usize, item: PlaylistItem) -> Result<(), VideoError> {
+        item.validate()?;
+        self.context.playlist.borrow_mut().insert(index, item);
+        Ok(())
+    }
+
+    /// Removes an item; removing the one playing lets it finish before moving on.
+    pub fn remove(&self, index: usize) -> Option<PlaylistItem> {
+        self.context.playlist.borrow_mut().remove(index)
+    }
+
+    pub fn move_item(&self, from: usize, to: usize) -> bool {
+        self.context.playlist.borrow_mut().move_item(from, to)
+    }
+
+    pub fn clear(&self) {
+        self.context.playlist.borrow_mut().clear();
+    }
+
+    /// Appends the items of an M3U/M3U8 or XSPF file and returns how many there were.
+    pub fn add_from_text(&self, text: &str, base_url: &str) -> Result<usize, VideoError> {
+        let items = parse_playlist(text, base_url)?;
+        let count = items.len();
+        let mut playlist = self.context.playlist.borrow_mut();
+        for item in items {
+            playlist.push(item);
+        }
+        Ok(count)
+    }
+
+    /// Fetches an M3U/M3U8 or XSPF file and appends its items; resolves with how many there were.
+    pub fn load(&self, url: String) -> Promise {
+        let context = Rc::downgrade(&self.context);
+        future_to_promise(async move {
+            let text = fetch_text(&url).await?;
+            let context = context.upgrade().ok_or_else(|| VideoError::StateError("Player was destroyed".to_string()))?;
+            let count = Playlist::new(context).add_from_text(&text, &url)?;
+            Ok(JsValue::from(count as u32))
+        })
+    }
+
+    pub fn play(&self, index: usize) -> Result<(), JsValue> {
+        play_item_for(&self.context, index, true)
+    }
+
+    pub fn next(&self) -> Result<bool, JsValue> {
+        playlist_next_for(&self.context)
+    }
+
+    pub fn previous(&self) -> Result<bool, JsValue> {
+        playlist_previous_for(&self.context)
+    }
+
+    pub fn set_repeat(&self, repeat: RepeatMode) {
+        self.context.playlist.borrow_mut().set_repeat(repeat);
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.context.playlist.borrow().repeat()
+    }
+
+    /// Shuffles the play order; the same seed always gives the same order. `None`
+    /// plays the items in list order again.
+    pub fn set_shuffle(&self, seed: Option<u32>) {
+        self.context.playlist.borrow_mut().set_shuffle(seed.map(u64::from));
+    }
+
+    pub fn shuffle_seed(&self) -> Option<u32> {
+        self.context.playlist.borrow().shuffle().map(|seed| seed as u32)
+    }
+
+    /// Item indices in the order they play.
+    pub fn play_order(&self) -> Vec<usize> {
+        self.context.playlist.borrow().play_order()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(len: usize) -> Queue {
+        Queue::new((0..len).map(|index| PlaylistItem::new(format!("{}.mp4", index), "video/mp4".to_string(), None)).collect())
+    }
+
+    fn urls(queue: &Queue) -> Vec<String> {
+        queue.items().iter().map(PlaylistItem::url).collect()
+    }
+
+    #[test]
+    fn test_next_previous_and_repeat() {
+        let mut queue = queue(3);
+        assert_eq!(queue.next_index(), Some(0));
+        assert_eq!(queue.previous_index(), None);
+        queue.set_current(2);
+        assert_eq!(queue.next_index(), None);
+        assert_eq!(queue.ended_index(), None);
+        assert_eq!(queue.previous_index(), Some(1));
+        queue.set_repeat(RepeatMode::All);
+        assert_eq!(queue.next_index(), Some(0));
+        queue.set_current(0);
+        assert_eq!(queue.previous_index(), Some(2));
+        queue.set_repeat(RepeatMode::One);
+        assert_eq!(queue.ended_index(), Some(0));
+        assert_eq!(queue.next_index(), Some(1));
+    }
+
+    #[test]
+    fn test_reproducible_shuffle() {
+        let order = shuffle_order(10, 42);
+        assert_eq!(order, shuffle_order(10, 42));
+        assert_ne!(order, shuffle_order(10, 43));
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+
+        let mut queue = queue(10);
+        queue.set_shuffle(Some(42));
+        queue.set_current(order[0]);
+        let mut played = vec![order[0]];
+        while let Some(index) = queue.next_index() {
+            queue.set_current(index);
+            played.push(index);
+        }
+        assert_eq!(played, order);
+        assert!(shuffle_order(0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_edits_keep_the_current_item() {
+        let mut queue = queue(4);
+        queue.set_current(1);
+        queue.insert(0, PlaylistItem::new("new.mp4".to_string(), "video/mp4".to_string(), None));
+        assert_eq!(queue.current(), Some(2));
+        assert!(queue.move_item(2, 0));
+        assert_eq!(queue.current(), Some(0));
+        assert_eq!(urls(&queue), ["1.mp4", "new.mp4", "0.mp4", "2.mp4", "3.mp4"]);
+        assert!(queue.move_item(1, 3));
+        assert_eq!(urls(&queue), ["1.mp4", "0.mp4", "2.mp4", "new.mp4", "3.mp4"]);
+        assert!(!queue.move_item(0, 9));
+        queue.remove(2);
+        assert_eq!(queue.current(), Some(0));
+
+        // Removing the playing item continues with the one that took its place
+        queue.set_current(1);
+        assert_eq!(queue.remove(1).map(|item| item.url()), Some("0.mp4".to_string()));
+        assert_eq!(queue.current(), None);
+        assert_eq!(queue.next_index(), Some(1));
+        assert_eq!(queue.previous_index(), Some(0));
+        assert_eq!(queue.remove(9), None);
+    }
+
+    #[test]
+    fn test_parse_playlist_by_content() {
+        assert_eq!(parse_playlist("\u{feff}#EXTM3U\nvideo.webm\n", "https://example.com/").unwrap()[0].url(), "https://example.com/video.webm");
+        let xspf = r#"<playlist xmlns="http://xspf.org/ns/0/"><trackList><track><location>a.mpd</location></track></trackList></playlist>"#;
+        let items = parse_playlist(xspf, "https://example.com/list.xspf").unwrap();
+        assert_eq!(items[0].sources[0].mime_type, "application/dash+xml");
+        assert_eq!(mime_type_for_url("https://example.com/a/song.MP3?x=1"), "audio/mpeg");
+        assert_eq!(mime_type_for_url("https://example.com/watch"), "video/mp4");
+    }
+
+    #[test]
+    fn test_item_validation_and_resume_id() {
+        let mut item = PlaylistItem::new("https://example.com/a.mp4#t=10".to_string(), "video/mp4".to_string(), Some("A".to_string()));
+        assert!(item.validate().is_ok());
+        assert_eq!(item.resume_id().as_deref(), Some("https://example.com/a.mp4"));
+        item.set_content_id(Some("lesson-a".to_string()));
+        assert_eq!(item.resume_id().as_deref(), Some("lesson-a"));
+        item.add_source("ftp://example.com/a.webm".to_string(), "video/webm".to_string(), None);
+        assert!(matches!(item.validate(), Err(VideoError::InvalidSourceUrl(_))));
+    }
+}
@@ -0,0 +1,87 @@
+use roxmltree::{Document, Node};
+use crate::player::error::VideoError;
+use crate::player::playlist::{mime_type_for_url, PlaylistItem};
+use crate::player::url::resolve_url;
+
+const XSPF_NAMESPACE: &str = "http://xspf.org/ns/0/";
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Parses an XSPF playlist. Every `<track>` with a `<location>` becomes an item,
+/// its further locations alternative sources, and its `<title>` and `<image>` the
+/// item's title and poster. Relative URLs honour `xml:base`.
+pub fn parse_xspf(text: &str, base_url: &str) -> Result<Vec<PlaylistItem>, VideoError> {
+    let document = Document::parse(text).map_err(|e| VideoError::PlaylistParseError(e.to_string()))?;
+    let root = document.root_element();
+    if !is_element(root, "playlist") {
+        return Err(VideoError::PlaylistParseError("missing playlist root element".to_string()));
+    }
+    let base_url = node_base(root, base_url);
+    let Some(track_list) = root.children().find(|node| is_element(*node, "trackList")) else {
+        return Ok(Vec::new());
+    };
+
+    let mut items = Vec::new();
+    for track in track_list.children().filter(|node| is_element(*node, "track")) {
+        let base_url = node_base(track, &base_url);
+        let mut locations = track
+            .children()
+            .filter(|node| is_element(*node, "location"))
+            .filter_map(text_of)
+            .map(|location| resolve_url(&base_url, &location));
+        let Some(url) = locations.next() else {
+            continue;
+        };
+        let title = track.children().find(|node| is_element(*node, "title")).and_then(text_of);
+        let mut item = PlaylistItem::new(url.clone(), mime_type_for_url(&url).to_string(), title);
+        for url in locations {
+            item.add_source(url.clone(), mime_type_for_url(&url).to_string(), None);
+        }
+        item.poster = track
+            .children()
+            .find(|node| is_element(*node, "image"))
+            .and_then(text_of)
+            .map(|image| resolve_url(&base_url, &image));
+        items.push(item);
+    }
+    Ok(items)
+}
+
+fn is_element(node: Node, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name
+        && node.tag_name().namespace().is_none_or(|namespace| namespace == XSPF_NAMESPACE)
+}
+
+/// Trimmed text of an element, `None` when empty.
+fn text_of(node: Node) -> Option<String> {
+    let text: String = node.children().filter_map(|child| child.text()).collect();
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+fn node_base(node: Node, base_url: &str) -> String {
+    match node.attribute((XML_NAMESPACE, "base")) {
+        Some(base) => resolve_url(base_url, base),
+        None => base_url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xspf() {
+        let items = parse_xspf(include_str!("fixtures/course.xspf"), "https://example.com/course/list.xspf").unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title.as_deref(), Some("Lesson 1 & setup"));
+        assert_eq!(items[0].url(), "https://example.com/course/video/lesson1.mp4");
+        assert_eq!(items[0].poster.as_deref(), Some("https://example.com/course/thumbs/1.jpg"));
+        assert_eq!(items[1].title.as_deref(), Some("Lesson 2"));
+        let sources: Vec<(&str, &str)> = items[1].sources.iter().map(|source| (source.url.as_str(), source.mime_type.as_str())).collect();
+        assert_eq!(sources, [
+            ("https://cdn.example.com/media/lesson2.webm", "video/webm"),
+            ("https://cdn.example.com/media/lesson2.mp4", "video/mp4"),
+        ]);
+        assert!(matches!(parse_xspf("<rss/>", ""), Err(VideoError::PlaylistParseError(_))));
+        assert!(matches!(parse_xspf("<playlist>", ""), Err(VideoError::PlaylistParseError(_))));
+    }
+}
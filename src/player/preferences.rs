@@ -0,0 +1,193 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::logger::Logger;
+use crate::player::context::PlayerContext;
+use crate::player::error::{Operation, VideoError};
+use crate::storage::local_storage;
+
+/// Schema version written with every saved blob. Bump it when a field changes
+/// meaning and teach `migrate` how to bring the previous version forward.
+pub const PREFERENCES_VERSION: u64 = 1;
+/// `localStorage` key used unless the page configures another one.
+pub const DEFAULT_PREFERENCES_KEY: &str = "videoPlayer.preferences";
+
+// Separate keys the volume and mute state were kept under before the blob existed
+const LEGACY_KEY_VOLUME: &str = "videoPlayer.volume";
+const LEGACY_KEY_MUTED: &str = "videoPlayer.muted";
+
+// Playback rates browsers accept
+const MIN_PLAYBACK_SPEED: f64 = 0.0625;
+const MAX_PLAYBACK_SPEED: f64 = 16.0;
+
+/// Settings the viewer chose that carry over to the next visit. Fields left
+/// `None` have never been set and leave the page's configuration alone.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// Element volume, from 0 to 1.
+    pub volume: Option<f64>,
+    pub muted: Option<bool>,
+    pub playback_speed: Option<f64>,
+    /// BCP 47 tag of the caption track last shown, `None` when captions were off.
+    pub caption_language: Option<String>,
+    /// Short label of the rendition last picked, such as "720p"; `None` for automatic selection.
+    pub quality: Option<String>,
+}
+
+impl Preferences {
+    /// Drops values that are out of range, so a hand-edited or corrupted blob
+    /// cannot put the element into a state it rejects.
+    pub fn sanitized(mut self) -> Preferences {
+        self.volume = self.volume.filter(|volume| (0.0..=1.0).contains(volume));
+        self.playback_speed = self.playback_speed.filter(|speed| (MIN_PLAYBACK_SPEED..=MAX_PLAYBACK_SPEED).contains(speed));
+        self.caption_language = self.caption_language.filter(|language| !language.is_empty());
+        self.quality = self.quality.filter(|quality| !quality.is_empty());
+        self
+    }
+}
+
+/// Brings a stored blob of any earlier schema version up to `PREFERENCES_VERSION`.
+/// Blobs written by a newer player, or that are not objects, are ignored.
+pub fn migrate(mut blob: Value) -> Option<Preferences> {
+    let version = blob.get("version")?.as_u64()?;
+    if version > PREFERENCES_VERSION {
+        return None;
+    }
+    let object = blob.as_object_mut()?;
+    object.remove("version");
+    serde_json::from_value::<Preferences>(blob).ok().map(Preferences::sanitized)
+}
+
+/// Reads a saved blob; `None` if it does not parse or comes from a newer version.
+pub fn parse_preferences(text: &str) -> Option<Preferences> {
+    migrate(serde_json::from_str(text).ok()?)
+}
+
+/// The blob `parse_preferences` reads back.
+pub fn serialize_preferences(preferences: &Preferences) -> String {
+    let mut blob = serde_json::to_value(preferences).unwrap_or_else(|_| Value::Object(Default::default()));
+    if let Some(object) = blob.as_object_mut() {
+        object.insert("version".to_string(), Value::from(PREFERENCES_VERSION));
+    }
+    blob.to_string()
+}
+
+/// Preferences from the separate volume and mute keys older players wrote,
+/// ignoring values that do not parse.
+pub fn parse_legacy_preferences(volume: Option<&str>, muted: Option<&str>) -> Preferences {
+    Preferences {
+        volume: volume.and_then(|volume| volume.parse::<f64>().ok()),
+        muted: muted.and_then(|muted| muted.parse::<bool>().ok()),
+        ..Preferences::default()
+    }
+    .sanitized()
+}
+
+/// Loads the preferences saved under `key`, moving the legacy volume keys into
+/// the blob on first use. Storage that is unavailable gives the defaults.
+pub fn load_preferences(key: &str) -> Preferences {
+    let Some(storage) = local_storage() else {
+        return Preferences::default();
+    };
+    if let Some(text) = storage.get_item(key).ok().flatten() {
+        return parse_preferences(&text).unwrap_or_default();
+    }
+    let volume = storage.get_item(LEGACY_KEY_VOLUME).ok().flatten();
+    let muted = storage.get_item(LEGACY_KEY_MUTED).ok().flatten();
+    if volume.is_none() && muted.is_none() {
+        return Preferences::default();
+    }
+    let preferences = parse_legacy_preferences(volume.as_deref(), muted.as_deref());
+    if save_preferences(key, &preferences).is_ok() {
+        storage.remove_item(LEGACY_KEY_VOLUME).unwrap_or_default();
+        storage.remove_item(LEGACY_KEY_MUTED).unwrap_or_default();
+    }
+    preferences
+}
+
+/// Saves `preferences` under `key`. Fails when storage is unavailable or full.
+pub fn save_preferences(key: &str, preferences: &Preferences) -> Result<(), VideoError> {
+    let storage = local_storage()
+        .ok_or_else(|| VideoError::VideoOperationFailed("localStorage is unavailable".to_string()))?;
+    storage
+        .set_item(key, &serialize_preferences(preferences))
+        .map_err(|e| match VideoError::from_js(Operation::Other, &e) {
+            quota @ VideoError::QuotaExceeded(_) => quota,
+            _ => VideoError::VideoOperationFailed(format!("Failed to save preferences: {:?}", e)),
+        })
+}
+
+/// Changes this player's preferences and saves them if anything changed. A failed
+/// save is only logged; the preferences still apply until the page is closed.
+pub fn update_preferences_for<F>(context: &PlayerContext, update: F)
+where
+    F: FnOnce(&mut Preferences),
+{
+    let preferences = {
+        let Ok(mut preferences) = context.preferences.try_borrow_mut() else {
+            return;
+        };
+        let previous = preferences.clone();
+        update(&mut preferences);
+        if *preferences == previous {
+            return;
+        }
+        preferences.clone()
+    };
+    let Some(key) = context.preferences_key.borrow().clone() else {
+        return;
+    };
+    if let Err(error) = save_preferences(&key, &preferences) {
+        Logger::warn(&error.to_string()).unwrap_or_default();
+    }
+}
+
+pub fn preferences_for(context: &PlayerContext) -> Preferences {
+    context.preferences.borrow().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preferences_round_trip() {
+        let preferences = Preferences {
+            volume: Some(0.5),
+            muted: Some(false),
+            playback_speed: Some(1.5),
+            caption_language: Some("pt-BR".to_string()),
+            quality: Some("720p".to_string()),
+        };
+        let blob = serialize_preferences(&preferences);
+        assert!(blob.contains(r#""version":1"#));
+        assert_eq!(parse_preferences(&blob), Some(preferences));
+        assert_eq!(parse_preferences(&serialize_preferences(&Preferences::default())), Some(Preferences::default()));
+    }
+
+    #[test]
+    fn test_migrate_rejects_unknown_versions_and_bad_values() {
+        assert_eq!(parse_preferences(r#"{"version": 2, "volume": 0.5}"#), None);
+        assert_eq!(parse_preferences(r#"{"volume": 0.5}"#), None);
+        assert_eq!(parse_preferences("not json"), None);
+        assert_eq!(parse_preferences("[1]"), None);
+        // Unknown fields are ignored and out-of-range values dropped
+        assert_eq!(
+            parse_preferences(r#"{"version": 1, "volume": 3, "playback_speed": 2, "theme": "dark", "quality": ""}"#),
+            Some(Preferences { playback_speed: Some(2.0), ..Preferences::default() })
+        );
+    }
+
+    #[test]
+    fn test_parse_legacy_preferences() {
+        assert_eq!(
+            parse_legacy_preferences(Some("0.25"), Some("true")),
+            Preferences { volume: Some(0.25), muted: Some(true), ..Preferences::default() }
+        );
+        assert_eq!(parse_legacy_preferences(Some("1.5"), Some("yes")), Preferences::default());
+        assert_eq!(
+            parse_legacy_preferences(None, Some("false")),
+            Preferences { muted: Some(false), ..Preferences::default() }
+        );
+    }
+}
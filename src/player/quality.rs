@@ -0,0 +1,361 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::Element;
+use crate::logger::Logger;
+use crate::player::abr::{AbrController, RenditionInfo};
+use crate::player::config::VideoSource;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::menu::hide_menus_for;
+use crate::player::events::{publish_for, PlayerEvent};
+use crate::player::mse::dispatch_quality_change;
+use crate::player::preferences::{preferences_for, update_preferences_for};
+use crate::player::segments::SegmentProvider;
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
+use crate::player::get_element_by_action;
+use crate::player::loader::set_start_time;
+use crate::player::watchdog::select_source_for;
+
+// Context menu item that opens the quality menu
+pub const MENU_ACTION_QUALITY: &str = "quality";
+// Attribute holding an option's rendition index, or "auto"
+const QUALITY_ATTRIBUTE: &str = "data-quality";
+const QUALITY_AUTO: &str = "auto";
+
+/// Where the renditions offered in the quality menu come from.
+#[derive(Clone)]
+pub enum QualitySource {
+    /// An adaptive stream, switched through its ABR controller.
+    Adaptive {
+        provider: Rc<RefCell<dyn SegmentProvider>>,
+        abr: Rc<RefCell<AbrController>>,
+    },
+    /// Alternative progressive files, switched by pointing the element at one of them.
+    Progressive(Vec<VideoSource>),
+}
+
+impl QualitySource {
+    /// The menu entries, "Auto" first and then the renditions from highest to lowest.
+    pub fn options(&self) -> Vec<QualityOption> {
+        match self {
+            QualitySource::Adaptive { provider, abr } => {
+                let renditions = provider.borrow().renditions();
+                let current = if abr.borrow().manual().is_none() { abr.borrow().current() } else { None };
+                rendition_options(&renditions, current)
+            }
+            QualitySource::Progressive(sources) => {
+                let mut options = vec![QualityOption { index: None, label: "Auto".to_string() }];
+                options.extend(sources.iter().enumerate().map(|(index, source)| QualityOption {
+                    index: Some(index),
+                    label: source.label.clone().unwrap_or_else(|| file_name(&source.url).to_string()),
+                }));
+                options
+            }
+        }
+    }
+
+    /// Short names of the renditions by index, such as "720p" or a file name, which
+    /// the preferences remember a manual choice by.
+    pub fn preference_labels(&self) -> Vec<String> {
+        match self {
+            QualitySource::Adaptive { provider, .. } => provider.borrow().renditions().iter().map(short_label).collect(),
+            QualitySource::Progressive(sources) => sources
+                .iter()
+                .map(|source| source.label.clone().unwrap_or_else(|| file_name(&source.url).to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityOption {
+    /// Rendition index, `None` for automatic selection.
+    pub index: Option<usize>,
+    pub label: String,
+}
+
+/// Lists `renditions` by descending bandwidth after an "Auto" entry that names the
+/// rendition automatic selection is currently playing.
+pub fn rendition_options(renditions: &[RenditionInfo], auto_current: Option<usize>) -> Vec<QualityOption> {
+    let auto_label = match auto_current.and_then(|index| renditions.get(index)) {
+        Some(rendition) => format!("Auto ({})", short_label(rendition)),
+        None => "Auto".to_string(),
+    };
+    let mut order: Vec<usize> = (0..renditions.len()).collect();
+    order.sort_by_key(|index| std::cmp::Reverse(renditions[*index].bandwidth));
+
+    let mut options = vec![QualityOption { index: None, label: auto_label }];
+    options.extend(order.into_iter().map(|index| QualityOption {
+        index: Some(index),
+        label: rendition_label(&renditions[index]),
+    }));
+    options
+}
+
+/// "720p · 2.8 Mbps", or just the bitrate when the height is unknown.
+pub fn rendition_label(rendition: &RenditionInfo) -> String {
+    match rendition.height {
+        Some(height) => format!("{}p · {}", height, format_bitrate(rendition.bandwidth)),
+        None => format_bitrate(rendition.bandwidth),
+    }
+}
+
+fn short_label(rendition: &RenditionInfo) -> String {
+    match rendition.height {
+        Some(height) => format!("{}p", height),
+        None => format_bitrate(rendition.bandwidth),
+    }
+}
+
+pub fn format_bitrate(bits_per_second: u64) -> String {
+    if bits_per_second >= 1_000_000 {
+        format!("{:.1} Mbps", bits_per_second as f64 / 1_000_000.0)
+    } else {
+        format!("{} kbps", bits_per_second / 1000)
+    }
+}
+
+/// The `data-quality` value for a selection.
+pub fn quality_value(index: Option<usize>) -> String {
+    index.map(|index| index.to_string()).unwrap_or_else(|| QUALITY_AUTO.to_string())
+}
+
+/// Reads a `data-quality` value back; `None` if it is neither "auto" nor an index.
+pub fn parse_quality_value(value: &str) -> Option<Option<usize>> {
+    if value == QUALITY_AUTO {
+        return Some(None);
+    }
+    value.parse().ok().map(Some)
+}
+
+fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').find(|part| !part.is_empty()).unwrap_or(path)
+}
+
+#[wasm_bindgen]
+pub fn set_quality(index: Option<usize>) -> Result<(), JsValue> {
+    set_quality_for(&default_context(), index)
+}
+
+#[wasm_bindgen]
+pub fn get_quality() -> Option<usize> {
+    get_quality_for(&default_context())
+}
+
+#[wasm_bindgen]
+pub fn update_quality_active_state(index: Option<usize>) -> Result<(), JsValue> {
+    update_quality_active_state_for(&default_context(), index)
+}
+
+/// Selects a rendition, or automatic selection with `None`, keeping the playback position.
+pub fn set_quality_for(context: &PlayerContext, index: Option<usize>) -> Result<(), JsValue> {
+    Logger::trace("Entering set_quality()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let Some(source) = context.quality_source() else {
+        return Ok(());
+    };
+    match &source {
+        QualitySource::Adaptive { provider, abr } => {
+            abr.borrow_mut().set_manual(index);
+            // Manual choices skip hysteresis, so the switch applies from the next segment on
+            let renditions = provider.borrow().renditions();
+            let switch = match index {
+                Some(_) => abr.borrow_mut().choose(&renditions, 0.0, 0.0, js_sys::Date::now() / 1000.0),
+                None => None,
+            };
+            if let Some(switch) = switch {
+                provider.borrow_mut().select_rendition(switch.index);
+                dispatch_quality_change(context, &switch);
+            }
+        }
+        QualitySource::Progressive(sources) => {
+            let selected = match index {
+                Some(index) => Some(sources.get(index).ok_or_else(|| {
+                    let error = VideoError::InvalidConfig(format!("no quality with index {}", index));
+                    report_error_for(context, &error).unwrap_or_default();
+                    error
+                })?),
+                None => None,
+            };
+            switch_progressive_source(context, selected.map(|selected| selected.url.as_str())).inspect_err(|error| {
+                report_error_for(context, error).unwrap_or_default();
+            })?;
+        }
+    }
+    dispatch_for(context, Action::QualitySelected { quality: index }).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    let label = index.and_then(|index| source.preference_labels().into_iter().nth(index));
+    update_preferences_for(context, |preferences| preferences.quality = label);
+    if matches!(source, QualitySource::Progressive(_)) {
+        publish_for(context, PlayerEvent::QualityChange { quality: index, automatic: index.is_none(), bandwidth: None, height: None });
+    }
+    hide_menus_for(context)?;
+    Ok(())
+}
+
+/// Picks the rendition the viewer chose on an earlier visit, once the quality
+/// source is known, if one with the same name is on offer.
+pub fn apply_quality_preference_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let (Some(source), Some(preferred)) = (context.quality_source(), preferences_for(context).quality) else {
+        return Ok(());
+    };
+    match source.preference_labels().iter().position(|label| *label == preferred) {
+        Some(index) => set_quality_for(context, Some(index)),
+        None => Ok(()),
+    }
+}
+
+pub fn get_quality_for(context: &PlayerContext) -> Option<usize> {
+    context.state().ok().and_then(|state| state.quality)
+}
+
+/// Points the element at `url`, or back at its `<source>` children, resuming where it was.
+fn switch_progressive_source(context: &PlayerContext, url: Option<&str>) -> Result<(), VideoError> {
+    let video_element = context.video_element()?;
+    let position = video_element.current_time();
+    let was_playing = !video_element.paused();
+    match url {
+        Some(url) => video_element.set_src(url),
+        None => video_element
+            .remove_attribute("src")
+            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to reset source: {:?}", e)))?,
+    }
+    video_element.load();
+    select_source_for(context, url);
+    set_start_time(&video_element, position)?;
+    if was_playing {
+        // The returned promise settles once enough of the new source has loaded
+        let _ = video_element.play();
+    }
+    Ok(())
+}
+
+/// Rebuilds the options of the quality menu from the current quality source.
+pub fn render_quality_menu_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Some(source) = context.quality_source() else {
+        return Ok(());
+    };
+    let window = web_sys::window().ok_or(VideoError::WindowNotFound)?;
+    let document = window.document().ok_or(VideoError::DocumentNotFound)?;
+    let quality_menu = context.element(&context.ids.quality_menu)?;
+
+    quality_menu.set_text_content(None);
+    for option in source.options() {
+        let element = document.create_element("div")?;
+        element.set_attribute("class", &context.classes.quality_option)?;
+        element.set_attribute(QUALITY_ATTRIBUTE, &quality_value(option.index))?;
+        element.set_text_content(Some(&option.label));
+        quality_menu.append_child(&element)?;
+    }
+    update_quality_active_state_for(context, get_quality_for(context))
+}
+
+/// Shows the context menu's quality item only when there is more than one rendition to pick.
+pub fn update_quality_menu_item_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Ok(quality_item) = get_element_by_action(&context.ids.context_menu, MENU_ACTION_QUALITY) else {
+        return Ok(());
+    };
+    let available = context.quality_source().is_some_and(|source| source.options().len() > 2);
+    quality_item.toggle_attribute_with_force("hidden", !available)?;
+    Ok(())
+}
+
+/// Returns the selection an element inside the quality menu stands for.
+pub fn quality_of(element: &Element) -> Option<Option<usize>> {
+    let option = element.closest(&format!("[{}]", QUALITY_ATTRIBUTE)).ok()??;
+    parse_quality_value(&option.get_attribute(QUALITY_ATTRIBUTE)?)
+}
+
+pub fn update_quality_active_state_for(context: &PlayerContext, index: Option<usize>) -> Result<(), JsValue> {
+    Logger::trace("Entering update_quality_active_state()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let element_classes = &context.classes;
+    let quality_menu = context.element(&context.ids.quality_menu)?;
+    let quality_options = quality_menu.query_selector_all(&format!(".{}", element_classes.quality_option))
+        .map_err(|e| {
+            let error = VideoError::VideoOperationFailed(format!("Failed to get quality options: {:?}", e));
+            report_error_for(context, &error).unwrap_or_default();
+            error
+        })?;
+
+    let selected = quality_value(index);
+    for i in 0..quality_options.length() {
+        let option = quality_options.get(i)
+            .ok_or_else(|| {
+                let error = VideoError::VideoOperationFailed("Failed to get quality option".to_string());
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?
+            .dyn_into::<Element>()
+            .map_err(|e| {
+                let error = VideoError::VideoOperationFailed(format!("Failed to convert Node to Element: {:?}", e));
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?;
+
+        let class_name = if option.get_attribute(QUALITY_ATTRIBUTE).as_deref() == Some(selected.as_str()) {
+            &element_classes.quality_option_active
+        } else {
+            &element_classes.quality_option
+        };
+        option.set_attribute("class", class_name)
+            .map_err(|e| {
+                let error = VideoError::VideoOperationFailed(format!("Failed to set quality option class: {:?}", e));
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ladder() -> Vec<RenditionInfo> {
+        vec![
+            RenditionInfo { bandwidth: 800_000, width: Some(640), height: Some(360) },
+            RenditionInfo { bandwidth: 2_800_000, width: Some(1280), height: Some(720) },
+            RenditionInfo::new(96_000),
+        ]
+    }
+
+    #[test]
+    fn test_rendition_options_are_sorted_by_bandwidth() {
+        let options = rendition_options(&ladder(), Some(1));
+        let labels: Vec<&str> = options.iter().map(|option| option.label.as_str()).collect();
+        assert_eq!(labels, ["Auto (720p)", "720p · 2.8 Mbps", "360p · 800 kbps", "96 kbps"]);
+        let indices: Vec<Option<usize>> = options.iter().map(|option| option.index).collect();
+        assert_eq!(indices, [None, Some(1), Some(0), Some(2)]);
+    }
+
+    #[test]
+    fn test_progressive_options_use_labels_or_file_names() {
+        let source = QualitySource::Progressive(vec![
+            VideoSource::new("media/1080.mp4".to_string(), "video/mp4".to_string(), Some("1080p".to_string())),
+            VideoSource::new("https://example.com/media/480.mp4?token=1".to_string(), "video/mp4".to_string(), None),
+        ]);
+        let labels: Vec<String> = source.options().into_iter().map(|option| option.label).collect();
+        assert_eq!(labels, ["Auto", "1080p", "480.mp4"]);
+        assert_eq!(source.preference_labels(), ["1080p", "480.mp4"]);
+    }
+
+    #[test]
+    fn test_quality_values_round_trip() {
+        for index in [None, Some(0), Some(3)] {
+            assert_eq!(parse_quality_value(&quality_value(index)), Some(index));
+        }
+        assert_eq!(parse_quality_value("high"), None);
+    }
+}
@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+use web_sys::Element;
+use crate::logger::Logger;
+use crate::player::context::PlayerContext;
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::loader::set_start_time;
+use crate::player::time::format_time;
+use crate::storage::local_storage;
+
+/// Schema version written with the saved positions.
+pub const RESUME_VERSION: u64 = 1;
+// localStorage key the positions of every video are kept under
+const RESUME_STORAGE_KEY: &str = "videoPlayer.positions";
+// Videos remembered before the least recently watched is forgotten
+pub const RESUME_CAPACITY: usize = 100;
+// Positions closer to the start than this are not worth offering
+const MIN_RESUME_POSITION: f64 = 10.0;
+// A video within this many seconds, or this share of its duration, of the end counts as watched
+const WATCHED_REMAINING: f64 = 15.0;
+const WATCHED_FRACTION: f64 = 0.95;
+// Milliseconds between saves from `timeupdate`
+const SAVE_INTERVAL_MS: f64 = 5000.0;
+
+// Prompt buttons
+const PROMPT_ACTION_RESUME: &str = "resume";
+const PROMPT_ACTION_RESTART: &str = "restart";
+
+/// What happens when a video the viewer has watched part of loads again.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResumeMode {
+    /// Always start from the beginning, and remember nothing.
+    Off,
+    /// Ask with a "Resume from 12:34?" prompt.
+    #[default]
+    Prompt,
+    /// Seek straight to where the viewer left off.
+    Auto,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResumeEntry {
+    pub id: String,
+    pub position: f64,
+    pub duration: f64,
+    /// When the position was saved, in milliseconds since the epoch.
+    pub updated: f64,
+}
+
+/// Saved positions, most recently watched first, bounded to `capacity` entries.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResumePositions {
+    entries: Vec<ResumeEntry>,
+    capacity: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredPositions {
+    version: u64,
+    entries: Vec<ResumeEntry>,
+}
+
+impl ResumePositions {
+    pub fn new(capacity: usize) -> ResumePositions {
+        ResumePositions { entries: Vec::new(), capacity }
+    }
+
+    /// Reads saved positions back; anything unreadable or from a newer version starts empty.
+    pub fn parse(text: &str, capacity: usize) -> ResumePositions {
+        let mut positions = ResumePositions::new(capacity);
+        let Ok(blob) = serde_json::from_str::<Value>(text) else {
+            return positions;
+        };
+        if blob.get("version").and_then(Value::as_u64).is_none_or(|version| version > RESUME_VERSION) {
+            return positions;
+        }
+        if let Ok(stored) = serde_json::from_value::<StoredPositions>(blob) {
+            positions.entries = stored.entries
+                .into_iter()
+                .filter(|entry| entry.position.is_finite() && entry.duration.is_finite())
+                .collect();
+            positions.entries.sort_by(|a, b| b.updated.total_cmp(&a.updated));
+            positions.entries.truncate(capacity);
+        }
+        positions
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(&StoredPositions { version: RESUME_VERSION, entries: self.entries.clone() }).unwrap_or_default()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ResumeEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Saves a position as the most recent one, forgetting the least recently
+    /// watched video once there are more than `capacity`.
+    pub fn record(&mut self, entry: ResumeEntry) {
+        self.remove(&entry.id);
+        self.entries.insert(0, entry);
+        self.entries.truncate(self.capacity);
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.entries.retain(|entry| entry.id != id);
+    }
+
+    /// Keeps only the `count` most recent entries.
+    pub fn truncate(&mut self, count: usize) {
+        self.entries.truncate(count);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    pub fn ids(&self) -> Vec<&str> {
+        self.entries.iter().map(|entry| entry.id.as_str()).collect()
+    }
+}
+
+/// Whether playback at `position` is close enough to the end to count as finished.
+pub fn is_watched(position: f64, duration: f64) -> bool {
+    duration.is_finite() && duration > 0.0
+        && (duration - position <= WATCHED_REMAINING || position >= duration * WATCHED_FRACTION)
+}
+
+/// Where a video should resume from, or `None` to start at the beginning: near
+/// the start there is nothing to resume, and near the end it has been watched.
+pub fn resume_target(position: f64, duration: f64) -> Option<f64> {
+    (position >= MIN_RESUME_POSITION && !is_watched(position, duration)).then_some(position)
+}
+
+/// Resume bookkeeping for one player.
+#[derive(Default)]
+pub struct ResumeState {
+    pub mode: ResumeMode,
+    /// Identifies the video in the saved positions, `None` when resuming is off.
+    pub content_id: Option<String>,
+    pub prompt: Option<Element>,
+    // Time of the last save from `timeupdate`, in milliseconds since the epoch
+    last_saved: f64,
+}
+
+fn load_positions() -> ResumePositions {
+    local_storage()
+        .and_then(|storage| storage.get_item(RESUME_STORAGE_KEY).ok().flatten())
+        .map(|text| ResumePositions::parse(&text, RESUME_CAPACITY))
+        .unwrap_or_else(|| ResumePositions::new(RESUME_CAPACITY))
+}
+
+/// Saves the positions, dropping the older half and trying once more if storage
+/// is full. Storage that is unavailable just means positions are not remembered.
+fn store_positions(positions: &mut ResumePositions) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+    if storage.set_item(RESUME_STORAGE_KEY, &positions.serialize()).is_ok() {
+        return;
+    }
+    positions.truncate(positions.len() / 2);
+    if let Err(e) = storage.set_item(RESUME_STORAGE_KEY, &positions.serialize()) {
+        Logger::warn(&format!("Failed to save playback position: {:?}", e)).unwrap_or_default();
+    }
+}
+
+/// Looks up where the video saved as `content_id` was left and resumes there or
+/// offers to, depending on the player's mode. Called whenever media is loaded; a
+/// prompt still showing for the previous video is removed first.
+pub fn restore_position_for(context: &PlayerContext, content_id: Option<String>, start_time: f64) -> Result<(), VideoError> {
+    dismiss_resume_prompt_for(context);
+    let mode = {
+        let mut resume = context.resume.borrow_mut();
+        let mode = resume.mode;
+        resume.content_id = content_id.filter(|_| mode != ResumeMode::Off);
+        resume.last_saved = 0.0;
+        resume.mode
+    };
+    let Some(id) = context.resume.borrow().content_id.clone() else {
+        return Ok(());
+    };
+    // An explicit start time wins over the saved position
+    if start_time > 0.0 {
+        return Ok(());
+    }
+    let Some(position) = load_positions().get(&id).and_then(|entry| resume_target(entry.position, entry.duration)) else {
+        return Ok(());
+    };
+    match mode {
+        ResumeMode::Auto => set_start_time(&context.video_element()?, position),
+        _ => show_resume_prompt_for(context, position)
+            .map_err(|e| VideoError::VideoOperationFailed(format!("Failed to show resume prompt: {:?}", e))),
+    }
+}
+
+/// Saves the current position of this video. From `timeupdate` saves are spaced
+/// out; `force` saves straight away, as when the page is being hidden.
+pub fn record_position_for(context: &PlayerContext, force: bool) -> Result<(), JsValue> {
+    let now = js_sys::Date::now();
+    let id = {
+        let resume = context.resume.borrow();
+        let Some(id) = resume.content_id.clone() else {
+            return Ok(());
+        };
+        // Until the viewer answers the prompt the saved position must not be overwritten
+        if resume.prompt.is_some() || (!force && now - resume.last_saved < SAVE_INTERVAL_MS) {
+            return Ok(());
+        }
+        id
+    };
+    let video_element = context.video_element()?;
+    let position = video_element.current_time();
+    let duration = video_element.duration();
+    if !duration.is_finite() || duration <= 0.0 {
+        return Ok(());
+    }
+    context.resume.borrow_mut().last_saved = now;
+    let mut positions = load_positions();
+    match resume_target(position, duration) {
+        Some(position) => positions.record(ResumeEntry { id, position, duration, updated: now }),
+        // Finished videos start over next time
+        None => positions.remove(&id),
+    }
+    store_positions(&mut positions);
+    Ok(())
+}
+
+/// Inserts the "Resume from 12:34?" prompt after the video element.
+fn show_resume_prompt_for(context: &PlayerContext, position: f64) -> Result<(), JsValue> {
+    let document = web_sys::window().ok_or(VideoError::WindowNotFound)?.document().ok_or(VideoError::DocumentNotFound)?;
+    let video_element = context.video_element()?;
+
+    let prompt = document.create_element("div")?;
+    prompt.set_attribute("class", &context.classes.resume_prompt)?;
+    prompt.set_attribute("role", "alertdialog")?;
+    let message = document.create_element("span")?;
+    message.set_text_content(Some(&format!("Resume from {}?", format_time(position))));
+    prompt.append_child(&message)?;
+    for (action, label) in [(PROMPT_ACTION_RESUME, "Resume"), (PROMPT_ACTION_RESTART, "Start over")] {
+        let button = document.create_element("button")?;
+        button.set_attribute("type", "button")?;
+        button.set_attribute("data-action", action)?;
+        button.set_attribute("data-position", &position.to_string())?;
+        button.set_text_content(Some(label));
+        prompt.append_child(&button)?;
+    }
+    video_element.after_with_node_1(&prompt)?;
+    context.track_element(prompt.clone());
+    context.resume.borrow_mut().prompt = Some(prompt);
+    Ok(())
+}
+
+/// Handles a click inside the resume prompt: seeks to the saved position or
+/// starts over, then removes the prompt. Clicks anywhere else are ignored.
+pub fn resume_prompt_click_for(context: &PlayerContext, target: &Element) -> Result<(), JsValue> {
+    let in_prompt = context.resume.borrow().prompt.as_ref().is_some_and(|prompt| prompt.contains(Some(target)));
+    if !in_prompt {
+        return Ok(());
+    }
+    let Some(button) = target.closest("[data-action]")? else {
+        return Ok(());
+    };
+    let position = button.get_attribute("data-position").and_then(|position| position.parse::<f64>().ok());
+    match (button.get_attribute("data-action").as_deref(), position) {
+        (Some(PROMPT_ACTION_RESUME), Some(position)) => {
+            Logger::debug(&format!("Resuming from {}", position)).map_err(|e| {
+                let error = VideoError::VideoOperationFailed(e.to_string());
+                report_error_for(context, &error).unwrap_or_default();
+                error
+            })?;
+            set_start_time(&context.video_element()?, position)?;
+        }
+        (Some(PROMPT_ACTION_RESTART), _) => {}
+        _ => return Ok(()),
+    }
+    dismiss_resume_prompt_for(context);
+    Ok(())
+}
+
+/// Removes the prompt, for example once playback starts without an answer.
+pub fn dismiss_resume_prompt_for(context: &PlayerContext) {
+    let prompt = context.resume.borrow_mut().prompt.take();
+    if let Some(prompt) = prompt {
+        prompt.remove();
+        context.untrack_element(&prompt);
+    }
+}
+
+/// Forgets this video's position, as when it plays to the end.
+pub fn forget_position_for(context: &PlayerContext) {
+    let Some(id) = context.resume.borrow().content_id.clone() else {
+        return;
+    };
+    let mut positions = load_positions();
+    if positions.get(&id).is_some() {
+        positions.remove(&id);
+        store_positions(&mut positions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, position: f64, updated: f64) -> ResumeEntry {
+        ResumeEntry { id: id.to_string(), position, duration: 600.0, updated }
+    }
+
+    #[test]
+    fn test_positions_are_least_recently_used() {
+        let mut positions = ResumePositions::new(3);
+        for (index, id) in ["a", "b", "c"].iter().enumerate() {
+            positions.record(entry(id, 60.0, index as f64));
+        }
+        assert_eq!(positions.ids(), ["c", "b", "a"]);
+        // Watching "a" again makes it the most recent, so "b" is the one evicted
+        positions.record(entry("a", 120.0, 3.0));
+        positions.record(entry("d", 30.0, 4.0));
+        assert_eq!(positions.ids(), ["d", "a", "c"]);
+        assert_eq!(positions.get("a").map(|entry| entry.position), Some(120.0));
+        positions.remove("c");
+        assert_eq!(positions.ids(), ["d", "a"]);
+    }
+
+    #[test]
+    fn test_positions_round_trip() {
+        let mut positions = ResumePositions::new(2);
+        positions.record(entry("https://example.com/a.mp4", 754.5, 1.0));
+        positions.record(entry("lesson-2", 90.0, 2.0));
+        assert_eq!(ResumePositions::parse(&positions.serialize(), 2), positions);
+        // Older entries beyond the capacity are dropped on load
+        assert_eq!(ResumePositions::parse(&positions.serialize(), 1).ids(), ["lesson-2"]);
+        assert_eq!(ResumePositions::parse("garbage", 2).len(), 0);
+        assert_eq!(ResumePositions::parse(r#"{"version": 9, "entries": []}"#, 2).len(), 0);
+    }
+
+    #[test]
+    fn test_resume_target() {
+        assert_eq!(resume_target(754.0, 3600.0), Some(754.0));
+        assert_eq!(resume_target(3.0, 3600.0), None);
+        // The last 5%, or the last 15 seconds of a short video, count as watched
+        assert_eq!(resume_target(3500.0, 3600.0), None);
+        assert_eq!(resume_target(50.0, 60.0), None);
+        assert_eq!(resume_target(40.0, 60.0), Some(40.0));
+        assert!(!is_watched(100.0, f64::INFINITY));
+    }
+}
@@ -0,0 +1,307 @@
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, HtmlElement, KeyboardEvent, PointerEvent};
+use crate::logger::Logger;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::media::MediaBackend;
+use crate::player::play_pause::{pause_video_for, start_video_for};
+use crate::player::state::Action;
+use crate::player::store::dispatch_for;
+use crate::player::time::format_time;
+
+// Seconds the arrow keys move playback by
+const KEYBOARD_SEEK_STEP: f64 = 5.0;
+// Share of the duration Page Up and Page Down move playback by
+const KEYBOARD_PAGE_FRACTION: f64 = 0.1;
+// Primary mouse button, or a touch or pen contact
+const PRIMARY_BUTTON: i16 = 0;
+
+/// The elements the player builds inside the page's seek bar container.
+#[derive(Clone)]
+pub struct SeekBar {
+    pub root: Element,
+    pub buffered: Element,
+    pub played: Element,
+    pub thumb: Element,
+    pub tooltip: Element,
+}
+
+/// Share of the bar's width at `x`, clamped to the bar.
+pub fn fraction_at(x: f64, left: f64, width: f64) -> f64 {
+    if width <= 0.0 {
+        return 0.0;
+    }
+    ((x - left) / width).clamp(0.0, 1.0)
+}
+
+/// Buffered ranges as `(left, width)` percentages of the bar.
+pub fn buffered_segments(ranges: &[(f64, f64)], duration: f64) -> Vec<(f64, f64)> {
+    if !duration.is_finite() || duration <= 0.0 {
+        return Vec::new();
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| (start.clamp(0.0, duration), end.clamp(0.0, duration)))
+        .filter(|(start, end)| end > start)
+        .map(|(start, end)| (start / duration * 100.0, (end - start) / duration * 100.0))
+        .collect()
+}
+
+/// Where a key press on the focused seek bar moves playback to.
+pub fn keyboard_seek_target(key: &str, current_time: f64, duration: f64) -> Option<f64> {
+    if !duration.is_finite() || duration <= 0.0 {
+        return None;
+    }
+    let target = match key {
+        "ArrowLeft" | "ArrowDown" => current_time - KEYBOARD_SEEK_STEP,
+        "ArrowRight" | "ArrowUp" => current_time + KEYBOARD_SEEK_STEP,
+        "PageDown" => current_time - duration * KEYBOARD_PAGE_FRACTION,
+        "PageUp" => current_time + duration * KEYBOARD_PAGE_FRACTION,
+        "Home" => 0.0,
+        "End" => duration,
+        _ => return None,
+    };
+    Some(target.clamp(0.0, duration))
+}
+
+#[wasm_bindgen]
+pub fn seek_to(seconds: f64) -> Result<(), JsValue> {
+    seek_to_for(&default_context(), seconds)
+}
+
+/// Moves playback to `seconds`, clamped to the media's duration.
+pub fn seek_to_for(context: &PlayerContext, seconds: f64) -> Result<(), JsValue> {
+    Logger::debug(&format!("Seeking to {}", seconds)).map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    if !seek_media(&context.video_element()?, seconds) {
+        return Ok(());
+    }
+    render_seek_bar_for(context)
+}
+
+/// Moves `media` to `seconds` within its duration. Returns `false` without seeking
+/// while the duration is unknown or the media is live.
+pub fn seek_media(media: &impl MediaBackend, seconds: f64) -> bool {
+    let duration = media.duration();
+    if !duration.is_finite() {
+        return false;
+    }
+    media.set_current_time(seconds.clamp(0.0, duration));
+    true
+}
+
+/// Builds the buffered, played, thumb and tooltip elements inside the seek bar.
+/// Returns `Ok(())` without doing anything when the page has no seek bar.
+pub fn setup_seek_bar_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Ok(root) = context.element(&context.ids.seek_bar) else {
+        return Ok(());
+    };
+    let document = web_sys::window().ok_or(VideoError::WindowNotFound)?.document().ok_or(VideoError::DocumentNotFound)?;
+    let classes = &context.classes;
+    // Setting listeners up again rebuilds the bar rather than stacking a second one
+    if let Some(previous) = context.seek_bar.borrow_mut().take() {
+        for element in [previous.buffered, previous.played, previous.thumb, previous.tooltip] {
+            element.remove();
+        }
+    }
+
+    root.set_attribute("class", &classes.seek_bar)?;
+    root.set_attribute("role", "slider")?;
+    root.set_attribute("tabindex", "0")?;
+    root.set_attribute("aria-valuemin", "0")?;
+    if !root.has_attribute("aria-label") {
+        root.set_attribute("aria-label", "Seek")?;
+    }
+
+    let mut children = Vec::new();
+    for class_name in [&classes.seek_bar_buffered, &classes.seek_bar_played, &classes.seek_bar_thumb, &classes.seek_bar_tooltip] {
+        let element = document.create_element("div")?;
+        element.set_attribute("class", class_name)?;
+        root.append_child(&element)?;
+        context.track_element(element.clone());
+        children.push(element);
+    }
+    let [buffered, played, thumb, tooltip]: [Element; 4] = children.try_into().map_err(|_| VideoError::StateError("Seek bar elements missing".to_string()))?;
+    *context.seek_bar.borrow_mut() = Some(SeekBar { root, buffered, played, thumb, tooltip });
+    render_seek_bar_for(context)
+}
+
+/// Draws playback progress and the buffered ranges.
+pub fn render_seek_bar_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Some(seek_bar) = context.seek_bar.borrow().clone() else {
+        return Ok(());
+    };
+    let video_element = context.video_element()?;
+    let duration = video_element.duration();
+    let current_time = video_element.current_time();
+    let percent = if duration.is_finite() && duration > 0.0 {
+        (current_time / duration * 100.0).clamp(0.0, 100.0)
+    } else {
+        0.0
+    };
+
+    seek_bar.played.set_attribute("style", &format!("width: {}%;", percent))?;
+    seek_bar.thumb.set_attribute("style", &format!("left: {}%;", percent))?;
+    if duration.is_finite() {
+        seek_bar.root.set_attribute("aria-valuemax", &format!("{:.0}", duration))?;
+        seek_bar.root.set_attribute("aria-valuenow", &format!("{:.0}", current_time))?;
+        seek_bar.root.set_attribute("aria-valuetext", &format!("{} of {}", format_time(current_time), format_time(duration)))?;
+    }
+
+    let document = web_sys::window().ok_or(VideoError::WindowNotFound)?.document().ok_or(VideoError::DocumentNotFound)?;
+    seek_bar.buffered.set_text_content(None);
+    for (left, width) in buffered_segments(&MediaBackend::buffered(&video_element), duration) {
+        let range = document.create_element("div")?;
+        range.set_attribute("style", &format!("left: {}%; width: {}%;", left, width))?;
+        seek_bar.buffered.append_child(&range)?;
+    }
+    Ok(())
+}
+
+/// Time under the pointer, or `None` when the duration is not known yet.
+fn time_at(context: &PlayerContext, seek_bar: &SeekBar, event: &PointerEvent) -> Result<Option<(f64, f64)>, JsValue> {
+    let duration = context.video_element()?.duration();
+    if !duration.is_finite() || duration <= 0.0 {
+        return Ok(None);
+    }
+    let rect = seek_bar.root.get_bounding_client_rect();
+    let fraction = fraction_at(event.client_x() as f64, rect.left(), rect.width());
+    Ok(Some((fraction, fraction * duration)))
+}
+
+fn show_tooltip(context: &PlayerContext, seek_bar: &SeekBar, fraction: f64, time: f64) -> Result<(), JsValue> {
+    seek_bar.tooltip.set_text_content(Some(&format_time(time)));
+    seek_bar.tooltip.set_attribute("style", &format!("left: {}%;", fraction * 100.0))?;
+    seek_bar.tooltip.set_attribute("class", &context.classes.seek_bar_tooltip_show)?;
+    Ok(())
+}
+
+/// Starts a drag: captures the pointer, pauses playback and seeks to the pointer.
+pub fn seek_bar_pointer_down_for(context: &PlayerContext, event: &PointerEvent) -> Result<(), JsValue> {
+    let Some(seek_bar) = context.seek_bar.borrow().clone() else {
+        return Ok(());
+    };
+    if event.button() != PRIMARY_BUTTON {
+        return Ok(());
+    }
+    let Some((fraction, time)) = time_at(context, &seek_bar, event)? else {
+        return Ok(());
+    };
+    event.prevent_default();
+    seek_bar.root.set_pointer_capture(event.pointer_id())?;
+    if let Ok(root) = seek_bar.root.clone().dyn_into::<HtmlElement>() {
+        root.focus().unwrap_or_default();
+    }
+
+    let was_playing = !context.video_element()?.paused();
+    dispatch_for(context, Action::SeekDragStarted { was_playing })?;
+    if was_playing {
+        pause_video_for(context)?;
+    }
+    seek_bar.root.set_attribute("class", &context.classes.seek_bar_dragging)?;
+    show_tooltip(context, &seek_bar, fraction, time)?;
+    seek_to_for(context, time)
+}
+
+/// Updates the hover tooltip, and scrubs while a drag is in progress.
+pub fn seek_bar_pointer_move_for(context: &PlayerContext, event: &PointerEvent) -> Result<(), JsValue> {
+    let Some(seek_bar) = context.seek_bar.borrow().clone() else {
+        return Ok(());
+    };
+    let Some((fraction, time)) = time_at(context, &seek_bar, event)? else {
+        return Ok(());
+    };
+    show_tooltip(context, &seek_bar, fraction, time)?;
+    if context.state()?.seek_drag.is_some() {
+        seek_to_for(context, time)?;
+    }
+    Ok(())
+}
+
+/// Ends a drag, resuming playback if it was playing when the drag started.
+pub fn seek_bar_pointer_up_for(context: &Rc<PlayerContext>, event: &PointerEvent) -> Result<(), JsValue> {
+    let Some(seek_bar) = context.seek_bar.borrow().clone() else {
+        return Ok(());
+    };
+    let Some(was_playing) = context.state()?.seek_drag else {
+        return Ok(());
+    };
+    dispatch_for(context, Action::SeekDragEnded)?;
+    if seek_bar.root.has_pointer_capture(event.pointer_id()) {
+        seek_bar.root.release_pointer_capture(event.pointer_id())?;
+    }
+    seek_bar.root.set_attribute("class", &context.classes.seek_bar)?;
+    seek_bar.tooltip.set_attribute("class", &context.classes.seek_bar_tooltip)?;
+    if was_playing {
+        start_video_for(context)?;
+    }
+    Ok(())
+}
+
+/// Hides the tooltip once the pointer leaves the bar, unless it is dragging.
+pub fn seek_bar_pointer_leave_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Some(seek_bar) = context.seek_bar.borrow().clone() else {
+        return Ok(());
+    };
+    if context.state()?.seek_drag.is_none() {
+        seek_bar.tooltip.set_attribute("class", &context.classes.seek_bar_tooltip)?;
+    }
+    Ok(())
+}
+
+/// Seeks with the arrow, Page Up/Down, Home and End keys while the bar has focus.
+pub fn seek_bar_key_down_for(context: &PlayerContext, event: &KeyboardEvent) -> Result<(), JsValue> {
+    let video_element = context.video_element()?;
+    let Some(target) = keyboard_seek_target(&event.key(), video_element.current_time(), video_element.duration()) else {
+        return Ok(());
+    };
+    event.prevent_default();
+    seek_to_for(context, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::media::fake::FakeMedia;
+
+    #[test]
+    fn test_fraction_is_clamped_to_bar() {
+        assert_eq!(fraction_at(150.0, 100.0, 200.0), 0.25);
+        assert_eq!(fraction_at(50.0, 100.0, 200.0), 0.0);
+        assert_eq!(fraction_at(400.0, 100.0, 200.0), 1.0);
+        assert_eq!(fraction_at(100.0, 100.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_seek_media() {
+        let media = FakeMedia::new(60.0);
+        assert!(seek_media(&media, 75.0));
+        assert_eq!(media.current_time(), 60.0);
+        assert!(seek_media(&media, -3.0));
+        assert_eq!(media.current_time(), 0.0);
+        assert!(!seek_media(&FakeMedia::new(f64::INFINITY), 10.0));
+        media.set_buffered(vec![(0.0, 30.0)]);
+        assert_eq!(buffered_segments(&media.buffered(), media.duration()), vec![(0.0, 50.0)]);
+    }
+
+    #[test]
+    fn test_buffered_segments() {
+        assert_eq!(buffered_segments(&[(0.0, 10.0), (50.0, 120.0), (30.0, 30.0)], 100.0), vec![(0.0, 10.0), (50.0, 50.0)]);
+        assert!(buffered_segments(&[(0.0, 10.0)], f64::NAN).is_empty());
+        assert!(buffered_segments(&[(0.0, 10.0)], f64::INFINITY).is_empty());
+    }
+
+    #[test]
+    fn test_keyboard_seek_target() {
+        assert_eq!(keyboard_seek_target("ArrowRight", 10.0, 60.0), Some(15.0));
+        assert_eq!(keyboard_seek_target("ArrowLeft", 2.0, 60.0), Some(0.0));
+        assert_eq!(keyboard_seek_target("PageUp", 10.0, 60.0), Some(16.0));
+        assert_eq!(keyboard_seek_target("End", 10.0, 60.0), Some(60.0));
+        assert_eq!(keyboard_seek_target("Enter", 10.0, 60.0), None);
+        assert_eq!(keyboard_seek_target("ArrowRight", 10.0, f64::NAN), None);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::player::abr::RenditionInfo;
+use crate::player::error::VideoError;
+
+/// Shortest wait between reloads of a live manifest. Manifests may ask for none at
+/// all, which would reload them in a tight loop.
+pub const MIN_REFRESH_INTERVAL: f64 = 1.0;
+
+/// Inclusive byte range, matching the HTTP `Range` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn from_length(start: u64, length: u64) -> ByteRange {
+        ByteRange {
+            start,
+            end: start + length.saturating_sub(1),
+        }
+    }
+
+    pub fn header_value(&self) -> String {
+        format!("bytes={}-{}", self.start, self.end)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentKind {
+    Init,
+    Media,
+    /// Segment index data handed back through `index_loaded` instead of being appended.
+    Index,
+}
+
+/// A segment the MSE pipeline should fetch and append next.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentRequest {
+    pub url: String,
+    pub byte_range: Option<ByteRange>,
+    pub kind: SegmentKind,
+    /// Where the segment starts on the media element timeline, in seconds.
+    pub start: f64,
+    pub duration: f64,
+    /// The segment does not continue the timeline of the one appended before it.
+    pub discontinuity: bool,
+}
+
+/// Turns a streaming manifest into an ordered list of segment requests.
+/// Implementations are pure Rust; all network and MSE work lives in `mse`.
+pub trait SegmentProvider {
+    /// MIME type including codecs, passed to `addSourceBuffer`.
+    fn mime_type(&self) -> String;
+
+    /// URL of the manifest that has to be (re)loaded before more segments are available.
+    fn manifest_url(&self) -> String;
+
+    /// True until a manifest for the current selection has been loaded.
+    fn needs_manifest(&self) -> bool;
+
+    fn update_manifest(&mut self, text: &str) -> Result<(), VideoError>;
+
+    /// Receives the bytes of a `SegmentKind::Index` request.
+    fn index_loaded(&mut self, _data: &[u8]) -> Result<(), VideoError> {
+        Ok(())
+    }
+
+    /// The next segment to append, or `None` if nothing is available right now.
+    fn next_segment(&mut self) -> Option<SegmentRequest>;
+
+    /// Restarts segment selection from the segment covering `time`.
+    fn seek(&mut self, time: f64);
+
+    /// Total duration for on-demand content, `None` while the stream is live.
+    fn duration(&self) -> Option<f64>;
+
+    /// How long to wait before reloading a live manifest, `None` once it has ended.
+    fn refresh_interval(&self) -> Option<f64>;
+
+    /// Every segment has been handed out and no more will follow.
+    fn is_complete(&self) -> bool;
+
+    /// Renditions this provider can switch between, in manifest order.
+    fn renditions(&self) -> Vec<RenditionInfo> {
+        Vec::new()
+    }
+
+    fn rendition(&self) -> usize {
+        0
+    }
+
+    /// Continues from the next segment on with the rendition at `index`.
+    fn select_rendition(&mut self, _index: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_range_header() {
+        let range = ByteRange::from_length(1000, 500);
+        assert_eq!(range, ByteRange { start: 1000, end: 1499 });
+        assert_eq!(range.header_value(), "bytes=1000-1499");
+    }
+}
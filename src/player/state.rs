@@ -1,15 +1,228 @@
-use once_cell::sync::Lazy;
-use std::sync::Mutex;
+use std::collections::VecDeque;
+use serde::Serialize;
 
-pub static VIDEO_STATE: Lazy<Mutex<VideoState>> = Lazy::new(|| {
-    Mutex::new(VideoState {
-        is_muted: false,
-        playback_speed: 1.0,
-    })
-});
-
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct VideoState {
     pub is_muted: bool,
+    /// Element volume, from 0 to 1.
+    pub volume: f64,
+    /// Last level above zero, which unmuting restores.
+    pub last_volume: f64,
     pub playback_speed: f64,
-} 
\ No newline at end of file
+    /// Rendition picked in the quality menu, `None` for automatic selection.
+    pub quality: Option<usize>,
+    /// Caption track being shown, `None` when captions are off.
+    pub caption_track: Option<usize>,
+    /// Track `toggle_captions` turns back on.
+    pub last_caption_track: usize,
+    /// Set while the seek bar is being dragged, to whether playback resumes afterwards.
+    pub seek_drag: Option<bool>,
+}
+
+impl Default for VideoState {
+    fn default() -> Self {
+        VideoState {
+            is_muted: false,
+            volume: 1.0,
+            last_volume: 1.0,
+            playback_speed: 1.0,
+            quality: None,
+            caption_track: None,
+            last_caption_track: 0,
+            seek_drag: None,
+        }
+    }
+}
+
+/// Every change to a `VideoState` is one of these, applied by `reduce`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// The element's volume or mute state changed.
+    VolumeChanged { volume: f64, muted: bool },
+    PlaybackSpeedChanged { speed: f64 },
+    QualitySelected { quality: Option<usize> },
+    CaptionTrackSelected { track: Option<usize> },
+    SeekDragStarted { was_playing: bool },
+    SeekDragEnded,
+    /// Other media is being loaded, so its renditions and tracks no longer apply.
+    MediaUnloaded,
+}
+
+/// The state after `action`. Pure, so every transition can be replayed and tested.
+pub fn reduce(state: &VideoState, action: &Action) -> VideoState {
+    let mut next = state.clone();
+    match action {
+        Action::VolumeChanged { volume, muted } => {
+            next.volume = volume.clamp(0.0, 1.0);
+            next.is_muted = *muted;
+            if next.volume > 0.0 {
+                next.last_volume = next.volume;
+            }
+        }
+        Action::PlaybackSpeedChanged { speed } => {
+            if speed.is_finite() && *speed > 0.0 {
+                next.playback_speed = *speed;
+            }
+        }
+        Action::QualitySelected { quality } => next.quality = *quality,
+        Action::CaptionTrackSelected { track } => {
+            next.caption_track = *track;
+            if let Some(track) = track {
+                next.last_caption_track = *track;
+            }
+        }
+        Action::SeekDragStarted { was_playing } => next.seek_drag = Some(*was_playing),
+        Action::SeekDragEnded => next.seek_drag = None,
+        Action::MediaUnloaded => {
+            next.quality = None;
+            next.caption_track = None;
+            next.seek_drag = None;
+        }
+    }
+    next
+}
+
+/// The most recent actions dispatched to a store, for stepping back through them
+/// while debugging. Actions beyond `capacity` are folded into the starting state.
+#[derive(Clone, Debug)]
+pub struct ActionLog {
+    initial: VideoState,
+    actions: VecDeque<Action>,
+    capacity: usize,
+}
+
+impl ActionLog {
+    pub fn new(initial: VideoState, capacity: usize) -> ActionLog {
+        ActionLog { initial, actions: VecDeque::new(), capacity: capacity.max(1) }
+    }
+
+    fn record(&mut self, action: Action) {
+        if self.actions.len() == self.capacity {
+            if let Some(oldest) = self.actions.pop_front() {
+                self.initial = reduce(&self.initial, &oldest);
+            }
+        }
+        self.actions.push_back(action);
+    }
+
+    pub fn actions(&self) -> Vec<Action> {
+        self.actions.iter().cloned().collect()
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// The state after the first `count` logged actions; 0 gives the state the log starts from.
+    pub fn state_at(&self, count: usize) -> VideoState {
+        self.actions.iter().take(count).fold(self.initial.clone(), |state, action| reduce(&state, action))
+    }
+}
+
+/// Holds one player's `VideoState`; the only way to change it is `dispatch`.
+#[derive(Default)]
+pub struct Store {
+    state: VideoState,
+    log: Option<ActionLog>,
+}
+
+impl Store {
+    pub fn state(&self) -> &VideoState {
+        &self.state
+    }
+
+    /// Applies `action` and returns the state before it. Actions that change nothing,
+    /// such as the element echoing a volume the player just set, are not logged.
+    pub fn dispatch(&mut self, action: Action) -> VideoState {
+        let next = reduce(&self.state, &action);
+        if let Some(log) = self.log.as_mut().filter(|_| next != self.state) {
+            log.record(action);
+        }
+        std::mem::replace(&mut self.state, next)
+    }
+
+    /// Starts logging actions, keeping the last `capacity` of them.
+    pub fn enable_log(&mut self, capacity: usize) {
+        self.log = Some(ActionLog::new(self.state.clone(), capacity));
+    }
+
+    pub fn disable_log(&mut self) {
+        self.log = None;
+    }
+
+    pub fn log(&self) -> Option<&ActionLog> {
+        self.log.as_ref()
+    }
+
+    /// Jumps to the state after the first `count` logged actions and returns the
+    /// state before the jump. The log itself is left as it is.
+    pub fn travel_to(&mut self, count: usize) -> Option<VideoState> {
+        let target = self.log.as_ref()?.state_at(count);
+        Some(std::mem::replace(&mut self.state, target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_volume_and_mute_stay_together() {
+        let state = reduce(&VideoState::default(), &Action::VolumeChanged { volume: 0.4, muted: true });
+        assert!(state.is_muted);
+        assert_eq!((state.volume, state.last_volume), (0.4, 0.4));
+        // Turning the level down to zero keeps the last audible level for unmuting
+        let state = reduce(&state, &Action::VolumeChanged { volume: 0.0, muted: false });
+        assert!(!state.is_muted);
+        assert_eq!((state.volume, state.last_volume), (0.0, 0.4));
+        let state = reduce(&state, &Action::VolumeChanged { volume: 3.0, muted: false });
+        assert_eq!(state.volume, 1.0);
+    }
+
+    #[test]
+    fn test_selections_and_unloading() {
+        let mut state = VideoState::default();
+        for action in [
+            Action::CaptionTrackSelected { track: Some(2) },
+            Action::CaptionTrackSelected { track: None },
+            Action::QualitySelected { quality: Some(1) },
+            Action::PlaybackSpeedChanged { speed: 1.5 },
+            Action::PlaybackSpeedChanged { speed: f64::NAN },
+            Action::SeekDragStarted { was_playing: true },
+        ] {
+            state = reduce(&state, &action);
+        }
+        assert_eq!((state.caption_track, state.last_caption_track), (None, 2));
+        assert_eq!((state.quality, state.playback_speed, state.seek_drag), (Some(1), 1.5, Some(true)));
+        let state = reduce(&state, &Action::MediaUnloaded);
+        assert_eq!((state.quality, state.caption_track, state.seek_drag), (None, None, None));
+        assert_eq!((state.playback_speed, state.last_caption_track), (1.5, 2));
+    }
+
+    #[test]
+    fn test_action_log_replays_and_travels() {
+        let mut store = Store::default();
+        store.dispatch(Action::PlaybackSpeedChanged { speed: 2.0 });
+        store.enable_log(2);
+        store.dispatch(Action::VolumeChanged { volume: 0.5, muted: false });
+        store.dispatch(Action::QualitySelected { quality: Some(0) });
+        store.dispatch(Action::CaptionTrackSelected { track: Some(1) });
+        store.dispatch(Action::CaptionTrackSelected { track: Some(1) });
+
+        // The oldest action was folded into the starting state
+        let log = store.log().unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.state_at(0).volume, 0.5);
+        assert_eq!(log.state_at(2), *store.state());
+
+        let previous = store.travel_to(1).unwrap();
+        assert_eq!(previous.caption_track, Some(1));
+        assert_eq!(store.state().caption_track, None);
+        assert_eq!((store.state().quality, store.state().playback_speed), (Some(0), 2.0));
+
+        store.disable_log();
+        assert!(store.travel_to(0).is_none());
+    }
+}
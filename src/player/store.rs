@@ -0,0 +1,63 @@
+use wasm_bindgen::prelude::*;
+use crate::player::captions::{render_captions_for, update_caption_active_state_for};
+use crate::player::context::PlayerContext;
+use crate::player::error::VideoError;
+use crate::player::mute::set_mute_button_text_for;
+use crate::player::playback_speed::update_playback_speed_active_state_for;
+use crate::player::quality::update_quality_active_state_for;
+use crate::player::state::{Action, VideoState};
+use crate::player::volume::render_volume_slider_for;
+
+/// Applies `action` to the player's state and brings the controls in line with
+/// whatever it changed.
+pub fn dispatch_for(context: &PlayerContext, action: Action) -> Result<(), VideoError> {
+    let (previous, state) = context.dispatch(action)?;
+    if previous != state {
+        render_state_for(context, Some(&previous), &state);
+    }
+    Ok(())
+}
+
+/// Draws the controls from `state`: only the parts that differ from `previous`,
+/// or all of them without one. Controls the page does not have are skipped.
+pub fn render_state_for(context: &PlayerContext, previous: Option<&VideoState>, state: &VideoState) {
+    if previous.is_none_or(|previous| (previous.volume, previous.is_muted) != (state.volume, state.is_muted)) {
+        set_mute_button_text_for(context, state.is_muted || state.volume == 0.0).unwrap_or_default();
+        render_volume_slider_for(context, state).unwrap_or_default();
+    }
+    if previous.is_none_or(|previous| previous.playback_speed != state.playback_speed) {
+        update_playback_speed_active_state_for(context, state.playback_speed).unwrap_or_default();
+    }
+    if previous.is_none_or(|previous| previous.quality != state.quality) {
+        update_quality_active_state_for(context, state.quality).unwrap_or_default();
+    }
+    if previous.is_none_or(|previous| previous.caption_track != state.caption_track) {
+        update_caption_active_state_for(context, state.caption_track).unwrap_or_default();
+        render_captions_for(context).unwrap_or_default();
+    }
+}
+
+/// Starts logging the player's actions, keeping the last `capacity`.
+pub fn enable_action_log_for(context: &PlayerContext, capacity: usize) {
+    context.store.borrow_mut().enable_log(capacity);
+}
+
+/// The logged actions, oldest first; empty when logging is off.
+pub fn action_log_for(context: &PlayerContext) -> Vec<Action> {
+    context.store.borrow().log().map(|log| log.actions()).unwrap_or_default()
+}
+
+/// Puts the player back in the state after the first `count` logged actions: the
+/// element's volume and speed, and every control.
+pub fn travel_to_for(context: &PlayerContext, count: usize) -> Result<(), JsValue> {
+    let Some(previous) = context.store.borrow_mut().travel_to(count) else {
+        return Err(VideoError::StateError("the action log is not enabled".to_string()).into());
+    };
+    let state = context.state()?;
+    let video_element = context.video_element()?;
+    video_element.set_volume(state.volume);
+    video_element.set_muted(state.is_muted);
+    video_element.set_playback_rate(state.playback_speed);
+    render_state_for(context, Some(&previous), &state);
+    Ok(())
+}
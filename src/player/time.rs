@@ -1,28 +1,17 @@
 use wasm_bindgen::prelude::*;
-use crate::player::{get_video_element, get_element_by_id};
 use crate::logger::Logger;
-use crate::player::error::{VideoError, show_error};
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::media::MediaBackend;
 
 #[wasm_bindgen]
 pub fn get_video_time() -> Result<f64, JsValue> {
-    Logger::info("Entering get_video_time()").map_err(|e| {
-        let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
-        error
-    })?;
-    let video_element = get_video_element()?;
-    Ok(video_element.current_time())
+    get_video_time_for(&default_context())
 }
 
 #[wasm_bindgen]
 pub fn get_video_duration() -> Result<f64, JsValue> {
-    Logger::info("Entering get_video_duration()").map_err(|e| {
-        let error = VideoError::VideoOperationFailed(e.to_string());
-        show_error(&error.to_string()).unwrap_or_default();
-        error
-    })?;
-    let video_element = get_video_element()?;
-    Ok(video_element.duration())
+    get_video_duration_for(&default_context())
 }
 
 #[wasm_bindgen]
@@ -34,17 +23,63 @@ pub fn format_time(seconds: f64) -> String {
 
 #[wasm_bindgen]
 pub async fn update_time_display() -> Result<(), JsValue> {
-    let video_element = get_video_element()?;
-    let current_time = video_element.current_time();
-    let duration = video_element.duration();
-    
-    let current_time_display = get_element_by_id("currentTime")?;
-    let total_time_display = get_element_by_id("totalTime")?;
-    
-    current_time_display.set_text_content(Some(&format_time(current_time)));
-    if !duration.is_nan() {
-        total_time_display.set_text_content(Some(&format_time(duration)));
+    update_time_display_for(&default_context())
+}
+
+pub fn get_video_time_for(context: &PlayerContext) -> Result<f64, JsValue> {
+    Logger::trace("Entering get_video_time()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let video_element = context.video_element()?;
+    Ok(video_element.current_time())
+}
+
+pub fn get_video_duration_for(context: &PlayerContext) -> Result<f64, JsValue> {
+    Logger::trace("Entering get_video_duration()").map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let video_element = context.video_element()?;
+    Ok(video_element.duration())
+}
+
+/// The current time and duration of `media` as displayed; the duration is `None`
+/// until the metadata has loaded.
+pub fn time_display(media: &impl MediaBackend) -> (String, Option<String>) {
+    let duration = media.duration();
+    (format_time(media.current_time()), (!duration.is_nan()).then(|| format_time(duration)))
+}
+
+pub fn update_time_display_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let (current_time, duration) = time_display(&context.video_element()?);
+
+    let current_time_display = context.element(&context.ids.current_time)?;
+    let total_time_display = context.element(&context.ids.total_time)?;
+
+    current_time_display.set_text_content(Some(&current_time));
+    if let Some(duration) = duration {
+        total_time_display.set_text_content(Some(&duration));
     }
-    
+
     Ok(())
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::media::fake::{block_on, FakeMedia};
+
+    #[test]
+    fn test_time_display_follows_playback() {
+        let media = FakeMedia::new(125.0);
+        assert_eq!(time_display(&media), ("0:00".to_string(), Some("2:05".to_string())));
+        block_on(media.play()).unwrap();
+        media.advance(61.5);
+        assert_eq!(time_display(&media).0, "1:01");
+        // No metadata yet
+        assert_eq!(time_display(&FakeMedia::new(f64::NAN)).1, None);
+    }
+}
@@ -0,0 +1,118 @@
+/// Resolves `reference` against `base` the way a browser resolves relative links,
+/// without going through `web_sys::Url` so manifest parsing stays testable natively.
+pub fn resolve_url(base: &str, reference: &str) -> String {
+    if reference.is_empty() {
+        return base.to_string();
+    }
+    if has_scheme(reference) {
+        return reference.to_string();
+    }
+
+    let (scheme, rest) = match base.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, base),
+    };
+
+    if let Some(network_path) = reference.strip_prefix("//") {
+        return match scheme {
+            Some(scheme) => format!("{}://{}", scheme, network_path),
+            None => format!("//{}", network_path),
+        };
+    }
+
+    // Split the base into authority and path, dropping any query or fragment
+    let rest = rest.split(['?', '#']).next().unwrap_or("");
+    let (authority, base_path) = if scheme.is_some() {
+        match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        }
+    } else {
+        ("", rest)
+    };
+    let prefix = match scheme {
+        Some(scheme) => format!("{}://{}", scheme, authority),
+        None => String::new(),
+    };
+
+    if reference.starts_with('?') || reference.starts_with('#') {
+        return format!("{}{}{}", prefix, base_path, reference);
+    }
+
+    let (reference_path, suffix) = match reference.find(['?', '#']) {
+        Some(index) => (&reference[..index], &reference[index..]),
+        None => (reference, ""),
+    };
+
+    let merged = if reference_path.starts_with('/') {
+        reference_path.to_string()
+    } else {
+        match base_path.rfind('/') {
+            Some(index) => format!("{}{}", &base_path[..=index], reference_path),
+            None => reference_path.to_string(),
+        }
+    };
+
+    format!("{}{}{}", prefix, remove_dot_segments(&merged), suffix)
+}
+
+fn has_scheme(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            let mut chars = scheme.chars();
+            chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        None => false,
+    }
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let absolute = path.starts_with('/');
+    let mut output: Vec<&str> = Vec::new();
+    let segments: Vec<&str> = path.split('/').collect();
+    let last = segments.len().saturating_sub(1);
+    for (index, segment) in segments.iter().enumerate() {
+        match *segment {
+            "." => {
+                if index == last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                if output.len() > usize::from(absolute) {
+                    output.pop();
+                }
+                if index == last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+    let joined = output.join("/");
+    if absolute && !joined.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_url() {
+        let base = "https://cdn.example.com/video/hls/master.m3u8?token=1";
+        assert_eq!(resolve_url(base, "720p/index.m3u8"), "https://cdn.example.com/video/hls/720p/index.m3u8");
+        assert_eq!(resolve_url(base, "../dash/manifest.mpd"), "https://cdn.example.com/video/dash/manifest.mpd");
+        assert_eq!(resolve_url(base, "/root.ts"), "https://cdn.example.com/root.ts");
+        assert_eq!(resolve_url(base, "//mirror.example.com/a.ts"), "https://mirror.example.com/a.ts");
+        assert_eq!(resolve_url(base, "http://other.example.com/b.ts"), "http://other.example.com/b.ts");
+        assert_eq!(resolve_url(base, "seg.ts?part=2"), "https://cdn.example.com/video/hls/seg.ts?part=2");
+        assert_eq!(resolve_url("https://cdn.example.com", "seg.ts"), "https://cdn.example.com/seg.ts");
+        assert_eq!(resolve_url("/media/list.m3u8", "./a/../b.ts"), "/media/b.ts");
+        assert_eq!(resolve_url("media/list.m3u8", "b.ts"), "media/b.ts");
+    }
+}
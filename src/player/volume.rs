@@ -0,0 +1,275 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{HtmlInputElement, KeyboardEvent, WheelEvent};
+use crate::logger::Logger;
+use crate::player::context::{default_context, PlayerContext};
+use crate::player::error::{report_error_for, VideoError};
+use crate::player::media::MediaBackend;
+use crate::player::preferences::update_preferences_for;
+use crate::player::state::{Action, VideoState};
+use crate::player::store::dispatch_for;
+
+// Loudness range the slider spans; its bottom end is silence
+const VOLUME_RANGE_DB: f64 = 50.0;
+// Share of the slider one arrow key press or wheel notch moves
+const VOLUME_STEP: f64 = 0.05;
+const VOLUME_PAGE_STEP: f64 = 0.2;
+// The slider is an <input type="range"> running from 0 to this
+const SLIDER_MAX: f64 = 100.0;
+// Level unmuting restores when nothing louder has been heard yet
+const DEFAULT_RESTORE_VOLUME: f64 = 1.0;
+
+/// Maps a slider position to an element volume so equal slider steps sound like
+/// equal changes in loudness.
+pub fn slider_to_volume(position: f64) -> f64 {
+    if position <= 0.0 {
+        return 0.0;
+    }
+    10f64.powf((position.min(1.0) - 1.0) * VOLUME_RANGE_DB / 20.0)
+}
+
+/// The inverse of `slider_to_volume`.
+pub fn volume_to_slider(volume: f64) -> f64 {
+    if volume <= 0.0 {
+        return 0.0;
+    }
+    (1.0 + 20.0 * volume.min(1.0).log10() / VOLUME_RANGE_DB).clamp(0.0, 1.0)
+}
+
+/// The slider position after a key press, or `None` for keys the slider ignores.
+pub fn keyboard_slider_target(key: &str, position: f64) -> Option<f64> {
+    let target = match key {
+        "ArrowUp" | "ArrowRight" => position + VOLUME_STEP,
+        "ArrowDown" | "ArrowLeft" => position - VOLUME_STEP,
+        "PageUp" => position + VOLUME_PAGE_STEP,
+        "PageDown" => position - VOLUME_PAGE_STEP,
+        "Home" => 0.0,
+        "End" => 1.0,
+        _ => return None,
+    };
+    Some(target.clamp(0.0, 1.0))
+}
+
+/// The slider position after a wheel turn; scrolling up turns the volume up a step.
+pub fn wheel_slider_target(delta_y: f64, position: f64) -> f64 {
+    if delta_y == 0.0 {
+        return position;
+    }
+    (position - VOLUME_STEP * delta_y.signum()).clamp(0.0, 1.0)
+}
+
+#[wasm_bindgen]
+pub fn set_volume(volume: f64) -> Result<(), JsValue> {
+    set_volume_for(&default_context(), volume)
+}
+
+#[wasm_bindgen]
+pub fn get_volume() -> Result<f64, JsValue> {
+    get_volume_for(&default_context())
+}
+
+/// Sets the element volume (0 to 1). A level above zero also unmutes; zero mutes
+/// and keeps the previous level for the next unmute.
+pub fn set_volume_for(context: &PlayerContext, volume: f64) -> Result<(), JsValue> {
+    Logger::debug(&format!("Setting volume to {}", volume)).map_err(|e| {
+        let error = VideoError::VideoOperationFailed(e.to_string());
+        report_error_for(context, &error).unwrap_or_default();
+        error
+    })?;
+    let video_element = context.video_element()?;
+    let action = apply_volume(&video_element, volume).inspect_err(|error| {
+        report_error_for(context, error).unwrap_or_default();
+    })?;
+    remember_volume_for(context, &action);
+    dispatch_for(context, action)?;
+    Ok(())
+}
+
+/// Sets the volume of `media` as `set_volume_for` describes, returning the action
+/// recording the result.
+pub fn apply_volume(media: &impl MediaBackend, volume: f64) -> Result<Action, VideoError> {
+    if !volume.is_finite() {
+        return Err(VideoError::VideoOperationFailed(format!("Invalid volume: {}", volume)));
+    }
+    let volume = volume.clamp(0.0, 1.0);
+    if volume > 0.0 {
+        media.set_volume(volume);
+        media.set_muted(false);
+    } else {
+        media.set_muted(true);
+    }
+    Ok(Action::VolumeChanged { volume: media.volume(), muted: media.muted() })
+}
+
+/// Where the slider sits for `media`; a muted player sits at silence.
+pub fn media_slider_position(media: &impl MediaBackend) -> f64 {
+    if media.muted() { 0.0 } else { volume_to_slider(media.volume()) }
+}
+
+pub fn get_volume_for(context: &PlayerContext) -> Result<f64, JsValue> {
+    let video_element = context.video_element()?;
+    Ok(video_element.volume())
+}
+
+/// The level unmuting returns to.
+pub fn restore_volume_for(context: &PlayerContext) -> f64 {
+    context.state().map(|state| state.last_volume).unwrap_or(DEFAULT_RESTORE_VOLUME)
+}
+
+/// Records the element's volume in the player's state after a `volumechange`,
+/// which redraws the slider and mute button. Nothing is saved: the element also
+/// changes for mutes the viewer never chose, such as `config.muted`, the muted
+/// autoplay fallback or a crossfade.
+pub fn sync_volume_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let video_element = context.video_element()?;
+    dispatch_for(context, Action::VolumeChanged { volume: video_element.volume(), muted: video_element.muted() })?;
+    Ok(())
+}
+
+/// Saves the level and mute state in `action` to the preferences, for changes
+/// the viewer asked for.
+pub fn remember_volume_for(context: &PlayerContext, action: &Action) {
+    if let Action::VolumeChanged { volume, muted } = *action {
+        update_preferences_for(context, |preferences| {
+            preferences.volume = Some(volume);
+            preferences.muted = Some(muted);
+        });
+    }
+}
+
+/// Moves the slider, if there is one, to the level in `state`.
+pub fn render_volume_slider_for(context: &PlayerContext, state: &VideoState) -> Result<(), JsValue> {
+    let Some(slider) = volume_slider(context) else {
+        return Ok(());
+    };
+    let silent = state.is_muted || state.volume == 0.0;
+    let position = if silent { 0.0 } else { volume_to_slider(state.volume) };
+    slider.set_value(&format!("{}", (position * SLIDER_MAX).round()));
+    slider.set_attribute("aria-valuetext", &format!("{}%", (state.volume * 100.0).round() as u32))?;
+    Ok(())
+}
+
+fn volume_slider(context: &PlayerContext) -> Option<HtmlInputElement> {
+    context.element(&context.ids.volume_slider).ok()?.dyn_into::<HtmlInputElement>().ok()
+}
+
+fn slider_position(slider: &HtmlInputElement) -> f64 {
+    slider.value().parse::<f64>().map(|value| value / SLIDER_MAX).unwrap_or(0.0).clamp(0.0, 1.0)
+}
+
+fn apply_slider_position(context: &PlayerContext, position: f64) -> Result<(), JsValue> {
+    set_volume_for(context, slider_to_volume(position))
+}
+
+/// Moves the volume by `steps` slider steps along the perceptual curve, as the
+/// keyboard shortcuts do. Turning a muted player up starts from silence.
+pub fn step_volume_for(context: &PlayerContext, steps: f64) -> Result<(), JsValue> {
+    let position = media_slider_position(&context.video_element()?);
+    apply_slider_position(context, (position + steps * VOLUME_STEP).clamp(0.0, 1.0))
+}
+
+/// Prepares the optional volume slider; without one only the mute button controls audio.
+pub fn setup_volume_slider_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Some(slider) = volume_slider(context) else {
+        return Ok(());
+    };
+    slider.set_type("range");
+    slider.set_min("0");
+    slider.set_max(&SLIDER_MAX.to_string());
+    slider.set_step("1");
+    if !slider.has_attribute("aria-label") {
+        slider.set_attribute("aria-label", "Volume")?;
+    }
+    sync_volume_for(context)?;
+    render_volume_slider_for(context, &context.state()?)
+}
+
+pub fn volume_slider_input_for(context: &PlayerContext) -> Result<(), JsValue> {
+    let Some(slider) = volume_slider(context) else {
+        return Ok(());
+    };
+    apply_slider_position(context, slider_position(&slider))
+}
+
+pub fn volume_slider_wheel_for(context: &PlayerContext, event: &WheelEvent) -> Result<(), JsValue> {
+    let Some(slider) = volume_slider(context) else {
+        return Ok(());
+    };
+    event.prevent_default();
+    apply_slider_position(context, wheel_slider_target(event.delta_y(), slider_position(&slider)))
+}
+
+pub fn volume_slider_key_down_for(context: &PlayerContext, event: &KeyboardEvent) -> Result<(), JsValue> {
+    let Some(slider) = volume_slider(context) else {
+        return Ok(());
+    };
+    let Some(position) = keyboard_slider_target(&event.key(), slider_position(&slider)) else {
+        return Ok(());
+    };
+    event.prevent_default();
+    apply_slider_position(context, position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::context::PlayerContext;
+    use crate::player::element_ids::{ElementClasses, ElementIds};
+    use crate::player::media::fake::{block_on, FakeMedia};
+    use crate::player::play_pause::play_media;
+    use crate::player::preferences::preferences_for;
+
+    #[test]
+    fn test_perceptual_curve() {
+        assert_eq!(slider_to_volume(0.0), 0.0);
+        assert_eq!(slider_to_volume(1.0), 1.0);
+        // Half way is 25 dB down, far quieter than half the amplitude
+        assert!((slider_to_volume(0.5) - 0.0562).abs() < 0.001);
+        for position in [0.1, 0.25, 0.5, 0.9] {
+            assert!((volume_to_slider(slider_to_volume(position)) - position).abs() < 1e-9);
+        }
+        assert_eq!(volume_to_slider(0.0), 0.0);
+        assert_eq!(volume_to_slider(0.000001), 0.0);
+    }
+
+    #[test]
+    fn test_keyboard_and_wheel_steps() {
+        assert_eq!(keyboard_slider_target("ArrowUp", 0.5), Some(0.55));
+        assert_eq!(keyboard_slider_target("ArrowLeft", 0.02), Some(0.0));
+        assert_eq!(keyboard_slider_target("End", 0.2), Some(1.0));
+        assert_eq!(keyboard_slider_target("m", 0.2), None);
+        assert_eq!(wheel_slider_target(-120.0, 0.5), 0.55);
+        assert_eq!(wheel_slider_target(3.0, 0.5), 0.45);
+        assert_eq!(wheel_slider_target(0.0, 0.5), 0.5);
+        assert_eq!(wheel_slider_target(-1.0, 0.98), 1.0);
+    }
+
+    #[test]
+    fn test_apply_volume() {
+        let media = FakeMedia::new(10.0);
+        media.set_muted(true);
+        // A level above zero unmutes
+        assert_eq!(apply_volume(&media, 0.3).unwrap(), Action::VolumeChanged { volume: 0.3, muted: false });
+        assert_eq!(apply_volume(&media, 7.0).unwrap(), Action::VolumeChanged { volume: 1.0, muted: false });
+        // Zero mutes and keeps the level for unmuting
+        assert_eq!(apply_volume(&media, 0.0).unwrap(), Action::VolumeChanged { volume: 1.0, muted: true });
+        assert_eq!(media_slider_position(&media), 0.0);
+        assert!(apply_volume(&media, f64::NAN).is_err());
+        assert!(media.muted());
+    }
+
+    #[test]
+    fn test_only_chosen_volume_changes_are_saved() {
+        let context = PlayerContext::new(ElementIds::default(), ElementClasses::default());
+        let media = FakeMedia::new(10.0);
+        media.block_unmuted_autoplay(true);
+        // The muted autoplay fallback reaches the state, as a volumechange does, but is not saved
+        assert!(block_on(play_media(&media, true)).unwrap());
+        context.dispatch(Action::VolumeChanged { volume: media.volume(), muted: media.muted() }).unwrap();
+        assert!(context.state().unwrap().is_muted);
+        assert_eq!(preferences_for(&context).muted, None);
+
+        remember_volume_for(&context, &apply_volume(&media, 0.4).unwrap());
+        let preferences = preferences_for(&context);
+        assert_eq!((preferences.volume, preferences.muted), (Some(0.4), Some(false)));
+    }
+}
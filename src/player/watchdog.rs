@@ -0,0 +1,594 @@
+use std::rc::Rc;
+use serde::Serialize;
+use crate::logger::Logger;
+use crate::player::config::VideoSource;
+use crate::player::context::PlayerContext;
+use crate::player::dash::is_dash_source;
+use crate::player::error::{report_error_for, resolve_errors_for, VideoError};
+use crate::player::events::{publish_for, PlayerEvent};
+use crate::player::fetch::sleep_ms;
+use crate::player::hls::{is_hls_source, HLS_MIME_TYPES};
+use crate::player::mse::is_media_source_supported;
+use crate::player::play_pause::start_video_for;
+use crate::player::loader::{reload_source_for, set_start_time};
+
+/// Seconds without playback moving before it counts as stuck, unless configured otherwise.
+pub const DEFAULT_STALL_TIMEOUT: f64 = 8.0;
+/// Retries of a stuck source before failing over, unless configured otherwise.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+// Delay before the first retry, doubled for each one after it up to the maximum
+const BASE_RETRY_DELAY: f64 = 1.0;
+const MAX_RETRY_DELAY: f64 = 30.0;
+// Share of a source's health each stall leaves it
+const STALL_PENALTY: f64 = 0.5;
+// Health a source regains per second of smooth playback
+const HEALTH_RECOVERY_RATE: f64 = 1.0 / 60.0;
+// Forward jumps longer than this are seeks rather than playback
+const MAX_PLAYBACK_STEP: f64 = 5.0;
+// Milliseconds between checks on whether playback is stuck
+const POLL_INTERVAL_MS: i32 = 500;
+
+/// When playback counts as stuck, and how hard to try before giving up on a source.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecoveryPolicy {
+    pub stall_timeout: f64,
+    pub max_retries: u32,
+    pub base_delay: f64,
+    pub max_delay: f64,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        RecoveryPolicy {
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: BASE_RETRY_DELAY,
+            max_delay: MAX_RETRY_DELAY,
+        }
+    }
+}
+
+impl RecoveryPolicy {
+    /// Seconds to wait before retry number `attempt`, counting from 1.
+    pub fn retry_delay(&self, attempt: u32) -> f64 {
+        (self.base_delay * 2f64.powi(attempt.saturating_sub(1).min(30) as i32)).min(self.max_delay)
+    }
+
+    /// Health below which a source is given up on: that of a source which stalled
+    /// once more than it may be retried.
+    fn min_health(&self) -> f64 {
+        STALL_PENALTY.powi(self.max_retries.min(64) as i32)
+    }
+}
+
+/// What the media element reported, as the watchdog sees it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Signal {
+    /// `waiting` or `stalled`: the buffer ran dry, or data stopped arriving.
+    Starving,
+    /// `progress`: data arrived.
+    Progress,
+    /// `timeupdate`, with the position it reported.
+    TimeUpdate(f64),
+}
+
+/// Something the player should do, or tell the page, about stuck playback.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecoveryStep {
+    /// Playback is stuck, and will be retried in `delay` seconds.
+    Stalled { attempt: u32, delay: f64 },
+    /// Reload the current source and continue from `position`.
+    Retry { attempt: u32, position: f64 },
+    /// Switch to source `source` and continue from `position`.
+    Failover { source: usize, position: f64 },
+    /// Playback moves again after a retry or failover.
+    Recovered,
+    /// Every source has stalled too often; nothing more is tried.
+    Failed,
+}
+
+/// Decides when playback is stuck and what to do about it. The clock is passed in,
+/// so tests can run it on virtual time.
+///
+/// Each source has a health score between 0 and 1 that halves when it stalls and
+/// slowly recovers while it plays. A stuck source is reloaded after a delay that
+/// doubles with each attempt, until its health drops below what `max_retries`
+/// stalls leave; playback then moves to the healthiest other source.
+#[derive(Clone, Debug)]
+pub struct StallWatchdog {
+    policy: RecoveryPolicy,
+    health: Vec<f64>,
+    current: usize,
+    position: f64,
+    // When playback last moved, or data last arrived while it was starving
+    last_activity: f64,
+    starving: bool,
+    // Consecutive retries since playback last recovered
+    attempts: u32,
+    retry_at: Option<f64>,
+    // Where playback was when it got stuck, to continue from after a reload
+    resume_at: Option<f64>,
+    recovered: bool,
+    failed: bool,
+}
+
+impl StallWatchdog {
+    /// Watches playback of the first of `sources` alternative sources, starting at `now` seconds.
+    pub fn new(policy: RecoveryPolicy, sources: usize, now: f64) -> StallWatchdog {
+        StallWatchdog {
+            policy,
+            health: vec![1.0; sources.max(1)],
+            current: 0,
+            position: 0.0,
+            last_activity: now,
+            starving: false,
+            attempts: 0,
+            retry_at: None,
+            resume_at: None,
+            recovered: false,
+            failed: false,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Notes that the element is playing another of the sources, such as after a quality switch.
+    pub fn set_current(&mut self, source: usize) {
+        if source < self.health.len() {
+            self.current = source;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn health(&self, source: usize) -> f64 {
+        self.health.get(source).copied().unwrap_or(0.0)
+    }
+
+    pub fn observe(&mut self, signal: Signal, now: f64) {
+        match signal {
+            Signal::Starving => self.starving = true,
+            // Data trickling in while the buffer is dry means it is still loading
+            Signal::Progress if self.starving => self.last_activity = now,
+            Signal::Progress => {}
+            Signal::TimeUpdate(position) => {
+                let step = position - self.position;
+                if step == 0.0 {
+                    return;
+                }
+                self.position = position;
+                match self.resume_at {
+                    // A reload starts from 0 and then seeks back, neither of which is playback
+                    Some(resume_at) if position <= resume_at => return,
+                    Some(_) => {
+                        self.resume_at = None;
+                        self.recovered = true;
+                    }
+                    None if self.failed && step > 0.0 => self.recovered = true,
+                    None => {}
+                }
+                if step > 0.0 && step <= MAX_PLAYBACK_STEP {
+                    let health = &mut self.health[self.current];
+                    *health = (*health + step * HEALTH_RECOVERY_RATE).min(1.0);
+                }
+                self.starving = false;
+                self.last_activity = now;
+            }
+        }
+    }
+
+    /// What to do at `now`, given whether the element is meant to be playing.
+    pub fn poll(&mut self, now: f64, playing: bool) -> Option<RecoveryStep> {
+        if self.recovered {
+            self.recovered = false;
+            self.failed = false;
+            self.attempts = 0;
+            self.retry_at = None;
+            return Some(RecoveryStep::Recovered);
+        }
+        // Time spent paused never counts as stuck, and a retry is not worth it any more
+        if !playing {
+            self.last_activity = now;
+            self.retry_at = None;
+            return None;
+        }
+        if self.failed {
+            return None;
+        }
+        if let Some(retry_at) = self.retry_at {
+            if now < retry_at {
+                return None;
+            }
+            self.retry_at = None;
+            self.last_activity = now;
+            return Some(RecoveryStep::Retry { attempt: self.attempts, position: self.resume_position() });
+        }
+        if now - self.last_activity < self.policy.stall_timeout {
+            return None;
+        }
+
+        let position = self.resume_position();
+        self.resume_at = Some(position);
+        self.health[self.current] *= STALL_PENALTY;
+        if self.health[self.current] >= self.policy.min_health() {
+            self.attempts += 1;
+            let delay = self.policy.retry_delay(self.attempts);
+            self.retry_at = Some(now + delay);
+            return Some(RecoveryStep::Stalled { attempt: self.attempts, delay });
+        }
+        match self.next_source() {
+            Some(source) => {
+                self.current = source;
+                self.attempts = 0;
+                self.last_activity = now;
+                Some(RecoveryStep::Failover { source, position })
+            }
+            None => {
+                self.failed = true;
+                Some(RecoveryStep::Failed)
+            }
+        }
+    }
+
+    /// Starts over after the viewer asked to try again: the current source gets
+    /// another chance even if it had been given up on.
+    pub fn restart(&mut self, now: f64) {
+        self.health[self.current] = self.health[self.current].max(self.policy.min_health());
+        self.resume_at = Some(self.resume_position());
+        self.attempts = 0;
+        self.retry_at = None;
+        self.last_activity = now;
+        self.recovered = false;
+        self.failed = false;
+    }
+
+    fn resume_position(&self) -> f64 {
+        self.resume_at.unwrap_or(self.position)
+    }
+
+    /// The healthiest other source still worth trying, the next one in order on a tie.
+    fn next_source(&self) -> Option<usize> {
+        let count = self.health.len();
+        let min_health = self.policy.min_health();
+        (1..count)
+            .map(|offset| (self.current + offset) % count)
+            .filter(|source| self.health[*source] >= min_health)
+            .fold(None, |best: Option<usize>, source| match best {
+                Some(best) if self.health[best] >= self.health[source] => Some(best),
+                _ => Some(source),
+            })
+    }
+}
+
+/// The sources playback can fall back on, in order: each usable source followed by its mirrors.
+pub fn recovery_candidates<F>(sources: &[VideoSource], usable: F) -> Vec<VideoSource>
+where
+    F: Fn(&VideoSource) -> bool,
+{
+    sources
+        .iter()
+        .filter(|source| usable(source))
+        .flat_map(|source| {
+            std::iter::once(source.clone()).chain(source.mirrors.iter().map(|mirror| VideoSource {
+                url: mirror.clone(),
+                mirrors: Vec::new(),
+                ..source.clone()
+            }))
+        })
+        .collect()
+}
+
+/// The stall watchdog of one player and the sources it can fail over to.
+#[derive(Default)]
+pub struct RecoveryState {
+    pub policy: RecoveryPolicy,
+    watchdog: Option<StallWatchdog>,
+    candidates: Vec<VideoSource>,
+    running: bool,
+}
+
+fn now() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+/// Starts watching newly loaded media, whose alternatives are `sources`.
+pub fn watch_media_for(context: &PlayerContext, sources: &[VideoSource]) -> Result<(), VideoError> {
+    let video_element = context.video_element()?;
+    let candidates = recovery_candidates(sources, |source| {
+        let native = !video_element.can_play_type(&source.mime_type).is_empty();
+        let stream = is_hls_source(&source.url, &source.mime_type) || is_dash_source(&source.url, &source.mime_type);
+        native || (stream && is_media_source_supported())
+            || (is_hls_source(&source.url, &source.mime_type) && !video_element.can_play_type(HLS_MIME_TYPES[0]).is_empty())
+    });
+    let mut recovery = context.recovery.borrow_mut();
+    recovery.watchdog = Some(StallWatchdog::new(recovery.policy, candidates.len(), now()));
+    recovery.candidates = candidates;
+    Ok(())
+}
+
+/// Notes that the element was pointed at another source outside of recovery, as a
+/// quality switch does: the candidate for `url`, or the first one when the element
+/// went back to its `<source>` children.
+pub fn select_source_for(context: &PlayerContext, url: Option<&str>) {
+    let mut recovery = context.recovery.borrow_mut();
+    let source = candidate_index(&recovery.candidates, url);
+    if let (Some(source), Some(watchdog)) = (source, recovery.watchdog.as_mut()) {
+        watchdog.set_current(source);
+    }
+}
+
+fn candidate_index(candidates: &[VideoSource], url: Option<&str>) -> Option<usize> {
+    match url {
+        Some(url) => candidates.iter().position(|candidate| candidate.url == url),
+        None => (!candidates.is_empty()).then_some(0),
+    }
+}
+
+/// Checks on playback every half second for as long as the player exists.
+pub fn start_watchdog_for(context: &Rc<PlayerContext>) {
+    if std::mem::replace(&mut context.recovery.borrow_mut().running, true) {
+        return;
+    }
+    let context = Rc::downgrade(context);
+    wasm_bindgen_futures::spawn_local(async move {
+        loop {
+            if sleep_ms(POLL_INTERVAL_MS).await.is_err() {
+                break;
+            }
+            let Some(context) = context.upgrade() else {
+                break;
+            };
+            // Teardown resets the state, which stops the loop
+            if !context.recovery.borrow().running {
+                break;
+            }
+            check_playback_for(&context).unwrap_or_default();
+        }
+    });
+}
+
+/// Passes what the video element reported on to the watchdog.
+pub fn watchdog_signal_for(context: &PlayerContext, signal: Signal) {
+    if let Some(watchdog) = context.recovery.borrow_mut().watchdog.as_mut() {
+        watchdog.observe(signal, now());
+    }
+}
+
+fn check_playback_for(context: &Rc<PlayerContext>) -> Result<(), VideoError> {
+    let video_element = context.video_element()?;
+    let step = {
+        let mut recovery = context.recovery.borrow_mut();
+        let Some(watchdog) = recovery.watchdog.as_mut() else {
+            return Ok(());
+        };
+        watchdog.poll(now(), !video_element.paused() && !video_element.ended())
+    };
+    if let Some(step) = step {
+        apply_step_for(context, step)?;
+    }
+    Ok(())
+}
+
+/// Carries out `step`, and reports it on the error display and to `recovery` handlers.
+fn apply_step_for(context: &Rc<PlayerContext>, step: RecoveryStep) -> Result<(), VideoError> {
+    Logger::info(&format!("Stall recovery: {:?}", step)).unwrap_or_default();
+    publish_for(context, PlayerEvent::Recovery { step: step.clone() });
+    match step {
+        RecoveryStep::Stalled { attempt, delay } => {
+            let error = VideoError::PlaybackStalled(format!("retrying in {} seconds (attempt {})", delay, attempt));
+            report_error_for(context, &error).unwrap_or_default();
+        }
+        RecoveryStep::Retry { position, .. } => {
+            let source = current_candidate_for(context);
+            if let Some(source) = source {
+                reload_source_for(context, &source, position)?;
+            }
+        }
+        RecoveryStep::Failover { source, position } => {
+            let candidate = context.recovery.borrow().candidates.get(source).cloned();
+            if let Some(candidate) = candidate {
+                reload_source_for(context, &candidate, position)?;
+                let error = VideoError::SourceFailover(format!("now playing {}", candidate.url));
+                report_error_for(context, &error).unwrap_or_default();
+            }
+        }
+        RecoveryStep::Recovered => {
+            resolve_errors_for(context, &["playback_stalled", "source_failover", "network_error"]).unwrap_or_default();
+        }
+        RecoveryStep::Failed => {
+            let error = VideoError::NetworkError("playback stalled on every source".to_string());
+            report_error_for(context, &error).unwrap_or_default();
+        }
+    }
+    Ok(())
+}
+
+/// Loads the current source again where playback stopped, for a viewer pressing
+/// Retry on an error.
+pub fn retry_playback_for(context: &Rc<PlayerContext>) -> Result<(), VideoError> {
+    let video_element = context.video_element()?;
+    if let Some(watchdog) = context.recovery.borrow_mut().watchdog.as_mut() {
+        watchdog.restart(now());
+    }
+    let position = video_element.current_time();
+    match current_candidate_for(context) {
+        Some(source) => reload_source_for(context, &source, position),
+        None => {
+            video_element.load();
+            set_start_time(&video_element, position)?;
+            start_video_for(context).map_err(|e|
+                VideoError::VideoOperationFailed(format!("Failed to restart playback: {:?}", e)))
+        }
+    }
+}
+
+fn current_candidate_for(context: &PlayerContext) -> Option<VideoSource> {
+    let recovery = context.recovery.borrow();
+    let current = recovery.watchdog.as_ref()?.current();
+    recovery.candidates.get(current).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RecoveryPolicy {
+        RecoveryPolicy { stall_timeout: 8.0, max_retries: 2, base_delay: 1.0, max_delay: 30.0 }
+    }
+
+    /// Plays from `watchdog`'s position for `seconds`, a quarter second at a time.
+    fn play(watchdog: &mut StallWatchdog, clock: &mut f64, seconds: f64) -> Vec<RecoveryStep> {
+        let mut steps = Vec::new();
+        for _ in 0..(seconds * 4.0) as usize {
+            *clock += 0.25;
+            let position = watchdog.position + 0.25;
+            watchdog.observe(Signal::TimeUpdate(position), *clock);
+            steps.extend(watchdog.poll(*clock, true));
+        }
+        steps
+    }
+
+    /// Lets `seconds` pass without playback moving, polling every half second.
+    fn freeze(watchdog: &mut StallWatchdog, clock: &mut f64, seconds: f64) -> Vec<RecoveryStep> {
+        let mut steps = Vec::new();
+        for _ in 0..(seconds * 2.0) as usize {
+            *clock += 0.5;
+            steps.extend(watchdog.poll(*clock, true));
+        }
+        steps
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let policy = RecoveryPolicy::default();
+        let delays: Vec<f64> = (1..=7).map(|attempt| policy.retry_delay(attempt)).collect();
+        assert_eq!(delays, [1.0, 2.0, 4.0, 8.0, 16.0, 30.0, 30.0]);
+        assert_eq!(policy.retry_delay(u32::MAX), 30.0);
+    }
+
+    #[test]
+    fn test_stuck_playback_is_retried_with_backoff() {
+        let mut clock = 0.0;
+        let mut watchdog = StallWatchdog::new(policy(), 2, clock);
+        assert!(play(&mut watchdog, &mut clock, 10.0).is_empty());
+
+        watchdog.observe(Signal::Starving, clock);
+        assert!(freeze(&mut watchdog, &mut clock, 7.5).is_empty());
+        assert_eq!(freeze(&mut watchdog, &mut clock, 0.5), [RecoveryStep::Stalled { attempt: 1, delay: 1.0 }]);
+        assert_eq!(freeze(&mut watchdog, &mut clock, 1.0), [RecoveryStep::Retry { attempt: 1, position: 10.0 }]);
+
+        // The reload starts over at 0 and seeks back before playback counts as moving again
+        watchdog.observe(Signal::TimeUpdate(0.0), clock);
+        watchdog.observe(Signal::TimeUpdate(10.0), clock);
+        assert!(freeze(&mut watchdog, &mut clock, 7.5).is_empty());
+        assert_eq!(freeze(&mut watchdog, &mut clock, 0.5), [RecoveryStep::Stalled { attempt: 2, delay: 2.0 }]);
+        assert!(freeze(&mut watchdog, &mut clock, 1.5).is_empty());
+        assert_eq!(freeze(&mut watchdog, &mut clock, 0.5), [RecoveryStep::Retry { attempt: 2, position: 10.0 }]);
+
+        assert_eq!(play(&mut watchdog, &mut clock, 1.0), [RecoveryStep::Recovered]);
+        assert!((watchdog.health(0) - (0.25 + 1.0 / 60.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_playback_moving_again_cancels_the_retry() {
+        let mut clock = 0.0;
+        let mut watchdog = StallWatchdog::new(policy(), 1, clock);
+        play(&mut watchdog, &mut clock, 2.0);
+        assert_eq!(freeze(&mut watchdog, &mut clock, 8.0), [RecoveryStep::Stalled { attempt: 1, delay: 1.0 }]);
+        assert_eq!(play(&mut watchdog, &mut clock, 5.0), [RecoveryStep::Recovered]);
+    }
+
+    #[test]
+    fn test_data_arriving_while_starved_is_not_a_stall() {
+        let mut clock = 0.0;
+        let mut watchdog = StallWatchdog::new(policy(), 1, clock);
+        watchdog.observe(Signal::Starving, clock);
+        for _ in 0..20 {
+            clock += 1.0;
+            watchdog.observe(Signal::Progress, clock);
+            assert_eq!(watchdog.poll(clock, true), None);
+        }
+        // Time spent paused does not count either
+        for _ in 0..20 {
+            clock += 1.0;
+            assert_eq!(watchdog.poll(clock, false), None);
+        }
+        assert_eq!(freeze(&mut watchdog, &mut clock, 8.0).len(), 1);
+    }
+
+    #[test]
+    fn test_failover_to_the_healthiest_source() {
+        let mut clock = 0.0;
+        let mut watchdog = StallWatchdog::new(policy(), 3, clock);
+        // Source 1 stalled once earlier, so source 2 is healthier
+        watchdog.set_current(1);
+        freeze(&mut watchdog, &mut clock, 9.0);
+        play(&mut watchdog, &mut clock, 1.0);
+        watchdog.set_current(0);
+        play(&mut watchdog, &mut clock, 5.0);
+        assert!(watchdog.health(1) < 1.0);
+
+        let steps = freeze(&mut watchdog, &mut clock, 30.0);
+        assert_eq!(steps, [
+            RecoveryStep::Stalled { attempt: 1, delay: 1.0 },
+            RecoveryStep::Retry { attempt: 1, position: 6.0 },
+            RecoveryStep::Stalled { attempt: 2, delay: 2.0 },
+            RecoveryStep::Retry { attempt: 2, position: 6.0 },
+            RecoveryStep::Failover { source: 2, position: 6.0 },
+        ]);
+        assert_eq!(watchdog.current(), 2);
+    }
+
+    #[test]
+    fn test_gives_up_once_every_source_is_unhealthy() {
+        let mut clock = 0.0;
+        let mut watchdog = StallWatchdog::new(RecoveryPolicy { max_retries: 0, ..policy() }, 2, clock);
+        play(&mut watchdog, &mut clock, 5.0);
+        assert_eq!(freeze(&mut watchdog, &mut clock, 8.0), [RecoveryStep::Failover { source: 1, position: 5.0 }]);
+        assert_eq!(freeze(&mut watchdog, &mut clock, 8.0), [RecoveryStep::Failed]);
+        assert!(freeze(&mut watchdog, &mut clock, 60.0).is_empty());
+
+        // Playback that picks up by itself afterwards counts as recovered
+        assert_eq!(play(&mut watchdog, &mut clock, 1.0), [RecoveryStep::Recovered]);
+    }
+
+    #[test]
+    fn test_restart_gives_the_source_another_chance() {
+        let mut clock = 0.0;
+        let mut watchdog = StallWatchdog::new(RecoveryPolicy { max_retries: 0, ..policy() }, 1, clock);
+        play(&mut watchdog, &mut clock, 5.0);
+        assert_eq!(freeze(&mut watchdog, &mut clock, 8.0), [RecoveryStep::Failed]);
+
+        watchdog.restart(clock);
+        assert_eq!(freeze(&mut watchdog, &mut clock, 8.0), [RecoveryStep::Failed]);
+        watchdog.restart(clock);
+        assert_eq!(play(&mut watchdog, &mut clock, 1.0), [RecoveryStep::Recovered]);
+    }
+
+    #[test]
+    fn test_candidates_follow_each_source_with_its_mirrors() {
+        let mut mp4 = VideoSource::new("https://a.example.com/v.mp4".to_string(), "video/mp4".to_string(), Some("HD".to_string()));
+        mp4.add_mirror("https://b.example.com/v.mp4".to_string());
+        let webm = VideoSource::new("https://a.example.com/v.webm".to_string(), "video/webm".to_string(), None);
+        let ogg = VideoSource::new("https://a.example.com/v.ogv".to_string(), "video/ogg".to_string(), None);
+        let candidates = recovery_candidates(&[mp4, webm, ogg], |source| source.mime_type != "video/ogg");
+        let urls: Vec<_> = candidates.iter().map(|candidate| candidate.url.as_str()).collect();
+        assert_eq!(urls, ["https://a.example.com/v.mp4", "https://b.example.com/v.mp4", "https://a.example.com/v.webm"]);
+        assert_eq!(candidates[1].label.as_deref(), Some("HD"));
+    }
+
+    #[test]
+    fn test_selected_source_is_found_by_its_exact_url() {
+        let hd = VideoSource::new("hd/video.mp4".to_string(), "video/mp4".to_string(), Some("HD".to_string()));
+        let sd = VideoSource::new("video.mp4".to_string(), "video/mp4".to_string(), Some("SD".to_string()));
+        let candidates = recovery_candidates(&[hd, sd], |_| true);
+        assert_eq!(candidate_index(&candidates, Some("video.mp4")), Some(1));
+        assert_eq!(candidate_index(&candidates, Some("hd/video.mp4")), Some(0));
+        assert_eq!(candidate_index(&candidates, Some("blob:https://example.com/1")), None);
+        // Back on the <source> children, the element plays the first candidate
+        assert_eq!(candidate_index(&candidates, None), Some(0));
+        assert_eq!(candidate_index(&[], None), None);
+    }
+}
@@ -0,0 +1,7 @@
+use web_sys::Storage;
+
+/// The page's localStorage, or `None` where the browser does not allow it.
+pub(crate) fn local_storage() -> Option<Storage> {
+    // Throws when storage is disabled, for example for sandboxed frames
+    web_sys::window()?.local_storage().ok().flatten()
+}